@@ -1,13 +1,21 @@
 pub mod base;
 pub mod buffer;
+mod builtin_font;
 pub mod button;
 pub mod commands;
 pub mod cursor;
+pub mod edit;
+pub mod frame_stats;
+pub mod geometry_cache;
 pub mod image;
 pub mod input;
 pub mod panel;
 mod progress;
+mod property;
+mod scrollbar;
 pub mod style;
+mod style_config;
+pub mod svg_export;
 pub mod text;
 pub mod text_engine;
 pub mod ui_context;
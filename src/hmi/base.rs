@@ -1,4 +1,6 @@
-use crate::math::{rectangle::RectangleF32, vec2::Vec2F32};
+use crate::math::{
+  rectangle::RectangleF32, vec2::Vec2F32, vertex_types::VertexPTC,
+};
 use enumflags2::BitFlags;
 use enumflags2_derive::EnumFlags;
 
@@ -93,7 +95,7 @@ pub struct DrawNullTexture {
   /// <! texture handle to a texture containing a white pixel
   pub texture: GenericHandle,
   /// <! Coordinates of the white pixel in the above texture
-  pub uv: Vec2F32,
+  pub uv:      Vec2F32,
 }
 
 impl std::default::Default for DrawNullTexture {
@@ -105,17 +107,86 @@ impl std::default::Default for DrawNullTexture {
   }
 }
 
+impl DrawNullTexture {
+  /// Id(0) is never a valid texture, so it marks an unset null texture.
+  pub fn is_valid(&self) -> bool {
+    self.texture != GenericHandle::Id(0)
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct ConvertConfig {
   pub global_alpha:         f32,
   pub line_aa:              AntialiasingType,
   pub shape_aa:             AntialiasingType,
   pub circle_segment_count: u32,
+  /// When positive, circles pick their segment count from the radius (see
+  /// `Consts::circle_segments_for_radius`) instead of using
+  /// `circle_segment_count`.
+  pub circle_quality:       f32,
   pub arc_segment_count:    u32,
   pub curve_segment_count:  u32,
   pub null:                 DrawNullTexture,
   pub vertex_layout:        Vec<DrawVertexLayoutElement>,
   pub vertex_size:          usize,
+  /// Rounds the vertex positions to whole pixels, for crisp edges at
+  /// fractional coordinates. Leave it off so animated shapes move smoothly.
+  pub snap_to_pixel:        bool,
+  /// Emits the vertex colors with premultiplied alpha, to be drawn with
+  /// `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)`. Turn it on when the font
+  /// atlas is built with `premultiply_alpha(true)`.
+  pub premultiplied_alpha:  bool,
+}
+
+impl ConvertConfig {
+  /// Sets the null texture to a 1x1 white texture. All solid fills sample this
+  /// texture, so it must be set before converting any commands.
+  pub fn with_white_pixel(self, handle: GenericHandle) -> Self {
+    Self {
+      null: DrawNullTexture {
+        texture: handle,
+        uv:      Vec2F32::new(0.5f32, 0.5f32),
+      },
+      ..self
+    }
+  }
+
+  /// The draw list always outputs `VertexPTC` vertices, so `vertex_size` must
+  /// match its size or uploading the vertex buffer will corrupt it.
+  pub fn validate(&self) -> Result<(), String> {
+    let expected = std::mem::size_of::<VertexPTC>();
+    if self.vertex_size != expected {
+      return Err(format!(
+        "ConvertConfig.vertex_size is {} bytes but the draw list emits \
+         VertexPTC vertices of {} bytes",
+        self.vertex_size, expected
+      ));
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+impl ConvertConfig {
+  /// No antialiasing, 22 segments for curves and circles and no null
+  /// texture, add one with `with_white_pixel()` before converting fills.
+  pub fn for_tests() -> ConvertConfig {
+    ConvertConfig {
+      global_alpha:         1f32,
+      line_aa:              AntialiasingType::Off,
+      shape_aa:             AntialiasingType::Off,
+      circle_segment_count: 22,
+      circle_quality:       1f32,
+      arc_segment_count:    22,
+      curve_segment_count:  22,
+      null:                 DrawNullTexture::default(),
+      vertex_layout:        vec![],
+      vertex_size:          std::mem::size_of::<VertexPTC>(),
+      snap_to_pixel:        false,
+      premultiplied_alpha:  false,
+    }
+  }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -185,6 +256,24 @@ pub struct DrawVertexLayoutElement {
 pub struct Consts {}
 
 impl Consts {
+  pub const CIRCLE_SEGMENTS_MAX: u32 = 512;
+  pub const CIRCLE_SEGMENTS_MIN: u32 = 8;
+
+  /// Number of segments for a circle of radius `r`, so that the polygon
+  /// never strays more than half a pixel from the real outline. A `quality`
+  /// above 1 lowers that error (more segments), below 1 raises it.
+  pub fn circle_segments_for_radius(r: f32, quality: f32) -> u32 {
+    let max_error = 0.5f32 / quality.max(std::f32::EPSILON);
+    if r <= max_error {
+      return Consts::CIRCLE_SEGMENTS_MIN;
+    }
+
+    let segments = std::f32::consts::PI / (1f32 - max_error / r).acos();
+    (segments.ceil() as u32)
+      .max(Consts::CIRCLE_SEGMENTS_MIN)
+      .min(Consts::CIRCLE_SEGMENTS_MAX)
+  }
+
   pub fn null_rect() -> crate::math::rectangle::RectangleF32 {
     crate::math::rectangle::RectangleF32::new(
       -8192_f32, -8192_f32, 16834_f32, 16834_f32,
@@ -234,3 +323,61 @@ impl WidgetStates {
     }
   }
 }
+
+/// What an accessible widget is, as reported to an accessibility bridge.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WidgetRole {
+  Button,
+  Checkbox,
+  Slider,
+  Edit,
+  Label,
+}
+
+/// Name and role recorded for a widget allocated this frame.
+#[derive(Clone, Debug)]
+pub struct AccessibleWidget {
+  pub name:   String,
+  pub role:   WidgetRole,
+  pub bounds: RectangleF32,
+}
+
+/// Access to the system clipboard, supplied by the application.
+pub trait ClipboardProvider {
+  fn set_text(&mut self, text: &str);
+  fn get_text(&self) -> Option<String>;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config(size: usize) -> ConvertConfig {
+    ConvertConfig {
+      vertex_size: size,
+      ..ConvertConfig::for_tests()
+    }
+  }
+
+  #[test]
+  fn test_validate_vertex_size() {
+    assert!(config(std::mem::size_of::<VertexPTC>()).validate().is_ok());
+
+    let err = config(20).validate().unwrap_err();
+    assert!(err.contains("vertex_size is 20 bytes"));
+  }
+
+  #[test]
+  fn test_circle_segments_grow_with_radius() {
+    let small = Consts::circle_segments_for_radius(4f32, 1f32);
+    let large = Consts::circle_segments_for_radius(200f32, 1f32);
+    assert!(small < large);
+    assert_eq!(small, Consts::CIRCLE_SEGMENTS_MIN);
+
+    assert!(Consts::circle_segments_for_radius(200f32, 4f32) > large);
+    assert_eq!(
+      Consts::circle_segments_for_radius(1e9f32, 1f32),
+      Consts::CIRCLE_SEGMENTS_MAX
+    );
+  }
+}
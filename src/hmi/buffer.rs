@@ -146,6 +146,75 @@ where
   }
 }
 
+/// Bump arena for temporary data rebuilt every frame. Allocations are ranges
+/// of a single vector that keeps its memory on `reset()`, so once it has
+/// grown to the size of a frame nothing more gets allocated.
+#[derive(Clone, Debug, Default)]
+pub struct ScratchArena<T>
+where
+  T: Copy + Clone,
+{
+  mem:         Vec<T>,
+  allocations: u32,
+}
+
+impl<T> ScratchArena<T>
+where
+  T: Copy + Clone,
+{
+  pub fn new() -> Self {
+    ScratchArena {
+      mem:         Vec::new(),
+      allocations: 0,
+    }
+  }
+
+  /// Appends the elements, returning where they are in the arena.
+  pub fn alloc_extend<I>(&mut self, elements: I) -> std::ops::Range<usize>
+  where
+    I: IntoIterator<Item = T>,
+  {
+    let start_idx = self.mem.len();
+    let cap = self.mem.capacity();
+    self.mem.extend(elements);
+    if self.mem.capacity() != cap {
+      self.allocations += 1;
+    }
+
+    start_idx .. self.mem.len()
+  }
+
+  pub fn push(&mut self, e: T) {
+    self.alloc_extend(std::iter::once(e));
+  }
+
+  pub fn get(&self, range: std::ops::Range<usize>) -> &[T] {
+    &self.mem[range]
+  }
+
+  pub fn elements(&self) -> &[T] {
+    &self.mem
+  }
+
+  pub fn len(&self) -> usize {
+    self.mem.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.mem.is_empty()
+  }
+
+  /// Frees all allocations, the memory is kept for reuse.
+  pub fn reset(&mut self) {
+    self.mem.clear();
+  }
+
+  /// Number of times the arena had to grow its memory.
+  pub fn allocations(&self) -> u32 {
+    self.allocations
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -222,4 +291,22 @@ mod tests {
       .enumerate()
       .for_each(|(i, v)| assert_eq!(i as i32 + 1, *v));
   }
+
+  #[test]
+  fn test_scratch_arena_reuses_memory() {
+    let mut arena = ScratchArena::<i32>::new();
+
+    let first = arena.alloc_extend(0 .. 4);
+    let second = arena.alloc_extend(4 .. 8);
+    assert_eq!(arena.get(first), &[0, 1, 2, 3]);
+    assert_eq!(arena.get(second), &[4, 5, 6, 7]);
+
+    let allocations = arena.allocations();
+    arena.reset();
+    assert!(arena.is_empty());
+
+    arena.alloc_extend(0 .. 8);
+    assert_eq!(arena.len(), 8);
+    assert_eq!(arena.allocations(), allocations);
+  }
 }
@@ -17,14 +17,14 @@ use crate::{
 };
 use enumflags2::BitFlags;
 
-fn draw_symbol(
+pub(crate) fn draw_symbol(
   out: &mut CommandBuffer,
   typ: SymbolType,
   content: RectangleF32,
   background: RGBAColor,
   foreground: RGBAColor,
   border_width: f32,
-  font: Font,
+  font: &Font,
 ) {
   match typ {
     SymbolType::X
@@ -93,7 +93,7 @@ fn draw_symbol(
   };
 }
 
-fn button_behaviour(
+pub(crate) fn button_behaviour(
   state: &mut BitFlags<WidgetStates>,
   r: RectangleF32,
   i: Option<&Input>,
@@ -112,7 +112,9 @@ fn button_behaviour(
         if behavior != ButtonBehaviour::ButtonDefault {
           i.is_mouse_down(MouseButtonId::ButtonLeft)
         } else {
-          i.is_mouse_pressed(MouseButtonId::ButtonLeft)
+          // activate on release, so the press can still be cancelled by
+          // moving out of the button
+          i.has_mouse_up_in_rect(MouseButtonId::ButtonLeft, &r)
         }
       } else {
         false
@@ -146,20 +148,9 @@ pub fn draw_button<'a>(
     &style.normal
   };
 
-  match background {
-    StyleItem::Img(ref i) => {
-      out.draw_image(*bounds, *i, RGBAColor::new(255, 255, 255));
-    }
-
-    StyleItem::Color(ref c) => {
-      out.fill_rect(*bounds, style.rounding, *c);
-      out.stroke_rect(
-        *bounds,
-        style.rounding,
-        style.border,
-        style.border_color,
-      );
-    }
+  background.draw(out, *bounds, style.rounding);
+  if let StyleItem::Color(_) = background {
+    out.stroke_rect(*bounds, style.rounding, style.border, style.border_color);
   }
 
   background
@@ -198,15 +189,12 @@ fn draw_button_text(
   style: &StyleButton,
   txt: &str,
   alignment: BitFlags<TextAlign>,
-  font: Font,
+  font: &Font,
 ) {
   let background = draw_button(out, bounds, state, style);
 
   // select correct colors/images
-  let background = match background {
-    StyleItem::Color(c) => *c,
-    _ => style.text_background,
-  };
+  let background = background.background_color(style.text_background);
 
   let text = if state.intersects(WidgetStates::Hover) {
     style.text_hover
@@ -239,7 +227,7 @@ pub fn do_button_text(
   behavior: ButtonBehaviour,
   style: &StyleButton,
   i: Option<&Input>,
-  font: Font,
+  font: &Font,
 ) -> bool {
   let (res, content) = do_button(state, out, bounds, style, i, behavior);
   // TODO: draw begin support
@@ -255,15 +243,12 @@ fn draw_button_symbol(
   state: BitFlags<WidgetStates>,
   style: &StyleButton,
   typ: SymbolType,
-  font: Font,
+  font: &Font,
 ) {
   // select correct colors/images
   let background = draw_button(out, bounds, state, style);
 
-  let bg = match background {
-    StyleItem::Color(c) => *c,
-    _ => style.text_background,
-  };
+  let bg = background.background_color(style.text_background);
 
   let sym = if state.intersects(WidgetStates::Hover) {
     style.text_hover
@@ -284,7 +269,7 @@ pub fn do_button_symbol(
   behavior: ButtonBehaviour,
   style: &StyleButton,
   i: Option<&Input>,
-  font: Font,
+  font: &Font,
 ) -> bool {
   let (res, content) = do_button(state, out, bounds, style, i, behavior);
   // TODO: support for custom drawing (draw_begin)
@@ -339,7 +324,7 @@ fn draw_button_text_symbol(
   style: &StyleButton,
   s: &str,
   typ: SymbolType,
-  font: Font,
+  font: &Font,
 ) {
   // select correct background colors/images
   let background = draw_button(out, bounds, state, style);
@@ -355,10 +340,7 @@ fn draw_button_text_symbol(
   draw_symbol(out, typ, *symbol, style.text_background, sym, 0f32, font);
 
   let text = Text {
-    background: match background {
-      StyleItem::Color(c) => *c,
-      _ => style.text_background,
-    },
+    background: background.background_color(style.text_background),
     text:       if state.intersects(WidgetStates::Hover) {
       style.text_hover
     } else if state.intersects(WidgetStates::Activated) {
@@ -381,7 +363,7 @@ pub fn do_button_text_symbol(
   align: BitFlags<TextAlign>,
   behavior: ButtonBehaviour,
   style: &StyleButton,
-  f: Font,
+  f: &Font,
   i: Option<&Input>,
 ) -> bool {
   let (result, content_rect) =
@@ -423,17 +405,14 @@ fn draw_button_text_image(
   state: BitFlags<WidgetStates>,
   style: &StyleButton,
   s: &str,
-  font: Font,
+  font: &Font,
   img: &Image,
 ) {
   let background = draw_button(out, bounds, state, style);
 
   // select correct colors
   let text = Text {
-    background: match background {
-      StyleItem::Color(c) => *c,
-      _ => style.text_background,
-    },
+    background: background.background_color(style.text_background),
     text:       if state.intersects(WidgetStates::Hover) {
       style.text_hover
     } else if state.intersects(WidgetStates::Activated) {
@@ -457,7 +436,7 @@ pub fn do_button_text_image(
   align: BitFlags<TextAlign>,
   behavior: ButtonBehaviour,
   style: &StyleButton,
-  font: Font,
+  font: &Font,
   i: Option<&Input>,
 ) -> bool {
   let (result, content) = do_button(state, out, bounds, style, i, behavior);
@@ -490,3 +469,128 @@ pub fn do_button_text_image(
 
   result
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::{
+    base::{Consts, DrawNullTexture, GenericHandle},
+    commands::Command,
+    style::Style,
+    text_engine::{FontAtlasBuilder, FontConfigBuilder, TTFDataSource},
+  };
+
+  #[test]
+  fn test_image_style_item_draws_image() {
+    let mut style = Style::new(Font::default()).button;
+    style.normal = StyleItem::Img(Image::image_id(1));
+    style.hover = StyleItem::Img(Image::image_id(2));
+    style.active = StyleItem::Img(Image::image_id(3));
+
+    let bounds = RectangleF32::new(0f32, 0f32, 64f32, 24f32);
+    [
+      BitFlags::default(),
+      BitFlags::from(WidgetStates::Hover),
+      BitFlags::from(WidgetStates::Activated),
+    ]
+    .iter()
+    .for_each(|state| {
+      let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+      draw_button(&mut out, &bounds, *state, &style);
+
+      assert!(out.commands().iter().any(|cmd| match cmd {
+        Command::Image(_) => true,
+        _ => false,
+      }));
+    });
+  }
+
+  #[test]
+  fn test_label_is_centered_in_tall_button() {
+    use crate::{
+      hmi::{
+        base::{AntialiasingType, ConvertConfig},
+        vertex_output::DrawList,
+      },
+      sys::headless::HeadlessTarget,
+    };
+
+    let mut builder = FontAtlasBuilder::new(96).unwrap();
+    builder.keep_pixels(true);
+    let font = builder
+      .add_font(
+        &FontConfigBuilder::new().size(14f32).build(),
+        TTFDataSource::File(std::path::PathBuf::from("DroidSans.ttf")),
+      )
+      .unwrap();
+    builder
+      .build(|_, _, _| Some((GenericHandle::Id(1), DrawNullTexture::default())))
+      .unwrap();
+
+    let mut style = Style::new(font.clone()).button;
+    style.padding = Vec2F32::same(0f32);
+    style.border = 0f32;
+    style.rounding = 0f32;
+
+    let bounds = RectangleF32::new(0f32, 0f32, 120f32, 80f32);
+    let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+    do_button_text(
+      &mut BitFlags::default(),
+      &mut out,
+      bounds,
+      "Hg",
+      TextAlign::centered(),
+      ButtonBehaviour::ButtonDefault,
+      &style,
+      None,
+      &font,
+    );
+
+    // render only the label, every covered pixel is a glyph pixel
+    let (cmds, len) = out.commands_range();
+    let text_cmds = (0 .. len)
+      .map(|i| unsafe { cmds.add(i) })
+      .filter(|&cmd| matches!(unsafe { &*cmd }, Command::Text(_)))
+      .collect::<Vec<_>>();
+    assert_eq!(text_cmds.len(), 1);
+
+    let config = ConvertConfig {
+      null: font.draw_null_texture(),
+      ..ConvertConfig::for_tests()
+    };
+    let mut dl =
+      DrawList::new(config, AntialiasingType::Off, AntialiasingType::Off)
+        .unwrap();
+    let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+    dl.convert(&text_cmds, &mut vertices, &mut indices, &mut draw_cmds);
+
+    let mut target = HeadlessTarget::new(bounds.w as u32, bounds.h as u32);
+    let (texels, tex_width, tex_height) = font.texture_pixels().unwrap();
+    target.add_texture(font.texture(), tex_width, tex_height, &texels);
+    target.render(&vertices, &indices, &draw_cmds);
+
+    let covered_rows = (0 .. target.height())
+      .filter(|&y| (0 .. target.width()).any(|x| target.pixel(x, y).a > 0))
+      .collect::<Vec<_>>();
+    let (first, last) = (
+      covered_rows[0] as f32,
+      *covered_rows.last().unwrap() as f32 + 1f32,
+    );
+
+    // the glyphs, from the top of the H to the bottom of the g, sit in the
+    // line box centered in the button ...
+    let line_top = (bounds.h - font.line_height()) * 0.5f32;
+    assert!(
+      first >= line_top - 1f32 && last <= line_top + font.line_height() + 1f32
+    );
+    // ... with as much room above as below them, give or take the gap
+    // between the cap height and the ascender
+    let (above, below) = (first, bounds.h - last);
+    assert!(
+      (above - below).abs() <= 4f32,
+      "{} above, {} below",
+      above,
+      below
+    );
+  }
+}
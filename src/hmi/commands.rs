@@ -1,6 +1,16 @@
 use crate::{
-  hmi::{base::Consts, image::Image, text_engine::Font},
-  math::{colors::RGBAColor, rectangle::RectangleF32, vec2::Vec2I16},
+  hmi::{
+    base::{Consts, GenericHandle},
+    image::Image,
+    text_engine::{Font, TextDirection},
+    vertex_output::DrawIndexType,
+  },
+  math::{
+    colors::{RGBAColor, RGBAColorF32},
+    rectangle::RectangleF32,
+    vec2::{Vec2F32, Vec2I16},
+    vertex_types::VertexPTC,
+  },
 };
 
 #[derive(Copy, Clone, Debug)]
@@ -11,12 +21,45 @@ pub struct CmdScissor {
   pub h: u16,
 }
 
+/// How lines and polylines are stroked.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LineStyle {
+  Solid,
+  /// `dash` pixels drawn, then `gap` pixels left out
+  Dashed {
+    dash: f32,
+    gap:  f32,
+  },
+  /// dots as long as the line is thick, one dot apart
+  Dotted,
+}
+
+impl LineStyle {
+  /// Lengths of the drawn and the left out runs, `None` for solid lines (and
+  /// for dash patterns that can't be drawn). Runs are at least half a pixel
+  /// long, so a tiny pattern doesn't split a line into countless pieces.
+  pub fn pattern(&self, line_thickness: f32) -> Option<(f32, f32)> {
+    const MIN_RUN_LENGTH: f32 = 0.5_f32;
+
+    match *self {
+      LineStyle::Solid => None,
+      LineStyle::Dashed { dash, gap } => Some((dash, gap)),
+      LineStyle::Dotted => {
+        Some((line_thickness.max(1_f32), line_thickness.max(1_f32)))
+      }
+    }
+    .filter(|(on, off)| *on > 0_f32 && *off > 0_f32)
+    .map(|(on, off)| (on.max(MIN_RUN_LENGTH), off.max(MIN_RUN_LENGTH)))
+  }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct CmdLine {
   pub line_thickness: u16,
   pub begin:          Vec2I16,
   pub end:            Vec2I16,
   pub color:          RGBAColor,
+  pub style:          LineStyle,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -49,6 +92,31 @@ pub struct CmdRectFilled {
   pub color:    RGBAColor,
 }
 
+/// Filled rectangle with a radius for each corner (top-left, top-right,
+/// bottom-right, bottom-left).
+#[derive(Copy, Clone, Debug)]
+pub struct CmdRectFilledCorners {
+  pub radii: [u16; 4],
+  pub x:     i16,
+  pub y:     i16,
+  pub w:     u16,
+  pub h:     u16,
+  pub color: RGBAColor,
+}
+
+/// Filled rectangle with a soft shadow fading out over `blur` pixels around
+/// it.
+#[derive(Copy, Clone, Debug)]
+pub struct CmdRectShadow {
+  pub rounding: u16,
+  pub blur:     u16,
+  pub x:        i16,
+  pub y:        i16,
+  pub w:        u16,
+  pub h:        u16,
+  pub color:    RGBAColor,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct CmdRectMulticolor {
   pub x:      i16,
@@ -86,15 +154,19 @@ pub struct CmdCircle {
   pub w:              u16,
   pub h:              u16,
   pub color:          RGBAColor,
+  /// Segment count, 0 lets the converter pick one.
+  pub segments:       u16,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct CmdCircleFilled {
-  pub x:     i16,
-  pub y:     i16,
-  pub w:     u16,
-  pub h:     u16,
-  pub color: RGBAColor,
+  pub x:        i16,
+  pub y:        i16,
+  pub w:        u16,
+  pub h:        u16,
+  pub color:    RGBAColor,
+  /// Segment count, 0 lets the converter pick one.
+  pub segments: u16,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -134,6 +206,7 @@ pub struct CmdPolyline {
   pub color:          RGBAColor,
   pub line_thickness: u16,
   pub points:         Vec<Vec2I16>,
+  pub style:          LineStyle,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -146,6 +219,18 @@ pub struct CmdImage {
   pub color: RGBAColor,
 }
 
+/// Indexed triangles with a texture, passed through to the vertex and index
+/// buffers as they are. Indices are relative to the first vertex.
+#[derive(Clone, Debug)]
+pub struct CmdMesh {
+  pub texture:  GenericHandle,
+  pub vertices: Vec<VertexPTC>,
+  pub indices:  Vec<DrawIndexType>,
+}
+
+/// Color for a range of chars (char indices, not bytes) of a text.
+pub type TextColorSpan = (std::ops::Range<usize>, RGBAColorF32);
+
 #[derive(Clone, Debug)]
 pub struct CmdText {
   pub font:       Font,
@@ -157,6 +242,9 @@ pub struct CmdText {
   pub h:          u16,
   pub height:     f32,
   pub text:       String,
+  pub direction:  TextDirection,
+  /// Colors overriding the foreground, empty for single color text.
+  pub spans:      Vec<TextColorSpan>,
 }
 
 #[derive(Debug, Clone)]
@@ -167,6 +255,8 @@ pub enum Command {
   Curve(CmdCurve),
   Rect(CmdRect),
   RectFilled(CmdRectFilled),
+  RectFilledCorners(CmdRectFilledCorners),
+  RectShadow(CmdRectShadow),
   RectMulticolor(CmdRectMulticolor),
   Triangle(CmdTriangle),
   TriangleFilled(CmdTriangleFilled),
@@ -179,6 +269,7 @@ pub enum Command {
   Polyline(CmdPolyline),
   Image(CmdImage),
   Text(CmdText),
+  Mesh(CmdMesh),
 }
 
 #[derive(Clone, Debug)]
@@ -214,6 +305,12 @@ impl CommandBuffer {
     }
   }
 
+  /// The recorded commands, for tests that look at what was drawn.
+  #[cfg(test)]
+  pub fn commands(&self) -> &[Command] {
+    &self.base
+  }
+
   pub fn clear(&mut self) {
     self.base.clear();
     self.clip = None;
@@ -244,12 +341,35 @@ impl CommandBuffer {
     y1: f32,
     line_thickness: f32,
     color: RGBAColor,
+  ) {
+    self.stroke_line_styled(
+      x0,
+      y0,
+      x1,
+      y1,
+      line_thickness,
+      color,
+      LineStyle::Solid,
+    );
+  }
+
+  /// Line that is dashed or dotted according to `style`.
+  pub fn stroke_line_styled(
+    &mut self,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    line_thickness: f32,
+    color: RGBAColor,
+    style: LineStyle,
   ) {
     let cmd = CmdLine {
       line_thickness: line_thickness as u16,
       begin: Vec2I16::new(x0 as i16, y0 as i16),
       end: Vec2I16::new(x1 as i16, y1 as i16),
       color,
+      style,
     };
 
     self.base.push(Command::Line(cmd));
@@ -322,6 +442,18 @@ impl CommandBuffer {
     r: RectangleF32,
     line_thickness: f32,
     color: RGBAColor,
+  ) {
+    self.stroke_circle_segments(r, line_thickness, color, 0);
+  }
+
+  /// Like `stroke_circle`, but with a fixed segment count instead of the one
+  /// picked at conversion time.
+  pub fn stroke_circle_segments(
+    &mut self,
+    r: RectangleF32,
+    line_thickness: f32,
+    color: RGBAColor,
+    segments: u16,
   ) {
     if r.w == 0_f32 || r.h == 0_f32 || line_thickness <= 0_f32 {
       return;
@@ -339,6 +471,7 @@ impl CommandBuffer {
       w: r.w.max(0_f32) as u16,
       h: r.h.max(0_f32) as u16,
       color,
+      segments,
     };
 
     self.base.push(Command::Circle(cmd));
@@ -410,6 +543,23 @@ impl CommandBuffer {
     points: &[f32],
     line_thickness: f32,
     color: RGBAColor,
+  ) {
+    self.stroke_polyline_styled(
+      points,
+      line_thickness,
+      color,
+      LineStyle::Solid,
+    );
+  }
+
+  /// Polyline that is dashed or dotted according to `style`, the pattern
+  /// continues around the corners.
+  pub fn stroke_polyline_styled(
+    &mut self,
+    points: &[f32],
+    line_thickness: f32,
+    color: RGBAColor,
+    style: LineStyle,
   ) {
     if color.a == 0 || line_thickness <= 0_f32 {
       return;
@@ -424,6 +574,7 @@ impl CommandBuffer {
         .zip(points.iter().skip(1).step_by(2))
         .map(|(&x, &y)| Vec2I16::new(x as i16, y as i16))
         .collect(),
+      style,
     };
 
     self.base.push(Command::Polyline(cmd));
@@ -479,6 +630,82 @@ impl CommandBuffer {
     self.base.push(Command::RectFilled(cmd));
   }
 
+  /// Drop shadow of `rect`, moved by `offset` and blurred over `blur` pixels
+  /// around it. Draw it before the rectangle casting it.
+  pub fn fill_rect_shadow(
+    &mut self,
+    rect: RectangleF32,
+    rounding: f32,
+    color: RGBAColor,
+    blur: f32,
+    offset: Vec2F32,
+  ) {
+    if color.a == 0 || rect.w == 0_f32 || rect.h == 0_f32 {
+      return;
+    }
+
+    let blur = blur.max(0_f32);
+    let shadow =
+      RectangleF32::new(rect.x + offset.x, rect.y + offset.y, rect.w, rect.h);
+    let extent = RectangleF32::new(
+      shadow.x - blur,
+      shadow.y - blur,
+      shadow.w + 2_f32 * blur,
+      shadow.h + 2_f32 * blur,
+    );
+    let is_clipped =
+      self.clip.map_or(false, |clip_r| !clip_r.intersect(&extent));
+    if is_clipped {
+      return;
+    }
+
+    let cmd = CmdRectShadow {
+      rounding: rounding as u16,
+      blur: blur as u16,
+      x: shadow.x as i16,
+      y: shadow.y as i16,
+      w: shadow.w as u16,
+      h: shadow.h as u16,
+      color,
+    };
+
+    self.base.push(Command::RectShadow(cmd));
+  }
+
+  /// Fills a rectangle with a different rounding for each corner, in the
+  /// order top-left, top-right, bottom-right, bottom-left.
+  pub fn fill_rect_corners(
+    &mut self,
+    rect: RectangleF32,
+    radii: [f32; 4],
+    color: RGBAColor,
+  ) {
+    if color.a == 0 || rect.w == 0_f32 || rect.h == 0_f32 {
+      return;
+    }
+
+    let is_clipped = self.clip.map_or(false, |clip_r| !clip_r.intersect(&rect));
+    if is_clipped {
+      return;
+    }
+
+    let cmd = CmdRectFilledCorners {
+      radii: [
+        radii[0] as u16,
+        radii[1] as u16,
+        radii[2] as u16,
+        radii[3] as u16,
+      ],
+      x: rect.x as i16,
+      y: rect.y as i16,
+      w: rect.w as u16,
+      h: rect.h as u16,
+      color,
+    };
+
+    self.base.push(Command::RectFilledCorners(cmd));
+  }
+
   pub fn fill_rect_multicolor(
     &mut self,
     rect: RectangleF32,
@@ -511,6 +738,17 @@ impl CommandBuffer {
   }
 
   pub fn fill_circle(&mut self, r: RectangleF32, color: RGBAColor) {
+    self.fill_circle_segments(r, color, 0);
+  }
+
+  /// Like `fill_circle`, but with a fixed segment count instead of the one
+  /// picked at conversion time.
+  pub fn fill_circle_segments(
+    &mut self,
+    r: RectangleF32,
+    color: RGBAColor,
+    segments: u16,
+  ) {
     if color.a == 0 || r.w == 0_f32 || r.h == 0_f32 {
       return;
     }
@@ -526,6 +764,7 @@ impl CommandBuffer {
       w: r.w as u16,
       h: r.h as u16,
       color,
+      segments,
     };
 
     self.base.push(Command::CircleFilled(cmd));
@@ -625,15 +864,123 @@ impl CommandBuffer {
     self.base.push(Command::Image(cmd));
   }
 
+  /// Like `draw_image()` but the image is tinted with the RGB of `rgb_tint`
+  /// and drawn with opacity `alpha`, e.g. to fade out a colored icon.
+  pub fn draw_image_ex(
+    &mut self,
+    r: RectangleF32,
+    img: Image,
+    rgb_tint: RGBAColor,
+    alpha: f32,
+  ) {
+    self.draw_image(r, img, rgb_tint.with_alpha(alpha));
+  }
+
+  /// Textured triangle mesh, for custom shapes like icons or minimaps.
+  /// Skipped if its bounding box is entirely outside the clip rect. The
+  /// indices refer to `vertices`, a mesh with indices past its last vertex is
+  /// not drawn.
+  pub fn draw_mesh(
+    &mut self,
+    texture: GenericHandle,
+    vertices: &[VertexPTC],
+    indices: &[DrawIndexType],
+  ) {
+    if vertices.is_empty() || indices.is_empty() {
+      return;
+    }
+
+    let (min, max) = vertices.iter().fold(
+      (vertices[0].pos, vertices[0].pos),
+      |(min, max), v| {
+        (
+          Vec2F32::new(min.x.min(v.pos.x), min.y.min(v.pos.y)),
+          Vec2F32::new(max.x.max(v.pos.x), max.y.max(v.pos.y)),
+        )
+      },
+    );
+    let bbox = RectangleF32::new(min.x, min.y, max.x - min.x, max.y - min.y);
+    let is_clipped = self.clip.map_or(false, |clip_r| !clip_r.intersect(&bbox));
+    if is_clipped {
+      return;
+    }
+
+    self.base.push(Command::Mesh(CmdMesh {
+      texture,
+      vertices: vertices.to_vec(),
+      indices: indices.to_vec(),
+    }));
+  }
+
   pub fn draw_text(
     &mut self,
     r: RectangleF32,
     s: &str,
-    font: Font,
+    font: &Font,
+    background: RGBAColor,
+    foreground: RGBAColor,
+  ) {
+    self.draw_text_directed(
+      r,
+      s,
+      font,
+      background,
+      foreground,
+      TextDirection::LeftToRight,
+    );
+  }
+
+  /// Draws text laid out in the given base direction. Right to left text
+  /// starts at the right edge of the rectangle.
+  pub fn draw_text_directed(
+    &mut self,
+    r: RectangleF32,
+    s: &str,
+    font: &Font,
+    background: RGBAColor,
+    foreground: RGBAColor,
+    direction: TextDirection,
+  ) {
+    self.push_text(r, s, font, background, foreground, direction, vec![]);
+  }
+
+  /// Draws text where the chars covered by `spans` use the span's color
+  /// instead of the foreground (for syntax highlighting and the like).
+  pub fn draw_text_spans(
+    &mut self,
+    r: RectangleF32,
+    s: &str,
+    font: &Font,
     background: RGBAColor,
     foreground: RGBAColor,
+    spans: &[TextColorSpan],
   ) {
-    if s.is_empty() || background.a == 0 || foreground.a == 0 {
+    self.push_text(
+      r,
+      s,
+      font,
+      background,
+      foreground,
+      TextDirection::LeftToRight,
+      spans.to_vec(),
+    );
+  }
+
+  fn push_text(
+    &mut self,
+    r: RectangleF32,
+    s: &str,
+    font: &Font,
+    background: RGBAColor,
+    foreground: RGBAColor,
+    direction: TextDirection,
+    spans: Vec<TextColorSpan>,
+  ) {
+    // whitespace only text has no visible glyphs
+    if s.chars().all(char::is_whitespace)
+      || background.a == 0
+      || foreground.a == 0
+    {
       return;
     }
 
@@ -642,7 +989,7 @@ impl CommandBuffer {
     }
 
     let cmd = CmdText {
-      font,
+      font: font.clone(),
       background,
       foreground,
       x: r.x as i16,
@@ -651,11 +998,86 @@ impl CommandBuffer {
       h: r.h as u16,
       height: 0f32,
       text: font.clamped_string(s, r.w),
+      direction,
+      spans,
     };
 
     self.base.push(Command::Text(cmd));
   }
 
+  /// Draws text cut down to fit the width of the rectangle. If `ellipsis` is
+  /// true and the text had to be cut, a trailing ellipsis marks the
+  /// truncation.
+  pub fn draw_text_clamped(
+    &mut self,
+    r: RectangleF32,
+    s: &str,
+    font: &Font,
+    background: RGBAColor,
+    foreground: RGBAColor,
+    ellipsis: bool,
+  ) {
+    const ELLIPSIS: char = '\u{2026}';
+
+    // without room for the ellipsis the text is only cut
+    let ellipsis_width = font.query(ELLIPSIS).xadvance;
+    if !ellipsis || font.text_width(s) <= r.w || ellipsis_width >= r.w {
+      let clamped = font.clamped_string(s, r.w);
+      self.draw_text(r, &clamped, font, background, foreground);
+      return;
+    }
+
+    let mut clamped = font.clamped_string(s, r.w - ellipsis_width);
+    clamped.push(ELLIPSIS);
+    self.draw_text(r, &clamped, font, background, foreground);
+  }
+
+  /// Replaces every color of the commands from index `first` on with the
+  /// result of `f`.
+  pub fn map_colors<F>(&mut self, first: usize, f: F)
+  where
+    F: Fn(RGBAColor) -> RGBAColor,
+  {
+    let f32_color = |c: RGBAColorF32| RGBAColorF32::from(f(RGBAColor::from(c)));
+
+    self.base.iter_mut().skip(first).for_each(|cmd| match cmd {
+      Command::Nop | Command::Scissor(_) => {}
+      Command::Line(c) => c.color = f(c.color),
+      Command::Curve(c) => c.color = f(c.color),
+      Command::Rect(c) => c.color = f(c.color),
+      Command::RectFilled(c) => c.color = f(c.color),
+      Command::RectFilledCorners(c) => c.color = f(c.color),
+      Command::RectShadow(c) => c.color = f(c.color),
+      Command::RectMulticolor(c) => {
+        c.left = f(c.left);
+        c.top = f(c.top);
+        c.bottom = f(c.bottom);
+        c.right = f(c.right);
+      }
+      Command::Triangle(c) => c.color = f(c.color),
+      Command::TriangleFilled(c) => c.color = f(c.color),
+      Command::Circle(c) => c.color = f(c.color),
+      Command::CircleFilled(c) => c.color = f(c.color),
+      Command::Arc(c) => c.color = f(c.color),
+      Command::ArcFilled(c) => c.color = f(c.color),
+      Command::Polygon(c) => c.color = f(c.color),
+      Command::PolygonFilled(c) => c.color = f(c.color),
+      Command::Polyline(c) => c.color = f(c.color),
+      Command::Image(c) => c.color = f(c.color),
+      Command::Text(c) => {
+        c.background = f(c.background);
+        c.foreground = f(c.foreground);
+        c.spans
+          .iter_mut()
+          .for_each(|(_, color)| *color = f32_color(*color));
+      }
+      Command::Mesh(c) => c
+        .vertices
+        .iter_mut()
+        .for_each(|v| v.color = f32_color(v.color)),
+    });
+  }
+
   pub fn push_scissor(&mut self, r: RectangleF32) {
     self.clip.replace(r);
 
@@ -671,3 +1093,57 @@ impl CommandBuffer {
 
 #[cfg(feature = "VERTEX_BUFFER_OUTPUT")]
 mod vertex_buffer_output {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::text_engine::FontAtlas;
+
+  #[test]
+  fn test_draw_text_clamped_ellipsis() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut cmds = CommandBuffer::new(Some(Consts::null_rect()), 16);
+
+    cmds.draw_text_clamped(
+      RectangleF32::new(0f32, 0f32, 60f32, 16f32),
+      "a rather long label",
+      &font,
+      RGBAColor::new(0, 0, 0),
+      RGBAColor::new(255, 255, 255),
+      true,
+    );
+
+    match &cmds.base[0] {
+      Command::Text(txt) => {
+        assert!(txt.text.ends_with('\u{2026}'));
+        assert!(font.text_width(&txt.text) <= 60f32);
+      }
+      _ => panic!("expected a text command"),
+    }
+  }
+
+  #[test]
+  fn test_draw_text_clamped_cuts_text() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let draw = |font: &Font, w: f32, ellipsis: bool| {
+      let mut cmds = CommandBuffer::new(Some(Consts::null_rect()), 16);
+      cmds.draw_text_clamped(
+        RectangleF32::new(0f32, 0f32, w, 16f32),
+        "a rather long label",
+        font,
+        RGBAColor::new(0, 0, 0),
+        RGBAColor::new(255, 255, 255),
+        ellipsis,
+      );
+      match &cmds.base[0] {
+        Command::Text(txt) => txt.text.clone(),
+        _ => panic!("expected a text command"),
+      }
+    };
+
+    assert_eq!(draw(&font, 60f32, false), "a rathe");
+
+    // no room for the ellipsis next to the first char
+    assert_eq!(draw(&font, 8f32, true), "a");
+  }
+}
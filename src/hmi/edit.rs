@@ -0,0 +1,91 @@
+// Cursor movement helpers for the edit widget. All positions are char
+// indices, never byte indices, so multi-byte UTF-8 characters are never
+// split.
+
+/// Number of chars in the text (the last valid cursor position).
+pub fn char_count(text: &str) -> usize {
+  text.chars().count()
+}
+
+/// Converts a char index into the matching byte offset, clamped to the end of
+/// the text.
+pub fn byte_offset(text: &str, char_pos: usize) -> usize {
+  text
+    .char_indices()
+    .nth(char_pos)
+    .map_or(text.len(), |(offset, _)| offset)
+}
+
+pub fn next_char(text: &str, pos: usize) -> usize {
+  (pos + 1).min(char_count(text))
+}
+
+pub fn prev_char(pos: usize) -> usize {
+  pos.saturating_sub(1)
+}
+
+/// Start of the next word: skips the rest of the current word and the
+/// whitespace after it.
+pub fn next_word(text: &str, pos: usize) -> usize {
+  let mut chars = text.chars().skip(pos).peekable();
+  let mut pos = pos;
+
+  while chars.peek().map_or(false, |c| !c.is_whitespace()) {
+    chars.next();
+    pos += 1;
+  }
+
+  while chars.peek().map_or(false, |c| c.is_whitespace()) {
+    chars.next();
+    pos += 1;
+  }
+
+  pos
+}
+
+/// Start of the current word, or of the previous one if the cursor already
+/// is at the beginning of a word.
+pub fn prev_word(text: &str, pos: usize) -> usize {
+  let pos = pos.min(char_count(text));
+  let mut chars = text.chars().take(pos).collect::<Vec<_>>();
+  let mut pos = pos;
+
+  while chars.last().map_or(false, |c| c.is_whitespace()) {
+    chars.pop();
+    pos -= 1;
+  }
+
+  while chars.last().map_or(false, |c| !c.is_whitespace()) {
+    chars.pop();
+    pos -= 1;
+  }
+
+  pos
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_word_jumps() {
+    let txt = "hello world";
+    assert_eq!(next_word(txt, 0), 6);
+    assert_eq!(next_word(txt, 6), 11);
+    assert_eq!(next_word(txt, 11), 11);
+    assert_eq!(prev_word(txt, 11), 6);
+    assert_eq!(prev_word(txt, 6), 0);
+    assert_eq!(prev_word(txt, 0), 0);
+  }
+
+  #[test]
+  fn test_multibyte_chars() {
+    let txt = "héllo wörld";
+    assert_eq!(char_count(txt), 11);
+    assert_eq!(next_word(txt, 0), 6);
+    assert_eq!(prev_word(txt, 9), 6);
+    assert_eq!(next_char(txt, 1), 2);
+    assert_eq!(byte_offset(txt, 2), 3);
+    assert_eq!(&txt[byte_offset(txt, 6) ..], "wörld");
+  }
+}
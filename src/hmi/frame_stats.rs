@@ -0,0 +1,81 @@
+/// Ring buffer of the most recent frame times (in seconds).
+#[derive(Copy, Clone, Debug)]
+pub struct FrameStats {
+  times: [f32; FrameStats::HISTORY_SIZE],
+  head:  usize,
+  count: usize,
+}
+
+impl FrameStats {
+  pub const HISTORY_SIZE: usize = 64;
+
+  pub fn new() -> FrameStats {
+    FrameStats {
+      times: [0f32; FrameStats::HISTORY_SIZE],
+      head:  0,
+      count: 0,
+    }
+  }
+
+  pub fn push(&mut self, dt: f32) {
+    self.times[self.head] = dt;
+    self.head = (self.head + 1) % Self::HISTORY_SIZE;
+    self.count = (self.count + 1).min(Self::HISTORY_SIZE);
+  }
+
+  pub fn len(&self) -> usize {
+    self.count
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.count == 0
+  }
+
+  /// Frame times from oldest to newest.
+  pub fn iter<'a>(&'a self) -> impl Iterator<Item = f32> + 'a {
+    let first =
+      (self.head + Self::HISTORY_SIZE - self.count) % Self::HISTORY_SIZE;
+    (0 .. self.count).map(move |i| self.times[(first + i) % Self::HISTORY_SIZE])
+  }
+
+  pub fn average_frame_time(&self) -> f32 {
+    if self.count == 0 {
+      0f32
+    } else {
+      self.iter().sum::<f32>() / self.count as f32
+    }
+  }
+
+  pub fn max_frame_time(&self) -> f32 {
+    self.iter().fold(0f32, |max_time, t| max_time.max(t))
+  }
+
+  pub fn fps(&self) -> f32 {
+    let avg = self.average_frame_time();
+    if avg > 0f32 {
+      1f32 / avg
+    } else {
+      0f32
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rolling_average() {
+    let mut stats = FrameStats::new();
+    assert_eq!(stats.fps(), 0f32);
+
+    stats.push(0.02f32);
+    stats.push(0.04f32);
+    assert!((stats.average_frame_time() - 0.03f32).abs() < 1.0e-6f32);
+
+    // old values fall out of the window
+    (0 .. FrameStats::HISTORY_SIZE).for_each(|_| stats.push(0.01f32));
+    assert_eq!(stats.len(), FrameStats::HISTORY_SIZE);
+    assert!((stats.fps() - 100f32).abs() < 0.01f32);
+  }
+}
@@ -0,0 +1,377 @@
+use crate::{
+  hmi::{
+    base::GenericHandle,
+    commands::{Command, LineStyle},
+    text_engine::TextDirection,
+    vertex_output::{DrawCommand, DrawIndexType, DrawList, DrawListStats},
+  },
+  math::{
+    colors::{RGBAColor, RGBAColorF32},
+    vec2::Vec2I16,
+    vertex_types::VertexPTC,
+  },
+};
+
+use std::{
+  collections::{hash_map::DefaultHasher, HashMap},
+  hash::{Hash, Hasher},
+};
+
+struct CachedGeometry {
+  hash:     u64,
+  seen:     bool,
+  vertices: Vec<VertexPTC>,
+  indices:  Vec<DrawIndexType>,
+  commands: Vec<DrawCommand>,
+  stats:    DrawListStats,
+}
+
+/// Keeps the converted geometry of each window around, so windows whose
+/// commands did not change since the last frame skip the conversion step.
+pub struct GeometryCache {
+  entries:       HashMap<usize, CachedGeometry>,
+  regenerations: u32,
+  stats:         DrawListStats,
+}
+
+impl GeometryCache {
+  pub fn new() -> GeometryCache {
+    GeometryCache {
+      entries:       HashMap::new(),
+      regenerations: 0,
+      stats:         DrawListStats::default(),
+    }
+  }
+
+  /// Number of times geometry had to be generated from commands.
+  pub fn regenerations(&self) -> u32 {
+    self.regenerations
+  }
+
+  /// Stats of all the geometry appended since `reset_stats()`, cached or
+  /// not.
+  pub fn stats(&self) -> DrawListStats {
+    self.stats
+  }
+
+  pub fn reset_stats(&mut self) {
+    self.stats = DrawListStats::default();
+  }
+
+  /// Hashes the commands together with the generation of the draw list's
+  /// settings, the fonts hash the state of their atlas.
+  fn hash_commands(cmds: &[*const Command], generation: u32) -> u64 {
+    let mut state = DefaultHasher::new();
+    generation.hash(&mut state);
+    cmds
+      .iter()
+      .for_each(|cmd| Self::hash_command(unsafe { &**cmd }, &mut state));
+    state.finish()
+  }
+
+  fn hash_command(cmd: &Command, state: &mut DefaultHasher) {
+    let color = |state: &mut DefaultHasher, c: RGBAColor| {
+      [c.r, c.g, c.b, c.a].hash(state);
+    };
+    let color_f32 = |state: &mut DefaultHasher, c: RGBAColorF32| {
+      [c.r, c.g, c.b, c.a]
+        .iter()
+        .for_each(|v| v.to_bits().hash(state));
+    };
+    let point = |state: &mut DefaultHasher, p: Vec2I16| {
+      (p.x, p.y).hash(state);
+    };
+    let handle = |state: &mut DefaultHasher, h: GenericHandle| match h {
+      GenericHandle::Ptr(ptr) => (0u8, ptr).hash(state),
+      GenericHandle::Id(id) => (1u8, id).hash(state),
+    };
+    let style = |state: &mut DefaultHasher, s: LineStyle| match s {
+      LineStyle::Solid => 0u8.hash(state),
+      LineStyle::Dashed { dash, gap } => {
+        (1u8, dash.to_bits(), gap.to_bits()).hash(state)
+      }
+      LineStyle::Dotted => 2u8.hash(state),
+    };
+
+    std::mem::discriminant(cmd).hash(state);
+    match cmd {
+      Command::Nop => {}
+      Command::Scissor(c) => (c.x, c.y, c.w, c.h).hash(state),
+      Command::Line(c) => {
+        c.line_thickness.hash(state);
+        point(state, c.begin);
+        point(state, c.end);
+        color(state, c.color);
+        style(state, c.style);
+      }
+      Command::Curve(c) => {
+        c.line_thickness.hash(state);
+        point(state, c.begin);
+        point(state, c.end);
+        c.ctrl.iter().for_each(|p| point(state, *p));
+        color(state, c.color);
+      }
+      Command::Rect(c) => {
+        (c.rounding, c.line_thickness, c.x, c.y, c.w, c.h).hash(state);
+        color(state, c.color);
+      }
+      Command::RectFilled(c) => {
+        (c.rounding, c.x, c.y, c.w, c.h).hash(state);
+        color(state, c.color);
+      }
+      Command::RectFilledCorners(c) => {
+        (c.radii, c.x, c.y, c.w, c.h).hash(state);
+        color(state, c.color);
+      }
+      Command::RectShadow(c) => {
+        (c.rounding, c.blur, c.x, c.y, c.w, c.h).hash(state);
+        color(state, c.color);
+      }
+      Command::RectMulticolor(c) => {
+        (c.x, c.y, c.w, c.h).hash(state);
+        [c.left, c.top, c.bottom, c.right]
+          .iter()
+          .for_each(|c| color(state, *c));
+      }
+      Command::Triangle(c) => {
+        c.line_thickness.hash(state);
+        [c.a, c.b, c.c].iter().for_each(|p| point(state, *p));
+        color(state, c.color);
+      }
+      Command::TriangleFilled(c) => {
+        [c.a, c.b, c.c].iter().for_each(|p| point(state, *p));
+        color(state, c.color);
+      }
+      Command::Circle(c) => {
+        (c.x, c.y, c.line_thickness, c.w, c.h, c.segments).hash(state);
+        color(state, c.color);
+      }
+      Command::CircleFilled(c) => {
+        (c.x, c.y, c.w, c.h, c.segments).hash(state);
+        color(state, c.color);
+      }
+      Command::Arc(c) => {
+        (c.cx, c.cy, c.r, c.line_thickness).hash(state);
+        (c.a[0].to_bits(), c.a[1].to_bits()).hash(state);
+        color(state, c.color);
+      }
+      Command::ArcFilled(c) => {
+        (c.cx, c.cy, c.r).hash(state);
+        (c.a[0].to_bits(), c.a[1].to_bits()).hash(state);
+        color(state, c.color);
+      }
+      Command::Polygon(c) => {
+        c.line_thickness.hash(state);
+        c.points.len().hash(state);
+        c.points.iter().for_each(|p| point(state, *p));
+        color(state, c.color);
+      }
+      Command::PolygonFilled(c) => {
+        c.points.len().hash(state);
+        c.points.iter().for_each(|p| point(state, *p));
+        color(state, c.color);
+      }
+      Command::Polyline(c) => {
+        c.line_thickness.hash(state);
+        c.points.len().hash(state);
+        c.points.iter().for_each(|p| point(state, *p));
+        color(state, c.color);
+        style(state, c.style);
+      }
+      Command::Image(c) => {
+        (c.x, c.y, c.w, c.h).hash(state);
+        handle(state, c.img.handle);
+        (c.img.w, c.img.h, c.img.region).hash(state);
+        color(state, c.color);
+      }
+      Command::Text(c) => {
+        c.font.hash(state);
+        color(state, c.background);
+        color(state, c.foreground);
+        (c.x, c.y, c.w, c.h, c.height.to_bits()).hash(state);
+        c.text.hash(state);
+        (c.direction == TextDirection::RightToLeft).hash(state);
+        c.spans.len().hash(state);
+        c.spans.iter().for_each(|(range, span_color)| {
+          (range.start, range.end).hash(state);
+          color_f32(state, *span_color);
+        });
+      }
+      Command::Mesh(c) => {
+        handle(state, c.texture);
+        c.vertices.len().hash(state);
+        c.vertices.iter().for_each(|vtx| {
+          [vtx.pos.x, vtx.pos.y, vtx.texcoords.x, vtx.texcoords.y]
+            .iter()
+            .for_each(|v| v.to_bits().hash(state));
+          color_f32(state, vtx.color);
+        });
+        c.indices.hash(state);
+      }
+    }
+  }
+
+  /// Drops the entries not used since the last call, the windows they belong
+  /// to were closed or not drawn in any viewport. Call once per frame.
+  pub fn end_frame(&mut self) {
+    self.entries.retain(|_, e| e.seen);
+    self.entries.values_mut().for_each(|e| e.seen = false);
+  }
+
+  /// Appends the geometry for the commands to the output buffers, scaling
+  /// the alpha of the vertices by `alpha` (and the color too, when the draw
+  /// list emits premultiplied colors). Commands without a key (like the
+  /// overlay) are always converted. Geometry that doesn't fit the 16 bit
+  /// index range of the output anymore is dropped.
+  pub fn convert(
+    &mut self,
+    draw_list: &mut DrawList,
+    key: Option<usize>,
+    alpha: f32,
+    cmds: &[*const Command],
+    out_cmds: &mut Vec<DrawCommand>,
+    out_vertices: &mut Vec<VertexPTC>,
+    out_indices: &mut Vec<DrawIndexType>,
+  ) {
+    let color_scale = if draw_list.premultiplied_alpha() {
+      alpha
+    } else {
+      1f32
+    };
+    let scale = RGBAColorF32::new_with_alpha(
+      color_scale,
+      color_scale,
+      color_scale,
+      alpha,
+    );
+
+    let key = match key {
+      Some(key) => key,
+      None => {
+        self.regenerations += 1;
+        let (mut commands, mut vertices, mut indices) =
+          (vec![], vec![], vec![]);
+        draw_list.convert(cmds, &mut vertices, &mut indices, &mut commands);
+        self.stats.merge(&draw_list.last_stats());
+        Self::append(
+          &commands,
+          &vertices,
+          &indices,
+          scale,
+          out_cmds,
+          out_vertices,
+          out_indices,
+        );
+        return;
+      }
+    };
+
+    let hash = Self::hash_commands(cmds, draw_list.generation());
+    let is_cached = self.entries.get(&key).map_or(false, |e| e.hash == hash);
+
+    if !is_cached {
+      self.regenerations += 1;
+      let (mut commands, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      draw_list.convert(cmds, &mut vertices, &mut indices, &mut commands);
+      self.entries.insert(
+        key,
+        CachedGeometry {
+          hash,
+          seen: false,
+          vertices,
+          indices,
+          commands,
+          stats: draw_list.last_stats(),
+        },
+      );
+    }
+
+    let entry = self.entries.get_mut(&key).unwrap();
+    entry.seen = true;
+    self.stats.merge(&entry.stats);
+    Self::append(
+      &entry.commands,
+      &entry.vertices,
+      &entry.indices,
+      scale,
+      out_cmds,
+      out_vertices,
+      out_indices,
+    );
+  }
+
+  fn append(
+    commands: &[DrawCommand],
+    vertices: &[VertexPTC],
+    indices: &[DrawIndexType],
+    scale: RGBAColorF32,
+    out_cmds: &mut Vec<DrawCommand>,
+    out_vertices: &mut Vec<VertexPTC>,
+    out_indices: &mut Vec<DrawIndexType>,
+  ) {
+    // the draw commands have no vertex offset, the indices address the
+    // whole output buffer
+    if out_vertices.len() + vertices.len() > DrawIndexType::MAX as usize + 1 {
+      return;
+    }
+
+    let vertex_base = out_vertices.len() as DrawIndexType;
+
+    out_vertices.extend(vertices.iter().map(|vtx| {
+      let mut vtx = *vtx;
+      vtx.color.r *= scale.r;
+      vtx.color.g *= scale.g;
+      vtx.color.b *= scale.b;
+      vtx.color.a *= scale.a;
+      vtx
+    }));
+    out_indices.extend(indices.iter().map(|idx| idx + vertex_base));
+    out_cmds.extend_from_slice(commands);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::base::Consts;
+
+  #[test]
+  fn test_geometry_past_the_index_range_is_dropped() {
+    let vertex = VertexPTC {
+      pos:       Default::default(),
+      texcoords: Default::default(),
+      color:     RGBAColorF32::new(1f32, 1f32, 1f32),
+    };
+    let commands = [DrawCommand {
+      element_count: 3,
+      clip_rect:     Consts::null_rect(),
+      texture:       GenericHandle::Id(1),
+    }];
+    let scale = RGBAColorF32::new_with_alpha(1f32, 1f32, 1f32, 1f32);
+
+    let mut out_cmds = vec![];
+    let mut out_vertices = vec![vertex; DrawIndexType::MAX as usize - 2];
+    let mut out_indices = vec![];
+    let append = |out_cmds: &mut Vec<DrawCommand>,
+                  out_vertices: &mut Vec<VertexPTC>,
+                  out_indices: &mut Vec<DrawIndexType>| {
+      GeometryCache::append(
+        &commands,
+        &[vertex; 3],
+        &[0, 1, 2],
+        scale,
+        out_cmds,
+        out_vertices,
+        out_indices,
+      );
+    };
+
+    // the last vertices that can be indexed
+    append(&mut out_cmds, &mut out_vertices, &mut out_indices);
+    assert_eq!(out_indices, vec![65533, 65534, 65535]);
+    // no room left, the indices would wrap around
+    append(&mut out_cmds, &mut out_vertices, &mut out_indices);
+    assert_eq!(out_vertices.len(), DrawIndexType::MAX as usize + 1);
+    assert_eq!((out_cmds.len(), out_indices.len()), (1, 3));
+  }
+}
@@ -3,7 +3,11 @@ use crate::{
     base::GenericHandle, commands::CommandBuffer, ui_context::WindowPtr,
     window::Window,
   },
-  math::{colors::RGBAColor, rectangle::RectangleF32},
+  math::{
+    colors::RGBAColor,
+    rectangle::{RectangleF32, RectangleI32},
+    vec2::Vec2F32,
+  },
 };
 
 #[derive(Copy, Debug, Clone)]
@@ -37,6 +41,49 @@ impl Image {
     }
   }
 
+  /// Image referencing a region (in pixels) of a texture of size full_w x
+  /// full_h, like a sprite in a sprite sheet.
+  pub fn sub_image(
+    handle: GenericHandle,
+    full_w: u16,
+    full_h: u16,
+    region: RectangleI32,
+  ) -> Image {
+    Image {
+      handle,
+      w: full_w,
+      h: full_h,
+      region: [
+        region.x as u16,
+        region.y as u16,
+        region.w as u16,
+        region.h as u16,
+      ],
+    }
+  }
+
+  /// Sprite sheet animation. `frame_rect` is the region of the first frame,
+  /// the following frames are laid out left to right, `columns` per row.
+  pub fn animated(
+    handle: GenericHandle,
+    full_w: u16,
+    full_h: u16,
+    frame_rect: RectangleI32,
+    frame_count: u16,
+    columns: u16,
+  ) -> AnimatedImage {
+    AnimatedImage {
+      first_frame: Self::sub_image(handle, full_w, full_h, frame_rect),
+      frame_count: frame_count.max(1),
+      columns:     columns.max(1),
+    }
+  }
+
+  /// Image covering the whole texture.
+  pub fn whole(handle: GenericHandle, w: u16, h: u16) -> Image {
+    Self::sub_image(handle, w, h, RectangleI32::new(0, 0, w as i32, h as i32))
+  }
+
   pub fn image_handle(handle: GenericHandle) -> Image {
     Image {
       handle,
@@ -57,4 +104,89 @@ impl Image {
   pub fn is_subimage(&self) -> bool {
     self.w != 0 && self.h != 0
   }
+
+  pub fn texture_size(&self) -> (u16, u16) {
+    (self.w, self.h)
+  }
+
+  pub fn region_rect(&self) -> RectangleI32 {
+    RectangleI32::new(
+      self.region[0] as i32,
+      self.region[1] as i32,
+      self.region[2] as i32,
+      self.region[3] as i32,
+    )
+  }
+
+  /// Top left and bottom right texture coordinates of the image's region.
+  pub fn uv(&self) -> (Vec2F32, Vec2F32) {
+    if !self.is_subimage() {
+      return (Vec2F32::same(0_f32), Vec2F32::same(1_f32));
+    }
+
+    (
+      Vec2F32::new(
+        self.region[0] as f32 / self.w as f32,
+        self.region[1] as f32 / self.h as f32,
+      ),
+      Vec2F32::new(
+        (self.region[0] + self.region[2]) as f32 / self.w as f32,
+        (self.region[1] + self.region[3]) as f32 / self.h as f32,
+      ),
+    )
+  }
+}
+
+/// Frames of an animation stored in a sprite sheet, see `Image::animated()`.
+#[derive(Copy, Debug, Clone)]
+pub struct AnimatedImage {
+  pub first_frame: Image,
+  pub frame_count: u16,
+  pub columns:     u16,
+}
+
+impl AnimatedImage {
+  /// Sub image of the frame with index `frame_index`, wrapping around after
+  /// the last frame.
+  pub fn animated_frame(&self, frame_index: usize) -> Image {
+    let frame = (frame_index % self.frame_count as usize) as u16;
+    let (col, row) = (frame % self.columns, frame / self.columns);
+    let [x, y, w, h] = self.first_frame.region;
+
+    Image {
+      region: [x + col * w, y + row * h, w, h],
+      ..self.first_frame
+    }
+  }
+
+  pub fn frame_count(&self) -> usize {
+    self.frame_count as usize
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_animated_frames_wrap_to_next_row() {
+    let anim = Image::animated(
+      GenericHandle::Id(1),
+      256,
+      256,
+      RectangleI32::new(0, 64, 32, 32),
+      12,
+      4,
+    );
+
+    let first = anim.animated_frame(0);
+    let second = anim.animated_frame(1);
+    let next_row = anim.animated_frame(4);
+
+    assert_eq!(first.region, [0, 64, 32, 32]);
+    assert_eq!(second.region, [32, 64, 32, 32]);
+    assert_eq!(next_row.region, [0, 96, 32, 32]);
+    assert_ne!(first.region[1], next_row.region[1]);
+    assert_eq!(anim.animated_frame(12).region, first.region);
+  }
 }
@@ -17,6 +17,7 @@ pub enum KeyId {
   KeyDown,
   KeyLeft,
   KeyRight,
+  KeyEscape,
   // Shortcuts: text field
   KeyTextInsertMode,
   KeyTextReplaceMode,
@@ -49,17 +50,26 @@ pub enum MouseButtonId {
 
 #[derive(Copy, Debug, Clone)]
 pub struct MouseButton {
-  pub down:        bool,
-  pub clicked:     u32,
-  pub clicked_pos: Vec2F32,
+  pub down:          bool,
+  pub clicked:       u32,
+  pub clicked_pos:   Vec2F32,
+  /// Position where the button was last pressed.
+  pub down_pos:      Vec2F32,
+  /// Farthest the mouse got from `down_pos` while the button is held, as of
+  /// this frame and as of the previous one.
+  pub drag_max:      f32,
+  pub drag_max_prev: f32,
 }
 
 impl MouseButton {
   pub fn new() -> MouseButton {
     MouseButton {
-      down:        false,
-      clicked:     0,
-      clicked_pos: Vec2F32::same(0f32),
+      down:          false,
+      clicked:       0,
+      clicked_pos:   Vec2F32::same(0f32),
+      down_pos:      Vec2F32::same(0f32),
+      drag_max:      0f32,
+      drag_max_prev: 0f32,
     }
   }
 }
@@ -93,42 +103,124 @@ impl MouseState {
 
 #[derive(Copy, Debug, Clone)]
 pub struct KeyState {
-  pub down:    bool,
-  pub clicked: u32,
+  pub down:     bool,
+  pub clicked:  u32,
+  /// seconds the key has been held since it was pressed
+  pub held:     f32,
+  /// a repeat interval elapsed during the last `update_key_repeat()`
+  pub repeated: bool,
 }
 
 impl KeyState {
   pub fn new() -> Self {
     Self {
-      down:    false,
-      clicked: 0,
+      down:     false,
+      clicked:  0,
+      held:     0f32,
+      repeated: false,
     }
   }
 }
 
 #[derive(Copy, Debug, Clone)]
 pub struct KeyboardState {
-  pub keys:     [KeyState; KeyId::KeyMax as usize],
-  pub text:     [char; KeyboardState::INPUT_MAX as usize],
-  pub text_len: i32,
+  pub keys:         [KeyState; KeyId::KeyMax as usize],
+  pub text:         [char; KeyboardState::INPUT_MAX as usize],
+  pub text_len:     i32,
+  /// seconds a key has to be held before it starts repeating
+  pub repeat_delay: f32,
+  /// repeats per second once the delay has passed
+  pub repeat_rate:  f32,
 }
 
 impl KeyboardState {
+  pub const DEFAULT_REPEAT_DELAY: f32 = 0.5f32;
+  pub const DEFAULT_REPEAT_RATE: f32 = 20f32;
   pub const INPUT_MAX: u32 = 16;
 
   pub fn new() -> Self {
     Self {
-      keys:     [KeyState::new(); KeyId::KeyMax as usize],
-      text:     [0u8 as char; KeyboardState::INPUT_MAX as usize],
-      text_len: 0,
+      keys:         [KeyState::new(); KeyId::KeyMax as usize],
+      text:         [0u8 as char; KeyboardState::INPUT_MAX as usize],
+      text_len:     0,
+      repeat_delay: KeyboardState::DEFAULT_REPEAT_DELAY,
+      repeat_rate:  KeyboardState::DEFAULT_REPEAT_RATE,
     }
   }
 }
 
 #[derive(Copy, Debug, Clone)]
+pub struct TouchPoint {
+  pub id:     u64,
+  pub pos:    Vec2F32,
+  pub active: bool,
+}
+
+impl TouchPoint {
+  pub fn new() -> Self {
+    Self {
+      id:     0,
+      pos:    Vec2F32::same(0f32),
+      active: false,
+    }
+  }
+}
+
+/// Touch points for the current and the previous frame. Gestures are derived
+/// by comparing the two, so backends without touch input never touch this.
+#[derive(Copy, Debug, Clone)]
+pub struct TouchState {
+  pub points: [TouchPoint; TouchState::MAX_POINTS],
+  pub prev:   [TouchPoint; TouchState::MAX_POINTS],
+}
+
+impl TouchState {
+  pub const MAX_POINTS: usize = 4;
+
+  pub fn new() -> Self {
+    Self {
+      points: [TouchPoint::new(); TouchState::MAX_POINTS],
+      prev:   [TouchPoint::new(); TouchState::MAX_POINTS],
+    }
+  }
+
+  fn active_count(pts: &[TouchPoint]) -> usize {
+    pts.iter().filter(|p| p.active).count()
+  }
+
+  fn find_prev(&self, id: u64) -> Option<&TouchPoint> {
+    self.prev.iter().find(|p| p.active && p.id == id)
+  }
+}
+
+/// Input reported by the backend, queued with `Input::queue_event` and
+/// applied in order at the start of the next frame.
+#[derive(Copy, Debug, Clone)]
+pub enum InputEvent {
+  MouseMove {
+    x: i32,
+    y: i32,
+  },
+  MouseButton {
+    id:   MouseButtonId,
+    x:    i32,
+    y:    i32,
+    down: bool,
+  },
+  Wheel(Vec2F32),
+  Key {
+    key:  KeyId,
+    down: bool,
+  },
+  Char(char),
+}
+
+#[derive(Debug, Clone)]
 pub struct Input {
   pub keyboard: KeyboardState,
   pub mouse:    MouseState,
+  pub touch:    TouchState,
+  events:       Vec<InputEvent>,
 }
 
 impl Input {
@@ -136,29 +228,58 @@ impl Input {
     Input {
       keyboard: KeyboardState::new(),
       mouse:    MouseState::new(),
+      touch:    TouchState::new(),
+      events:   Vec::new(),
     }
   }
 
+  /// Queues an event, it is applied by the next `begin()`. Backends can push
+  /// events as they arrive instead of calling `motion()`, `button()` and the
+  /// like between `begin()` and `end()`.
+  pub fn queue_event(&mut self, event: InputEvent) {
+    self.events.push(event);
+  }
+
+  /// Applies the queued events in the order they were received.
+  pub fn process_queue(&mut self) {
+    let events = std::mem::take(&mut self.events);
+    events.iter().for_each(|event| match *event {
+      InputEvent::MouseMove { x, y } => self.motion(x, y),
+      InputEvent::MouseButton { id, x, y, down } => self.button(id, x, y, down),
+      InputEvent::Wheel(delta) => self.scroll(delta),
+      InputEvent::Key { key, down } => self.key(key, down),
+      InputEvent::Char(c) => self.glyph(c),
+    });
+
+    // keep the allocation for the next frame
+    self.events = events;
+    self.events.clear();
+  }
+
   pub fn begin(&mut self) {
-    self
-      .mouse
-      .buttons
-      .iter_mut()
-      .for_each(|btn_state| btn_state.clicked = 0);
+    self.mouse.buttons.iter_mut().for_each(|btn_state| {
+      btn_state.clicked = 0;
+      btn_state.drag_max_prev = btn_state.drag_max;
+    });
 
     self.keyboard.text_len = 0;
     self.mouse.scroll_delta = Vec2F32::same(0f32);
     self.mouse.prev = self.mouse.pos;
     self.mouse.delta = Vec2F32::same(0f32);
 
-    self
-      .keyboard
-      .keys
-      .iter_mut()
-      .for_each(|key_state| key_state.clicked = 0);
+    self.keyboard.keys.iter_mut().for_each(|key_state| {
+      key_state.clicked = 0;
+    });
+
+    self.touch.prev = self.touch.points;
+    self.process_queue();
   }
 
   pub fn end(&mut self) {
+    // single finger drag scrolls
+    let pan = self.pan_delta();
+    self.mouse.scroll_delta += pan;
+
     if self.mouse.grab {
       self.mouse.grab = false;
     }
@@ -170,10 +291,50 @@ impl Input {
     }
   }
 
+  /// Advances the time the keys have been held by the frame time and flags
+  /// the keys whose repeat interval elapsed, see `is_key_repeated()`. Called
+  /// by `UiContext::clear()` once per frame, the keys flagged there repeat
+  /// during the following frame.
+  pub fn update_key_repeat(&mut self, delta_time_sec: f32) {
+    let delay = self.keyboard.repeat_delay;
+    let rate = self.keyboard.repeat_rate;
+    // number of repeats produced by holding a key for `held` seconds
+    let repeats = |held: f32| {
+      if held < delay {
+        0
+      } else {
+        ((held - delay) * rate) as u32 + 1
+      }
+    };
+
+    self.keyboard.keys.iter_mut().for_each(|key| {
+      let pressed =
+        (key.down && key.clicked != 0) || (!key.down && key.clicked >= 2);
+      if !key.down || pressed {
+        key.held = 0f32;
+        key.repeated = false;
+      } else {
+        let held = key.held + delta_time_sec;
+        key.repeated = repeats(held) > repeats(key.held);
+        key.held = held;
+      }
+    });
+  }
+
   pub fn motion(&mut self, x: i32, y: i32) {
     self.mouse.pos.x = x as f32;
     self.mouse.pos.y = y as f32;
     self.mouse.delta = self.mouse.pos - self.mouse.prev;
+
+    let pos = self.mouse.pos;
+    self
+      .mouse
+      .buttons
+      .iter_mut()
+      .filter(|btn| btn.down)
+      .for_each(|btn| {
+        btn.drag_max = btn.drag_max.max((pos - btn.down_pos).len());
+      });
   }
 
   pub fn key(&mut self, key: KeyId, down: bool) {
@@ -188,6 +349,11 @@ impl Input {
     }
 
     btn.clicked_pos = Vec2F32::new(x as f32, y as f32);
+    if down {
+      btn.down_pos = btn.clicked_pos;
+      btn.drag_max = 0f32;
+      btn.drag_max_prev = 0f32;
+    }
     btn.down = down;
     btn.clicked += 1;
   }
@@ -196,6 +362,63 @@ impl Input {
     self.mouse.scroll_delta += val;
   }
 
+  /// Updates (or releases when `down` is false) the touch point with the
+  /// given id.
+  pub fn touch(&mut self, id: u64, x: i32, y: i32, down: bool) {
+    let pos = Vec2F32::new(x as f32, y as f32);
+    let slot = self
+      .touch
+      .points
+      .iter()
+      .position(|p| p.active && p.id == id)
+      .or_else(|| {
+        if down {
+          self.touch.points.iter().position(|p| !p.active)
+        } else {
+          None
+        }
+      });
+
+    if let Some(idx) = slot {
+      self.touch.points[idx] = TouchPoint {
+        id,
+        pos,
+        active: down,
+      };
+    }
+  }
+
+  /// Movement of a single finger drag since the last frame.
+  pub fn pan_delta(&self) -> Vec2F32 {
+    if TouchState::active_count(&self.touch.points) != 1 {
+      return Vec2F32::same(0f32);
+    }
+
+    self
+      .touch
+      .points
+      .iter()
+      .find(|p| p.active)
+      .and_then(|p| self.touch.find_prev(p.id).map(|prev| p.pos - prev.pos))
+      .unwrap_or(Vec2F32::same(0f32))
+  }
+
+  /// Change in distance between two fingers since the last frame. Positive
+  /// values mean the fingers moved apart (zoom in).
+  pub fn pinch_delta(&self) -> f32 {
+    if TouchState::active_count(&self.touch.points) != 2 {
+      return 0f32;
+    }
+
+    let mut pts = self.touch.points.iter().filter(|p| p.active);
+    let (a, b) = (pts.next().unwrap(), pts.next().unwrap());
+
+    match (self.touch.find_prev(a.id), self.touch.find_prev(b.id)) {
+      (Some(pa), Some(pb)) => (a.pos - b.pos).len() - (pa.pos - pb.pos).len(),
+      _ => 0f32,
+    }
+  }
+
   pub fn glyph(&mut self, glyph: char) {
     if self.keyboard.text_len < KeyboardState::INPUT_MAX as i32 {
       self.keyboard.text[self.keyboard.text_len as usize] = glyph;
@@ -302,7 +525,61 @@ impl Input {
 
   pub fn is_mouse_released(&self, id: MouseButtonId) -> bool {
     let btn = &self.mouse.buttons[id as usize];
-    btn.down && btn.clicked != 0
+    !btn.down && btn.clicked != 0
+  }
+
+  /// True if the button was released inside the rectangle this frame, after
+  /// being pressed inside it.
+  pub fn has_mouse_up_in_rect(
+    &self,
+    id: MouseButtonId,
+    b: &RectangleF32,
+  ) -> bool {
+    let btn = &self.mouse.buttons[id as usize];
+    self.is_mouse_released(id)
+      && b.contains_point(btn.clicked_pos.x, btn.clicked_pos.y)
+      && b.contains_point(btn.down_pos.x, btn.down_pos.y)
+  }
+
+  /// True while the button is held and the mouse has moved more than
+  /// `threshold_px` away from where it was pressed, so small jitter while
+  /// clicking doesn't start a drag. Stays true until the button is released,
+  /// even if the mouse moves back.
+  pub fn is_mouse_dragging(
+    &self,
+    id: MouseButtonId,
+    threshold_px: f32,
+  ) -> bool {
+    let btn = &self.mouse.buttons[id as usize];
+    btn.down && btn.drag_max > threshold_px
+  }
+
+  /// Movement since the button was pressed, zero if it isn't held.
+  pub fn mouse_drag_delta(&self, id: MouseButtonId) -> Vec2F32 {
+    let btn = &self.mouse.buttons[id as usize];
+    if btn.down {
+      self.mouse.pos - btn.down_pos
+    } else {
+      Vec2F32::same(0f32)
+    }
+  }
+
+  /// How far to move something that is dragged with the button this frame.
+  /// On the frame the drag starts this is all the movement held back by the
+  /// threshold, afterwards the frame's mouse delta.
+  pub fn mouse_drag_step(
+    &self,
+    id: MouseButtonId,
+    threshold_px: f32,
+  ) -> Vec2F32 {
+    let btn = &self.mouse.buttons[id as usize];
+    if !self.is_mouse_dragging(id, threshold_px) {
+      Vec2F32::same(0f32)
+    } else if btn.drag_max_prev <= threshold_px {
+      self.mouse_drag_delta(id)
+    } else {
+      self.mouse.delta
+    }
   }
 
   pub fn is_key_pressed(&self, key: KeyId) -> bool {
@@ -315,8 +592,164 @@ impl Input {
     (!k.down && k.clicked != 0) || (k.down && k.clicked >= 2)
   }
 
+  /// True on the frame the key is pressed, then every
+  /// `1 / keyboard.repeat_rate` seconds once it has been held for
+  /// `keyboard.repeat_delay` seconds.
+  pub fn is_key_repeated(&self, key: KeyId) -> bool {
+    let k = &self.keyboard.keys[key as usize];
+    self.is_key_pressed(key) || (k.down && k.repeated)
+  }
+
   pub fn is_key_down(&self, key: KeyId) -> bool {
     let k = &self.keyboard.keys[key as usize];
     k.down
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_touch_pan_delta() {
+    let mut input = Input::new();
+    input.begin();
+    input.touch(7, 10, 10, true);
+    input.end();
+
+    input.begin();
+    input.touch(7, 15, 20, true);
+    input.end();
+
+    let pan = input.pan_delta();
+    assert_eq!(pan.x, 5f32);
+    assert_eq!(pan.y, 10f32);
+    assert_eq!(input.pinch_delta(), 0f32);
+  }
+
+  #[test]
+  fn test_mouse_up_in_rect() {
+    let r = RectangleF32::new(0f32, 0f32, 50f32, 50f32);
+    let mut input = Input::new();
+
+    input.begin();
+    input.button(MouseButtonId::ButtonLeft, 10, 10, true);
+    input.end();
+    assert!(!input.is_mouse_released(MouseButtonId::ButtonLeft));
+
+    input.begin();
+    input.button(MouseButtonId::ButtonLeft, 20, 20, false);
+    input.end();
+    assert!(input.is_mouse_released(MouseButtonId::ButtonLeft));
+    assert!(input.has_mouse_up_in_rect(MouseButtonId::ButtonLeft, &r));
+
+    input.begin();
+    input.button(MouseButtonId::ButtonLeft, 10, 10, true);
+    input.end();
+    input.begin();
+    input.button(MouseButtonId::ButtonLeft, 80, 80, false);
+    input.end();
+    assert!(!input.has_mouse_up_in_rect(MouseButtonId::ButtonLeft, &r));
+  }
+
+  #[test]
+  fn test_mouse_drag_threshold() {
+    let mut input = Input::new();
+    input.begin();
+    input.motion(10, 10);
+    input.button(MouseButtonId::ButtonLeft, 10, 10, true);
+    input.end();
+
+    input.begin();
+    input.motion(12, 11);
+    input.end();
+    assert!(!input.is_mouse_dragging(MouseButtonId::ButtonLeft, 4f32));
+    assert_eq!(
+      input.mouse_drag_step(MouseButtonId::ButtonLeft, 4f32).x,
+      0f32
+    );
+
+    input.begin();
+    input.motion(16, 10);
+    input.end();
+    assert!(input.is_mouse_dragging(MouseButtonId::ButtonLeft, 4f32));
+    let delta = input.mouse_drag_delta(MouseButtonId::ButtonLeft);
+    assert_eq!((delta.x, delta.y), (6f32, 0f32));
+    // the movement held back by the threshold is applied at once
+    assert_eq!(
+      input.mouse_drag_step(MouseButtonId::ButtonLeft, 4f32).x,
+      6f32
+    );
+
+    input.begin();
+    input.motion(18, 10);
+    input.end();
+    assert_eq!(
+      input.mouse_drag_step(MouseButtonId::ButtonLeft, 4f32).x,
+      2f32
+    );
+
+    input.begin();
+    input.button(MouseButtonId::ButtonLeft, 18, 10, false);
+    input.end();
+    assert!(!input.is_mouse_dragging(MouseButtonId::ButtonLeft, 4f32));
+  }
+
+  #[test]
+  fn test_queued_click_fires_once() {
+    let r = RectangleF32::new(0f32, 0f32, 50f32, 50f32);
+    let mut input = Input::new();
+
+    input.queue_event(InputEvent::MouseMove { x: 20, y: 20 });
+    input.queue_event(InputEvent::MouseButton {
+      id:   MouseButtonId::ButtonLeft,
+      x:    20,
+      y:    20,
+      down: true,
+    });
+    input.queue_event(InputEvent::MouseButton {
+      id:   MouseButtonId::ButtonLeft,
+      x:    20,
+      y:    20,
+      down: false,
+    });
+    input.queue_event(InputEvent::Char('a'));
+
+    input.begin();
+    input.end();
+    assert_eq!((input.mouse.pos.x, input.mouse.pos.y), (20f32, 20f32));
+    assert!(input.mouse_clicked(MouseButtonId::ButtonLeft, &r));
+    assert_eq!(input.keyboard.text_len, 1);
+
+    input.begin();
+    input.end();
+    assert!(!input.mouse_clicked(MouseButtonId::ButtonLeft, &r));
+    assert_eq!(input.keyboard.text_len, 0);
+  }
+
+  #[test]
+  fn test_held_key_repeats() {
+    let mut input = Input::new();
+    input.keyboard.repeat_delay = 0.5f32;
+    input.keyboard.repeat_rate = 4f32;
+
+    let mut frame = |key: Option<bool>| {
+      input.begin();
+      key.map(|down| input.key(KeyId::KeyDown, down));
+      input.end();
+      input.update_key_repeat(0.125f32);
+      (
+        input.is_key_repeated(KeyId::KeyDown),
+        input.is_key_released(KeyId::KeyDown),
+      )
+    };
+
+    assert_eq!(frame(Some(true)), (true, false));
+    // held for 1.25 seconds: once after the delay, then every 0.25 seconds
+    let fired = (0 .. 10).filter(|_| frame(None).0).count();
+    assert_eq!(fired, 4);
+
+    assert_eq!(frame(Some(false)), (false, true));
+    assert_eq!(frame(None), (false, false));
+  }
+}
@@ -118,6 +118,8 @@ pub struct RowLayout {
   pub item:        RectangleF32,
   pub tree_depth:  i32,
   pub templates:   [f32; MAX_LAYOUT_ROW_TEMPLATE_COLUMNS],
+  /// The next widget continues the current row (`UiContext::same_line`).
+  pub same_line:   bool,
 }
 
 impl std::default::Default for RowLayout {
@@ -136,6 +138,7 @@ impl std::default::Default for RowLayout {
       item:        RectangleF32::new(0f32, 0f32, 0f32, 0f32),
       tree_depth:  0,
       templates:   [0f32; MAX_LAYOUT_ROW_TEMPLATE_COLUMNS],
+      same_line:   false,
     }
   }
 }
@@ -1,9 +1,11 @@
 use crate::{
   hmi::{
-    base::WidgetStates,
+    base::{TextAlign, WidgetStates},
     commands::CommandBuffer,
     input::{Input, MouseButtonId},
     style::{StyleItem, StyleProgress},
+    text::{widget_text, Text},
+    text_engine::Font,
   },
   math::{
     colors::RGBAColor, rectangle::RectangleF32, utility::clamp, vec2::Vec2F32,
@@ -11,6 +13,31 @@ use crate::{
 };
 use enumflags2::BitFlags;
 
+/// Text shown on top of a progress bar.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProgressLabel {
+  /// "42%"
+  Percent,
+  /// "42/100"
+  Fraction,
+}
+
+impl ProgressLabel {
+  pub fn format(&self, value: u32, max: u32) -> String {
+    match *self {
+      ProgressLabel::Percent => format!(
+        "{}%",
+        if max == 0 {
+          0
+        } else {
+          (value.min(max) as u64 * 100 / max as u64) as u32
+        }
+      ),
+      ProgressLabel::Fraction => format!("{}/{}", value.min(max), max),
+    }
+  }
+}
+
 fn progress_behaviour(
   state: BitFlags<WidgetStates>,
   input: Option<&mut Input>,
@@ -155,3 +182,37 @@ pub fn do_progress(
   draw_progress(cmd_buff, state, style, bounds, &cursor, value, max);
   (state, prog_value)
 }
+
+/// Draws the progress text centered on the bar, in black or white, whichever
+/// contrasts better with the filled part.
+pub fn draw_progress_label(
+  cmd_buff: &mut CommandBuffer,
+  bounds: &RectangleF32,
+  value: u32,
+  max: u32,
+  label: ProgressLabel,
+  style: &StyleProgress,
+  font: &Font,
+) {
+  let fill = style
+    .cursor_normal
+    .background_color(style.normal.background_color(style.border_color));
+  let text = Text {
+    padding:    Vec2F32::same(0f32),
+    background: fill,
+    text:       if fill.luminance() > 0.5f32 {
+      RGBAColor::new(0, 0, 0)
+    } else {
+      RGBAColor::new(255, 255, 255)
+    },
+  };
+
+  widget_text(
+    cmd_buff,
+    *bounds,
+    &label.format(value, max),
+    &text,
+    TextAlign::centered(),
+    font,
+  );
+}
@@ -0,0 +1,253 @@
+use crate::{
+  hmi::{
+    base::{ButtonBehaviour, TextAlign, WidgetStates},
+    button::{button_behaviour, draw_symbol},
+    commands::CommandBuffer,
+    input::{Input, KeyId, MouseButtonId},
+    style::StyleProperty,
+    text::{widget_text, Text},
+    text_engine::Font,
+    window::{PropertyState, PropertyStatus},
+  },
+  math::{rectangle::RectangleF32, utility::clamp, vec2::Vec2F32},
+};
+use enumflags2::BitFlags;
+
+/// Chars accepted while the value is typed in.
+fn is_numeric_char(c: char) -> bool {
+  c.is_ascii_digit() || c == '.' || c == '-' || c == '+'
+}
+
+fn property_deactivate(prop: &mut PropertyState) {
+  prop.active = 0;
+  prop.name = 0;
+  prop.state = PropertyStatus::Default;
+  prop.buffer.clear();
+  prop.length = 0;
+  prop.cursor = 0;
+}
+
+/// Parses the typed text, an invalid number keeps the old value.
+fn property_commit(prop: &PropertyState, val: f32, min: f32, max: f32) -> f32 {
+  prop
+    .buffer
+    .trim()
+    .parse::<f32>()
+    .map_or(val, |typed| clamp(min, typed, max))
+}
+
+fn property_behaviour(
+  prop: &mut PropertyState,
+  id: u32,
+  bounds: RectangleF32,
+  dec: RectangleF32,
+  inc: RectangleF32,
+  field: RectangleF32,
+  val: f32,
+  min: f32,
+  max: f32,
+  step: f32,
+  inc_per_pixel: f32,
+  i: &Input,
+) -> f32 {
+  let is_active = prop.active != 0 && prop.name == id;
+  let status = if is_active {
+    prop.state
+  } else {
+    PropertyStatus::Default
+  };
+
+  match status {
+    PropertyStatus::Edit => {
+      let typed = &i.keyboard.text[.. i.keyboard.text_len as usize];
+      prop
+        .buffer
+        .extend(typed.iter().filter(|c| is_numeric_char(**c)));
+      if i.is_key_pressed(KeyId::KeyBackspace) {
+        prop.buffer.pop();
+      }
+      prop.length = prop.buffer.chars().count() as i32;
+      prop.cursor = prop.length;
+
+      if i.is_key_pressed(KeyId::KeyEnter) {
+        let val = property_commit(prop, val, min, max);
+        property_deactivate(prop);
+        val
+      } else if i.is_key_pressed(KeyId::KeyEscape) {
+        property_deactivate(prop);
+        val
+      } else if i.is_mouse_pressed(MouseButtonId::ButtonLeft)
+        && !i.is_mouse_hovering_rect(&field)
+      {
+        // clicking somewhere else commits, like Nuklear does
+        let val = property_commit(prop, val, min, max);
+        property_deactivate(prop);
+        val
+      } else {
+        val
+      }
+    }
+
+    PropertyStatus::Drag => {
+      if i.is_mouse_down(MouseButtonId::ButtonLeft) {
+        val + i.mouse.delta.x * inc_per_pixel
+      } else {
+        let btn = &i.mouse.buttons[MouseButtonId::ButtonLeft as usize];
+        let moved = (btn.clicked_pos.x - btn.down_pos.x).abs() >= 1f32
+          || (btn.clicked_pos.y - btn.down_pos.y).abs() >= 1f32;
+
+        if moved || !i.is_mouse_hovering_rect(&field) {
+          property_deactivate(prop);
+        } else {
+          // a click without dragging starts typing in the value
+          prop.state = PropertyStatus::Edit;
+          prop.buffer = format!("{}", val);
+          prop.length = prop.buffer.chars().count() as i32;
+          prop.cursor = prop.length;
+        }
+        val
+      }
+    }
+
+    PropertyStatus::Default => {
+      let mut ws = BitFlags::<WidgetStates>::empty();
+      if button_behaviour(&mut ws, dec, Some(i), ButtonBehaviour::ButtonDefault)
+      {
+        val - step
+      } else if button_behaviour(
+        &mut ws,
+        inc,
+        Some(i),
+        ButtonBehaviour::ButtonDefault,
+      ) {
+        val + step
+      } else if i.is_mouse_hovering_rect(&bounds)
+        && i.mouse.scroll_delta.y != 0f32
+      {
+        // one step per wheel tick, up increases the value
+        val + step * i.mouse.scroll_delta.y
+      } else {
+        if i.is_mouse_pressed(MouseButtonId::ButtonLeft)
+          && i.is_mouse_hovering_rect(&field)
+        {
+          prop.active = 1;
+          prop.name = id;
+          prop.state = PropertyStatus::Drag;
+        }
+        val
+      }
+    }
+  }
+}
+
+/// Property widget: a value that can be stepped with the buttons on both
+/// sides or the mouse wheel, dragged horizontally, or typed in after clicking
+/// it. Typing is committed with Enter and cancelled with Escape. The state
+/// lives in the window and is keyed by `id`, so only one property is active at
+/// a time.
+pub(crate) fn do_property(
+  prop: &mut PropertyState,
+  out: &mut CommandBuffer,
+  id: u32,
+  bounds: RectangleF32,
+  name: &str,
+  val: f32,
+  min: f32,
+  max: f32,
+  step: f32,
+  inc_per_pixel: f32,
+  style: &StyleProperty,
+  i: Option<&Input>,
+  font: &Font,
+) -> f32 {
+  let sym_size = (bounds.h - 2f32 * (style.border + style.padding.y))
+    .min(font.scale)
+    .max(0f32);
+  let sym_y = bounds.y + (bounds.h - sym_size) * 0.5f32;
+
+  let dec = RectangleF32::new(
+    bounds.x + style.border + style.padding.x,
+    sym_y,
+    sym_size,
+    sym_size,
+  );
+  let inc = RectangleF32::new(
+    bounds.x + bounds.w - style.border - style.padding.x - sym_size,
+    sym_y,
+    sym_size,
+    sym_size,
+  );
+  let field = RectangleF32::new(
+    dec.x + dec.w + style.padding.x,
+    bounds.y + style.border,
+    (inc.x - style.padding.x - (dec.x + dec.w + style.padding.x)).max(0f32),
+    bounds.h - 2f32 * style.border,
+  );
+
+  let val = i.map_or(val, |i| {
+    clamp(
+      min,
+      property_behaviour(
+        prop,
+        id,
+        bounds,
+        dec,
+        inc,
+        field,
+        val,
+        min,
+        max,
+        step,
+        inc_per_pixel,
+        i,
+      ),
+      max,
+    )
+  });
+
+  let is_active = prop.active != 0 && prop.name == id;
+  let hovered = i.map_or(false, |i| i.is_mouse_hovering_rect(&bounds));
+  let (background, label) = if is_active {
+    (&style.active, style.label_active)
+  } else if hovered {
+    (&style.hover, style.label_hover)
+  } else {
+    (&style.normal, style.label_normal)
+  };
+
+  background.draw(out, bounds, style.rounding);
+  out.stroke_rect(bounds, style.rounding, style.border, style.border_color);
+
+  let bg = background.background_color(style.label_normal);
+  draw_symbol(out, style.sym_left, dec, bg, label, 1f32, font);
+  draw_symbol(out, style.sym_right, inc, bg, label, 1f32, font);
+
+  let text = Text {
+    padding: Vec2F32::same(0f32),
+    background: bg,
+    text: label,
+  };
+
+  if is_active && prop.state == PropertyStatus::Edit {
+    widget_text(
+      out,
+      field,
+      &format!("{}|", prop.buffer),
+      &text,
+      TextAlign::centered(),
+      font,
+    );
+  } else {
+    widget_text(out, field, name, &text, TextAlign::left(), font);
+    widget_text(
+      out,
+      field,
+      &format!("{:.2}", val),
+      &text,
+      TextAlign::right(),
+      font,
+    );
+  }
+
+  val
+}
@@ -0,0 +1,387 @@
+use crate::{
+  hmi::{
+    base::{ButtonBehaviour, WidgetStates},
+    button::do_button_symbol,
+    commands::CommandBuffer,
+    input::{Input, MouseButtonId},
+    style::StyleScrollbar,
+    text_engine::Font,
+  },
+  math::{rectangle::RectangleF32, utility::clamp, vec2::Vec2F32},
+};
+use enumflags2::BitFlags;
+
+/// Direction the cursor of a scrollbar moves in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ScrollbarAxis {
+  Horizontal,
+  Vertical,
+}
+
+impl ScrollbarAxis {
+  /// Position and length of `r` along the axis.
+  fn span(self, r: &RectangleF32) -> (f32, f32) {
+    match self {
+      ScrollbarAxis::Horizontal => (r.x, r.w),
+      ScrollbarAxis::Vertical => (r.y, r.h),
+    }
+  }
+
+  /// Thickness of `r` across the axis.
+  fn thickness(self, r: &RectangleF32) -> f32 {
+    match self {
+      ScrollbarAxis::Horizontal => r.h,
+      ScrollbarAxis::Vertical => r.w,
+    }
+  }
+
+  /// `r` moved to `pos` and resized to `len` along the axis.
+  fn with_span(self, r: &RectangleF32, pos: f32, len: f32) -> RectangleF32 {
+    match self {
+      ScrollbarAxis::Horizontal => RectangleF32 {
+        x: pos,
+        w: len,
+        ..*r
+      },
+      ScrollbarAxis::Vertical => RectangleF32 {
+        y: pos,
+        h: len,
+        ..*r
+      },
+    }
+  }
+
+  fn component(self, v: Vec2F32) -> f32 {
+    match self {
+      ScrollbarAxis::Horizontal => v.x,
+      ScrollbarAxis::Vertical => v.y,
+    }
+  }
+
+  fn component_mut(self, v: &mut Vec2F32) -> &mut f32 {
+    match self {
+      ScrollbarAxis::Horizontal => &mut v.x,
+      ScrollbarAxis::Vertical => &mut v.y,
+    }
+  }
+}
+
+/// Cursor of a scrollbar, sized to the visible part of the content.
+pub(crate) fn scrollbar_cursor(
+  axis: ScrollbarAxis,
+  track: &RectangleF32,
+  offset: f32,
+  max_offset: f32,
+  view: f32,
+  content: f32,
+) -> RectangleF32 {
+  let (track_pos, track_len) = axis.span(track);
+  let cursor_len = (track_len * view / content)
+    .max(axis.thickness(track))
+    .min(track_len);
+  axis.with_span(
+    track,
+    track_pos + (offset / max_offset).min(1f32) * (track_len - cursor_len),
+    cursor_len,
+  )
+}
+
+/// Drags the cursor with the mouse and scrolls with the wheel (when
+/// `has_scrolling`) by `step`. Returns the new offset.
+pub(crate) fn scrollbar_behaviour(
+  state: &mut BitFlags<WidgetStates>,
+  axis: ScrollbarAxis,
+  i: &mut Input,
+  has_scrolling: bool,
+  track: &RectangleF32,
+  cursor: &RectangleF32,
+  offset: f32,
+  max_offset: f32,
+  step: f32,
+) -> f32 {
+  *state = WidgetStates::reset(*state);
+  if i.is_mouse_hovering_rect(track) {
+    *state = WidgetStates::Hover.into();
+  }
+
+  let left_mouse_down = i.has_mouse_down(MouseButtonId::ButtonLeft);
+  let left_mouse_click_in_cursor =
+    i.has_mouse_click_down_in_rect(MouseButtonId::ButtonLeft, cursor, true);
+  let wheel = axis.component(i.mouse.scroll_delta);
+
+  let new_offset = if left_mouse_down && left_mouse_click_in_cursor {
+    *state = WidgetStates::active();
+    // the cursor follows the mouse, a pixel on the track scrolls by the
+    // share of the content it stands for
+    let (cursor_pos, cursor_len) = axis.span(cursor);
+    let travel = axis.span(track).1 - cursor_len;
+    let new_offset = if travel > 0f32 {
+      clamp(
+        0f32,
+        offset + axis.component(i.mouse.delta) * max_offset / travel,
+        max_offset,
+      )
+    } else {
+      offset
+    };
+
+    // keep the click inside the cursor while it moves
+    *axis.component_mut(
+      &mut i.mouse.buttons[MouseButtonId::ButtonLeft as usize].clicked_pos,
+    ) = cursor_pos
+      + (new_offset - offset) * travel / max_offset
+      + cursor_len / 2f32;
+    new_offset
+  } else if has_scrolling && wheel != 0f32 {
+    offset - wheel * step
+  } else {
+    offset
+  };
+
+  if state.contains(WidgetStates::Hover)
+    && !i.is_mouse_prev_hovering_rect(track)
+  {
+    state.insert(WidgetStates::Entered);
+  } else if i.is_mouse_prev_hovering_rect(track) {
+    state.insert(WidgetStates::Left);
+  }
+
+  clamp(0f32, new_offset, max_offset)
+}
+
+/// Track and cursor of a scrollbar, with the buttons (when
+/// `StyleScrollbar::show_buttons`) stepping the offset by `step`. Returns
+/// the new offset and where the track and the cursor are.
+pub(crate) fn scrollbar_layout(
+  state: &mut BitFlags<WidgetStates>,
+  out: &mut CommandBuffer,
+  axis: ScrollbarAxis,
+  bounds: RectangleF32,
+  has_scrolling: bool,
+  offset: f32,
+  view: f32,
+  content: f32,
+  step: f32,
+  style: &StyleScrollbar,
+  i: Option<&mut Input>,
+  font: &Font,
+) -> (f32, RectangleF32, RectangleF32) {
+  let max_offset = (content - view).max(0f32);
+  let mut offset = clamp(0f32, offset, max_offset);
+  let mut track = bounds;
+
+  if style.show_buttons {
+    let (pos, len) = axis.span(&bounds);
+    let button_len = axis.thickness(&bounds);
+    let dec = axis.with_span(&bounds, pos, button_len);
+    let inc = axis.with_span(&bounds, pos + len - button_len, button_len);
+
+    let mut ws = BitFlags::<WidgetStates>::empty();
+    if do_button_symbol(
+      &mut ws,
+      out,
+      dec,
+      style.dec_symbol,
+      ButtonBehaviour::ButtonRepeater,
+      &style.dec_button,
+      i.as_deref(),
+      font,
+    ) {
+      offset = (offset - step).max(0f32);
+    }
+    if do_button_symbol(
+      &mut ws,
+      out,
+      inc,
+      style.inc_symbol,
+      ButtonBehaviour::ButtonRepeater,
+      &style.inc_button,
+      i.as_deref(),
+      font,
+    ) {
+      offset = (offset + step).min(max_offset);
+    }
+
+    track = axis.with_span(
+      &bounds,
+      pos + button_len,
+      (len - 2f32 * button_len).max(0f32),
+    );
+  }
+
+  let cursor =
+    scrollbar_cursor(axis, &track, offset, max_offset, view, content);
+  let offset = i.map_or(offset, |i| {
+    scrollbar_behaviour(
+      state,
+      axis,
+      i,
+      has_scrolling,
+      &track,
+      &cursor,
+      offset,
+      max_offset,
+      step,
+    )
+  });
+  let cursor =
+    scrollbar_cursor(axis, &track, offset, max_offset, view, content);
+
+  (offset, track, cursor)
+}
+
+/// Draws the track and the cursor with the style items of the state, faded
+/// by `alpha` in [0, 1].
+pub(crate) fn draw_scrollbar(
+  out: &mut CommandBuffer,
+  state: BitFlags<WidgetStates>,
+  track: RectangleF32,
+  cursor: RectangleF32,
+  style: &StyleScrollbar,
+  alpha: f32,
+) {
+  let (background, cursor_item) = if state.contains(WidgetStates::Activated) {
+    (style.active, style.cursor_active)
+  } else if state.contains(WidgetStates::Hover) {
+    (style.hover, style.cursor_hover)
+  } else {
+    (style.normal, style.cursor_normal)
+  };
+
+  background.draw_faded(out, track, style.rounding, alpha);
+  cursor_item.draw_faded(out, cursor, style.rounding_cursor, alpha);
+}
+
+/// Scrollbar for `content` pixels of content of which `view` pixels are
+/// visible, along `axis`. The cursor can be dragged, the optional buttons
+/// and the wheel (when `has_scrolling`) move the offset by `step`. Returns
+/// the new offset.
+pub(crate) fn do_scrollbar(
+  state: &mut BitFlags<WidgetStates>,
+  out: &mut CommandBuffer,
+  axis: ScrollbarAxis,
+  bounds: RectangleF32,
+  has_scrolling: bool,
+  offset: f32,
+  view: f32,
+  content: f32,
+  step: f32,
+  style: &StyleScrollbar,
+  i: Option<&mut Input>,
+  font: &Font,
+) -> f32 {
+  if content <= view {
+    return 0f32;
+  }
+
+  let (offset, track, cursor) = scrollbar_layout(
+    state,
+    out,
+    axis,
+    bounds,
+    has_scrolling,
+    offset,
+    view,
+    content,
+    step,
+    style,
+    i,
+    font,
+  );
+  draw_scrollbar(out, *state, track, cursor, style, 1f32);
+
+  offset
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    hmi::{
+      base::Consts,
+      commands::Command,
+      image::Image,
+      style::{Style, StyleItem},
+    },
+    math::colors::RGBAColor,
+  };
+
+  #[test]
+  fn test_image_style_items_draw_images() {
+    let mut style = Style::new(Font::default()).scrollh;
+    style.normal = StyleItem::Img(Image::image_id(1));
+    style.cursor_normal = StyleItem::Img(Image::image_id(2));
+
+    let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+    draw_scrollbar(
+      &mut out,
+      BitFlags::default(),
+      RectangleF32::new(0f32, 0f32, 100f32, 10f32),
+      RectangleF32::new(0f32, 0f32, 20f32, 10f32),
+      &style,
+      1f32,
+    );
+
+    let images = out
+      .commands()
+      .iter()
+      .filter(|cmd| match cmd {
+        Command::Image(_) => true,
+        _ => false,
+      })
+      .count();
+    assert_eq!(images, 2);
+  }
+
+  #[test]
+  fn test_faded_scrollbar_keeps_image_items() {
+    let mut style = Style::new(Font::default()).scrollv;
+    style.normal = StyleItem::Img(Image::image_id(1));
+    style.cursor_normal = StyleItem::Color(RGBAColor::new(200, 100, 50));
+
+    let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+    draw_scrollbar(
+      &mut out,
+      BitFlags::default(),
+      RectangleF32::new(0f32, 0f32, 10f32, 100f32),
+      RectangleF32::new(0f32, 0f32, 10f32, 20f32),
+      &style,
+      0.5f32,
+    );
+
+    let alphas = out
+      .commands()
+      .iter()
+      .filter_map(|cmd| match cmd {
+        Command::Image(img) => Some(img.color.a),
+        Command::RectFilled(rect) => Some(rect.color.a),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(alphas, vec![127, 127]);
+  }
+
+  #[test]
+  fn test_cursor_moves_along_the_axis() {
+    let track = RectangleF32::new(10f32, 20f32, 10f32, 100f32);
+    let top = scrollbar_cursor(
+      ScrollbarAxis::Vertical,
+      &track,
+      0f32,
+      300f32,
+      100f32,
+      400f32,
+    );
+    let bottom = scrollbar_cursor(
+      ScrollbarAxis::Vertical,
+      &track,
+      300f32,
+      300f32,
+      100f32,
+      400f32,
+    );
+
+    assert_eq!(top, RectangleF32::new(10f32, 20f32, 10f32, 25f32));
+    assert_eq!(bottom, RectangleF32::new(10f32, 95f32, 10f32, 25f32));
+  }
+}
@@ -1,12 +1,14 @@
 use crate::{
   hmi::{
     base::TextAlign,
+    commands::CommandBuffer,
     cursor::Cursor,
     image::Image,
     panel::{PanelFlags, PanelType},
+    text::TextSpacing,
     text_engine::Font,
   },
-  math::{colors::RGBAColor, vec2::Vec2F32},
+  math::{colors::RGBAColor, rectangle::RectangleF32, vec2::Vec2F32},
 };
 
 use enumflags2::BitFlags;
@@ -46,12 +48,56 @@ impl StyleItem {
   fn hide() -> StyleItem {
     StyleItem::Color(RGBAColor::new_with_alpha(0, 0, 0, 0))
   }
+
+  /// Fills the rectangle with the item: images are stretched over it, colors
+  /// fill it with the given rounding.
+  pub fn draw(&self, out: &mut CommandBuffer, r: RectangleF32, rounding: f32) {
+    match *self {
+      StyleItem::Img(img) => {
+        out.draw_image(r, img, RGBAColor::new(255, 255, 255))
+      }
+      StyleItem::Color(c) => out.fill_rect(r, rounding, c),
+    }
+  }
+
+  /// Like `draw`, with the item faded by `alpha` in [0, 1]: images are
+  /// tinted, colors keep their own alpha scaled.
+  pub fn draw_faded(
+    &self,
+    out: &mut CommandBuffer,
+    r: RectangleF32,
+    rounding: f32,
+    alpha: f32,
+  ) {
+    let fade = |a: u8| (a as f32 * alpha) as u8;
+    match *self {
+      StyleItem::Img(img) => out.draw_image(
+        r,
+        img,
+        RGBAColor::new_with_alpha(255, 255, 255, fade(255)),
+      ),
+      StyleItem::Color(c) => out.fill_rect(
+        r,
+        rounding,
+        RGBAColor::new_with_alpha(c.r, c.g, c.b, fade(c.a)),
+      ),
+    }
+  }
+
+  /// Color to use as text background on top of this item.
+  pub fn background_color(&self, fallback: RGBAColor) -> RGBAColor {
+    match *self {
+      StyleItem::Color(c) => c,
+      StyleItem::Img(_) => fallback,
+    }
+  }
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct StyleText {
   pub color:   RGBAColor,
   pub padding: Vec2F32,
+  pub spacing: TextSpacing,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -218,6 +264,15 @@ pub struct StyleScrollbar {
   pub dec_button:   StyleButton,
   pub inc_symbol:   SymbolType,
   pub dec_symbol:   SymbolType,
+
+  // auto hide
+  /// fade the scrollbar out while it is neither hovered nor used
+  pub auto_hide: bool,
+  /// seconds it takes an idle scrollbar to fade out
+  pub fade_time: f32,
+
+  /// ease towards the offset set by the mouse wheel instead of jumping
+  pub smooth_scroll: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -397,6 +452,8 @@ pub struct StyleWindow {
   pub group_border_color:      RGBAColor,
   pub tooltip_border_color:    RGBAColor,
   pub scaler:                  StyleItem,
+  /// Drawn over everything behind a modal window.
+  pub modal_backdrop:          RGBAColor,
 
   pub border:                 f32,
   pub combo_border:           f32,
@@ -466,10 +523,87 @@ pub enum StyleCursor {
   CursorCount,
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Predefined color tables, see `Style::from_theme()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Theme {
+  Dark,
+  Light,
+  HighContrast,
+}
+
+impl Theme {
+  /// Colors in `StyleColors` order.
+  pub fn color_table(self) -> [(u8, u8, u8, u8); 28] {
+    match self {
+      Theme::Dark => Style::COLOR_TABLE,
+      Theme::Light => [
+        (70, 70, 70, 255),    // text
+        (175, 175, 175, 255), // window
+        (175, 175, 175, 255), // header
+        (0, 0, 0, 255),       // border
+        (185, 185, 185, 255), // button
+        (170, 170, 170, 255), // button hover
+        (160, 160, 160, 255), // button active
+        (150, 150, 150, 255), // toggle
+        (120, 120, 120, 255), // toggle hover
+        (175, 175, 175, 255), // toggle cursor
+        (190, 190, 190, 255), // select
+        (175, 175, 175, 255), // select active
+        (190, 190, 190, 255), // slider
+        (80, 80, 80, 255),    // slider cursor
+        (70, 70, 70, 255),    // slider cursor hover
+        (60, 60, 60, 255),    // slider cursor active
+        (175, 175, 175, 255), // property
+        (150, 150, 150, 255), // edit
+        (0, 0, 0, 255),       // edit cursor
+        (175, 175, 175, 255), // combo
+        (160, 160, 160, 255), // chart
+        (45, 45, 45, 255),    // chart color
+        (255, 0, 0, 255),     // chart color highlight
+        (180, 180, 180, 255), // scrollbar
+        (140, 140, 140, 255), // scrollbar cursor
+        (150, 150, 150, 255), // scrollbar cursor hover
+        (160, 160, 160, 255), // scrollbar cursor active
+        (180, 180, 180, 255), // tab header
+      ],
+      Theme::HighContrast => [
+        (255, 255, 255, 255), // text
+        (0, 0, 0, 255),       // window
+        (0, 0, 0, 255),       // header
+        (255, 255, 255, 255), // border
+        (20, 20, 20, 255),    // button
+        (0, 0, 128, 255),     // button hover
+        (0, 0, 200, 255),     // button active
+        (40, 40, 40, 255),    // toggle
+        (0, 0, 128, 255),     // toggle hover
+        (255, 255, 0, 255),   // toggle cursor
+        (20, 20, 20, 255),    // select
+        (0, 0, 200, 255),     // select active
+        (40, 40, 40, 255),    // slider
+        (255, 255, 0, 255),   // slider cursor
+        (255, 255, 128, 255), // slider cursor hover
+        (255, 255, 255, 255), // slider cursor active
+        (20, 20, 20, 255),    // property
+        (20, 20, 20, 255),    // edit
+        (255, 255, 0, 255),   // edit cursor
+        (20, 20, 20, 255),    // combo
+        (20, 20, 20, 255),    // chart
+        (255, 255, 0, 255),   // chart color
+        (255, 0, 0, 255),     // chart color highlight
+        (20, 20, 20, 255),    // scrollbar
+        (255, 255, 0, 255),   // scrollbar cursor
+        (255, 255, 128, 255), // scrollbar cursor hover
+        (255, 255, 255, 255), // scrollbar cursor active
+        (0, 0, 0, 255),       // tab header
+      ],
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
 pub struct Style {
-  pub font: Font,
-  // pub cursors:           [Cursor; Self::CURSOR_COUNT as usize],
+  pub font:              Font,
+  pub cursors:           [Option<Cursor>; Style::CURSOR_COUNT as usize],
   pub cursor_active:     usize,
   pub cursor_last:       usize,
   pub cursor_visible:    bool,
@@ -529,11 +663,16 @@ impl Style {
     Self::new_from_table(font, &Self::COLOR_TABLE)
   }
 
+  pub fn from_theme(font: Font, theme: Theme) -> Self {
+    Self::new_from_table(font, &theme.color_table())
+  }
+
   pub fn new_from_table(font: Font, table: &[(u8, u8, u8, u8)]) -> Self {
     // default button
     let text = StyleText {
       color:   table[StyleColors::ColorText as usize].into(),
       padding: Vec2F32::same(0f32),
+      spacing: TextSpacing::default(),
     };
 
     // default text
@@ -820,6 +959,9 @@ impl Style {
       rounding_cursor:     0f32,
       inc_button:          scroll_btn,
       dec_button:          scroll_btn,
+      auto_hide:           false,
+      fade_time:           0.5f32,
+      smooth_scroll:       false,
     };
 
     let scrollh = scroll;
@@ -1146,6 +1288,7 @@ impl Style {
       menu_border_color:       table[StyleColors::ColorBorder as usize].into(),
       group_border_color:      table[StyleColors::ColorBorder as usize].into(),
       tooltip_border_color:    table[StyleColors::ColorBorder as usize].into(),
+      modal_backdrop:          RGBAColor::new_with_alpha(0, 0, 0, 128),
       scaler:                  StyleItem::Color(
         table[StyleColors::ColorText as usize].into(),
       ),
@@ -1172,8 +1315,8 @@ impl Style {
 
     Style {
       font,
-      // pub cursors:           [Cursor; Self::CURSOR_COUNT as usize],
-      cursor_active: 0,
+      cursors: [None; Style::CURSOR_COUNT as usize],
+      cursor_active: StyleCursor::CursorArrow as usize,
       cursor_last: 0,
       cursor_visible: false,
       text,
@@ -1196,6 +1339,16 @@ impl Style {
     }
   }
 
+  /// Sets the image drawn for a cursor. Cursors without an image are not
+  /// drawn.
+  pub fn set_cursor(&mut self, which: StyleCursor, cursor: Cursor) {
+    self.cursors[which as usize] = Some(cursor);
+  }
+
+  pub fn active_cursor(&self) -> Option<Cursor> {
+    self.cursors.get(self.cursor_active).and_then(|c| *c)
+  }
+
   pub fn get_panel_padding(&self, typ: BitFlags<PanelType>) -> Vec2F32 {
     if typ == PanelType::Window {
       self.window.padding
@@ -1277,10 +1430,10 @@ impl StackSize {
   pub const VECTOR_STACK_SIZE: usize = 16;
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct ConfigStackElement<T>
 where
-  T: Copy + Clone + std::fmt::Debug,
+  T: Clone + std::fmt::Debug,
 {
   pub address:   *mut T,
   pub old_value: T,
@@ -1288,7 +1441,7 @@ where
 
 impl<T> std::default::Default for ConfigStackElement<T>
 where
-  T: Copy + Clone + std::fmt::Debug + std::default::Default,
+  T: Clone + std::fmt::Debug + std::default::Default,
 {
   fn default() -> Self {
     Self {
@@ -1300,7 +1453,7 @@ where
 
 macro_rules! define_config_stack {
   ($name:ident, $tp:ty, $size:expr) => {
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Clone, Debug)]
     pub struct $name {
       pub head:     i32,
       pub elements: [ConfigStackElement<$tp>; $size],
@@ -1310,7 +1463,7 @@ macro_rules! define_config_stack {
       fn default() -> Self {
         Self {
           head:     0,
-          elements: [ConfigStackElement::<$tp>::default(); $size],
+          elements: Default::default(),
         }
       }
     }
@@ -1333,7 +1486,7 @@ define_config_stack!(
   StackSize::BUTTON_BEHAVIOR_STACK_SIZE
 );
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct ConfigurationStacks {
   pub style_items:       ConfigStackStyleItem,
   pub floats:            ConfigStackFloat,
@@ -1343,3 +1496,20 @@ pub struct ConfigurationStacks {
   pub fonts:             ConfigStackFont,
   pub button_behaviours: ConfigStackButtonBehaviour,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_themes_differ() {
+    let dark = Style::from_theme(Font::default(), Theme::Dark);
+    let light = Style::from_theme(Font::default(), Theme::Light);
+
+    assert_ne!(dark.window.background, light.window.background);
+    assert_eq!(
+      dark.window.background,
+      Style::new(Font::default()).window.background
+    );
+  }
+}
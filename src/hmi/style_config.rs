@@ -0,0 +1,430 @@
+// Saving and loading a Style as plain `key = value` lines, one per color or
+// metric, e.g. `button.padding = 2,2` or `window.background = #2d2d2dff`.
+// Image style items and symbols are not part of the format.
+
+use crate::{
+  hmi::{
+    style::{
+      Style, StyleButton, StyleChart, StyleCombo, StyleEdit, StyleItem,
+      StyleProgress, StyleProperty, StyleScrollbar, StyleSelectable,
+      StyleSlider, StyleTab, StyleText, StyleToggle, StyleWindow,
+      StyleWindowHeader,
+    },
+    text_engine::Font,
+  },
+  math::{colors::RGBAColor, vec2::Vec2F32},
+};
+
+enum Field<'a> {
+  Color(&'a mut RGBAColor),
+  Item(&'a mut StyleItem),
+  Float(&'a mut f32),
+  Vec2(&'a mut Vec2F32),
+  Bool(&'a mut bool),
+}
+
+type Fields<'a> = Vec<(String, Field<'a>)>;
+
+macro_rules! push_fields {
+  ($out:ident, $prefix:expr, $s:ident, $($kind:ident $name:ident),* $(,)?) => {
+    $(
+      $out.push((
+        format!("{}.{}", $prefix, stringify!($name)),
+        Field::$kind(&mut $s.$name),
+      ));
+    )*
+  };
+}
+
+fn text_fields<'a>(out: &mut Fields<'a>, prefix: &str, s: &'a mut StyleText) {
+  push_fields!(out, prefix, s, Color color, Vec2 padding);
+  out.push((
+    format!("{}.line_spacing", prefix),
+    Field::Float(&mut s.spacing.line),
+  ));
+  out.push((
+    format!("{}.paragraph_spacing", prefix),
+    Field::Float(&mut s.spacing.paragraph),
+  ));
+}
+
+fn button_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleButton,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Color text_background, Color text_normal, Color text_hover,
+    Color text_active,
+    Float border, Float rounding, Vec2 padding, Vec2 image_padding,
+    Vec2 touch_padding,
+  );
+}
+
+fn toggle_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleToggle,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Item cursor_normal, Item cursor_hover,
+    Color text_background, Color text_normal, Color text_hover,
+    Color text_active,
+    Float border, Float spacing, Vec2 padding, Vec2 touch_padding,
+  );
+}
+
+fn selectable_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleSelectable,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item pressed,
+    Item normal_active, Item hover_active, Item pressed_active,
+    Color text_normal, Color text_hover, Color text_pressed,
+    Color text_normal_active, Color text_hover_active,
+    Color text_pressed_active, Color text_background,
+    Float rounding, Vec2 padding, Vec2 touch_padding, Vec2 image_padding,
+  );
+}
+
+fn slider_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleSlider,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Color bar_normal, Color bar_hover, Color bar_active, Color bar_filled,
+    Item cursor_normal, Item cursor_hover, Item cursor_active,
+    Float border, Float rounding, Float bar_height, Vec2 padding,
+    Vec2 spacing, Vec2 cursor_size, Bool show_buttons,
+  );
+  button_fields(out, &format!("{}.inc_button", prefix), &mut s.inc_button);
+  button_fields(out, &format!("{}.dec_button", prefix), &mut s.dec_button);
+}
+
+fn progress_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleProgress,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Item cursor_normal, Item cursor_hover, Item cursor_active,
+    Color cursor_border_color,
+    Float rounding, Float border, Float cursor_border, Float cursor_rounding,
+    Vec2 padding,
+  );
+}
+
+fn scrollbar_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleScrollbar,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Item cursor_normal, Item cursor_hover, Item cursor_active,
+    Color cursor_border_color,
+    Float border, Float rounding, Float border_cursor, Float rounding_cursor,
+    Vec2 padding, Bool show_buttons, Bool auto_hide, Float fade_time,
+    Bool smooth_scroll,
+  );
+  button_fields(out, &format!("{}.inc_button", prefix), &mut s.inc_button);
+  button_fields(out, &format!("{}.dec_button", prefix), &mut s.dec_button);
+}
+
+fn edit_fields<'a>(out: &mut Fields<'a>, prefix: &str, s: &'a mut StyleEdit) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Color cursor_normal, Color cursor_hover, Color cursor_text_normal,
+    Color cursor_text_hover,
+    Color text_normal, Color text_hover, Color text_active,
+    Color selected_normal, Color selected_hover, Color selected_text_normal,
+    Color selected_text_hover,
+    Float border, Float rounding, Float cursor_size, Vec2 scrollbar_size,
+    Vec2 padding, Float row_padding,
+  );
+  scrollbar_fields(out, &format!("{}.scrollbar", prefix), &mut s.scrollbar);
+}
+
+fn property_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleProperty,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Color label_normal, Color label_hover, Color label_active,
+    Float border, Float rounding, Vec2 padding,
+  );
+  edit_fields(out, &format!("{}.edit", prefix), &mut s.edit);
+  button_fields(out, &format!("{}.inc_button", prefix), &mut s.inc_button);
+  button_fields(out, &format!("{}.dec_button", prefix), &mut s.dec_button);
+}
+
+fn chart_fields<'a>(out: &mut Fields<'a>, prefix: &str, s: &'a mut StyleChart) {
+  push_fields!(out, prefix, s,
+    Item background, Color border_color, Color selected_color, Color color,
+    Float border, Float rounding, Vec2 padding,
+  );
+}
+
+fn tab_fields<'a>(out: &mut Fields<'a>, prefix: &str, s: &'a mut StyleTab) {
+  push_fields!(out, prefix, s,
+    Item background, Color border_color, Color text,
+    Float border, Float rounding, Float indent, Vec2 padding, Vec2 spacing,
+  );
+  button_fields(
+    out,
+    &format!("{}.tab_maximize_button", prefix),
+    &mut s.tab_maximize_button,
+  );
+  button_fields(
+    out,
+    &format!("{}.tab_minimize_button", prefix),
+    &mut s.tab_minimize_button,
+  );
+  button_fields(
+    out,
+    &format!("{}.node_maximize_button", prefix),
+    &mut s.node_maximize_button,
+  );
+  button_fields(
+    out,
+    &format!("{}.node_minimize_button", prefix),
+    &mut s.node_minimize_button,
+  );
+}
+
+fn combo_fields<'a>(out: &mut Fields<'a>, prefix: &str, s: &'a mut StyleCombo) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active, Color border_color,
+    Color label_normal, Color label_hover, Color label_active,
+    Color symbol_normal, Color symbol_hover, Color symbol_active,
+    Float border, Float rounding, Vec2 content_padding, Vec2 button_padding,
+    Vec2 spacing,
+  );
+  button_fields(out, &format!("{}.button", prefix), &mut s.button);
+}
+
+fn header_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleWindowHeader,
+) {
+  push_fields!(out, prefix, s,
+    Item normal, Item hover, Item active,
+    Color label_normal, Color label_hover, Color label_active,
+    Vec2 padding, Vec2 label_padding, Vec2 spacing,
+  );
+  button_fields(
+    out,
+    &format!("{}.close_button", prefix),
+    &mut s.close_button,
+  );
+  button_fields(
+    out,
+    &format!("{}.minimize_button", prefix),
+    &mut s.minimize_button,
+  );
+}
+
+fn window_fields<'a>(
+  out: &mut Fields<'a>,
+  prefix: &str,
+  s: &'a mut StyleWindow,
+) {
+  push_fields!(out, prefix, s,
+    Item fixed_background, Color background,
+    Color border_color, Color popup_border_color, Color combo_border_color,
+    Color contextual_border_color, Color menu_border_color,
+    Color group_border_color, Color tooltip_border_color, Item scaler,
+    Color modal_backdrop,
+    Float border, Float combo_border, Float contextual_border,
+    Float menu_border, Float group_border, Float tooltip_border,
+    Float popup_border, Float min_row_height_padding,
+    Float rounding, Vec2 spacing, Vec2 scrollbar_size, Vec2 min_size,
+    Vec2 padding, Vec2 group_padding, Vec2 popup_padding, Vec2 combo_padding,
+    Vec2 contextual_padding, Vec2 menu_padding, Vec2 tooltip_padding,
+  );
+  header_fields(out, &format!("{}.header", prefix), &mut s.header);
+}
+
+fn style_fields(s: &mut Style) -> Fields<'_> {
+  let mut out = vec![];
+  text_fields(&mut out, "text", &mut s.text);
+  button_fields(&mut out, "button", &mut s.button);
+  button_fields(&mut out, "contextual_button", &mut s.contextual_button);
+  button_fields(&mut out, "menu_button", &mut s.menu_button);
+  toggle_fields(&mut out, "option", &mut s.option);
+  toggle_fields(&mut out, "checkbox", &mut s.checkbox);
+  selectable_fields(&mut out, "selectable", &mut s.selectable);
+  slider_fields(&mut out, "slider", &mut s.slider);
+  progress_fields(&mut out, "progress", &mut s.progress);
+  property_fields(&mut out, "property", &mut s.property);
+  edit_fields(&mut out, "edit", &mut s.edit);
+  chart_fields(&mut out, "chart", &mut s.chart);
+  scrollbar_fields(&mut out, "scrollh", &mut s.scrollh);
+  scrollbar_fields(&mut out, "scrollv", &mut s.scrollv);
+  tab_fields(&mut out, "tab", &mut s.tab);
+  combo_fields(&mut out, "combo", &mut s.combo);
+  window_fields(&mut out, "window", &mut s.window);
+  out
+}
+
+fn parse_vec2(value: &str) -> Option<Vec2F32> {
+  let mut parts = value.split(',').map(|v| v.trim().parse::<f32>());
+  match (parts.next(), parts.next(), parts.next()) {
+    (Some(Ok(x)), Some(Ok(y)), None) => Some(Vec2F32::new(x, y)),
+    _ => None,
+  }
+}
+
+impl Field<'_> {
+  /// None for values that can't be written (image style items).
+  fn to_value(&self) -> Option<String> {
+    match self {
+      Field::Color(c) => Some(c.to_html()),
+      Field::Item(StyleItem::Color(c)) => Some(c.to_html()),
+      Field::Item(StyleItem::Img(_)) => None,
+      Field::Float(f) => Some(format!("{}", f)),
+      Field::Vec2(v) => Some(format!("{},{}", v.x, v.y)),
+      Field::Bool(b) => Some(format!("{}", b)),
+    }
+  }
+
+  fn set_value(&mut self, value: &str) -> bool {
+    match self {
+      Field::Color(c) => RGBAColor::from_html(value).map(|v| **c = v).is_ok(),
+      Field::Item(item) => RGBAColor::from_html(value)
+        .map(|v| **item = StyleItem::Color(v))
+        .is_ok(),
+      Field::Float(f) => value.parse::<f32>().map(|v| **f = v).is_ok(),
+      Field::Vec2(vec) => parse_vec2(value).map(|v| **vec = v).is_some(),
+      Field::Bool(b) => value.parse::<bool>().map(|v| **b = v).is_ok(),
+    }
+  }
+}
+
+impl Style {
+  /// Writes the colors and metrics of the style, one `key = value` per line.
+  pub fn to_config_string(&self) -> String {
+    let mut style = self.clone();
+    style_fields(&mut style)
+      .iter()
+      .filter_map(|(key, field)| {
+        field
+          .to_value()
+          .map(|value| format!("{} = {}\n", key, value))
+      })
+      .collect()
+  }
+
+  /// Loads a style written by `to_config_string()`. Keys that are missing
+  /// keep their default value, unknown keys and invalid values are skipped
+  /// and reported in the returned warnings, one per line. Empty lines and
+  /// lines starting with `#` are ignored.
+  pub fn from_config_string(font: Font, config: &str) -> (Style, Vec<String>) {
+    let mut style = Style::new(font);
+    let mut warnings = vec![];
+    {
+      let mut fields = style_fields(&mut style);
+
+      config
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .for_each(|line| {
+          let mut kv = line.splitn(2, '=').map(|s| s.trim());
+          let (key, value) = match (kv.next(), kv.next()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => {
+              warnings.push(format!("ignoring malformed line '{}'", line));
+              return;
+            }
+          };
+
+          match fields.iter_mut().find(|(name, _)| name == key) {
+            Some((_, field)) => {
+              if !field.set_value(value) {
+                warnings.push(format!("invalid value for {}: {}", key, value));
+              }
+            }
+            None => warnings.push(format!("ignoring unknown key {}", key)),
+          }
+        });
+    }
+
+    (style, warnings)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_style_config_round_trip() {
+    let mut style = Style::new(Font::default());
+    style.button.rounding = 7f32;
+    style.window.padding = Vec2F32::new(3f32, 9f32);
+    style.window.background = RGBAColor::new_with_alpha(1, 2, 3, 4);
+    style.slider.normal = StyleItem::Color(RGBAColor::new(200, 100, 50));
+    style.scrollv.smooth_scroll = true;
+
+    let config = style.to_config_string();
+    let (loaded, warnings) = Style::from_config_string(
+      Font::default(),
+      &format!("# saved theme\nno.such.key = 1\n{}", config),
+    );
+
+    assert_eq!(warnings, vec!["ignoring unknown key no.such.key"]);
+
+    assert_eq!(loaded.button.rounding, 7f32);
+    assert_eq!(
+      (loaded.window.padding.x, loaded.window.padding.y),
+      (3f32, 9f32)
+    );
+    assert_eq!(loaded.window.background, style.window.background);
+    assert_eq!(
+      loaded.slider.normal.background_color(RGBAColor::default()),
+      RGBAColor::new(200, 100, 50)
+    );
+    assert!(loaded.scrollv.smooth_scroll);
+    assert_eq!(loaded.to_config_string(), config);
+  }
+
+  #[test]
+  fn test_style_config_missing_keys_keep_defaults() {
+    let defaults = Style::new(Font::default());
+    let (loaded, warnings) =
+      Style::from_config_string(Font::default(), "button.rounding = 12");
+
+    assert!(warnings.is_empty());
+    assert_eq!(loaded.button.rounding, 12f32);
+    assert_eq!(loaded.window.border, defaults.window.border);
+    assert_eq!(loaded.text.color, defaults.text.color);
+  }
+
+  #[test]
+  fn test_style_config_reports_bad_lines() {
+    let (loaded, warnings) = Style::from_config_string(
+      Font::default(),
+      "button.rounding\nbutton.border = thick\nbutton.rounding = 5",
+    );
+
+    assert_eq!(
+      warnings,
+      vec![
+        "ignoring malformed line 'button.rounding'",
+        "invalid value for button.border: thick",
+      ]
+    );
+    assert_eq!(loaded.button.rounding, 5f32);
+  }
+}
@@ -0,0 +1,208 @@
+// Writes draw commands (see `UiContext::commands_iter()`) as SVG elements,
+// so the generated UI can be looked at without a renderer. Scissors and
+// images are not exported.
+
+use crate::{
+  hmi::commands::{Command, LineStyle},
+  math::colors::RGBAColor,
+};
+
+use std::fmt::Write;
+
+fn svg_paint(attr: &str, c: RGBAColor) -> String {
+  format!(
+    "{}=\"rgb({},{},{})\" {}-opacity=\"{}\"",
+    attr,
+    c.r,
+    c.g,
+    c.b,
+    attr,
+    c.a as f32 / 255f32
+  )
+}
+
+fn svg_dash(style: LineStyle, line_thickness: u16) -> String {
+  style
+    .pattern(line_thickness as f32)
+    .map_or(String::new(), |(on, off)| {
+      format!("stroke-dasharray=\"{} {}\" ", on, off)
+    })
+}
+
+fn svg_escape(text: &str) -> String {
+  text.chars().fold(String::new(), |mut s, c| {
+    match c {
+      '&' => s.push_str("&amp;"),
+      '<' => s.push_str("&lt;"),
+      '>' => s.push_str("&gt;"),
+      '"' => s.push_str("&quot;"),
+      _ => s.push(c),
+    }
+    s
+  })
+}
+
+fn svg_points(points: &[crate::math::vec2::Vec2I16]) -> String {
+  points
+    .iter()
+    .map(|p| format!("{},{}", p.x, p.y))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn svg_element(out: &mut String, cmd: &Command) -> std::fmt::Result {
+  match cmd {
+    Command::Line(l) => writeln!(
+      out,
+      "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"{}\" {}{} \
+       />",
+      l.begin.x,
+      l.begin.y,
+      l.end.x,
+      l.end.y,
+      l.line_thickness,
+      svg_dash(l.style, l.line_thickness),
+      svg_paint("stroke", l.color)
+    ),
+    Command::Rect(r) => writeln!(
+      out,
+      "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" \
+       stroke-width=\"{}\" fill=\"none\" {} />",
+      r.x,
+      r.y,
+      r.w,
+      r.h,
+      r.rounding,
+      r.line_thickness,
+      svg_paint("stroke", r.color)
+    ),
+    Command::RectFilled(r) => writeln!(
+      out,
+      "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" {} />",
+      r.x,
+      r.y,
+      r.w,
+      r.h,
+      r.rounding,
+      svg_paint("fill", r.color)
+    ),
+    Command::RectFilledCorners(r) => writeln!(
+      out,
+      "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" {} />",
+      r.x,
+      r.y,
+      r.w,
+      r.h,
+      r.radii.iter().max().unwrap_or(&0),
+      svg_paint("fill", r.color)
+    ),
+    Command::Circle(c) => writeln!(
+      out,
+      "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" stroke-width=\"{}\" \
+       fill=\"none\" {} />",
+      c.x as f32 + c.w as f32 * 0.5f32,
+      c.y as f32 + c.h as f32 * 0.5f32,
+      c.w as f32 * 0.5f32,
+      c.h as f32 * 0.5f32,
+      c.line_thickness,
+      svg_paint("stroke", c.color)
+    ),
+    Command::CircleFilled(c) => writeln!(
+      out,
+      "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {} />",
+      c.x as f32 + c.w as f32 * 0.5f32,
+      c.y as f32 + c.h as f32 * 0.5f32,
+      c.w as f32 * 0.5f32,
+      c.h as f32 * 0.5f32,
+      svg_paint("fill", c.color)
+    ),
+    Command::TriangleFilled(t) => writeln!(
+      out,
+      "<polygon points=\"{}\" {} />",
+      svg_points(&[t.a, t.b, t.c]),
+      svg_paint("fill", t.color)
+    ),
+    Command::PolygonFilled(p) => writeln!(
+      out,
+      "<polygon points=\"{}\" {} />",
+      svg_points(&p.points),
+      svg_paint("fill", p.color)
+    ),
+    Command::Polyline(p) => writeln!(
+      out,
+      "<polyline points=\"{}\" stroke-width=\"{}\" fill=\"none\" {}{} />",
+      svg_points(&p.points),
+      p.line_thickness,
+      svg_dash(p.style, p.line_thickness),
+      svg_paint("stroke", p.color)
+    ),
+    Command::Text(t) => writeln!(
+      out,
+      "<text x=\"{}\" y=\"{}\" dominant-baseline=\"hanging\" {}>{}</text>",
+      t.x,
+      t.y,
+      svg_paint("fill", t.foreground),
+      svg_escape(&t.text)
+    ),
+    _ => Ok(()),
+  }
+}
+
+/// Builds an SVG document of the given size out of the draw commands.
+pub fn commands_to_svg<'a, I>(cmds: I, width: u32, height: u32) -> String
+where
+  I: IntoIterator<Item = &'a Command>,
+{
+  let mut out = format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+    width, height
+  );
+
+  cmds.into_iter().for_each(|cmd| {
+    // writing into a String can't fail
+    let _ = svg_element(&mut out, cmd);
+  });
+
+  out.push_str("</svg>\n");
+  out
+}
+
+/// Writes the draw commands as an SVG file.
+pub fn write_svg<'a, I, P>(
+  path: P,
+  cmds: I,
+  width: u32,
+  height: u32,
+) -> std::io::Result<()>
+where
+  I: IntoIterator<Item = &'a Command>,
+  P: AsRef<std::path::Path>,
+{
+  std::fs::write(path, commands_to_svg(cmds, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    hmi::{base::Consts, commands::CommandBuffer},
+    math::rectangle::RectangleF32,
+  };
+
+  #[test]
+  fn test_filled_rect_to_svg() {
+    let mut cmds = CommandBuffer::new(Some(Consts::null_rect()), 4);
+    cmds.fill_rect(
+      RectangleF32::new(10f32, 20f32, 30f32, 40f32),
+      0f32,
+      RGBAColor::new(255, 0, 0),
+    );
+
+    let svg = commands_to_svg(cmds.commands().iter(), 100, 100);
+
+    assert!(svg.contains(
+      "<rect x=\"10\" y=\"20\" width=\"30\" height=\"40\" rx=\"0\" \
+       fill=\"rgb(255,0,0)\" fill-opacity=\"1\" />"
+    ));
+  }
+}
@@ -16,6 +16,24 @@ use crate::{
 
 use enumflags2::BitFlags;
 
+/// Spacing of the lines of multi-line text: the line height is multiplied by
+/// `line` and a blank line (a paragraph break, `\n\n`) is `paragraph` pixels
+/// taller. The default keeps the font's line height.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextSpacing {
+  pub line:      f32,
+  pub paragraph: f32,
+}
+
+impl std::default::Default for TextSpacing {
+  fn default() -> Self {
+    TextSpacing {
+      line:      1f32,
+      paragraph: 0f32,
+    }
+  }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Text {
   pub padding:    Vec2F32,
@@ -29,7 +47,7 @@ pub fn widget_text(
   s: &str,
   t: &Text,
   align: BitFlags<TextAlign>,
-  f: Font,
+  f: &Font,
 ) {
   let b = RectangleF32 {
     h: b.h.max(2f32 * t.padding.y),
@@ -54,7 +72,7 @@ pub fn widget_text(
     }
   } else if align.intersects(TextAlign::AlignCentered) {
     let w = 1f32.max(2f32 * t.padding.x + text_width);
-    let x = b.x + t.padding.x + ((b.w - 2f32 * t.padding.x) - label.w) / 2f32;
+    let x = b.x + t.padding.x + ((b.w - 2f32 * t.padding.x) - w) / 2f32;
     let x = x.max(b.x + t.padding.x);
     let w = (x + w).min(b.x + b.w);
     let w = if w >= x { w - x } else { w };
@@ -70,8 +88,9 @@ pub fn widget_text(
 
   // align in y-axis
   let label = if align.intersects(TextAlign::AlignMiddle) {
-    let y = b.y + b.h * 0.5f32 - f.scale * 0.5f32;
-    let h = (b.h * 0.5f32).max(b.h - (b.h * 0.5f32 + f.scale * 0.5f32));
+    let y = center_line_y(&b, f);
+    let half_line = f.line_height() * 0.5f32;
+    let h = (b.h * 0.5f32).max(b.h - (b.h * 0.5f32 + half_line));
     RectangleF32 { y, h, ..label }
   } else if align.intersects(TextAlign::AlignBottom) {
     RectangleF32 {
@@ -86,12 +105,20 @@ pub fn widget_text(
   out.draw_text(label, s, f, t.background, t.text);
 }
 
+/// Top of a single line of text centered vertically in `b`. Centers the
+/// ascender to descender span of the font instead of its nominal size, so the
+/// glyphs sit in the middle whatever the height of `b`.
+pub fn center_line_y(b: &RectangleF32, f: &Font) -> f32 {
+  b.y + (b.h - f.line_height()) * 0.5f32
+}
+
 pub fn widget_text_wrap(
   out: &mut CommandBuffer,
   b: RectangleF32,
   s: &str,
   t: &Text,
-  f: Font,
+  spacing: TextSpacing,
+  f: &Font,
 ) {
   let text = Text {
     padding: Vec2F32::same(0f32),
@@ -111,29 +138,65 @@ pub fn widget_text_wrap(
     h: 2f32 * t.padding.y + f.scale,
   };
 
-  let (mut fitting, _width) = f.clamp_text(s, line.w);
-  let mut done = 0usize;
-  while done < s.len() {
-    if (fitting <= 0) || (line.y + line.h) >= (b.y + b.h) {
-      break;
+  let advance = (f.scale + 2f32 * t.padding.y) * spacing.line;
+  'lines: for src_line in s.split('\n') {
+    let mut rest = src_line.trim_end_matches('\r');
+    if rest.is_empty() {
+      line.y += advance + spacing.paragraph;
+      continue;
     }
 
-    widget_text(
-      out,
-      line,
-      &s[done ..],
-      &text,
-      TextAlign::AlignLeft.into(),
-      f,
-    );
-
-    done += fitting as usize;
-    line.y += f.scale + 2f32 * t.padding.y;
-    let (fres, _) = f.clamp_text(&s[done ..], line.w);
-    fitting = fres;
+    while !rest.is_empty() {
+      let (fitting, _width) = f.clamp_text(rest, line.w);
+      if (fitting <= 0) || (line.y + line.h) >= (b.y + b.h) {
+        break 'lines;
+      }
+
+      let cut = rest
+        .char_indices()
+        .nth(fitting as usize)
+        .map_or(rest.len(), |(offset, _)| offset);
+      widget_text(
+        out,
+        line,
+        &rest[.. cut],
+        &text,
+        TextAlign::AlignLeft.into(),
+        f,
+      );
+
+      rest = &rest[cut ..];
+      line.y += advance;
+    }
   }
 }
 
+/// Size of a multi-line text: the width of its widest line and the height
+/// of all its lines, each `height` tall before applying `spacing`. Tabs are
+/// measured from the start of their line.
+pub fn measure_text_block(
+  font: &Font,
+  height: f32,
+  spacing: TextSpacing,
+  text: &str,
+) -> Vec2F32 {
+  if text.is_empty() {
+    return Vec2F32::same(0f32);
+  }
+
+  let advance = height * spacing.line;
+  text
+    .split('\n')
+    .map(|line| line.trim_end_matches('\r'))
+    .fold(Vec2F32::same(0f32), |size, line| {
+      if line.is_empty() {
+        Vec2F32::new(size.x, size.y + advance + spacing.paragraph)
+      } else {
+        Vec2F32::new(size.x.max(font.text_width(line)), size.y + advance)
+      }
+    })
+}
+
 pub fn text_colored(
   win: WindowPtr,
   style: &Style,
@@ -155,7 +218,7 @@ pub fn text_colored(
     s,
     &text,
     align,
-    style.font,
+    &style.font,
   );
 }
 
@@ -178,6 +241,53 @@ pub fn text_wrap_colored(
     bounds,
     s,
     &text,
-    style.font,
+    style.text.spacing,
+    &style.font,
   );
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::text_engine::FontAtlas;
+
+  #[test]
+  fn test_measure_text_block_uses_longest_line() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+
+    let spacing = TextSpacing::default();
+
+    let size =
+      measure_text_block(&font, 10f32, spacing, "short\na longer line");
+    assert_eq!(size.x, font.text_width("a longer line"));
+    assert_eq!(size.y, 20f32);
+
+    assert_eq!(measure_text_block(&font, 10f32, spacing, "one").y, 10f32);
+    assert_eq!(measure_text_block(&font, 10f32, spacing, "").y, 0f32);
+  }
+
+  #[test]
+  fn test_line_spacing_scales_block_height() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let text = "first paragraph\n\nsecond paragraph\nends here";
+
+    let natural =
+      measure_text_block(&font, 10f32, TextSpacing::default(), text);
+    let airy = TextSpacing {
+      line: 1.5f32,
+      ..TextSpacing::default()
+    };
+    let airy = measure_text_block(&font, 10f32, airy, text);
+
+    assert_eq!(natural.y, 40f32);
+    assert_eq!(airy.y, natural.y * 1.5f32);
+    assert_eq!(airy.x, natural.x);
+
+    let spaced = TextSpacing {
+      paragraph: 6f32,
+      ..TextSpacing::default()
+    };
+    // one blank line between the paragraphs
+    assert_eq!(measure_text_block(&font, 10f32, spaced, text).y, 46f32);
+  }
+}
@@ -1,10 +1,18 @@
 #![allow(dead_code)]
 
 use freetype_sys::*;
-use std::collections::HashMap;
+use std::{
+  borrow::Cow,
+  cell::{Ref, RefCell},
+  collections::HashMap,
+  rc::Rc,
+};
 
 use crate::{
-  hmi::base::{DrawNullTexture, GenericHandle},
+  hmi::{
+    base::{DrawNullTexture, GenericHandle},
+    builtin_font,
+  },
   math::{
     colors::RGBAColor, rectangle::RectangleI32, utility::roundup_multiple_of,
     vec2::Vec2F32,
@@ -65,11 +73,33 @@ impl Span {
       const FT_RASTER_FLAG_AA: FT_Int = 0x1;
       const FT_RASTER_FLAG_DIRECT: FT_Int = 0x2;
 
-      let mut raster_params = std::mem::zeroed::<FT_Raster_Params>();
-
-      raster_params.flags = FT_RASTER_FLAG_AA | FT_RASTER_FLAG_DIRECT;
-      raster_params.gray_spans = Span::raster_callback;
-      raster_params.user = spans as *mut _ as *mut libc::c_void;
+      // only gray_spans is called for direct AA rendering, but the callbacks
+      // can't be null, so zeroing the params is undefined behaviour
+      extern "C" fn unused_bit_test(
+        _: i32,
+        _: i32,
+        _: *mut libc::c_void,
+      ) -> i32 {
+        0
+      }
+      extern "C" fn unused_bit_set(_: i32, _: i32, _: *mut libc::c_void) {}
+
+      let mut raster_params = FT_Raster_Params {
+        target:      std::ptr::null(),
+        source:      std::ptr::null(),
+        flags:       FT_RASTER_FLAG_AA | FT_RASTER_FLAG_DIRECT,
+        gray_spans:  Span::raster_callback,
+        black_spans: Span::raster_callback,
+        bit_test:    unused_bit_test,
+        bit_set:     unused_bit_set,
+        user:        spans as *mut _ as *mut libc::c_void,
+        clip_box:    FT_BBox {
+          xMin: 0,
+          yMin: 0,
+          xMax: 0,
+          yMax: 0,
+        },
+      };
 
       FT_Outline_Render(
         library,
@@ -117,6 +147,25 @@ impl Span {
   }
 }
 
+/// Scales the color by the coverage (alpha) of the texel.
+fn premultiplied(c: RGBAColor) -> RGBAColor {
+  let scale = |v: u8| ((v as u32 * c.a as u32 + 127) / 255) as u8;
+  RGBAColor::new_with_alpha(scale(c.r), scale(c.g), scale(c.b), c.a)
+}
+
+/// Combining diacritical marks (e.g. U+0301 in "e\u{301}"), drawn over the
+/// preceding base character instead of after it.
+pub fn is_combining_mark(c: char) -> bool {
+  match c as u32 {
+    0x0300 ..= 0x036F
+    | 0x1AB0 ..= 0x1AFF
+    | 0x1DC0 ..= 0x1DFF
+    | 0x20D0 ..= 0x20FF
+    | 0xFE20 ..= 0xFE2F => true,
+    _ => false,
+  }
+}
+
 impl ::std::default::Default for Span {
   fn default() -> Span {
     Span::new(0, 0, 0, 0)
@@ -129,6 +178,7 @@ pub struct FontConfigBuilder {
   glyph_range:    Vec<std::ops::Range<char>>,
   fallback_glyph: char,
   pixel_snap:     bool,
+  tab_width:      u32,
 }
 
 impl FontConfigBuilder {
@@ -139,6 +189,7 @@ impl FontConfigBuilder {
       glyph_range:    vec![],
       fallback_glyph: '?',
       pixel_snap:     false,
+      tab_width:      FontConfig::DEFAULT_TAB_WIDTH,
     }
   }
 
@@ -149,6 +200,33 @@ impl FontConfigBuilder {
     }]
   }
 
+  pub fn default_arabic_glyph_ranges() -> Vec<std::ops::Range<char>> {
+    use std::ops::Range;
+
+    vec![
+      Range {
+        start: 0x0020 as char,
+        end:   0x00FF as char,
+      },
+      Range {
+        start: '\u{600}',
+        end:   '\u{6FF}',
+      },
+      Range {
+        start: '\u{750}',
+        end:   '\u{77F}',
+      },
+      Range {
+        start: '\u{FB50}',
+        end:   '\u{FDFF}',
+      },
+      Range {
+        start: '\u{FE70}',
+        end:   '\u{FEFF}',
+      },
+    ]
+  }
+
   pub fn default_cyrillic_glyph_ranges() -> Vec<std::ops::Range<char>> {
     use std::ops::Range;
 
@@ -201,6 +279,12 @@ impl FontConfigBuilder {
     self
   }
 
+  /// Distance between tab stops, in widths of the space character.
+  pub fn tab_width(&mut self, spaces: u32) -> &mut Self {
+    self.tab_width = spaces;
+    self
+  }
+
   pub fn build(&mut self) -> FontConfig {
     if self.glyph_range.is_empty() {
       self.add_glyph_range(Self::default_glyph_ranges());
@@ -214,6 +298,7 @@ impl FontConfigBuilder {
       glyph_range,
       fallback_glyph: self.fallback_glyph,
       pixel_snap: self.pixel_snap,
+      tab_width: self.tab_width,
     }
   }
 }
@@ -225,9 +310,12 @@ pub struct FontConfig {
   pub glyph_range:    Vec<std::ops::Range<char>>,
   pub fallback_glyph: char,
   pub pixel_snap:     bool,
+  pub tab_width:      u32,
 }
 
 impl FontConfig {
+  pub const DEFAULT_TAB_WIDTH: u32 = 4;
+
   fn calc_xadvance(&self, advance: i32) -> f32 {
     if self.pixel_snap {
       ((advance as f32 + 0.5f32) as i32) as f32 + self.spacing.x
@@ -245,6 +333,21 @@ impl FontConfig {
   }
 }
 
+/// Base direction used when laying out a string. There is no shaping or BiDi
+/// reordering, right to left text simply advances the pen leftwards starting
+/// from the right edge of the bounds.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TextDirection {
+  LeftToRight,
+  RightToLeft,
+}
+
+impl std::default::Default for TextDirection {
+  fn default() -> Self {
+    TextDirection::LeftToRight
+  }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct FontMetrics {
   pub size:                f32,
@@ -300,12 +403,16 @@ impl FontMetrics {
   }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// A font of a `FontAtlas`. Fonts share the atlas they were added to, so it
+/// stays alive as long as any of its fonts.
+#[derive(Clone)]
 pub struct Font {
   pub scale:     f32,
   glyph_tbl: u32,
   face_tbl:  u32,
-  atlas:     *const FontAtlas,
+  // the atlas `FontAtlasBuilder::build()` fills in and returns, None for
+  // `Font::default()`
+  atlas:     Option<FontAtlasPtr>,
 }
 
 impl std::default::Default for Font {
@@ -314,18 +421,39 @@ impl std::default::Default for Font {
       scale:     std::f32::MAX,
       glyph_tbl: std::u32::MAX,
       face_tbl:  std::u32::MAX,
-      atlas:     std::ptr::null_mut(),
+      atlas:     None,
     }
   }
 }
 
+impl std::fmt::Debug for Font {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_struct("Font")
+      .field("scale", &self.scale)
+      .field("glyph_tbl", &self.glyph_tbl)
+      .field("face_tbl", &self.face_tbl)
+      .finish()
+  }
+}
+
+/// Hashes the font's identity and the state of its atlas, text drawn with
+/// the font hashes differently once glyphs were added to the atlas.
+impl std::hash::Hash for Font {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.scale.to_bits().hash(state);
+    self.glyph_tbl.hash(state);
+    self.face_tbl.hash(state);
+    self
+      .atlas
+      .as_ref()
+      .map(|atlas| (Rc::as_ptr(atlas) as usize, atlas.borrow().generation))
+      .hash(state);
+  }
+}
+
 impl Font {
-  fn atlas_ref(&self) -> Option<&FontAtlas> {
-    if self.atlas.is_null() {
-      None
-    } else {
-      Some(unsafe { &*self.atlas })
-    }
+  fn atlas_ref(&self) -> Option<Ref<'_, FontAtlas>> {
+    self.atlas.as_ref().map(|atlas| atlas.borrow())
   }
 
   pub fn texture(&self) -> GenericHandle {
@@ -340,18 +468,66 @@ impl Font {
       .map_or(DrawNullTexture::default(), |atlas| atlas.draw_null_texture)
   }
 
+  /// True if the glyphs were baked with premultiplied alpha, the draw list
+  /// must then emit premultiplied colors
+  /// (`ConvertConfig::premultiplied_alpha`).
+  pub fn premultiplied_alpha(&self) -> bool {
+    self
+      .atlas_ref()
+      .map_or(false, |atlas| atlas.premultiply_alpha)
+  }
+
+  /// RGBA8 pixels of the atlas texture with its width and height, only
+  /// available if the atlas was built with `keep_pixels(true)`.
+  pub fn texture_pixels(&self) -> Option<(Ref<'_, [u8]>, u32, u32)> {
+    self
+      .atlas_ref()
+      .filter(|atlas| !atlas.pixels.is_empty())
+      .map(|atlas| {
+        let (width, height) = atlas.pixels_size;
+        (
+          Ref::map(atlas, |atlas| atlas.pixels.as_slice()),
+          width,
+          height,
+        )
+      })
+  }
+
   pub fn query(&self, codept: char) -> FontGlyph {
     self
       .atlas_ref()
       .map_or(FontGlyph::default(), |atlas| atlas.query(self, codept))
   }
 
+  /// Distance from the top of the ascender to the bottom of the descender,
+  /// the height the glyphs of a line actually cover. Fonts without face
+  /// metrics use their size.
+  pub fn line_height(&self) -> f32 {
+    self
+      .atlas_ref()
+      .and_then(|atlas| {
+        atlas
+          .faces
+          .get(self.face_tbl as usize)
+          .map(|metrics| metrics.ascender + metrics.descender)
+      })
+      .unwrap_or(self.scale)
+  }
+
   pub fn text_width(&self, text: &str) -> f32 {
     self
       .atlas_ref()
       .map_or(0f32, |atlas| atlas.text_width(self, text))
   }
 
+  /// Horizontal advance of a codepoint, with the pen at `pen_x` relative to
+  /// the start of the text.
+  pub fn advance(&self, codepoint: char, pen_x: f32) -> f32 {
+    self
+      .atlas_ref()
+      .map_or(0f32, |atlas| atlas.advance(self, codepoint, pen_x))
+  }
+
   pub fn clamp_text(&self, text: &str, max_width: f32) -> (i32, f32) {
     self
       .atlas_ref()
@@ -363,6 +539,12 @@ impl Font {
       atlas.clamped_string(self, text, max_width)
     })
   }
+
+  pub fn clamp_text_words<'a>(&self, text: &'a str, max_width: f32) -> &'a str {
+    self
+      .atlas_ref()
+      .map_or("", |atlas| atlas.clamp_text_words(self, text, max_width))
+  }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -474,6 +656,34 @@ impl BakedGlyph {
       }
     }
   }
+
+  /// Glyph table entry, for a glyph placed in an atlas texture of
+  /// `width` x `height`.
+  fn font_glyph(
+    &self,
+    metrics: &FontMetrics,
+    width: u32,
+    height: u32,
+  ) -> FontGlyph {
+    let ipw = 1f32 / width as f32;
+    let iph = 1f32 / height as f32;
+
+    FontGlyph {
+      codepoint:       self.codepoint,
+      xadvance:        self.advance_x,
+      bearing_x:       self.bearing_x,
+      bearing_y:       metrics.ascender - self.bearing_y,
+      bbox:            RectangleI32::new(0, 0, self.bbox.w, self.bbox.h),
+      uv_top_left:     Vec2F32::new(
+        self.bbox.x as f32 * ipw,
+        self.bbox.y as f32 * iph,
+      ),
+      uv_bottom_right: Vec2F32::new(
+        (self.bbox.x + self.bbox.w) as f32 * ipw,
+        (self.bbox.y + self.bbox.h) as f32 * iph,
+      ),
+    }
+  }
 }
 
 /// Extract all spans from a rasterized glyph.
@@ -516,8 +726,74 @@ fn extract_glyph_spans(
   Some((bearing_x, bearing_y, advance_x, glyph_spans))
 }
 
-/// Packs font glyphs into a rectangular texture.
-fn pack_rects(rects: &mut [BakedGlyph]) -> (u32, u32, f32) {
+/// Places a box of `w` x `h` into one of the free spaces and updates the
+/// spaces around it. Returns the top-left corner of the box, or None if no
+/// space is large enough.
+fn place_rect(
+  spaces: &mut Vec<RectangleI32>,
+  w: i32,
+  h: i32,
+) -> Option<(i32, i32)> {
+  // look for empty spaces that can accomodate the box
+  let i = (0 .. spaces.len())
+    .rev()
+    .find(|&i| w <= spaces[i].w && h <= spaces[i].h)?;
+
+  // found the space; add the box to its top-left corner
+  // |-------|-------|
+  // |  box  |       |
+  // |_______|       |
+  // |         space |
+  // |_______________|
+  let pos = (spaces[i].x, spaces[i].y);
+
+  if w == spaces[i].w && h == spaces[i].h {
+    // space matches the box exactly, remove it
+    let last = spaces.pop().unwrap();
+    if i < spaces.len() {
+      spaces[i] = last;
+    }
+  } else if h == spaces[i].h {
+    // space matches the box width; update it accordingly
+    // |---------------|
+    // |      box      |
+    // |_______________|
+    // | updated space |
+    // |_______________|
+    spaces[i].x += w;
+    spaces[i].w -= w;
+  } else if w == spaces[i].w {
+    // space matches the box width; update it accordingly
+    // |---------------|
+    // |      box      |
+    // |_______________|
+    // | updated space |
+    // |_______________|
+    spaces[i].y += h;
+    spaces[i].h -= h;
+  } else {
+    // otherwise the box splits the space into two spaces
+    // |-------|-----------|
+    // |  box  | new space |
+    // |_______|___________|
+    // | updated space     |
+    // |___________________|
+    spaces.push(RectangleI32::new(
+      spaces[i].x + w,
+      spaces[i].y,
+      spaces[i].w - w,
+      h,
+    ));
+    spaces[i].y += h;
+    spaces[i].h -= h;
+  }
+
+  Some(pos)
+}
+
+/// Packs font glyphs into a rectangular texture. Also returns the spaces left
+/// free, the one below the packed glyphs has an unbounded height.
+fn pack_rects(rects: &mut [BakedGlyph]) -> (u32, u32, f32, Vec<RectangleI32>) {
   let (area, max_width) = rects.iter().fold((0, 0), |acc, r| {
     (acc.0 + r.bbox.w * r.bbox.h, acc.1.max(r.bbox.w))
   });
@@ -533,82 +809,27 @@ fn pack_rects(rects: &mut [BakedGlyph]) -> (u32, u32, f32) {
   let mut width = 0u32;
   let mut height = 0u32;
 
-  (0 .. rects.len()).for_each(|idx_box| {
+  rects.iter_mut().for_each(|rect| {
     // filter non-renderables
-    if rects[idx_box].bbox.w == 0 {
+    if rect.bbox.w == 0 {
       return;
     }
-    (0 .. spaces.len()).rev().any(|i| {
-      // look for empty spaces that can accomodate the current box
-      if rects[idx_box].bbox.w > spaces[i].w
-        || rects[idx_box].bbox.h > spaces[i].h
-      {
-        return false;
-      }
 
-      // found the space; add the box to its top-left corner
-      // |-------|-------|
-      // |  box  |       |
-      // |_______|       |
-      // |         space |
-      // |_______________|
-
-      rects[idx_box].bbox.x = spaces[i].x;
-      rects[idx_box].bbox.y = spaces[i].y;
-
-      width =
-        width.max(rects[idx_box].bbox.x as u32 + rects[idx_box].bbox.w as u32);
-      height =
-        height.max(rects[idx_box].bbox.y as u32 + rects[idx_box].bbox.h as u32);
-
-      if rects[idx_box].bbox.w == spaces[i].w
-        && rects[idx_box].bbox.h == spaces[i].h
-      {
-        // space matches the box exactly, remove it
-        let last = spaces.pop().unwrap();
-        if i < spaces.len() {
-          spaces[i] = last;
-        }
-      } else if rects[idx_box].bbox.h == spaces[i].h {
-        // space matches the box width; update it accordingly
-        // |---------------|
-        // |      box      |
-        // |_______________|
-        // | updated space |
-        // |_______________|
-        spaces[i].x += rects[idx_box].bbox.w;
-        spaces[i].w -= rects[idx_box].bbox.w;
-      } else if rects[idx_box].bbox.w == spaces[i].w {
-        // space matches the box width; update it accordingly
-        // |---------------|
-        // |      box      |
-        // |_______________|
-        // | updated space |
-        // |_______________|
-        spaces[i].y += rects[idx_box].bbox.h;
-        spaces[i].h -= rects[idx_box].bbox.h;
-      } else {
-        // otherwise the box splits the space into two spaces
-        // |-------|-----------|
-        // |  box  | new space |
-        // |_______|___________|
-        // | updated space     |
-        // |___________________|
-        spaces.push(RectangleI32::new(
-          spaces[i].x + rects[idx_box].bbox.w,
-          spaces[i].y,
-          spaces[i].w - rects[idx_box].bbox.w,
-          rects[idx_box].bbox.h,
-        ));
-        spaces[i].y += rects[idx_box].bbox.h;
-        spaces[i].h -= rects[idx_box].bbox.h;
-      }
+    if let Some((x, y)) = place_rect(&mut spaces, rect.bbox.w, rect.bbox.h) {
+      rect.bbox.x = x;
+      rect.bbox.y = y;
 
-      true
-    });
+      width = width.max(rect.bbox.x as u32 + rect.bbox.w as u32);
+      height = height.max(rect.bbox.y as u32 + rect.bbox.h as u32);
+    }
   });
 
-  (width, height, (area as f32 / (width * height) as f32))
+  (
+    width,
+    height,
+    (area as f32 / (width * height) as f32),
+    spaces,
+  )
 }
 
 pub struct FontAtlasBuilder {
@@ -618,11 +839,19 @@ pub struct FontAtlasBuilder {
   fonts:             Vec<Font>,
   faces:             Vec<FontMetrics>,
   configs:           Vec<FontConfig>,
+  // faces stay alive for baking glyphs later, they are moved into the atlas
+  ft_faces:          Vec<Option<UniqueResource<FreetypeFaceHandle>>>,
+  ttf_data:          Vec<Cow<'static, [u8]>>,
+  // glyph table of the first built-in font
+  builtin:           Option<u32>,
   stroker:           UniqueResource<FreetypeStrokerHandle>,
-  lib:               UniqueResource<FreetypeLibraryHandle>,
+  lib:               Rc<UniqueResource<FreetypeLibraryHandle>>,
   glyphs_texture:    GenericHandle,
   draw_null_texture: DrawNullTexture,
-  atlas:             *mut FontAtlas,
+  atlas:             FontAtlasPtr,
+  premultiply_alpha: bool,
+  keep_pixels:       bool,
+  reserve_height:    u32,
 }
 
 impl FontAtlasBuilder {
@@ -651,100 +880,200 @@ impl FontAtlasBuilder {
           fonts: Vec::new(),
           faces: Vec::new(),
           configs: Vec::new(),
+          ft_faces: Vec::new(),
+          ttf_data: Vec::new(),
+          builtin: None,
           stroker,
-          lib: ftlib,
+          lib: Rc::new(ftlib),
           glyphs_texture: GenericHandle::Id(0),
           draw_null_texture: DrawNullTexture {
             texture: GenericHandle::Id(0),
             uv:      Vec2F32::new(0f32, 0f32),
           },
-          atlas: Box::into_raw(Box::new(FontAtlas::new())),
+          atlas: Rc::new(RefCell::new(FontAtlas::new())),
+          premultiply_alpha: false,
+          keep_pixels: false,
+          reserve_height: 0,
         })
       })
     })
   }
 
-  /// Add a font into the atlas from various sources.
+  /// Bake the glyphs with premultiplied alpha (color scaled by coverage).
+  /// Set `ConvertConfig::premultiplied_alpha` too when this is on, so the
+  /// vertex colors match, and render with
+  /// `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)` instead of
+  /// `GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA`.
+  pub fn premultiply_alpha(&mut self, premultiply: bool) -> &mut Self {
+    self.premultiply_alpha = premultiply;
+    self
+  }
+
+  /// Keep a copy of the atlas pixels after uploading them, for rasterizing
+  /// text on the CPU (see `sys::headless::bake_text`).
+  pub fn keep_pixels(&mut self, keep: bool) -> &mut Self {
+    self.keep_pixels = keep;
+    self
+  }
+
+  /// Leave `height` rows of free space at the bottom of the atlas texture,
+  /// for glyphs baked on demand with `FontAtlas::ensure_glyph()`.
+  pub fn reserve_height(&mut self, height: u32) -> &mut Self {
+    self.reserve_height = height;
+    self
+  }
+
+  /// Add a font into the atlas from various sources. Fails if the font file
+  /// cannot be read or if the data is not a valid TTF font.
   pub fn add_font(
     &mut self,
     font: &FontConfig,
     font_source: TTFDataSource,
-  ) -> Option<Font> {
+  ) -> Result<Font, &'static str> {
     match font_source {
-      TTFDataSource::File(fpath) => {
-        MemoryMappedFile::new(&fpath).ok().and_then(|mapped_ttf| {
-          self.add_font_from_bytes(font, mapped_ttf.as_slice())
-        })
-      }
+      TTFDataSource::File(fpath) => MemoryMappedFile::new(&fpath)
+        .map_err(|_| "failed to read the font file!")
+        .and_then(|mapped_ttf| {
+          self.add_font_from_bytes(
+            font,
+            Cow::Owned(mapped_ttf.as_slice().to_vec()),
+          )
+        }),
       TTFDataSource::StaticBytes(bytes) => {
-        self.add_font_from_bytes(font, bytes)
+        self.add_font_from_bytes(font, Cow::Borrowed(bytes))
       }
       TTFDataSource::OwnedBytes(bytes) => {
-        self.add_font_from_bytes(font, &bytes)
+        self.add_font_from_bytes(font, Cow::Owned(bytes))
       }
     }
   }
 
+  /// Adds the built-in 8x8 bitmap font, printable ASCII only. It needs no
+  /// font file, so it can stand in for a font `add_font()` failed to load.
+  /// The glyphs are scaled by a whole factor, to come close to the configured
+  /// size. The atlas also draws characters its other fonts are missing with
+  /// the first built-in font added.
+  pub fn add_builtin_font(&mut self, font: &FontConfig) -> Font {
+    let pixel_size = font.size * self.dpi as f32 / 72f32;
+    let scale =
+      ((pixel_size / builtin_font::CELL_SIZE as f32).round() as u32).max(1);
+    let cell = (builtin_font::CELL_SIZE * scale) as i32;
+    let descent = (builtin_font::DESCENT * scale) as f32;
+
+    let face_metrics = FontMetrics {
+      size:                font.size,
+      height:              cell as f32,
+      ascender:            cell as f32 - descent,
+      descender:           descent,
+      max_advance_width:   cell as f32,
+      max_advance_height:  cell as f32,
+      underline_pos:       -descent,
+      underline_thickness: scale as f32,
+    };
+
+    let font_handle = self.fonts.len() as u32;
+    let face_handle = self.faces.len() as u32;
+
+    (' ' ..= '~').for_each(|codepoint| {
+      let rows = match builtin_font::glyph_rows(codepoint) {
+        Some(rows) => rows,
+        None => return,
+      };
+
+      // every glyph fills the whole cell, blank ones are not renderable
+      let (bbox, pixels) = if rows.iter().all(|row| *row == 0) {
+        (RectangleI32::new(0, 0, 0, 0), vec![])
+      } else {
+        let pixels = (0 .. cell * cell)
+          .map(|texel| {
+            let (x, y) = ((texel % cell) as u32, (texel / cell) as u32);
+            if rows[(y / scale) as usize] & (1 << (x / scale)) != 0 {
+              RGBAColor::new_with_alpha(255, 255, 255, 255)
+            } else {
+              RGBAColor::new_with_alpha(0, 0, 0, 0)
+            }
+          })
+          .collect();
+        (RectangleI32::new(0, 0, cell, cell), pixels)
+      };
+
+      self.baked_glyphs.push(BakedGlyph {
+        advance_x: font.calc_xadvance(cell),
+        bearing_x: 0f32,
+        bearing_y: face_metrics.ascender,
+        font: font_handle,
+        codepoint: codepoint as u32,
+        bbox,
+        pixels,
+      });
+    });
+
+    self.faces.push(face_metrics);
+    let this_font = Font {
+      scale:     font.size,
+      glyph_tbl: font_handle,
+      face_tbl:  face_handle,
+      atlas:     Some(Rc::clone(&self.atlas)),
+    };
+    self.fonts.push(this_font.clone());
+    self.glyphs.push(HashMap::new());
+    self.configs.push(font.clone());
+    // nothing to bake on demand, there are no glyphs beyond ASCII
+    self.ft_faces.push(None);
+    self.builtin.get_or_insert(font_handle);
+
+    this_font
+  }
+
   /// Builds the font atlas containing all the fonts and glyphs that were added
   /// to it.
   pub fn build<F>(
     &mut self,
     fn_device_glyph_image_upload: F,
-  ) -> Result<Box<FontAtlas>, &'static str>
+  ) -> Result<FontAtlasPtr, &'static str>
   where
     F: Fn(u32, u32, &[u8]) -> Option<(GenericHandle, DrawNullTexture)>,
   {
-    assert!(!self.fonts.is_empty(), "You forgot to add any fonts!");
-    assert!(
-      !self.baked_glyphs.is_empty(),
-      "You forgot to add any fonts!"
-    );
-
-    if self.baked_glyphs.is_empty() {
+    if self.fonts.is_empty() || self.baked_glyphs.is_empty() {
       return Err("no fonts added to the atlas !");
     }
 
-    let (atlas_width, atlas_height, _) = pack_rects(&mut self.baked_glyphs);
+    let (atlas_width, atlas_height, _, free_spaces) =
+      pack_rects(&mut self.baked_glyphs);
     if atlas_width == 0 || atlas_height == 0 {
       return Err("error packing font glyph rects!");
     }
 
     let (atlas_width, atlas_height) = (
       roundup_multiple_of(atlas_width, 4),
-      roundup_multiple_of(atlas_height, 4),
+      roundup_multiple_of(atlas_height + self.reserve_height, 4),
     );
 
+    // what is left of the texture is used by glyphs baked on demand
+    let free_spaces = free_spaces
+      .into_iter()
+      .filter_map(|space| {
+        let w = space.w.min(atlas_width as i32 - space.x);
+        let h = space.h.min(atlas_height as i32 - space.y);
+        if w > 0 && h > 0 {
+          Some(RectangleI32::new(space.x, space.y, w, h))
+        } else {
+          None
+        }
+      })
+      .collect::<Vec<_>>();
+
     // build the glyph tables
     let baked_glyphs = std::mem::replace(&mut self.baked_glyphs, vec![]);
-    let ipw = 1f32 / (atlas_width) as f32;
-    let iph = 1f32 / (atlas_height) as f32;
 
     baked_glyphs.iter().for_each(|baked_glyph| {
       let font_glyphs_table = &mut self.glyphs[baked_glyph.font as usize];
       let font_metrics = &self.faces[baked_glyph.font as usize];
 
-      let new_glyph = FontGlyph {
-        codepoint:       baked_glyph.codepoint,
-        xadvance:        baked_glyph.advance_x,
-        bearing_x:       baked_glyph.bearing_x,
-        bearing_y:       font_metrics.ascender - baked_glyph.bearing_y,
-        bbox:            RectangleI32::new(
-          0,
-          0,
-          baked_glyph.bbox.w,
-          baked_glyph.bbox.h,
-        ),
-        uv_top_left:     Vec2F32::new(
-          (baked_glyph.bbox.x) as f32 * ipw,
-          (baked_glyph.bbox.y) as f32 * iph,
-        ),
-        uv_bottom_right: Vec2F32::new(
-          (baked_glyph.bbox.x + baked_glyph.bbox.w) as f32 * ipw,
-          (baked_glyph.bbox.y + baked_glyph.bbox.h) as f32 * iph,
-        ),
-      };
-
-      font_glyphs_table.insert(baked_glyph.codepoint, new_glyph);
+      font_glyphs_table.insert(
+        baked_glyph.codepoint,
+        baked_glyph.font_glyph(font_metrics, atlas_width, atlas_height),
+      );
     });
 
     // copy glyph pixels into the atlas texture
@@ -753,13 +1082,19 @@ impl FontAtlasBuilder {
       (atlas_width * atlas_height) as usize
     ];
 
+    let premultiply = self.premultiply_alpha;
     baked_glyphs.iter().for_each(|baked_glyph| {
       let bbox = baked_glyph.bbox;
       let mut src_idx = 0u32;
       (bbox.y .. (bbox.y + bbox.h)).for_each(|y| {
         (bbox.x .. (bbox.x + bbox.w)).for_each(|x| {
           let dst_idx = (y as u32 * atlas_width + x as u32) as usize;
-          atlas_pixels[dst_idx] = baked_glyph.pixels[src_idx as usize];
+          let texel = baked_glyph.pixels[src_idx as usize];
+          atlas_pixels[dst_idx] = if premultiply {
+            premultiplied(texel)
+          } else {
+            texel
+          };
           src_idx += 1;
         });
       });
@@ -774,16 +1109,33 @@ impl FontAtlasBuilder {
 
     fn_device_glyph_image_upload(atlas_width, atlas_height, pixels_slice)
       .and_then(|(glyphs_texture, draw_null_texture)| {
-        // Move all data to the atlas, our job is done.
-        let mut boxed_atlas = unsafe { Box::from_raw(self.atlas) };
-        boxed_atlas.glyphs_texture = glyphs_texture;
-        boxed_atlas.draw_null_texture = draw_null_texture;
-        boxed_atlas.configs = std::mem::replace(&mut self.configs, vec![]);
-        boxed_atlas.faces = std::mem::replace(&mut self.faces, vec![]);
-        boxed_atlas.fonts = std::mem::replace(&mut self.fonts, vec![]);
-        boxed_atlas.glyphs = std::mem::replace(&mut self.glyphs, vec![]);
-
-        Some(boxed_atlas)
+        // Move all data to the atlas the fonts share, our job is done. Fonts
+        // added after this go into a new atlas.
+        let atlas = std::mem::replace(
+          &mut self.atlas,
+          Rc::new(RefCell::new(FontAtlas::new())),
+        );
+        {
+          let mut atlas = atlas.borrow_mut();
+          atlas.glyphs_texture = glyphs_texture;
+          atlas.draw_null_texture = draw_null_texture;
+          if self.keep_pixels {
+            atlas.pixels = pixels_slice.to_vec();
+          }
+          atlas.pixels_size = (atlas_width, atlas_height);
+          atlas.free_spaces = free_spaces;
+          atlas.premultiply_alpha = premultiply;
+          atlas.configs = std::mem::replace(&mut self.configs, vec![]);
+          atlas.faces = std::mem::replace(&mut self.faces, vec![]);
+          atlas.glyphs = std::mem::replace(&mut self.glyphs, vec![]);
+          atlas.ft_faces = std::mem::replace(&mut self.ft_faces, vec![]);
+          atlas.ttf_data = std::mem::replace(&mut self.ttf_data, vec![]);
+          atlas.builtin = self.builtin.take();
+          atlas.ft_lib = Some(Rc::clone(&self.lib));
+        }
+        self.fonts.clear();
+
+        Some(atlas)
       })
       .ok_or("Failed to upload atlas to device!")
   }
@@ -792,11 +1144,15 @@ impl FontAtlasBuilder {
   fn add_font_from_bytes(
     &mut self,
     font: &FontConfig,
-    ttf_bytes: &[u8],
-  ) -> Option<Font> {
+    ttf_bytes: Cow<'static, [u8]>,
+  ) -> Result<Font, &'static str> {
+    if ttf_bytes.is_empty() {
+      return Err("empty TTF font data!");
+    }
+
     UniqueResource::<FreetypeFaceHandle>::from_handle(unsafe {
       let mut face: FT_Face = std::ptr::null_mut();
-      FT_New_Memory_Face(
+      let err = FT_New_Memory_Face(
         *self.lib.handle(),
         ttf_bytes.as_ptr() as *const FT_Byte,
         ttf_bytes.len() as FT_Long,
@@ -804,9 +1160,14 @@ impl FontAtlasBuilder {
         &mut face as *mut _,
       );
 
-      face
+      if err != 0 {
+        std::ptr::null_mut()
+      } else {
+        face
+      }
     })
-    .and_then(|face| {
+    .ok_or("invalid TTF font data!")
+    .map(|face| {
       let face_metrics =
         FontMetrics::extract(*face.handle(), font.size, self.dpi);
 
@@ -884,51 +1245,216 @@ impl FontAtlasBuilder {
         scale:     font.size,
         glyph_tbl: font_handle,
         face_tbl:  face_handle,
-        atlas:     self.atlas,
+        atlas:     Some(Rc::clone(&self.atlas)),
       };
-      self.fonts.push(this_font);
+      self.fonts.push(this_font.clone());
       self.glyphs.push(HashMap::new());
       self.configs.push(font.clone());
+      // the face reads from the font data, so both are kept around
+      self.ft_faces.push(Some(face));
+      self.ttf_data.push(ttf_bytes);
 
-      Some(this_font)
+      this_font
     })
   }
 }
 
+pub type FontAtlasPtr = Rc<RefCell<FontAtlas>>;
+
 pub struct FontAtlas {
   glyphs:            Vec<HashMap<u32, FontGlyph>>,
-  fonts:             Vec<Font>,
   faces:             Vec<FontMetrics>,
   configs:           Vec<FontConfig>,
   glyphs_texture:    GenericHandle,
   draw_null_texture: DrawNullTexture,
+  pixels:            Vec<u8>,
+  pixels_size:       (u32, u32),
+  /// Unused parts of the texture, where `ensure_glyph()` places new glyphs.
+  free_spaces:       Vec<RectangleI32>,
+  premultiply_alpha: bool,
+  // faces are dropped before the data they read from and before the library,
+  // None for the built-in font
+  ft_faces:          Vec<Option<UniqueResource<FreetypeFaceHandle>>>,
+  ttf_data:          Vec<Cow<'static, [u8]>>,
+  ft_lib:            Option<Rc<UniqueResource<FreetypeLibraryHandle>>>,
+  /// Glyph table of the built-in font, used for glyphs no other font has.
+  builtin:           Option<u32>,
+  /// Bumped by `ensure_glyph()` each time it adds a glyph.
+  generation:        u32,
 }
 
 impl FontAtlas {
   fn new() -> FontAtlas {
     FontAtlas {
       glyphs:            vec![],
-      fonts:             vec![],
       faces:             vec![],
       configs:           vec![],
       glyphs_texture:    GenericHandle::Id(0),
       draw_null_texture: DrawNullTexture::default(),
+      pixels:            vec![],
+      pixels_size:       (0, 0),
+      free_spaces:       vec![],
+      premultiply_alpha: false,
+      ft_faces:          vec![],
+      ttf_data:          vec![],
+      ft_lib:            None,
+      builtin:           None,
+      generation:        0,
+    }
+  }
+
+  /// Passes the glyphs texture to `fn_delete` so the device texture is
+  /// freed deterministically, e.g. before reloading the fonts, and releases
+  /// the FreeType faces. Text can still be measured afterwards but no longer
+  /// drawn or baked. Calling it again does nothing.
+  pub fn destroy<F>(&mut self, fn_delete: F)
+  where
+    F: FnOnce(GenericHandle),
+  {
+    if self.glyphs_texture != GenericHandle::Id(0) {
+      fn_delete(self.glyphs_texture);
+      self.glyphs_texture = GenericHandle::Id(0);
+    }
+
+    self.ft_faces.clear();
+    self.ttf_data.clear();
+    self.ft_lib = None;
+  }
+
+  /// Returns the glyph of a codepoint, baking it first if it wasn't part of
+  /// the font's glyph ranges. The glyph goes into free space of the texture
+  /// (see `FontAtlasBuilder::reserve_height()`) and `fn_upload` receives the
+  /// texture, the region that changed and its RGBA8 pixels to update the
+  /// device texture. None if the font has no such glyph or the texture is
+  /// full.
+  pub fn ensure_glyph<F>(
+    &mut self,
+    font: &Font,
+    codepoint: char,
+    fn_upload: F,
+  ) -> Option<FontGlyph>
+  where
+    F: FnOnce(GenericHandle, RectangleI32, &[u8]),
+  {
+    let font_idx = font.glyph_tbl as usize;
+    if let Some(glyph) = self.glyphs.get(font_idx)?.get(&(codepoint as u32)) {
+      return Some(*glyph);
     }
+
+    let face = *self.ft_faces.get(font_idx)?.as_ref()?.handle();
+    let lib = *self.ft_lib.as_ref()?.handle();
+    let (bearing_x, bearing_y, advance_x, glyph_spans) =
+      extract_glyph_spans(codepoint as u32, face, lib)?;
+
+    let mut baked_glyph = BakedGlyph::new(
+      codepoint as u32,
+      font.glyph_tbl,
+      bearing_x as f32,
+      bearing_y as f32,
+      self.configs[font_idx].calc_xadvance(advance_x),
+      &glyph_spans,
+    );
+
+    if baked_glyph.bbox.w != 0 {
+      let (x, y) = place_rect(
+        &mut self.free_spaces,
+        baked_glyph.bbox.w,
+        baked_glyph.bbox.h,
+      )?;
+      baked_glyph.bbox.x = x;
+      baked_glyph.bbox.y = y;
+
+      let premultiply = self.premultiply_alpha;
+      let texels = baked_glyph
+        .pixels
+        .iter()
+        .map(|&texel| {
+          if premultiply {
+            premultiplied(texel)
+          } else {
+            texel
+          }
+        })
+        .collect::<Vec<_>>();
+      let texels_slice = unsafe {
+        std::slice::from_raw_parts(
+          texels.as_ptr() as *const u8,
+          texels.len() * std::mem::size_of::<RGBAColor>(),
+        )
+      };
+
+      // keep the CPU copy in sync
+      if !self.pixels.is_empty() {
+        let texel_size = std::mem::size_of::<RGBAColor>();
+        let row_len = baked_glyph.bbox.w as usize * texel_size;
+        (0 .. baked_glyph.bbox.h as usize).for_each(|row| {
+          let dst = ((y as usize + row) * self.pixels_size.0 as usize
+            + x as usize)
+            * texel_size;
+          self.pixels[dst .. dst + row_len].copy_from_slice(
+            &texels_slice[row * row_len .. (row + 1) * row_len],
+          );
+        });
+      }
+
+      fn_upload(self.glyphs_texture, baked_glyph.bbox, texels_slice);
+    }
+
+    let glyph = baked_glyph.font_glyph(
+      &self.faces[font.face_tbl as usize],
+      self.pixels_size.0,
+      self.pixels_size.1,
+    );
+    self.glyphs[font_idx].insert(glyph.codepoint, glyph);
+    self.generation += 1;
+    Some(glyph)
   }
 
-  /// Query the properties of a font's glyph.
+  /// Query the properties of a font's glyph. Glyphs the font doesn't have
+  /// come from the built-in font, if the atlas has one.
   pub fn query(&self, font: &Font, codepoint: char) -> FontGlyph {
     let glyph_table = &self.glyphs[font.glyph_tbl as usize];
     glyph_table
       .get(&(codepoint as u32))
+      .or_else(|| {
+        self.builtin.and_then(|builtin| {
+          self.glyphs[builtin as usize].get(&(codepoint as u32))
+        })
+      })
       .map_or(FontGlyph::default(), |glyph_entry| *glyph_entry)
   }
 
+  /// Distance between two tab stops of a font.
+  fn tab_stop(&self, font: &Font) -> f32 {
+    let tab_width = self
+      .configs
+      .get(font.glyph_tbl as usize)
+      .map_or(FontConfig::DEFAULT_TAB_WIDTH, |cfg| cfg.tab_width);
+    self.query(font, ' ').xadvance * tab_width as f32
+  }
+
+  /// Horizontal advance of a codepoint, with the pen at `pen_x` relative to
+  /// the start of the text. Tabs advance to the next tab stop, combining
+  /// marks don't advance.
+  pub fn advance(&self, font: &Font, codepoint: char, pen_x: f32) -> f32 {
+    if is_combining_mark(codepoint) {
+      return 0f32;
+    }
+
+    if codepoint == '\t' {
+      let tab_stop = self.tab_stop(font);
+      if tab_stop > 0f32 {
+        return ((pen_x / tab_stop).floor() + 1f32) * tab_stop - pen_x;
+      }
+    }
+
+    self.query(font, codepoint).xadvance
+  }
+
   /// Compute the length of a string using a certain font in the atlas.
   pub fn text_width(&self, font: &Font, text: &str) -> f32 {
     text.chars().fold(0f32, |curr_len, curr_char| {
-      let glyph = self.query(font, curr_char);
-      curr_len + glyph.xadvance
+      curr_len + self.advance(font, curr_char, curr_len)
     })
   }
 
@@ -941,11 +1467,11 @@ impl FontAtlas {
     let mut glyph_count = 0;
     let mut width = 0f32;
     text.chars().all(|codepoint| {
-      let glyph_info = self.query(font, codepoint);
-      if (width + glyph_info.xadvance) > max_width {
+      let xadvance = self.advance(font, codepoint, width);
+      if (width + xadvance) > max_width {
         false
       } else {
-        width += glyph_info.xadvance;
+        width += xadvance;
         glyph_count += 1;
         true
       }
@@ -954,6 +1480,37 @@ impl FontAtlas {
     (glyph_count, width)
   }
 
+  /// Longest prefix of the text that fits into `max_width` without breaking
+  /// a word. A single word that doesn't fit is cut at the character that
+  /// overflows.
+  pub fn clamp_text_words<'a>(
+    &self,
+    font: &Font,
+    text: &'a str,
+    max_width: f32,
+  ) -> &'a str {
+    let (glyph_count, _) = self.clamp_text(font, text, max_width);
+    let cut = text
+      .char_indices()
+      .nth(glyph_count as usize)
+      .map_or(text.len(), |(offset, _)| offset);
+
+    if cut == text.len() {
+      return text;
+    }
+
+    // the overflowing char starts a new word, nothing to backtrack
+    if text[cut ..].starts_with(char::is_whitespace) {
+      return text[.. cut].trim_end();
+    }
+
+    text[.. cut]
+      .rfind(char::is_whitespace)
+      .map(|ws| text[.. ws].trim_end())
+      .filter(|fitted| !fitted.is_empty())
+      .unwrap_or(&text[.. cut])
+  }
+
   /// Create a string by clamping some text to a specified maximum width.
   pub fn clamped_string(
     &self,
@@ -966,9 +1523,9 @@ impl FontAtlas {
     text
       .chars()
       .take_while(|codepoint| {
-        let glyph_info = self.query(font, *codepoint);
-        if (width + glyph_info.xadvance) < max_width {
-          width += glyph_info.xadvance;
+        let xadvance = self.advance(font, *codepoint, width);
+        if (width + xadvance) <= max_width {
+          width += xadvance;
           true
         } else {
           false
@@ -977,3 +1534,261 @@ impl FontAtlas {
       .collect()
   }
 }
+
+#[cfg(test)]
+impl FontAtlas {
+  /// Builds a font without FreeType where every printable ASCII glyph (and
+  /// the ellipsis) has the same advance. Only useful for measuring text.
+  pub(crate) fn monospace_for_tests(advance: f32) -> Font {
+    let mut atlas = FontAtlas::new();
+    let glyphs = ((' ' as u32) ..= ('~' as u32))
+      .chain(std::iter::once('\u{2026}' as u32))
+      .map(|codepoint| {
+        (
+          codepoint,
+          FontGlyph {
+            codepoint,
+            xadvance: advance,
+            ..FontGlyph::default()
+          },
+        )
+      })
+      .collect();
+
+    atlas.glyphs.push(glyphs);
+    atlas.configs.push(FontConfigBuilder::new().build());
+    Font {
+      scale:     advance,
+      glyph_tbl: 0,
+      face_tbl:  0,
+      atlas:     Some(Rc::new(RefCell::new(atlas))),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_combining_mark_does_not_advance() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+    // a spacing glyph for the mark, like some fonts have
+    font.atlas.as_ref().unwrap().borrow_mut().glyphs[0].insert(
+      0x301,
+      FontGlyph {
+        codepoint: 0x301,
+        xadvance: 8f32,
+        ..FontGlyph::default()
+      },
+    );
+
+    assert_eq!(font.text_width("e\u{301}"), font.text_width("e"));
+    assert_eq!(font.text_width("e\u{301}x"), font.text_width("ex"));
+    assert_eq!(font.clamp_text("e\u{301}x", 16f32), (3, 16f32));
+  }
+
+  #[test]
+  fn test_clamp_text_words() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+
+    // 12 chars fit, "hello wonder" would cut "wonderful"
+    assert_eq!(
+      font.clamp_text_words("hello wonderful world", 96f32),
+      "hello"
+    );
+    assert_eq!(
+      font.clamp_text_words("hello world again", 96f32),
+      "hello world"
+    );
+    assert_eq!(font.clamp_text_words("short", 96f32), "short");
+  }
+
+  #[test]
+  fn test_clamp_text_words_long_word() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+
+    assert_eq!(
+      font.clamp_text_words("incomprehensibilities", 40f32),
+      "incom"
+    );
+  }
+
+  #[test]
+  fn test_add_font_rejects_invalid_ttf_data() {
+    let mut builder = FontAtlasBuilder::new(96).unwrap();
+    let cfg = FontConfigBuilder::new().build();
+
+    assert!(builder
+      .add_font(&cfg, TTFDataSource::StaticBytes(b"definitely not a font"))
+      .is_err());
+    assert!(builder
+      .add_font(&cfg, TTFDataSource::OwnedBytes(vec![]))
+      .is_err());
+    assert!(builder
+      .add_font(
+        &cfg,
+        TTFDataSource::File(std::path::PathBuf::from("missing-font.ttf"))
+      )
+      .is_err());
+  }
+
+  #[test]
+  fn test_build_empty_atlas_is_an_error() {
+    let mut builder = FontAtlasBuilder::new(96).unwrap();
+    let result = builder.build(|_, _, _| {
+      Some((
+        GenericHandle::Id(1),
+        DrawNullTexture {
+          texture: GenericHandle::Id(2),
+          uv:      Vec2F32::new(0f32, 0f32),
+        },
+      ))
+    });
+
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn test_ensure_glyph_bakes_missing_codepoint() {
+    let mut builder = FontAtlasBuilder::new(96).unwrap();
+    builder.reserve_height(32);
+    let font = builder
+      .add_font(
+        &FontConfigBuilder::new().size(14f32).build(),
+        TTFDataSource::File(std::path::PathBuf::from("DroidSans.ttf")),
+      )
+      .unwrap();
+    let atlas = builder
+      .build(|_, _, _| Some((GenericHandle::Id(1), DrawNullTexture::default())))
+      .unwrap();
+
+    // Cyrillic is not in the default glyph ranges
+    assert_eq!(font.query('\u{416}').codepoint, 0);
+
+    let mut uploaded = None;
+    let glyph = atlas
+      .borrow_mut()
+      .ensure_glyph(&font, '\u{416}', |texture, region, pixels| {
+        uploaded = Some((texture, region, pixels.len()));
+      })
+      .unwrap();
+
+    let (texture, region, len) = uploaded.unwrap();
+    assert_eq!(texture, GenericHandle::Id(1));
+    assert!(glyph.bbox.w > 0 && glyph.bbox.h > 0);
+    assert_eq!((region.w, region.h), (glyph.bbox.w, glyph.bbox.h));
+    assert_eq!(len, (region.w * region.h * 4) as usize);
+    assert!(glyph.uv_bottom_right.x <= 1f32 && glyph.uv_bottom_right.y <= 1f32);
+    assert_eq!(font.query('\u{416}').codepoint, '\u{416}' as u32);
+
+    // already baked, nothing to upload
+    assert!(atlas
+      .borrow_mut()
+      .ensure_glyph(&font, '\u{416}', |_, _, _| panic!("uploaded twice"))
+      .is_some());
+  }
+
+  #[test]
+  fn test_fonts_use_the_built_atlas() {
+    let upload = |_: u32, _: u32, _: &[u8]| {
+      Some((GenericHandle::Id(1), DrawNullTexture::default()))
+    };
+    let cfg = FontConfigBuilder::new().size(14f32).build();
+    let ttf = || TTFDataSource::File(std::path::PathBuf::from("DroidSans.ttf"));
+
+    let (font, atlas, other_font, other_atlas) = {
+      let mut builder = FontAtlasBuilder::new(96).unwrap();
+      let font = builder.add_font(&cfg, ttf()).unwrap();
+      let atlas = builder.build(upload).unwrap();
+      // building again only contains the fonts added in between
+      let other_font = builder.add_font(&cfg, ttf()).unwrap();
+      let other_atlas = builder.build(upload).unwrap();
+      (font, atlas, other_font, other_atlas)
+    };
+
+    assert!(Rc::ptr_eq(font.atlas.as_ref().unwrap(), &atlas));
+    assert!(Rc::ptr_eq(other_font.atlas.as_ref().unwrap(), &other_atlas));
+
+    let glyph = font.query('A');
+    assert_eq!(glyph.codepoint, 'A' as u32);
+    assert!(glyph.bbox.w > 0 && glyph.xadvance > 0f32);
+    assert_eq!(other_font.query('A').xadvance, glyph.xadvance);
+  }
+
+  #[test]
+  fn test_fonts_keep_the_atlas_alive() {
+    let font = FontAtlasBuilder::new(96)
+      .and_then(|mut builder| {
+        let font = builder
+          .add_font(
+            &FontConfigBuilder::new().size(14f32).build(),
+            TTFDataSource::File(std::path::PathBuf::from("DroidSans.ttf")),
+          )
+          .ok()?;
+        builder
+          .build(|_, _, _| {
+            Some((GenericHandle::Id(1), DrawNullTexture::default()))
+          })
+          .ok()?;
+        Some(font)
+      })
+      .unwrap();
+
+    // builder and returned atlas are gone, the font holds the last reference
+    assert_eq!(Rc::strong_count(font.atlas.as_ref().unwrap()), 1);
+    assert_eq!(font.query('A').codepoint, 'A' as u32);
+    assert_eq!(font.texture(), GenericHandle::Id(1));
+    assert!(font.text_width("AA") > 0f32);
+  }
+
+  #[test]
+  fn test_destroy_deletes_texture_once() {
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let atlas = font.atlas.clone().unwrap();
+    atlas.borrow_mut().glyphs_texture = GenericHandle::Id(7);
+
+    let mut deleted = vec![];
+    atlas.borrow_mut().destroy(|texture| deleted.push(texture));
+    atlas.borrow_mut().destroy(|texture| deleted.push(texture));
+
+    assert_eq!(deleted, vec![GenericHandle::Id(7)]);
+    assert_eq!(font.texture(), GenericHandle::Id(0));
+  }
+
+  #[test]
+  fn test_builtin_font_needs_no_ttf() {
+    use crate::sys::headless::bake_text;
+
+    // at 72 dpi an 8pt font keeps the 8x8 cells unscaled
+    let mut builder = FontAtlasBuilder::new(72).unwrap();
+    builder.keep_pixels(true);
+    let font =
+      builder.add_builtin_font(&FontConfigBuilder::new().size(8f32).build());
+    builder
+      .build(|_, _, _| Some((GenericHandle::Id(1), DrawNullTexture::default())))
+      .unwrap();
+
+    let glyph = font.query('A');
+    assert_eq!(glyph.codepoint, 'A' as u32);
+    assert_eq!((glyph.bbox.w, glyph.bbox.h), (8, 8));
+    assert_eq!(font.text_width("Hi!"), 24f32);
+    assert_eq!(font.query(' ').xadvance, 8f32);
+    assert_eq!(font.line_height(), 8f32);
+
+    // the bar of the 'A' is set from column 0 to 5 in row 4
+    let (pixels, width, _) =
+      bake_text(&font, "A", RGBAColor::new(255, 255, 255)).unwrap();
+    let alpha = |x: u32, y: u32| pixels[((y * width + x) * 4 + 3) as usize];
+    assert!(alpha(2, 4) > 0);
+    assert_eq!(alpha(7, 4), 0);
+  }
+
+  #[test]
+  fn test_premultiplied_glyph_texel() {
+    let (_, pixels) = Span::convert_to_pixels(&[Span::new(0, 0, 1, 127)]);
+    let texel = premultiplied(pixels[0]);
+
+    assert_eq!((texel.r, texel.g, texel.b, texel.a), (127, 127, 127, 127));
+  }
+}
@@ -1,33 +1,41 @@
 use crate::{
   hmi::{
     base::{
-      AntialiasingType, ButtonBehaviour, Consts, ConvertConfig, HashType,
-      TextAlign, WidgetLayoutStates, WidgetStates,
+      AccessibleWidget, AntialiasingType, ButtonBehaviour, ClipboardProvider,
+      Consts, ConvertConfig, HashType, TextAlign, WidgetLayoutStates,
+      WidgetRole, WidgetStates,
     },
+    buffer::ScratchArena,
     commands::{Command, CommandBuffer},
+    frame_stats::FrameStats,
+    geometry_cache::GeometryCache,
     image::Image,
-    input::{Input, MouseButtonId},
+    input::{Input, KeyId, MouseButtonId},
     panel::{LayoutFormat, Panel, PanelFlags, PanelRowLayoutType, PanelType},
+    progress::ProgressLabel,
+    scrollbar::{
+      do_scrollbar, draw_scrollbar, scrollbar_layout, ScrollbarAxis,
+    },
     style::{
-      ConfigurationStacks, Style, StyleButton, StyleHeaderAlign, StyleItem,
-      SymbolType,
+      ConfigurationStacks, Style, StyleButton, StyleCursor, StyleHeaderAlign,
+      StyleItem, SymbolType, Theme,
     },
     text_engine::Font,
-    vertex_output::{DrawCommand, DrawIndexType, DrawList},
-    window::Window,
+    vertex_output::{DrawCommand, DrawIndexType, DrawList, DrawListStats},
+    window::{PropertyStatus, ScrollState, TableState, Window},
   },
   math::{
     colors::RGBAColor,
     rectangle::RectangleF32,
     utility::{clamp, saturate},
-    vec2::Vec2F32,
+    vec2::{Vec2F32, Vec2U32},
     vertex_types::VertexPTC,
   },
 };
 
 use enumflags2::BitFlags;
 use murmurhash64::murmur_hash64a;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, ops::Range, rc::Rc};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum CollapseStates {
@@ -67,14 +75,21 @@ enum WindowInsertLocation {
 
 pub type WindowPtr = Rc<RefCell<Window>>;
 
+/// True if both point to the same window. Every window keeps its handle and
+/// its allocation for its whole lifetime, so comparing the allocations is the
+/// same as comparing `id.handle`, without borrowing either window.
+fn same_window(a: &WindowPtr, b: &WindowPtr) -> bool {
+  Rc::ptr_eq(a, b)
+}
+
 pub struct CommandsIterator<'a> {
-  cmds:   Vec<*const Command>,
+  cmds:   &'a [*const Command],
   pos:    usize,
   marker: std::marker::PhantomData<&'a Command>,
 }
 
 impl<'a> CommandsIterator<'a> {
-  fn new(cmds: Vec<*const Command>) -> CommandsIterator<'a> {
+  fn new(cmds: &'a [*const Command]) -> CommandsIterator<'a> {
     CommandsIterator {
       cmds,
       pos: 0usize,
@@ -97,6 +112,32 @@ impl<'a> std::iter::Iterator for CommandsIterator<'a> {
   }
 }
 
+/// One frame of the UI, see `UiContext::begin_frame()`. Derefs to the
+/// context and calls `clear()` when dropped, which ends the frame.
+pub struct Frame<'a> {
+  ctx: &'a mut UiContext,
+}
+
+impl<'a> std::ops::Deref for Frame<'a> {
+  type Target = UiContext;
+
+  fn deref(&self) -> &UiContext {
+    self.ctx
+  }
+}
+
+impl<'a> std::ops::DerefMut for Frame<'a> {
+  fn deref_mut(&mut self) -> &mut UiContext {
+    self.ctx
+  }
+}
+
+impl<'a> std::ops::Drop for Frame<'a> {
+  fn drop(&mut self) {
+    self.ctx.clear();
+  }
+}
+
 pub struct UiContext {
   pub input:             RefCell<Input>,
   pub style:             Style,
@@ -108,29 +149,63 @@ pub struct UiContext {
   // TODO: text edit support
   overlay: RefCell<CommandBuffer>,
   // windows
-  windows:        RefCell<Vec<WindowPtr>>,
-  active_win:     RefCell<Option<WindowPtr>>,
-  current_win:    RefCell<Option<WindowPtr>>,
-  seq:            u32,
-  win_handle_seq: usize,
-  commands_buff:  Vec<*const Command>,
+  windows:               RefCell<Vec<WindowPtr>>,
+  active_win:            RefCell<Option<WindowPtr>>,
+  current_win:           RefCell<Option<WindowPtr>>,
+  seq:                   u32,
+  win_handle_seq:        usize,
+  commands_buff:         ScratchArena<*const Command>,
+  batches:               Vec<(Option<usize>, f32, Range<usize>)>,
+  frame_stats:           FrameStats,
+  cursor_drawn:          bool,
+  geometry_cache:        GeometryCache,
+  debug_draw:            bool,
+  animations:            RefCell<HashMap<HashType, f32>>,
+  accessible_next:       RefCell<Option<(String, WidgetRole)>>,
+  accessibility:         RefCell<Vec<AccessibleWidget>>,
+  // mouse entered/left the last allocated widget this frame
+  hover_edges:           RefCell<(bool, bool)>,
+  display_size:          Option<Vec2F32>,
+  drag_margin:           f32,
+  id_stack:              Vec<HashType>,
+  modal:                 Option<HashType>,
+  // id of the table being built and the column of its next cell
+  table:                 Option<(HashType, usize)>,
+  // command buffer positions where the open begin_disabled() blocks start
+  disabled:              Vec<usize>,
+  clipboard:             Option<Box<dyn ClipboardProvider>>,
+  // size needed by the windows built since measure_begin()
+  measure:               Option<Vec2F32>,
+  // true while a combo box lays out the items of its drop down
+  combo_items:           bool,
 }
 
 impl UiContext {
+  /// Default width of the header strip that stays on screen when a window
+  /// is dragged towards the display edges.
+  pub const DEFAULT_DRAG_MARGIN: f32 = 32f32;
+  /// Opacity multiplied into the colors of disabled widgets.
+  pub const DISABLED_ALPHA: f32 = 0.5f32;
+  /// Distance in pixels the mouse has to move with the button held before
+  /// it counts as dragging.
+  pub const DRAG_THRESHOLD: f32 = 3f32;
+  /// Narrowest a table column can be made by dragging its separator.
+  pub const TABLE_MIN_COLUMN_WIDTH: f32 = 8f32;
+
   pub fn new(
     font: Font,
     config: ConvertConfig,
     line_aa: AntialiasingType,
     shape_aa: AntialiasingType,
-  ) -> UiContext {
-    Self {
+  ) -> Result<UiContext, String> {
+    Ok(Self {
       input:             RefCell::new(Input::new()),
       style:             Style::new(font),
       last_widget_state: RefCell::new(BitFlags::default()),
       button_behviour:   ButtonBehaviour::default(),
       stacks:            ConfigurationStacks::default(),
       delta_time_sec:    0f32,
-      draw_list:         DrawList::new(config, line_aa, shape_aa),
+      draw_list:         DrawList::new(config, line_aa, shape_aa)?,
       overlay:           RefCell::new(CommandBuffer::new(
         Some(RectangleF32::new(
           -8192_f32, -8192_f32, 16834_f32, 16834_f32,
@@ -142,10 +217,174 @@ impl UiContext {
       active_win:        RefCell::new(None),
       seq:               0,
       win_handle_seq:    0,
-      commands_buff:     vec![],
+      commands_buff:     ScratchArena::new(),
+      batches:           vec![],
+      frame_stats:       FrameStats::new(),
+      cursor_drawn:      false,
+      geometry_cache:    GeometryCache::new(),
+      debug_draw:        false,
+      animations:        RefCell::new(HashMap::new()),
+      accessible_next:   RefCell::new(None),
+      accessibility:     RefCell::new(vec![]),
+      hover_edges:       RefCell::new((false, false)),
+      display_size:      None,
+      drag_margin:       Self::DEFAULT_DRAG_MARGIN,
+      id_stack:          vec![],
+      modal:             None,
+      table:             None,
+      disabled:          vec![],
+      measure:           None,
+      clipboard:         None,
+      combo_items:       false,
+    })
+  }
+
+  /// Moves the value stored for `id` towards `target` by at most
+  /// `speed * delta_time_sec` and returns it. A new id starts out at the
+  /// target. The value doesn't move during the measure pass.
+  pub fn animate(&self, id: HashType, target: f32, speed: f32) -> f32 {
+    if self.measure.is_some() {
+      return self.animations.borrow().get(&id).copied().unwrap_or(target);
+    }
+
+    let max_step = speed * self.delta_time_sec;
+    let mut animations = self.animations.borrow_mut();
+    let value = animations.entry(id).or_insert(target);
+
+    let delta = target - *value;
+    *value = if delta.abs() <= max_step {
+      target
+    } else {
+      *value + max_step * delta.signum()
+    };
+
+    *value
+  }
+
+  /// Disables the widgets up to the matching `end_disabled()`: they ignore
+  /// input and are drawn grayed out. Blocks can be nested, but have to end
+  /// in the window they were started in.
+  pub fn begin_disabled(&mut self) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    let first = self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(0, |winptr| winptr.borrow().buffer_mut().len());
+    self.disabled.push(first);
+  }
+
+  pub fn end_disabled(&mut self) {
+    debug_assert!(self.current_win.borrow().is_some());
+    debug_assert!(
+      !self.disabled.is_empty(),
+      "end_disabled() without begin_disabled()"
+    );
+
+    let first = self.disabled.pop();
+    if !self.disabled.is_empty() {
+      // the outermost block grays out everything once
+      return;
+    }
+
+    first.map(|first| {
+      self.current_win.borrow().as_ref().map(|winptr| {
+        winptr.borrow().buffer_mut().map_colors(first, |c| {
+          let gray = c.to_grayscale();
+          RGBAColor {
+            a: (gray.a as f32 * Self::DISABLED_ALPHA) as u8,
+            ..gray
+          }
+        });
+      });
+    });
+  }
+
+  /// Clipboard used by widgets that copy text, like `label_selectable`.
+  pub fn set_clipboard(&mut self, clipboard: Box<dyn ClipboardProvider>) {
+    self.clipboard = Some(clipboard);
+  }
+
+  /// Size of the display the windows are drawn on. Movable windows are kept
+  /// inside it so that their header can always be grabbed again.
+  pub fn set_display_size(&mut self, size: Vec2F32) {
+    self.display_size = Some(size);
+  }
+
+  /// How much of the header (horizontally) must stay on screen when a window
+  /// is dragged past the left or right display edge.
+  pub fn set_window_drag_margin(&mut self, margin: f32) {
+    self.drag_margin = margin.max(0f32);
+  }
+
+  /// Replaces the style with one built from the theme's colors. The font
+  /// and the cursors are kept.
+  pub fn set_theme(&mut self, theme: Theme) {
+    let current = &self.style;
+    self.style = Style {
+      cursors: current.cursors,
+      cursor_active: current.cursor_active,
+      cursor_last: current.cursor_last,
+      cursor_visible: current.cursor_visible,
+      ..Style::from_theme(current.font.clone(), theme)
+    };
+  }
+
+  /// Pushes an id that gets mixed into the ids of the widgets that follow,
+  /// so widgets with the same label (e.g. in a loop) keep separate state.
+  pub fn push_id(&mut self, id: i32) {
+    let id = murmur_hash64a(&id.to_le_bytes(), self.id_seed());
+    self.id_stack.push(id);
+  }
+
+  pub fn push_id_str(&mut self, id: &str) {
+    let id = murmur_hash64a(id.as_bytes(), self.id_seed());
+    self.id_stack.push(id);
+  }
+
+  pub fn pop_id(&mut self) {
+    debug_assert!(!self.id_stack.is_empty(), "pop_id() without push_id()");
+    self.id_stack.pop();
+  }
+
+  fn id_seed(&self) -> HashType {
+    self.id_stack.last().cloned().unwrap_or(64)
+  }
+
+  /// Id of a widget identified by `label`, taking the id stack into account.
+  fn widget_id(&self, label: &str) -> HashType {
+    murmur_hash64a(label.as_bytes(), self.id_seed())
+  }
+
+  /// Outlines every allocated widget (green valid, orange read only, red
+  /// invalid) and every window clip rectangle (magenta) in the overlay.
+  pub fn set_debug_draw(&mut self, enabled: bool) {
+    self.debug_draw = enabled;
+  }
+
+  fn debug_outline(&self, r: RectangleF32, color: RGBAColor) {
+    // the measure pass draws nothing, not even in the overlay
+    if self.debug_draw && self.measure.is_none() {
+      self.overlay.borrow_mut().stroke_rect(r, 0f32, 1f32, color);
     }
   }
 
+  /// Attaches `name` and `role` to the next widget that gets allocated. The
+  /// recorded widgets can be read with `accessible_widgets()` until the next
+  /// call to `clear()`.
+  pub fn widget_set_accessible(&self, name: &str, role: WidgetRole) {
+    self.accessible_next.replace(Some((name.to_string(), role)));
+  }
+
+  /// Widgets labeled with `widget_set_accessible()` during this frame, in
+  /// allocation order.
+  pub fn accessible_widgets(
+    &self,
+  ) -> std::cell::Ref<'_, Vec<AccessibleWidget>> {
+    self.accessibility.borrow()
+  }
+
   pub fn input_mut(&self) -> std::cell::RefMut<Input> {
     self.input.borrow_mut()
   }
@@ -154,12 +393,62 @@ impl UiContext {
     self.input.borrow()
   }
 
+  /// Command buffer drawn on top of all windows. Reset by `clear()`.
+  pub fn overlay_mut(&self) -> std::cell::RefMut<'_, CommandBuffer> {
+    self.overlay.borrow_mut()
+  }
+
+  /// Starts a frame that lasted `dt` seconds. Build and convert the UI
+  /// through the returned guard, dropping it calls `clear()`.
+  pub fn begin_frame(&mut self, dt: f32) -> Frame<'_> {
+    debug_assert!(
+      self.current_win.borrow().is_none(),
+      "if this triggers you missed an end() call"
+    );
+
+    self.delta_time_sec = dt;
+    Frame { ctx: self }
+  }
+
   pub fn clear(&mut self) {
-    self.commands_buff.clear();
+    // a panic between begin() and end() drops the frame with the window still
+    // open, a second panic would abort
+    debug_assert!(
+      std::thread::panicking() || self.current_win.borrow().is_none(),
+      "if this triggers you missed an end() call"
+    );
+    // a block left open would disable the widgets of all the frames after
+    // this one
+    let disabled_open = !self.disabled.is_empty();
+    self.disabled.clear();
+    debug_assert!(
+      std::thread::panicking() || !disabled_open,
+      "if this triggers you missed an end_disabled() call"
+    );
+    // an id left pushed would change the ids, and so the state, of all the
+    // widgets in the frames after this one
+    let ids_pushed = !self.id_stack.is_empty();
+    self.id_stack.clear();
+    debug_assert!(
+      std::thread::panicking() || !ids_pushed,
+      "if this triggers you missed a pop_id() call"
+    );
+
+    self.commands_buff.reset();
     self.last_widget_state.replace(BitFlags::default());
-    // TODO: fix cursors
-    // ctx->style.cursor_active = ctx->style.cursors[NK_CURSOR_ARROW];
+    self.style.cursor_active = StyleCursor::CursorArrow as usize;
+    self.cursor_drawn = false;
     self.overlay.borrow_mut().clear();
+    self.accessible_next.replace(None);
+    self.accessibility.borrow_mut().clear();
+    self.hover_edges.replace((false, false));
+    self.geometry_cache.reset_stats();
+    // drops the geometry of windows no viewport drew this frame
+    self.geometry_cache.end_frame();
+    self
+      .input
+      .borrow_mut()
+      .update_key_repeat(self.delta_time_sec);
 
     // TODO: bad code, rewrite later
     let win_count = self.windows.borrow().len();
@@ -220,11 +509,51 @@ impl UiContext {
 
   fn finish(&mut self, _win: WindowPtr) {}
 
-  fn build(&mut self) -> Vec<*const Command> {
-    // TODO: draw cursor overlay
+  fn build(&mut self) -> &[*const Command] {
+    self.build_for_viewport(None)
+  }
+
+  /// Collects the commands of all visible windows, or only of the windows
+  /// assigned to `viewport` if given. The overlay goes to the default
+  /// viewport.
+  fn build_for_viewport(&mut self, viewport: Option<u32>) -> &[*const Command] {
+    let start = self.commands_buff.len();
+    self.build_batches(viewport);
+    self.commands_buff.get(start .. self.commands_buff.len())
+  }
+
+  /// Same as `build_for_viewport()` but keeps the commands of each window
+  /// separate, tagged with the window's handle and alpha. Overlay commands
+  /// have no handle. The commands go into the frame's scratch arena and the
+  /// batches, holding ranges of it, into `self.batches`.
+  fn build_batches(&mut self, viewport: Option<u32>) {
+    // draw cursor overlay (only once per frame, build() may be called
+    // multiple times)
+    if !self.cursor_drawn
+      && self.style.cursor_visible
+      && !self.input.borrow().mouse.grabbed
+    {
+      self.style.active_cursor().map(|cursor| {
+        let mouse_pos = self.input.borrow().mouse.pos;
+        let r = RectangleF32::new(
+          mouse_pos.x - cursor.offset.x,
+          mouse_pos.y - cursor.offset.y,
+          cursor.size.x,
+          cursor.size.y,
+        );
+        self.overlay.borrow_mut().draw_image(
+          r,
+          cursor.img,
+          RGBAColor::new(255, 255, 255),
+        );
+      });
+      self.cursor_drawn = true;
+    }
 
-    // build one big draw command list out of all window buffers
-    let mut cmds_buff: Vec<*const Command> = vec![];
+    // build one command list for each window buffer
+    let batches = &mut self.batches;
+    let commands_buff = &mut self.commands_buff;
+    batches.clear();
     let ctx_seq = self.seq;
     self
       .windows
@@ -246,16 +575,19 @@ impl UiContext {
           return false;
         }
 
-        true
+        // window belongs to another viewport
+        viewport.map_or(true, |id| wndptr.borrow().viewport == id)
       })
       .for_each(|wndptr| {
-        // collect all draw commands for this window into the draw command
-        // buffer
+        // collect all draw commands for this window
         let (cmds_ptr, cmds_len) =
           wndptr.borrow().buffer.borrow().commands_range();
-        (0 .. cmds_len).for_each(|cmd_offset| unsafe {
-          cmds_buff.push(cmds_ptr.offset(cmd_offset as isize));
-        })
+        let cmds = commands_buff.alloc_extend(
+          (0 .. cmds_len)
+            .map(|cmd_offset| unsafe { cmds_ptr.offset(cmd_offset as isize) }),
+        );
+        let wnd = wndptr.borrow();
+        batches.push((Some(wnd.id.borrow().handle), wnd.alpha, cmds));
       });
 
     // append all popup draw commands into lists
@@ -264,9 +596,18 @@ impl UiContext {
 
     });
 
-    // append overlay commands
-
-    cmds_buff
+    // append overlay commands, so they are drawn on top of everything else
+    let overlay = self.overlay.borrow();
+    let overlay_in_viewport =
+      viewport.map_or(true, |id| id == Window::DEFAULT_VIEWPORT);
+    if overlay_in_viewport && !overlay.is_empty() {
+      let (cmds_ptr, cmds_len) = overlay.commands_range();
+      let cmds = commands_buff.alloc_extend(
+        (0 .. cmds_len)
+          .map(|cmd_offset| unsafe { cmds_ptr.offset(cmd_offset as isize) }),
+      );
+      batches.push((None, 1f32, cmds));
+    }
   }
 
   pub fn commands_iter(&mut self) -> CommandsIterator {
@@ -279,8 +620,138 @@ impl UiContext {
     vertices: &'a mut Vec<VertexPTC>,
     elements: &'a mut Vec<DrawIndexType>,
   ) {
-    let commands = self.build();
-    self.draw_list.convert(&commands, vertices, elements, cmds);
+    self.convert_batches(None, cmds, vertices, elements);
+  }
+
+  /// Converts window by window, reusing the cached geometry of windows whose
+  /// commands did not change.
+  fn convert_batches(
+    &mut self,
+    viewport: Option<u32>,
+    cmds: &mut Vec<DrawCommand>,
+    vertices: &mut Vec<VertexPTC>,
+    elements: &mut Vec<DrawIndexType>,
+  ) {
+    self.build_batches(viewport);
+
+    for (key, alpha, batch) in self.batches.iter() {
+      self.geometry_cache.convert(
+        &mut self.draw_list,
+        *key,
+        *alpha,
+        self.commands_buff.get(batch.clone()),
+        cmds,
+        vertices,
+        elements,
+      );
+    }
+  }
+
+  /// Number of times window geometry was generated instead of being taken
+  /// from the cache.
+  pub fn geometry_regenerations(&self) -> u32 {
+    self.geometry_cache.regenerations()
+  }
+
+  /// Stats of the geometry converted during this frame, including the
+  /// windows whose geometry was taken from the cache. Reset by `clear()`.
+  pub fn convert_stats(&self) -> DrawListStats {
+    self.geometry_cache.stats()
+  }
+
+  /// Replaces the settings the commands are converted to vertices with, the
+  /// geometry of all windows is generated again. An invalid config is
+  /// rejected and the current one kept.
+  pub fn set_convert_config(
+    &mut self,
+    config: ConvertConfig,
+  ) -> Result<(), String> {
+    self.draw_list.set_config(config)
+  }
+
+  /// Like `convert()` but only emits draw data for the windows assigned to
+  /// the viewport (see `window_set_viewport()`).
+  pub fn convert_for_viewport<'a>(
+    &mut self,
+    viewport: u32,
+    cmds: &'a mut Vec<DrawCommand>,
+    vertices: &'a mut Vec<VertexPTC>,
+    elements: &'a mut Vec<DrawIndexType>,
+  ) {
+    self.convert_batches(Some(viewport), cmds, vertices, elements);
+  }
+
+  /// Debug overlay in the top left corner showing the FPS and a graph of
+  /// the recent frame times. Call once per frame.
+  pub fn frame_stats_overlay(&mut self, dt: f32) {
+    self.frame_stats.push(dt);
+
+    let chart = self.style.chart;
+    let font = self.style.font.clone();
+    let bounds = RectangleF32::new(4f32, 4f32, 160f32, 64f32);
+    let mut overlay = self.overlay.borrow_mut();
+
+    let text_background = match chart.background {
+      StyleItem::Img(img) => {
+        let white = RGBAColor::new(255, 255, 255);
+        overlay.draw_image(bounds, img, white);
+        white
+      }
+      StyleItem::Color(c) => {
+        overlay.fill_rect(bounds, chart.rounding, chart.border_color);
+        overlay.fill_rect(
+          RectangleF32::shrink(&bounds, chart.border),
+          chart.rounding,
+          c,
+        );
+        c
+      }
+    };
+
+    let pad = chart.padding;
+    let text_rect = RectangleF32::new(
+      bounds.x + pad.x,
+      bounds.y + pad.y,
+      bounds.w - 2f32 * pad.x,
+      font.scale,
+    );
+    overlay.draw_text(
+      text_rect,
+      &format!(
+        "{:.1} FPS ({:.2} ms)",
+        self.frame_stats.fps(),
+        self.frame_stats.average_frame_time() * 1000f32
+      ),
+      &font,
+      text_background,
+      self.style.text.color,
+    );
+
+    if self.frame_stats.len() < 2 {
+      return;
+    }
+
+    let graph = RectangleF32::new(
+      text_rect.x,
+      text_rect.y + text_rect.h + pad.y,
+      text_rect.w,
+      (bounds.y + bounds.h - pad.y) - (text_rect.y + text_rect.h + pad.y),
+    );
+    let max_time = self.frame_stats.max_frame_time().max(1.0e-6f32);
+    let step = graph.w / (FrameStats::HISTORY_SIZE - 1) as f32;
+    let points = self
+      .frame_stats
+      .iter()
+      .enumerate()
+      .flat_map(|(idx, t)| {
+        vec![
+          graph.x + idx as f32 * step,
+          graph.y + graph.h - (t / max_time) * graph.h,
+        ]
+      })
+      .collect::<Vec<_>>();
+
+    overlay.stroke_polyline(&points, 1f32, chart.color);
   }
 
   fn alloc_win_handle(&mut self) -> usize {
@@ -355,7 +826,7 @@ impl UiContext {
       .windows
       .borrow()
       .iter()
-      .position(|winptr| *winptr.borrow() == *win.borrow());
+      .position(|winptr| same_window(winptr, &win));
 
     window_pos.map(|win_idx| {
       self.windows.borrow_mut().remove(win_idx);
@@ -366,7 +837,7 @@ impl UiContext {
       Some(()), // no active window yet
       |winptr| {
         // the window to be removed was the active window
-        if *winptr.borrow() == *win.borrow() {
+        if same_window(winptr, &win) {
           Some(())
         } else {
           None
@@ -473,15 +944,51 @@ impl UiContext {
       self.current_win.borrow_mut().replace(winptr);
       return false;
     } else {
-      winptr.borrow().start();
+      let mut win = winptr.borrow_mut();
+      win.start();
+      win.popup.combo_count = 0;
     }
 
-    // window overlapping
-    self.do_window_overlapping(Rc::clone(&winptr));
+    let is_modal = self
+      .modal
+      .map_or(false, |modal| modal == winptr.borrow().id.borrow().name);
+    if is_modal {
+      self.modal_window_begin(&winptr);
+    } else if self.modal.is_some() {
+      // only the modal window gets input while it is open
+      winptr
+        .borrow_mut()
+        .flags
+        .insert(PanelFlags::WindowRom | PanelFlags::WindowRemoveRom);
+    } else {
+      // window overlapping
+      self.do_window_overlapping(Rc::clone(&winptr));
+    }
     self.current_win.borrow_mut().replace(Rc::clone(&winptr));
     self.panel_begin(title, PanelType::Window.into())
   }
 
+  /// Keeps the modal window on top and active, and dims everything behind it
+  /// by drawing the backdrop first in its command buffer.
+  fn modal_window_begin(&mut self, winptr: &WindowPtr) {
+    if !self.is_last_window(winptr) {
+      self.remove_window(Rc::clone(winptr));
+      self.insert_window(Rc::clone(winptr), WindowInsertLocation::Back);
+    }
+
+    winptr.borrow_mut().flags.remove(PanelFlags::WindowRom);
+    self.active_win.borrow_mut().replace(Rc::clone(winptr));
+
+    let backdrop = self.display_size.map_or(Consts::null_rect(), |size| {
+      RectangleF32::new(0f32, 0f32, size.x, size.y)
+    });
+    winptr.borrow().buffer_mut().fill_rect(
+      backdrop,
+      0f32,
+      self.style.window.modal_backdrop,
+    );
+  }
+
   fn find_window_index_by_handle(&self, handle: usize) -> Option<usize> {
     self
       .windows
@@ -495,9 +1002,7 @@ impl UiContext {
       .active_win
       .borrow()
       .as_ref()
-      .map_or(false, |active_win| {
-        *active_win.borrow().id.borrow() == *wndptr.borrow().id.borrow()
-      })
+      .map_or(false, |active_win| same_window(active_win, wndptr))
   }
 
   fn is_last_window(&self, wndptr: &WindowPtr) -> bool {
@@ -505,7 +1010,7 @@ impl UiContext {
       .windows
       .borrow()
       .last()
-      .map_or(false, |last_wnd| *last_wnd.borrow() == *wndptr.borrow())
+      .map_or(false, |last_wnd| same_window(last_wnd, wndptr))
   }
 
   fn do_window_overlapping(&mut self, winptr: WindowPtr) {
@@ -521,14 +1026,18 @@ impl UiContext {
       + 2f32 * self.style.window.header.padding.y
       + 2f32 * self.style.window.header.label_padding.y;
 
-    let win_bounds = if !flags.contains(PanelFlags::WindowMinimized) {
-      *winptr.borrow().bounds.borrow()
-    } else {
-      RectangleF32 {
-        h,
-        ..*winptr.borrow().bounds.borrow()
+    // minimized windows only cover their header
+    let visible_bounds = |w: &Window| {
+      if !w.flags.contains(PanelFlags::WindowMinimized) {
+        *w.bounds.borrow()
+      } else {
+        RectangleF32 {
+          h,
+          ..*w.bounds.borrow()
+        }
       }
     };
+    let win_bounds = visible_bounds(&winptr.borrow());
 
     let inpanel = self.input.borrow().has_mouse_click_down_in_rect(
       MouseButtonId::ButtonLeft,
@@ -539,139 +1048,85 @@ impl UiContext {
       .borrow()
       .is_button_clicked(MouseButtonId::ButtonLeft);
 
+    // an active popup of a window above also covers this window
+    let popup_covers = |w: &Window| {
+      w.popup.active
+        && !w.flags.contains(PanelFlags::WindowHidden)
+        && w.popup.win.as_ref().map_or(false, |popup_win| {
+          win_bounds.intersect(&popup_win.borrow().bounds())
+        })
+    };
+
+    // windows drawn after (above) this one
+    let windows_above = self
+      .find_window_index_by_handle(winptr.borrow().id.borrow().handle)
+      .map_or(vec![], |idx| self.windows.borrow()[idx + 1 ..].to_vec());
+
+    let mut iter = Some(Rc::clone(&winptr));
+
     // activate window if hovered and no other window is overlapping this window
     if !self.is_active_window(&winptr)
       && self.input.borrow().is_mouse_hovering_rect(&win_bounds)
       && !self.input.borrow().is_mouse_down(MouseButtonId::ButtonLeft)
     {
-      self
-        .find_window_index_by_handle(winptr.borrow().id.borrow().handle)
-        .and_then(|idx| {
-          if self.windows.borrow().len() >= (idx + 1) {
-            return None;
-          }
-
-          let iter = self.windows.borrow()[idx + 1 ..]
-            .iter()
-            .find(|itr| {
-              let iter_flags = itr.borrow().flags;
-
-              let iter_bounds =
-                if !iter_flags.contains(PanelFlags::WindowMinimized) {
-                  *itr.borrow().bounds.borrow()
-                } else {
-                  RectangleF32 {
-                    h,
-                    ..*itr.borrow().bounds.borrow()
-                  }
-                };
-
-              if iter_bounds.intersect(&win_bounds)
-                && !iter_flags.contains(PanelFlags::WindowHidden)
-              {
-                return true;
-              }
-
-              let res = itr.borrow().popup.active
-                && !iter_flags.contains(PanelFlags::WindowHidden)
-                && itr.borrow().popup.win.as_ref().map_or(false, |popup_win| {
-                  win_bounds.intersect(&popup_win.borrow().bounds())
-                });
-
-              res
-            })
-            .map(|wp| Rc::clone(wp));
-
-          // activate window if clicked
-          let iter = iter.and_then(|win| {
-            if !inpanel || self.is_last_window(&winptr) {
-              return None;
-            }
-            // try to find a panel with higher priority in the same position
-            self
-              .find_window_index_by_handle(win.borrow().id.borrow().handle)
-              .and_then(|idx| {
-                let window_list = self.windows.borrow();
-
-                if window_list.len() >= (idx + 1) {
-                  return None;
-                }
-
-                window_list[idx + 1 ..]
-                  .iter()
-                  .find(|iter| {
-                    let iter_flags = iter.borrow().flags;
-                    let iter_bounds =
-                      if !iter_flags.contains(PanelFlags::WindowMinimized) {
-                        *iter.borrow().bounds.borrow()
-                      } else {
-                        RectangleF32 {
-                          h,
-                          ..*iter.borrow().bounds.borrow()
-                        }
-                      };
-
-                    let mouse_pos = self.input.borrow().mouse.pos;
-                    if iter_bounds.contains_point(mouse_pos.x, mouse_pos.y)
-                      && !iter_flags.contains(PanelFlags::WindowHidden)
-                    {
-                      return true;
-                    }
-
-                    let res = iter.borrow().popup.active
-                      && !iter_flags.contains(PanelFlags::WindowHidden)
-                      && iter.borrow().popup.win.as_ref().map_or(
-                        false,
-                        |popup_win| {
-                          win_bounds.intersect(&popup_win.borrow().bounds())
-                        },
-                      );
-
-                    res
-                  })
-                  .map(|wp| Rc::clone(wp))
-              })
-          });
+      iter = windows_above
+        .iter()
+        .find(|itr| {
+          let itr = itr.borrow();
+          (visible_bounds(&itr).intersect(&win_bounds)
+            && !itr.flags.contains(PanelFlags::WindowHidden))
+            || popup_covers(&itr)
+        })
+        .map(Rc::clone);
+    }
 
-          if iter.is_some()
-            && !flags.contains(PanelFlags::WindowRom)
-            && flags.contains(PanelFlags::WindowBackground)
-          {
-            winptr.borrow_mut().flags.insert(PanelFlags::WindowRom);
-            let iter = iter.unwrap();
-            iter.borrow_mut().flags.remove(PanelFlags::WindowRom);
-            self.active_win.borrow_mut().replace(Rc::clone(&iter));
-            if !iter.borrow().flags.contains(PanelFlags::WindowBackground) {
-              // current window is active in that position so transfer to top
-              // at the highest priority in stack
-              self.remove_window(Rc::clone(&iter));
-              self.insert_window(iter, WindowInsertLocation::Back);
-            }
-          } else {
-            if iter.is_none() && !self.is_last_window(&winptr) {
-              if !winptr.borrow().flags.contains(PanelFlags::WindowBackground) {
-                // current window is active in that position so transfer to top
-                // at the highest priority in stack
-                self.remove_window(Rc::clone(&winptr));
-                self.insert_window(
-                  Rc::clone(&winptr),
-                  WindowInsertLocation::Back,
-                );
-              }
+    // activate window if clicked
+    if iter.is_some() && inpanel && !self.is_last_window(&winptr) {
+      // try to find a panel with higher priority in the same position
+      let mouse_pos = self.input.borrow().mouse.pos;
+      iter = windows_above
+        .iter()
+        .find(|itr| {
+          let itr = itr.borrow();
+          (visible_bounds(&itr).contains_point(mouse_pos.x, mouse_pos.y)
+            && !itr.flags.contains(PanelFlags::WindowHidden))
+            || popup_covers(&itr)
+        })
+        .map(Rc::clone);
+    }
 
-              winptr.borrow_mut().flags.remove(PanelFlags::WindowRom);
-              self.active_win.borrow_mut().replace(Rc::clone(&winptr));
-            }
+    if iter.is_some()
+      && !flags.contains(PanelFlags::WindowRom)
+      && flags.contains(PanelFlags::WindowBackground)
+    {
+      winptr.borrow_mut().flags.insert(PanelFlags::WindowRom);
+      let iter = iter.unwrap();
+      iter.borrow_mut().flags.remove(PanelFlags::WindowRom);
+      self.active_win.borrow_mut().replace(Rc::clone(&iter));
+      if !iter.borrow().flags.contains(PanelFlags::WindowBackground) {
+        // current window is active in that position so transfer to top
+        // at the highest priority in stack
+        self.remove_window(Rc::clone(&iter));
+        self.insert_window(iter, WindowInsertLocation::Back);
+      }
+    } else {
+      if iter.is_none() && !self.is_last_window(&winptr) {
+        if !winptr.borrow().flags.contains(PanelFlags::WindowBackground) {
+          // current window is active in that position so transfer to top
+          // at the highest priority in stack
+          self.remove_window(Rc::clone(&winptr));
+          self.insert_window(Rc::clone(&winptr), WindowInsertLocation::Back);
+        }
 
-            if !self.is_last_window(&winptr)
-              && !winptr.borrow().flags.contains(PanelFlags::WindowBackground)
-            {
-              winptr.borrow_mut().flags.insert(PanelFlags::WindowRom);
-            }
-          }
+        winptr.borrow_mut().flags.remove(PanelFlags::WindowRom);
+        self.active_win.borrow_mut().replace(Rc::clone(&winptr));
+      }
 
-          Some(())
-        });
+      if !self.is_last_window(&winptr)
+        && !winptr.borrow().flags.contains(PanelFlags::WindowBackground)
+      {
+        winptr.borrow_mut().flags.insert(PanelFlags::WindowRom);
+      }
     }
   }
 
@@ -695,9 +1150,73 @@ impl UiContext {
       self.panel_end();
     }
 
+    if self.measure.is_some() {
+      self.measure_window();
+    }
+
     *self.current_win.borrow_mut() = None;
   }
 
+  /// Starts measuring: the windows built until `measure_end()` only lay out
+  /// their widgets, they neither draw nor react to input.
+  pub fn measure_begin(&mut self) {
+    debug_assert!(
+      self.current_win.borrow().is_none(),
+      "measure_begin() must be called outside of begin()/end()"
+    );
+    self.measure = Some(Vec2F32::same(0f32));
+  }
+
+  /// Ends measuring and returns the size a window needs to show all the
+  /// content laid out since `measure_begin()`, the largest one if several
+  /// windows were measured. Build the UI again afterwards to draw it.
+  pub fn measure_end(&mut self) -> Vec2F32 {
+    debug_assert!(
+      self.current_win.borrow().is_none(),
+      "measure_end() must be called outside of begin()/end()"
+    );
+    self.measure.take().unwrap_or(Vec2F32::same(0f32))
+  }
+
+  /// Runs `ui` in measure mode, see `measure_begin()`.
+  pub fn measure<F>(&mut self, ui: F) -> Vec2F32
+  where
+    F: FnOnce(&mut UiContext),
+  {
+    self.measure_begin();
+    ui(self);
+    self.measure_end()
+  }
+
+  /// Adds the extent of the current window to the measured size and throws
+  /// away what it drew. The window is marked as not used in this frame, so it
+  /// can be begun again to draw it.
+  fn measure_window(&mut self) {
+    let seq = self.seq;
+    let size = self.current_win.borrow().as_ref().map(|winptr| {
+      winptr.borrow_mut().seq = seq.wrapping_sub(1);
+      let win = winptr.borrow();
+      let layout = win.layout.borrow();
+      let padding = self.style.get_panel_padding(layout.typ);
+      let content = layout.offsets.borrow().content;
+      win.buffer_mut().reset();
+
+      Vec2F32::new(
+        content.x + 2f32 * (padding.x + layout.border),
+        layout.header_height
+          + content.y
+          + padding.y
+          + layout.footer_height
+          + 2f32 * layout.border,
+      )
+    });
+
+    if let (Some(measured), Some(size)) = (self.measure.as_mut(), size) {
+      measured.x = measured.x.max(size.x);
+      measured.y = measured.y.max(size.y);
+    }
+  }
+
   pub fn window_get_bounds(&self) -> RectangleF32 {
     debug_assert!(self.current_win.borrow().is_some());
     self
@@ -770,9 +1289,7 @@ impl UiContext {
           .active_win
           .borrow()
           .as_ref()
-          .map_or(false, |active_win| {
-            *curr_win.borrow() == *active_win.borrow()
-          })
+          .map_or(false, |active_win| same_window(curr_win, active_win))
       })
   }
 
@@ -834,6 +1351,42 @@ impl UiContext {
     })
   }
 
+  /// True when the UI uses the mouse this frame: it is over a window or
+  /// popup, or a button held down was pressed on a window (dragging a
+  /// slider, scrollbar or the window itself). The application should ignore
+  /// mouse input then.
+  pub fn wants_capture_mouse(&self) -> bool {
+    if self.window_is_any_hovered() {
+      return true;
+    }
+
+    let input = self.input.borrow();
+    let pressed_on_window = |pos: Vec2F32| {
+      self.windows.borrow().iter().any(|winptr| {
+        let win = winptr.borrow();
+        !win.flags.contains(PanelFlags::WindowHidden)
+          && win.bounds.borrow().contains_point(pos.x, pos.y)
+      })
+    };
+
+    input
+      .mouse
+      .buttons
+      .iter()
+      .any(|btn| btn.down && pressed_on_window(btn.down_pos))
+  }
+
+  /// True while a widget takes typed text, so the application should ignore
+  /// keyboard input.
+  pub fn wants_capture_keyboard(&self) -> bool {
+    self.windows.borrow().iter().any(|winptr| {
+      let win = winptr.borrow();
+      win.edit.active != 0
+        || (win.property.active != 0
+          && win.property.state == PropertyStatus::Edit)
+    })
+  }
+
   pub fn window_is_collapsed(&self, name: &str) -> bool {
     self
       .find_window(murmur_hash64a(name.as_bytes(), 64), name)
@@ -868,6 +1421,17 @@ impl UiContext {
     self.find_window(murmur_hash64a(name.as_bytes(), 64), name)
   }
 
+  /// While a window is modal all other windows are read only and drawn
+  /// dimmed behind it.
+  pub fn window_set_modal(&mut self, name: &str, modal: bool) {
+    let hash = murmur_hash64a(name.as_bytes(), 64);
+    if modal {
+      self.modal = Some(hash);
+    } else if self.modal == Some(hash) {
+      self.modal = None;
+    }
+  }
+
   pub fn window_close(&mut self, name: &str) {
     self.window_find(name).and_then(|wnd| {
       debug_assert!(
@@ -933,17 +1497,59 @@ impl UiContext {
     });
   }
 
-  pub fn window_collapse_if<F: FnOnce() -> CollapseStates>(
-    &mut self,
-    name: &str,
-    condition: F,
-  ) {
-    self.window_collapse(name, condition());
+  pub fn window_set_viewport(&mut self, name: &str, viewport: u32) {
+    self.window_find(name).and_then(|win| {
+      win.borrow_mut().viewport = viewport;
+      Some(())
+    });
   }
 
-  pub fn window_show(&mut self, name: &str, s: ShowStates) {
+  /// Sets the opacity of the window, used to fade individual windows in or
+  /// out.
+  pub fn window_set_alpha(&mut self, name: &str, alpha: f32) {
     self.window_find(name).and_then(|win| {
-      match s {
+      win.borrow_mut().alpha = alpha.max(0f32).min(1f32);
+      Some(())
+    });
+  }
+
+  /// Scroll offset of the window's content.
+  pub fn window_get_scroll(&self, name: &str) -> Vec2F32 {
+    self.window_find(name).map_or(Vec2F32::same(0f32), |win| {
+      let scroll = win.borrow().scroll.borrow().scrollbar;
+      Vec2F32::new(scroll.x as f32, scroll.y as f32)
+    })
+  }
+
+  /// Sets the scroll offset of the window's content, clamped to the content
+  /// extent of the last frame.
+  pub fn window_set_scroll(&mut self, name: &str, offset: Vec2F32) {
+    self.window_find(name).and_then(|win| {
+      let win = win.borrow();
+      let mut scroll = win.scroll.borrow_mut();
+      let max_offset = scroll.max_offset();
+      scroll.scrollbar = Vec2U32::new(
+        clamp(0f32, offset.x, max_offset.x) as u32,
+        clamp(0f32, offset.y, max_offset.y) as u32,
+      );
+      scroll.target =
+        Vec2F32::new(scroll.scrollbar.x as f32, scroll.scrollbar.y as f32);
+
+      Some(())
+    });
+  }
+
+  pub fn window_collapse_if<F: FnOnce() -> CollapseStates>(
+    &mut self,
+    name: &str,
+    condition: F,
+  ) {
+    self.window_collapse(name, condition());
+  }
+
+  pub fn window_show(&mut self, name: &str, s: ShowStates) {
+    self.window_find(name).and_then(|win| {
+      match s {
         ShowStates::Hidden => {
           win.borrow_mut().flags.insert(PanelFlags::WindowHidden);
         }
@@ -1013,10 +1619,14 @@ impl UiContext {
 
     let scrollbar_size = self.style.window.scrollbar_size;
     let panel_padding = self.style.get_panel_padding(panel_type);
+    // the measure pass must not move, resize or close the window, the real
+    // pass that follows it would do it a second time
+    let measuring = self.measure.is_some();
 
     // window movement
     if win_flags.intersects(PanelFlags::WindowMovable)
       && !win_flags.intersects(PanelFlags::WindowRom)
+      && !measuring
     {
       let mut header = *winptr.borrow().bounds.borrow();
       if Panel::has_header(win_flags, Some(title)) {
@@ -1040,19 +1650,42 @@ impl UiContext {
         .borrow()
         .has_mouse_click_down_in_rect(MouseButtonId::ButtonLeft, &header, true);
 
-      if left_mouse_down && left_mouse_click_in_cursor && !left_mouse_clicked {
+      let left_mouse_dragging = self
+        .input
+        .borrow()
+        .is_mouse_dragging(MouseButtonId::ButtonLeft, Self::DRAG_THRESHOLD);
+
+      if left_mouse_down
+        && left_mouse_click_in_cursor
+        && !left_mouse_clicked
+        && left_mouse_dragging
+      {
         let win = winptr.borrow();
         let mut bounds = win.bounds.borrow_mut();
-        bounds.x += self.input.borrow().mouse.delta.x;
-        bounds.y += self.input.borrow().mouse.delta.y;
+        let old_pos = Vec2F32::new(bounds.x, bounds.y);
+        let step = self
+          .input
+          .borrow()
+          .mouse_drag_step(MouseButtonId::ButtonLeft, Self::DRAG_THRESHOLD);
+        bounds.x += step.x;
+        bounds.y += step.y;
+
+        // keep (part of) the header on screen
+        self.display_size.map(|display| {
+          let margin = self.drag_margin.min(bounds.w);
+          let header_height = header.h.min(bounds.h);
+          bounds.x = clamp(margin - bounds.w, bounds.x, display.x - margin);
+          bounds.y =
+            clamp(0f32, bounds.y, (display.y - header_height).max(0f32));
+        });
 
+        // the click position follows the window so the header stays grabbed
         let mut input = self.input.borrow_mut();
-        let mouse_delta = input.mouse.delta;
+        let moved = Vec2F32::new(bounds.x - old_pos.x, bounds.y - old_pos.y);
         input.mouse.buttons[MouseButtonId::ButtonLeft as usize].clicked_pos +=
-          mouse_delta;
+          moved;
 
-        // TODO: fix this shite
-        // ctx->style.cursor_active = ctx->style.cursors[NK_CURSOR_MOVE];
+        self.style.cursor_active = StyleCursor::CursorMove as usize;
       }
     }
 
@@ -1082,6 +1715,7 @@ impl UiContext {
       layout.row.ratio = std::ptr::null_mut();
       layout.row.item_width = 0f32;
       layout.row.tree_depth = 0;
+      layout.row.same_line = false;
       layout.row.height = panel_padding.y;
       layout.has_scrolling = true;
 
@@ -1121,7 +1755,7 @@ impl UiContext {
         .active_win
         .borrow()
         .as_ref()
-        .map_or(false, |active_win| *active_win.borrow() == *win);
+        .map_or(false, |active_win| same_window(active_win, &winptr));
 
       let (bk, txt_color) = if is_active_win {
         (
@@ -1183,6 +1817,7 @@ impl UiContext {
           }
 
           use crate::hmi::button::do_button_symbol;
+          let input = self.input.borrow();
           let result = do_button_symbol(
             &mut BitFlags::default(),
             &mut win.buffer_mut(),
@@ -1190,8 +1825,8 @@ impl UiContext {
             self.style.window.header.close_symbol,
             ButtonBehaviour::ButtonDefault,
             &self.style.window.header.close_button,
-            Some(&*self.input.borrow()),
-            self.style.font,
+            if measuring { None } else { Some(&*input) },
+            &self.style.font,
           );
 
           if result {
@@ -1217,6 +1852,7 @@ impl UiContext {
           }
 
           use crate::hmi::button::do_button_symbol;
+          let input = self.input.borrow();
           let result = do_button_symbol(
             &mut BitFlags::default(),
             &mut win.buffer_mut(),
@@ -1228,8 +1864,8 @@ impl UiContext {
             },
             ButtonBehaviour::ButtonDefault,
             &self.style.window.header.minimize_button,
-            Some(&*self.input.borrow()),
-            self.style.font,
+            if measuring { None } else { Some(&*input) },
+            &self.style.font,
           );
 
           if result && !win_flags.intersects(PanelFlags::WindowRom) {
@@ -1271,7 +1907,7 @@ impl UiContext {
             text:       txt_color,
           },
           TextAlign::left(),
-          self.style.font,
+          &self.style.font,
         );
       }
     }
@@ -1290,14 +1926,11 @@ impl UiContext {
         ..*bounds
       };
 
-      match self.style.window.fixed_background {
-        StyleItem::Img(ref img) => {
-          win
-            .buffer_mut()
-            .draw_image(body, *img, RGBAColor::new(255, 255, 255))
-        }
-        StyleItem::Color(clr) => win.buffer_mut().fill_rect(body, 0f32, clr),
-      }
+      self.style.window.fixed_background.draw(
+        &mut win.buffer_mut(),
+        body,
+        0f32,
+      );
     }
 
     // set clipping rectangle
@@ -1307,15 +1940,17 @@ impl UiContext {
       let clip = RectangleF32::union(&buffer_clip, &layout_clip);
       winptr.borrow().buffer_mut().push_scissor(clip);
       winptr.borrow().layout.borrow_mut().clip = clip;
+      self.debug_outline(clip, RGBAColor::new(255, 0, 255));
     }
 
     !layout_flags
       .intersects(PanelFlags::WindowHidden | PanelFlags::WindowMinimized)
   }
 
-  pub fn panel_end(&self) {
+  pub fn panel_end(&mut self) {
     debug_assert!(self.current_win.borrow().is_some());
 
+    let mut scaler_cursor = None;
     self
       .current_win
       .borrow()
@@ -1331,11 +1966,23 @@ impl UiContext {
 
         let scrollbar_size = self.style.window.scrollbar_size;
         let panel_padding = self.style.get_panel_padding(layout.typ);
+        // no scrolling or resizing in the measure pass, see panel_begin()
+        let measuring = self.measure.is_some();
 
         // update the current cursor Y-position to point over the last added
         // widget
         layout.at_y += layout.row.height;
 
+        // remember the content extent, used to clamp scrolling
+        {
+          let mut offsets = layout.offsets.borrow_mut();
+          offsets.content = Vec2F32::new(
+            (layout.max_x - layout.bounds.x).max(0f32),
+            (layout.at_y - layout.bounds.y).max(0f32),
+          );
+          offsets.view = Vec2F32::new(layout.bounds.w, layout.bounds.h);
+        }
+
         // dynamic panels
         if layout.flags.intersects(PanelFlags::WindowDynamic)
           && !layout.flags.intersects(PanelFlags::WindowMinimized)
@@ -1405,8 +2052,91 @@ impl UiContext {
           }
         }
 
-        // TODO: scrollbars
-        // TODO: hide scroll if no user input
+        if !layout
+          .flags
+          .intersects(PanelFlags::WindowNoScrollbar | PanelFlags::WindowRom)
+          && !measuring
+        {
+          self.scroll_with_wheel(&win, &mut layout.offsets.borrow_mut());
+        }
+
+        let no_input = layout
+          .flags
+          .intersects(PanelFlags::WindowRom | PanelFlags::WindowNoInput)
+          || measuring;
+
+        // vertical scrollbar, the wheel was handled above
+        let offsets = *layout.offsets.borrow();
+        if !layout.flags.intersects(
+          PanelFlags::WindowNoScrollbar | PanelFlags::WindowMinimized,
+        ) && offsets.content.y > offsets.view.y
+        {
+          let track = RectangleF32 {
+            x: layout.bounds.x + layout.bounds.w,
+            w: scrollbar_size.x,
+            ..layout.bounds
+          };
+          let auto_hide = self.style.scrollv.auto_hide
+            || layout.flags.intersects(PanelFlags::WindowScrollAutoHide);
+
+          let mut state = BitFlags::default();
+          let (offset, track, cursor) = {
+            let mut input = self.input.borrow_mut();
+            scrollbar_layout(
+              &mut state,
+              &mut win.buffer_mut(),
+              ScrollbarAxis::Vertical,
+              track,
+              false,
+              offsets.scrollbar.y as f32,
+              offsets.view.y,
+              offsets.content.y,
+              offsets.view.y * 0.1f32,
+              &self.style.scrollv,
+              if no_input { None } else { Some(&mut *input) },
+              &self.style.font,
+            )
+          };
+
+          let offset = offset.round() as u32;
+          if offset != offsets.scrollbar.y {
+            // dragged or stepped, smooth scrolling must not pull it back
+            let mut scroll = layout.offsets.borrow_mut();
+            scroll.scrollbar.y = offset;
+            scroll.target.y = offset as f32;
+          }
+
+          self.draw_scrollbar_v(&win, state, track, cursor, auto_hide);
+        }
+
+        // horizontal scrollbar, in the footer below the content
+        if !layout.flags.intersects(
+          PanelFlags::WindowNoScrollbar | PanelFlags::WindowMinimized,
+        ) && offsets.content.x > offsets.view.x
+        {
+          let track = RectangleF32 {
+            y: layout.bounds.y + layout.bounds.h,
+            h: scrollbar_size.y,
+            ..layout.bounds
+          };
+          let mut input = self.input.borrow_mut();
+          let has_scrolling = input.is_mouse_hovering_rect(&win.bounds());
+          let offset = do_scrollbar(
+            &mut BitFlags::default(),
+            &mut win.buffer_mut(),
+            ScrollbarAxis::Horizontal,
+            track,
+            has_scrolling,
+            offsets.scrollbar.x as f32,
+            offsets.view.x,
+            offsets.content.x,
+            offsets.view.x * 0.1f32,
+            &self.style.scrollh,
+            if no_input { None } else { Some(&mut *input) },
+            &self.style.font,
+          );
+          layout.offsets.borrow_mut().scrollbar.x = offset.round() as u32;
+        }
 
         // window border
         if layout.flags.intersects(PanelFlags::WindowBorder) {
@@ -1498,7 +2228,7 @@ impl UiContext {
           }
 
           // do window scaling
-          if !win.flags.intersects(PanelFlags::WindowRom) {
+          if !win.flags.intersects(PanelFlags::WindowRom) && !measuring {
             let mut scaler = scaler;
             let left_mouse_down = self
               .input
@@ -1514,7 +2244,37 @@ impl UiContext {
 
             let mut win_bounds = win.bounds.borrow_mut();
 
-            if left_mouse_down && left_mouse_click_in_scaler {
+            let resize_cursor =
+              if layout.flags.contains(PanelFlags::WindowScaleLeft) {
+                StyleCursor::CursorResizeTopRightDownLeft
+              } else {
+                StyleCursor::CursorResizeTopLeftDownRight
+              };
+
+            if self.input.borrow().is_mouse_hovering_rect(&scaler) {
+              scaler_cursor = Some(resize_cursor);
+            }
+
+            let double_click_in_scaler =
+              self.input.borrow().is_mouse_click_down_in_rect(
+                MouseButtonId::ButtonDouble,
+                &scaler,
+                true,
+              );
+
+            if double_click_in_scaler {
+              // snap the window to the content extent measured this frame
+              let window_size = self.style.window.min_size;
+              let content = layout.offsets.borrow().content;
+              win_bounds.w =
+                (win_bounds.w + content.x - layout.bounds.w).max(window_size.x);
+              if !layout.flags.contains(PanelFlags::WindowDynamic) {
+                win_bounds.h = (win_bounds.h + content.y + panel_padding.y
+                  - layout.bounds.h)
+                  .max(window_size.y);
+              }
+              scaler_cursor = Some(resize_cursor);
+            } else if left_mouse_down && left_mouse_click_in_scaler {
               let delta_x =
                 if layout.flags.contains(PanelFlags::WindowScaleLeft) {
                   win_bounds.x += self.input.borrow().mouse.delta.x;
@@ -1549,9 +2309,7 @@ impl UiContext {
                 }
               }
 
-              // TODO : fix cursor!
-              // ctx->style.cursor_active =
-              // ctx->style.cursors[NK_CURSOR_RESIZE_TOP_RIGHT_DOWN_LEFT];
+              scaler_cursor = Some(resize_cursor);
               self.input.borrow_mut().mouse.buttons
                 [MouseButtonId::ButtonLeft as usize]
                 .clicked_pos = Vec2F32::new(
@@ -1586,6 +2344,8 @@ impl UiContext {
 
         Some(())
       });
+
+    scaler_cursor.map(|c| self.style.cursor_active = c as usize);
   }
 
   /// progress bar
@@ -1597,8 +2357,74 @@ impl UiContext {
   ) -> (bool, u32) {
     debug_assert!(self.current_win.borrow().is_some());
 
+    self
+      .progress_bar(cur, max, modifiable)
+      .map_or((false, cur), |(_, value)| (value != cur, value))
+  }
+
+  /// Progress bar that can't be changed by the user, with the progress
+  /// written on top of it, as a percentage or as `cur/max`.
+  pub fn progress_labeled(&mut self, cur: u32, max: u32, fmt: ProgressLabel) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.progress_bar(cur, max, false).map(|(bounds, value)| {
+      self.current_win.borrow().as_ref().map(|winptr| {
+        use crate::hmi::progress::draw_progress_label;
+
+        draw_progress_label(
+          &mut winptr.borrow().buffer_mut(),
+          &bounds,
+          value,
+          max,
+          fmt,
+          &self.style.progress,
+          &self.style.font,
+        );
+      });
+    });
+  }
+
+  /// Allocates and draws a progress bar, returns its bounds and value or
+  /// `None` if it is not visible.
+  fn progress_bar(
+    &mut self,
+    cur: u32,
+    max: u32,
+    modifiable: bool,
+  ) -> Option<(RectangleF32, u32)> {
+    let (state, bounds) = self.widget();
+    if state == WidgetLayoutStates::Invalid {
+      return None;
+    }
+
+    let is_rom = state == WidgetLayoutStates::Rom
+      || self.current_win.borrow().as_ref().map_or(true, |winptr| {
+        winptr
+          .borrow()
+          .layout
+          .borrow()
+          .flags
+          .intersects(PanelFlags::WindowRom)
+      });
+
     use crate::hmi::progress::do_progress;
-    (false, 0)
+
+    self.current_win.borrow().as_ref().map(|winptr| {
+      let mut input = self.input.borrow_mut();
+      let mut widget_state = self.last_widget_state.borrow_mut();
+      let (new_state, value) = do_progress(
+        *widget_state,
+        &mut winptr.borrow().buffer_mut(),
+        &bounds,
+        cur,
+        max,
+        modifiable,
+        &self.style.progress,
+        if is_rom { None } else { Some(&mut *input) },
+      );
+      *widget_state = new_state;
+      (bounds, value)
+    })
   }
 
   pub fn prog(&mut self, cur: u32, max: u32, modifyable: bool) -> u32 {
@@ -1606,1545 +2432,5155 @@ impl UiContext {
     cur
   }
 
-  fn layout_row_calculate_usable_space(
-    style: &Style,
-    typ: BitFlags<PanelType>,
-    total_space: f32,
-    columns: i32,
-  ) -> f32 {
-    let spacing = style.window.spacing;
-    let padding = style.get_panel_padding(typ);
-    // calculate usable panel space
-    let panel_padding = 2f32 * padding.x;
-    let panel_spacing = (columns - 1).max(0) as f32 * spacing.x;
-    total_space - panel_padding - panel_spacing
-  }
+  /// Property widget for a float in `[min, max]`. `step` is added or removed
+  /// by the side buttons and per mouse wheel tick, `inc_per_pixel` is applied
+  /// while dragging. Clicking the value allows typing an exact number,
+  /// committed with Enter and reverted with Escape. Returns true if the value
+  /// changed.
+  pub fn property_float(
+    &mut self,
+    name: &str,
+    min: f32,
+    val: &mut f32,
+    max: f32,
+    step: f32,
+    inc_per_pixel: f32,
+  ) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
 
-  fn panel_layout(&self, win: &Window, height: f32, cols: i32) {
-    //  if one of these triggers you forgot to add an `if` condition around
-    // either a window, group, popup, combobox or contextual menu `begin`
-    // and `end` block. Example:
-    // if (nk_begin(...) {...} nk_end(...); or
-    // if (nk_group_begin(...) { nk_group_end(...);}
+    let (state, bounds) = self.widget();
+    if state == WidgetLayoutStates::Invalid {
+      return false;
+    }
 
-    let mut layout = win.layout.borrow_mut();
-    let style = &self.style;
+    let id = self.widget_id(name) as u32;
+    let style = self.style.property;
+    let font = self.style.font.clone();
+    let input = self.input.borrow();
 
-    debug_assert!(!layout.flags.contains(PanelFlags::WindowMinimized));
-    debug_assert!(!layout.flags.contains(PanelFlags::WindowHidden));
-    debug_assert!(!layout.flags.contains(PanelFlags::WindowClosed));
+    let rom = state == WidgetLayoutStates::Rom
+      || self.current_win.borrow().as_ref().map_or(true, |winptr| {
+        winptr
+          .borrow()
+          .layout
+          .borrow()
+          .flags
+          .intersects(PanelFlags::WindowRom)
+      });
 
-    let item_spacing = style.window.spacing;
-    layout.row.index = 0;
-    layout.at_y += layout.row.height;
-    layout.row.columns = cols;
-    layout.row.height = if height == 0f32 {
-      height.max(layout.row.min_height) + item_spacing.y
-    } else {
-      height + item_spacing.y
-    };
+    let new_val = self.current_win.borrow().as_ref().map_or(*val, |winptr| {
+      let mut win = winptr.borrow_mut();
 
-    layout.row.item_offset = 0f32;
+      use crate::hmi::property::do_property;
+      let mut prop = std::mem::take(&mut win.property);
+      let new_val = do_property(
+        &mut prop,
+        &mut win.buffer_mut(),
+        id,
+        bounds,
+        name,
+        *val,
+        min,
+        max,
+        step,
+        inc_per_pixel,
+        &style,
+        if rom { None } else { Some(&*input) },
+        &font,
+      );
+      win.property = prop;
+      new_val
+    });
 
-    if layout.flags.contains(PanelFlags::WindowDynamic) {
-      // draw background for dynamic panels
-      let bk = RectangleF32 {
-        y: layout.at_y - 1f32,
-        h: layout.row.height + 1f32,
-        ..*win.bounds.borrow()
-      };
+    // the wheel was used by the property, don't scroll the window with it
+    let wheel_used = !rom
+      && input.mouse.scroll_delta.y != 0f32
+      && input.is_mouse_hovering_rect(&bounds);
+    drop(input);
+    if wheel_used {
+      self.input.borrow_mut().mouse.scroll_delta.y = 0f32;
+    }
 
-      win
-        .buffer
-        .borrow_mut()
-        .fill_rect(bk, 0f32, style.window.background);
+    let changed = new_val != *val;
+    *val = new_val;
+    changed
+  }
+
+  pub fn property_int(
+    &mut self,
+    name: &str,
+    min: i32,
+    val: &mut i32,
+    max: i32,
+    step: i32,
+    inc_per_pixel: f32,
+  ) -> bool {
+    // integers past 2^24 don't survive the trip through f32, only take the
+    // value back when the property changed it
+    let mut fval = *val as f32;
+    if !self.property_float(
+      name,
+      min as f32,
+      &mut fval,
+      max as f32,
+      step as f32,
+      inc_per_pixel,
+    ) {
+      return false;
     }
+
+    let new_val = fval.round() as i32;
+    let changed = new_val != *val;
+    *val = new_val;
+    changed
   }
 
-  pub fn row_layout(
-    &self,
-    fmt: LayoutFormat,
-    height: f32,
-    cols: i32,
-    width: i32,
-  ) {
+  /// Busy indicator, an arc rotating a bit further each frame.
+  pub fn spinner(&mut self, radius: f32) {
+    const SPINNER_SPEED: f32 = 2f32 * std::f32::consts::PI;
+    const SPINNER_ARC: f32 = 1.5f32 * std::f32::consts::PI;
+
     debug_assert!(self.current_win.borrow().is_some());
 
+    let (state, bounds) = self.widget();
+    if state == WidgetLayoutStates::Invalid {
+      return;
+    }
+
+    let color = self
+      .style
+      .progress
+      .cursor_active
+      .background_color(self.style.text.color);
+    let dt = self.delta_time_sec;
+    let seed = self.id_seed();
+    let measuring = self.measure.is_some();
+
+    self.current_win.borrow().as_ref().map(|winptr| {
+      let mut win = winptr.borrow_mut();
+      // the phase is kept across frames, keyed by the position inside the
+      // window
+      let origin = win.bounds();
+      let key = murmur_hash64a(
+        format!(
+          "spinner{}x{}",
+          (bounds.x - origin.x) as i32,
+          (bounds.y - origin.y) as i32
+        )
+        .as_bytes(),
+        seed,
+      );
+
+      // the measure pass doesn't turn the spinner, only the real one does
+      let phase = if measuring {
+        win.spinners.get(&key).copied().unwrap_or(0f32)
+      } else {
+        let phase = win.spinners.entry(key).or_insert(0f32);
+        *phase = (*phase + dt * SPINNER_SPEED) % (2f32 * std::f32::consts::PI);
+        *phase
+      };
+
+      let radius = radius.min(bounds.w.min(bounds.h) * 0.5f32);
+      win.buffer_mut().stroke_arc(
+        bounds.x + bounds.w * 0.5f32,
+        bounds.y + bounds.h * 0.5f32,
+        radius,
+        phase,
+        phase + SPINNER_ARC,
+        2f32,
+        color,
+      );
+    });
+  }
+
+  /// True while a popup, like the drop down of a combo box, is open in any
+  /// window.
+  pub fn any_popup_open(&self) -> bool {
     self
-      .current_win
+      .windows
       .borrow()
-      .as_ref()
-      // .and_then(|winptr| Some(winptr.clone()))
-      .and_then(|winptr| {
-        self.panel_layout(&winptr.borrow(), height, cols);
-        if fmt == LayoutFormat::Dynamic {
-          winptr.borrow().layout.borrow_mut().row.typ =
-            PanelRowLayoutType::DynamicFixed;
-        } else {
-          winptr.borrow().layout.borrow_mut().row.typ =
-            PanelRowLayoutType::StaticFixed;
-        }
+      .iter()
+      .any(|winptr| winptr.borrow().popup.active)
+  }
 
-        let win = winptr.borrow();
-        let mut layout = win.layout.borrow_mut();
-        layout.row.ratio = std::ptr::null_mut();
-        layout.row.filled = 0f32;
-        layout.row.item_offset = 0f32;
-        layout.row.item_width = width as f32;
-        Some(())
-      });
+  /// Closes the popups of all windows, e.g. when the application switches
+  /// tools. Escape also closes an open combo box.
+  pub fn close_all_popups(&mut self) {
+    self.windows.borrow().iter().for_each(|winptr| {
+      let mut win = winptr.borrow_mut();
+      win.popup.active = false;
+      win.popup.name = 0;
+      win.popup.win = None;
+      win.popup.list = None;
+    });
   }
 
-  pub fn layout_ratio_from_pixel(&self, pixel_width: f32) -> f32 {
-    self.current_win.borrow().as_ref().map_or(0f32, |winptr| {
-      clamp(0f32, pixel_width / winptr.borrow().bounds.borrow().x, 1f32)
+  /// True when a popup of `height` opened below `anchor` would extend past
+  /// the bottom of the display, while opening it above, starting at `top`,
+  /// keeps it inside the content of the current window.
+  fn popup_opens_above(
+    &self,
+    anchor: RectangleF32,
+    height: f32,
+    top: f32,
+  ) -> bool {
+    let content_top = self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(0f32, |win| win.borrow().layout.borrow().bounds.y);
+
+    self.display_size.map_or(false, |display| {
+      anchor.y + anchor.h + height > display.y && top >= content_top
     })
   }
 
-  pub fn layout_row_dynamic(&self, height: f32, cols: i32) {
-    self.row_layout(LayoutFormat::Dynamic, height, cols, 0)
-  }
+  /// True if the mouse is over the drop down of a combo box that opened
+  /// above its header. The widgets under it were laid out before the combo,
+  /// so they would see the clicks meant for its items.
+  fn covered_by_popup(&self) -> bool {
+    if self.combo_items {
+      return false;
+    }
 
-  pub fn layout_row_static(&self, height: f32, item_width: i32, cols: i32) {
-    self.row_layout(LayoutFormat::Static, height, cols, item_width)
+    self.current_win.borrow().as_ref().map_or(false, |winptr| {
+      let win = winptr.borrow();
+      win.popup.active
+        && win.popup.list.map_or(false, |list| {
+          self.input.borrow().is_mouse_hovering_rect(&list)
+        })
+    })
   }
 
-  pub fn layout_row_begin(
-    &self,
-    fmt: LayoutFormat,
-    row_height: f32,
-    cols: i32,
-  ) {
+  /// Combo box whose items are drawn by `render_item`. The header shows the
+  /// selected item and toggles the drop down, which is laid out below it in
+  /// rows of `item_height`, `size.x` wide, with as many items as fit into
+  /// `size.y`; the mouse wheel scrolls through the remaining ones. If the drop
+  /// down does not fit between the header and the bottom of the display it
+  /// opens above the header instead, if there is room for it in the window, on
+  /// top of the widgets there (which ignore the mouse meanwhile).
+  /// `render_item` is invoked with the index of the selected item for the
+  /// header and with the index of every visible item, after the space for it
+  /// has been allocated. The open state is keyed by the order of the combo
+  /// boxes in the window and the id stack, see `push_id()`. Returns the
+  /// (possibly changed) selected index.
+  pub fn combo_callback<F>(
+    &mut self,
+    count: usize,
+    selected: usize,
+    item_height: f32,
+    size: Vec2F32,
+    render_item: F,
+  ) -> usize
+  where
+    F: Fn(&mut UiContext, usize),
+  {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
+    let visible = ((size.y / item_height) as usize).max(1).min(count);
+    let max_first = count - visible;
+
+    // the selected item, followed by the button with the arrow
+    let spacing = self.style.window.spacing;
+    self.layout_row_begin(LayoutFormat::Static, item_height, 2);
+    let item_width = (size.x - item_height - spacing.x).max(0f32);
+    self.layout_row_push(item_width);
+    let header = {
+      let r = self.layout_widget_bounds();
+      RectangleF32::new(r.x, r.y, size.x, r.h)
+    };
+    let selected_item = RectangleF32 {
+      w: item_width,
+      ..header
+    };
 
-      self.panel_layout(&win, row_height, cols);
-      let mut layout = win.layout.borrow_mut();
-      layout.row.typ = if fmt == LayoutFormat::Dynamic {
-        PanelRowLayoutType::DynamicRow
-      } else {
-        PanelRowLayoutType::StaticRow
-      };
+    let key = self.current_win.borrow().as_ref().map_or(0, |winptr| {
+      let mut win = winptr.borrow_mut();
+      win.popup.combo_count += 1;
+      self.widget_id(&format!("combo{}", win.popup.combo_count)) as u32
+    });
 
-      layout.row.ratio = std::ptr::null_mut();
-      layout.row.filled = 0f32;
-      layout.row.item_width = 0f32;
-      layout.row.item_offset = 0f32;
-      layout.row.columns = cols;
-      Some(())
+    let is_open = |ctx: &UiContext| {
+      ctx.current_win.borrow().as_ref().map_or(false, |winptr| {
+        let win = winptr.borrow();
+        win.popup.active && win.popup.name == key
+      })
+    };
+    // opens scrolled so that the selected item is visible
+    let set_open = |ctx: &UiContext, open: bool| {
+      ctx.current_win.borrow().as_ref().map(|winptr| {
+        let mut win = winptr.borrow_mut();
+        win.popup.active = open;
+        win.popup.name = if open { key } else { 0 };
+        win.popup.header = header;
+        win.popup.list = None;
+        win.popup.scroll =
+          (selected + 1).saturating_sub(visible).min(max_first) as u32;
+      });
+    };
+
+    let was_open = is_open(self);
+    let win_rom = self.current_win.borrow().as_ref().map_or(true, |winptr| {
+      winptr
+        .borrow()
+        .layout
+        .borrow()
+        .flags
+        .intersects(PanelFlags::WindowRom)
     });
-  }
+    let header_clicked = !win_rom
+      && !self.input_blocked()
+      && self
+        .input
+        .borrow()
+        .has_mouse_up_in_rect(MouseButtonId::ButtonLeft, &selected_item);
+    if selected < count {
+      render_item(self, selected);
+    } else {
+      self.spacing(1);
+    }
 
-  pub fn layout_row_push(&self, ratio_or_width: f32) {
-    debug_assert!(self.current_win.borrow().is_some());
+    self.layout_row_push(item_height);
+    let symbol = if was_open {
+      SymbolType::TriangleDown
+    } else {
+      SymbolType::TriangleRight
+    };
+    if self.button_symbol(symbol) || header_clicked {
+      set_open(self, !was_open);
+    }
+    self.layout_row_end();
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
+    if is_open(self) && self.input.borrow().is_key_pressed(KeyId::KeyEscape) {
+      set_open(self, false);
+    }
 
-      let mut layout = win.layout.borrow_mut();
-      if layout.row.typ != PanelRowLayoutType::StaticRow
-        || layout.row.typ != PanelRowLayoutType::DynamicRow
+    if !is_open(self) {
+      return selected;
+    }
+
+    let first = self.current_win.borrow().as_ref().map_or(0, |winptr| {
+      (winptr.borrow().popup.scroll as usize).min(max_first)
+    });
+    let list_height = visible as f32 * item_height;
+    let list_top = -list_height - spacing.y;
+    let top = self.layout_space_to_screen(Vec2F32::new(0f32, list_top));
+    let above = self.popup_opens_above(header, list_height, top.y);
+    let mut selection = selected;
+    let mut list = RectangleF32::new(top.x, top.y, size.x, list_height);
+
+    if above {
+      // the items are placed freely relative to the header row, which stays
+      // the current row so the widgets after the combo are not moved
+      let background = self.style.window.background;
+      self.current_win.borrow().as_ref().map(|winptr| {
+        let mut win = winptr.borrow_mut();
+        win.popup.list = Some(list);
+        {
+          let mut layout = win.layout.borrow_mut();
+          layout.row.typ = PanelRowLayoutType::StaticFree;
+          layout.row.columns = visible as i32;
+          layout.row.index = 0;
+        }
+        win.buffer_mut().fill_rect(list, 0f32, background);
+      });
+    }
+
+    self.combo_items = true;
+    for row_idx in 0 .. visible {
+      let idx = first + row_idx;
+      let row = if above {
+        let item = RectangleF32::new(
+          0f32,
+          list_top + row_idx as f32 * item_height,
+          size.x,
+          item_height,
+        );
+        self.layout_space_push(&item);
+        let pos = self.layout_space_to_screen(Vec2F32::new(item.x, item.y));
+        RectangleF32::new(pos.x, pos.y, item.w, item.h)
+      } else {
+        self.layout_row_static(item_height, size.x as i32, 1);
+        let r = self.layout_widget_bounds();
+        let r = RectangleF32::new(r.x, r.y, r.w.min(size.x), r.h);
+        if row_idx == 0 {
+          list = RectangleF32::new(r.x, r.y, r.w, list_height);
+        }
+        r
+      };
+      render_item(self, idx);
+
+      if self
+        .input
+        .borrow()
+        .has_mouse_up_in_rect(MouseButtonId::ButtonLeft, &row)
       {
-        return Some(());
+        selection = idx;
       }
+    }
 
-      if layout.row.typ == PanelRowLayoutType::DynamicRow {
-        let ratio = ratio_or_width;
-        if (ratio + layout.row.filled) > 1f32 {
-          return Some(());
-        }
+    self.combo_items = false;
 
-        layout.row.item_width = if ratio > 0f32 {
-          saturate(ratio)
-        } else {
-          1f32 - layout.row.filled
-        };
+    // the wheel scrolls the items instead of the window while over them
+    let wheel = {
+      let input = self.input.borrow();
+      if input.is_mouse_hovering_rect(&list) {
+        input.mouse.scroll_delta.y
       } else {
-        layout.row.item_width = ratio_or_width;
+        0f32
       }
+    };
+    if wheel != 0f32 {
+      self.input.borrow_mut().mouse.scroll_delta.y = 0f32;
+      let scroll = (first as f32 - wheel).round().max(0f32) as usize;
+      self.current_win.borrow().as_ref().map(|winptr| {
+        winptr.borrow_mut().popup.scroll = scroll.min(max_first) as u32;
+      });
+    }
 
-      Some(())
-    });
+    if selection != selected {
+      set_open(self, false);
+    }
+
+    selection
   }
 
-  pub fn layout_row_end(&self) {
+  /// Collapsible tree node. Returns true while the node is expanded, in which
+  /// case the following widgets are indented until the matching
+  /// `tree_pop()`. The state is keyed by `title` and the id stack, see
+  /// `push_id()`.
+  pub fn tree_push(&mut self, title: &str, initial: CollapseStates) -> bool {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
-      let mut layout = win.layout.borrow_mut();
-      debug_assert!(layout.row.typ == PanelRowLayoutType::StaticRow);
-      debug_assert!(layout.row.typ == PanelRowLayoutType::DynamicRow);
+    let id = self.widget_id(title);
+    let state = self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(initial, |winptr| {
+        *winptr.borrow_mut().trees.entry(id).or_insert(initial)
+      });
 
-      if layout.row.typ == PanelRowLayoutType::StaticRow
-        || layout.row.typ == PanelRowLayoutType::DynamicRow
-      {
-        layout.row.item_width = 0f32;
-        layout.row.item_offset = 0f32;
-      }
+    let row_height = self.style.font.scale + 2f32 * self.style.tab.padding.y;
+    self.layout_row_dynamic(row_height, 1);
 
-      Some(())
+    let tab = self.style.tab;
+    let (button, symbol) = if state == CollapseStates::Maximized {
+      (tab.node_maximize_button, tab.sym_maximize)
+    } else {
+      (tab.node_minimize_button, tab.sym_minimize)
+    };
+
+    let state = if self.button_symbol_text_styled(
+      &button,
+      symbol,
+      title,
+      TextAlign::left(),
+    ) {
+      let toggled = if state == CollapseStates::Maximized {
+        CollapseStates::Minimized
+      } else {
+        CollapseStates::Maximized
+      };
+
+      self.current_win.borrow().as_ref().map(|winptr| {
+        winptr.borrow_mut().trees.insert(id, toggled);
+      });
+      toggled
+    } else {
+      state
+    };
+
+    if state == CollapseStates::Minimized {
+      return false;
+    }
+
+    self.current_win.borrow().as_ref().map(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
+      layout.at_x += tab.indent;
+      layout.bounds.w = layout.bounds.w.max(tab.indent) - tab.indent;
+      layout.row.tree_depth += 1;
     });
+
+    true
   }
 
-  pub fn layout_row(&self, fmt: LayoutFormat, height: f32, ratio: &[f32]) {
+  /// Ends a tree node that was expanded by `tree_push()`.
+  pub fn tree_pop(&mut self) {
     debug_assert!(self.current_win.borrow().is_some());
 
+    let indent = self.style.tab.indent;
     self.current_win.borrow().as_ref().map(|winptr| {
       let win = winptr.borrow();
       let mut layout = win.layout.borrow_mut();
+      debug_assert!(
+        layout.row.tree_depth > 0,
+        "tree_pop() without tree_push()"
+      );
+      layout.at_x -= indent;
+      layout.bounds.w += indent;
+      layout.row.tree_depth -= 1;
+    });
+  }
 
-      self.panel_layout(&win, height, ratio.len() as i32);
-      if fmt == LayoutFormat::Dynamic {
-        // calculate width of undefined widget ratios
-        layout.row.ratio = ratio.as_ptr();
-        let (n_undef, r) = ratio.iter().fold((0i32, 0f32), |acc, r| {
-          if *r < 0f32 {
-            (acc.0 + 1, acc.1)
-          } else {
-            (acc.0, acc.1 + r)
+  /// Starts a table with one column per header. The header row has a
+  /// separator on the right edge of every column that can be dragged to
+  /// resize the column, which shifts the columns after it. The widths are
+  /// kept in the window, `widths` supplies them the first time and receives
+  /// the current ones on every call. Rows are started with `table_next_row()`
+  /// and filled with `table_cell()`, the table is closed with `table_end()`.
+  /// Returns false, without starting a table, if there are no headers or
+  /// their number differs from that of `widths`.
+  pub fn table_begin(&mut self, headers: &[&str], widths: &mut [f32]) -> bool {
+    const SEPARATOR_WIDTH: f32 = 6f32;
+
+    debug_assert!(self.current_win.borrow().is_some());
+
+    if headers.is_empty() || headers.len() != widths.len() {
+      return false;
+    }
+
+    let id = self.widget_id(&format!("table{}", headers.join("\t")));
+    let current =
+      self
+        .current_win
+        .borrow()
+        .as_ref()
+        .map_or(widths.to_vec(), |winptr| {
+          let mut win = winptr.borrow_mut();
+          let table = win
+            .tables
+            .entry(id)
+            .or_insert_with(|| TableState::new(widths));
+          if table.widths.len() != widths.len() {
+            *table = TableState::new(widths);
           }
+          table.widths.clone()
         });
 
-        let r = saturate(1f32 - r);
-        layout.row.typ = PanelRowLayoutType::Dynamic;
-        layout.row.item_width = if r > 0f32 && n_undef > 0 {
-          r / n_undef as f32
-        } else {
-          0f32
-        };
-      } else {
-        layout.row.ratio = ratio.as_ptr();
-        layout.row.typ = PanelRowLayoutType::Static;
-        layout.row.item_width = 0f32;
-      }
+    let style = self.style.button;
+    let height = self.style.font.scale + 2f32 * style.padding.y;
+    self.layout_row_begin(LayoutFormat::Static, height, headers.len() as i32);
+    let cells = current
+      .iter()
+      .map(|width| {
+        self.layout_row_push(*width);
+        self.widget()
+      })
+      .collect::<Vec<_>>();
 
-      layout.row.item_offset = 0f32;
-      layout.row.filled = 0f32;
+    let is_rom = self.current_win.borrow().as_ref().map_or(true, |winptr| {
+      winptr
+        .borrow()
+        .layout
+        .borrow()
+        .flags
+        .intersects(PanelFlags::WindowRom)
+    });
+    let spacing = self.style.window.spacing.x;
 
-      Some(())
+    self.current_win.borrow().as_ref().map(|winptr| {
+      let input = self.input.borrow();
+      let mut win = winptr.borrow_mut();
+      if let Some(table) = win.tables.get_mut(&id) {
+        cells.iter().enumerate().for_each(|(col, (state, cell))| {
+          let separator = RectangleF32::new(
+            cell.x + cell.w + (spacing - SEPARATOR_WIDTH) * 0.5f32,
+            cell.y,
+            SEPARATOR_WIDTH,
+            cell.h,
+          );
+
+          if is_rom || *state == WidgetLayoutStates::Rom {
+            table.drag = table.drag.filter(|drag| *drag != col);
+          } else if table.drag == Some(col) {
+            if input.is_mouse_down(MouseButtonId::ButtonLeft) {
+              table.widths[col] = (table.widths[col] + input.mouse.delta.x)
+                .max(Self::TABLE_MIN_COLUMN_WIDTH);
+            } else {
+              table.drag = None;
+            }
+          } else if table.drag.is_none()
+            && input.is_mouse_click_down_in_rect(
+              MouseButtonId::ButtonLeft,
+              &separator,
+              true,
+            )
+          {
+            table.drag = Some(col);
+          }
+        });
+
+        widths.copy_from_slice(&table.widths);
+      }
     });
-  }
 
-  pub fn layout_row_template_begin(&self, height: f32) {
-    debug_assert!(self.current_win.borrow().is_some());
+    let align = self
+      .current_win
+      .borrow()
+      .as_ref()
+      .and_then(|winptr| {
+        winptr
+          .borrow()
+          .tables
+          .get(&id)
+          .map(|table| table.align.clone())
+      })
+      .unwrap_or_default();
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
+    self.current_win.borrow().as_ref().map(|winptr| {
       let win = winptr.borrow();
-      self.panel_layout(&win, height, 1);
-
-      let mut layout = win.layout.borrow_mut();
-      layout.row.typ = PanelRowLayoutType::Template;
-      layout.row.columns = 0;
-      layout.row.ratio = std::ptr::null_mut();
-      layout.row.item_width = 0f32;
-      layout.row.item_height = 0f32;
-      layout.row.item_offset = 0f32;
-      layout.row.filled = 0f32;
-      layout.row.item.x = 0f32;
-      layout.row.item.y = 0f32;
-      layout.row.item.w = 0f32;
-      layout.row.item.h = 0f32;
+      cells
+        .iter()
+        .zip(headers.iter().zip(align.iter()))
+        .filter(|((state, _), _)| *state != WidgetLayoutStates::Invalid)
+        .for_each(|((_, cell), (header, align))| {
+          let background = style.normal.background_color(style.text_background);
+          style.normal.draw(&mut win.buffer_mut(), *cell, 0f32);
+          self.table_cell_text(
+            &win,
+            *cell,
+            header,
+            *align,
+            background,
+            style.text_normal,
+          );
 
-      Some(())
+          let x = cell.x + cell.w + spacing * 0.5f32;
+          win.buffer_mut().stroke_line(
+            x,
+            cell.y,
+            x,
+            cell.y + cell.h,
+            1f32,
+            style.border_color,
+          );
+        });
     });
+
+    self.table = Some((id, 0));
+    true
   }
 
-  pub fn layout_row_template_push_dynamic(&self) {
+  /// Starts the next row of the table, with as many cells as it has columns.
+  pub fn table_next_row(&mut self) {
     debug_assert!(self.current_win.borrow().is_some());
+    debug_assert!(
+      self.table.is_some(),
+      "table_next_row() without table_begin()"
+    );
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
-      let mut layout = win.layout.borrow_mut();
+    let columns = self.table.map_or(0, |(id, _)| self.table_columns(id));
+    let height = self.style.font.scale + 2f32 * self.style.text.padding.y;
+    self.layout_row_begin(LayoutFormat::Static, height, columns as i32);
+    self.table = self.table.map(|(id, _)| (id, 0));
+  }
 
-      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
-      debug_assert!(
-        layout.row.columns
-          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-      );
+  /// Text in the next cell of the current table row, aligned as set for the
+  /// column with `table_column_align()` and clipped to the cell.
+  pub fn table_cell(&mut self, text: &str) {
+    debug_assert!(self.current_win.borrow().is_some());
+    debug_assert!(self.table.is_some(), "table_cell() without table_begin()");
 
-      if layout.row.typ == PanelRowLayoutType::Template
-        && layout.row.columns
-          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-      {
-        let idx = layout.row.columns as usize;
-        layout.row.templates[idx] -= 1f32;
-        layout.row.columns += 1;
-      }
+    let (id, column) = match self.table {
+      Some(table) => table,
+      None => return,
+    };
 
-      Some(())
+    let (width, align) = match self.table_column(id, column) {
+      Some(col) => col,
+      None => return,
+    };
+
+    self.table = Some((id, column + 1));
+    self.layout_row_push(width);
+    let (state, bounds) = self.widget();
+    if state == WidgetLayoutStates::Invalid {
+      return;
+    }
+
+    self.current_win.borrow().as_ref().map(|winptr| {
+      self.table_cell_text(
+        &winptr.borrow(),
+        bounds,
+        text,
+        align,
+        self.style.window.background,
+        self.style.text.color,
+      );
     });
   }
 
-  pub fn layout_row_template_push_variable(&self, min_width: f32) {
+  /// Sets the text alignment of a column of the current table, used from the
+  /// next frame on for its header and for `table_cell()`.
+  pub fn table_column_align(
+    &mut self,
+    column: usize,
+    align: BitFlags<TextAlign>,
+  ) {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
-      let mut layout = win.layout.borrow_mut();
-
-      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
-      debug_assert!(
-        layout.row.columns
-          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-      );
-
-      if layout.row.typ == PanelRowLayoutType::Template
-        && layout.row.columns
-          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-      {
-        let idx = layout.row.columns as usize;
-        layout.row.templates[idx] = -min_width;
-        layout.row.columns += 1;
-      }
+    let id = match self.table {
+      Some((id, _)) => id,
+      None => return,
+    };
 
-      Some(())
+    self.current_win.borrow().as_ref().map(|winptr| {
+      winptr
+        .borrow_mut()
+        .tables
+        .get_mut(&id)
+        .and_then(|table| table.align.get_mut(column))
+        .map(|col| *col = align);
     });
   }
 
-  pub fn layout_row_template_push_static(&self, width: f32) {
+  pub fn table_end(&mut self) {
     debug_assert!(self.current_win.borrow().is_some());
+    debug_assert!(self.table.is_some(), "table_end() without table_begin()");
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
-      let mut layout = win.layout.borrow_mut();
-
-      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
-      debug_assert!(
-        layout.row.columns
-          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-      );
+    self.layout_row_end();
+    self.table = None;
+  }
 
-      if layout.row.typ == PanelRowLayoutType::Template
-        && layout.row.columns
-          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-      {
-        let idx = layout.row.columns as usize;
-        layout.row.templates[idx] = width;
-        layout.row.columns += 1;
-      }
+  fn table_columns(&self, id: HashType) -> usize {
+    self.current_win.borrow().as_ref().map_or(0, |winptr| {
+      winptr
+        .borrow()
+        .tables
+        .get(&id)
+        .map_or(0, |table| table.widths.len())
+    })
+  }
 
-      Some(())
-    });
+  fn table_column(
+    &self,
+    id: HashType,
+    column: usize,
+  ) -> Option<(f32, BitFlags<TextAlign>)> {
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      winptr.borrow().tables.get(&id).and_then(|table| {
+        table
+          .widths
+          .get(column)
+          .map(|width| (*width, table.align[column]))
+      })
+    })
   }
 
-  pub fn layout_row_template_end(&self) {
-    debug_assert!(self.current_win.borrow().is_some());
+  /// Draws the text of a table cell, clipped to the cell.
+  fn table_cell_text(
+    &self,
+    win: &Window,
+    cell: RectangleF32,
+    text: &str,
+    align: BitFlags<TextAlign>,
+    background: RGBAColor,
+    color: RGBAColor,
+  ) {
+    use crate::hmi::text::{widget_text, Text};
+
+    let mut out = win.buffer_mut();
+    let clip = out.clip();
+    out.push_scissor(RectangleF32::from_points(
+      cell.x.max(clip.x),
+      cell.y.max(clip.y),
+      (cell.x + cell.w).min(clip.x + clip.w),
+      (cell.y + cell.h).min(clip.y + clip.h),
+    ));
+
+    let txt = Text {
+      padding: self.style.text.padding,
+      background,
+      text: color,
+    };
+    widget_text(&mut out, cell, text, &txt, align, &self.style.font);
+    out.push_scissor(clip);
+  }
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      let win = winptr.borrow();
-      let mut layout = win.layout.borrow_mut();
+  fn layout_row_calculate_usable_space(
+    style: &Style,
+    typ: BitFlags<PanelType>,
+    total_space: f32,
+    columns: i32,
+  ) -> f32 {
+    let spacing = style.window.spacing;
+    let padding = style.get_panel_padding(typ);
+    // calculate usable panel space
+    let panel_padding = 2f32 * padding.x;
+    let panel_spacing = (columns - 1).max(0) as f32 * spacing.x;
+    total_space - panel_padding - panel_spacing
+  }
 
-      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
+  fn panel_layout(&self, win: &Window, height: f32, cols: i32) {
+    //  if one of these triggers you forgot to add an `if` condition around
+    // either a window, group, popup, combobox or contextual menu `begin`
+    // and `end` block. Example:
+    // if (nk_begin(...) {...} nk_end(...); or
+    // if (nk_group_begin(...) { nk_group_end(...);}
 
-      if layout.row.typ != PanelRowLayoutType::Template {
-        return Some(());
-      }
+    let mut layout = win.layout.borrow_mut();
+    let style = &self.style;
 
-      // from 0 .. layout.row.columns
-      let (
-        variable_count,
-        min_variable_count,
-        min_fixed_width,
-        total_fixed_width,
-        max_variable_width,
-      ) = (0 .. layout.row.columns).fold(
-        (0, 0, 0f32, 0f32, 0f32),
-        |(
-          variable_count,
-          min_variable_count,
-          min_fixed_width,
-          total_fixed_width,
-          max_variable_width,
-        ),
-         idx| {
-          let width = layout.row.templates[idx as usize];
-          if width >= 0f32 {
-            (
-              variable_count,
-              min_variable_count,
-              min_fixed_width + width,
-              total_fixed_width + width,
-              max_variable_width,
-            )
-          } else if width < -1f32 {
-            let width = -width;
-            (
-              variable_count + 1,
-              min_variable_count,
-              min_fixed_width,
-              total_fixed_width + width,
-              max_variable_width.max(width),
-            )
-          } else {
-            (
-              variable_count + 1,
-              min_variable_count + 1,
-              min_fixed_width,
-              total_fixed_width,
-              max_variable_width,
-            )
-          }
-        },
-      );
+    debug_assert!(!layout.flags.contains(PanelFlags::WindowMinimized));
+    debug_assert!(!layout.flags.contains(PanelFlags::WindowHidden));
+    debug_assert!(!layout.flags.contains(PanelFlags::WindowClosed));
 
-      if variable_count == 0 {
-        return Some(());
-      }
+    let item_spacing = style.window.spacing;
+    layout.row.index = 0;
+    layout.at_y += layout.row.height;
+    layout.row.columns = cols;
+    layout.row.height = if height == 0f32 {
+      height.max(layout.row.min_height) + item_spacing.y
+    } else {
+      height + item_spacing.y
+    };
 
-      let space = Self::layout_row_calculate_usable_space(
-        &self.style,
-        layout.typ,
-        layout.bounds.w,
-        layout.row.columns,
-      );
+    layout.row.item_offset = 0f32;
 
-      let var_width =
-        (space - min_fixed_width).max(0f32) / variable_count as f32;
-      let enough_space = var_width >= max_variable_width;
-      let var_width = if !enough_space {
-        (space - total_fixed_width).max(0f32) / min_variable_count as f32
-      } else {
-        var_width
+    if layout.flags.contains(PanelFlags::WindowDynamic) {
+      // draw background for dynamic panels
+      let bk = RectangleF32 {
+        y: layout.at_y - 1f32,
+        h: layout.row.height + 1f32,
+        ..*win.bounds.borrow()
       };
 
-      (0 .. layout.row.columns).for_each(|idx| {
-        let w = layout.row.templates[idx as usize];
-        let w = if w >= 0f32 {
-          w
+      win
+        .buffer
+        .borrow_mut()
+        .fill_rect(bk, 0f32, style.window.background);
+    }
+  }
+
+  pub fn row_layout(
+    &self,
+    fmt: LayoutFormat,
+    height: f32,
+    cols: i32,
+    width: i32,
+  ) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      // .and_then(|winptr| Some(winptr.clone()))
+      .and_then(|winptr| {
+        self.panel_layout(&winptr.borrow(), height, cols);
+        if fmt == LayoutFormat::Dynamic {
+          winptr.borrow().layout.borrow_mut().row.typ =
+            PanelRowLayoutType::DynamicFixed;
         } else {
-          if w < -1f32 && !enough_space {
-            -w
-          } else {
-            var_width
-          }
-        };
+          winptr.borrow().layout.borrow_mut().row.typ =
+            PanelRowLayoutType::StaticFixed;
+        }
 
-        layout.row.templates[idx as usize] = w;
+        let win = winptr.borrow();
+        let mut layout = win.layout.borrow_mut();
+        layout.row.ratio = std::ptr::null_mut();
+        layout.row.filled = 0f32;
+        layout.row.item_offset = 0f32;
+        layout.row.item_width = width as f32;
+        Some(())
       });
+  }
 
-      Some(())
-    });
+  pub fn layout_ratio_from_pixel(&self, pixel_width: f32) -> f32 {
+    self.current_win.borrow().as_ref().map_or(0f32, |winptr| {
+      clamp(0f32, pixel_width / winptr.borrow().bounds.borrow().x, 1f32)
+    })
   }
 
-  pub fn layout_space_begin(
+  pub fn layout_row_dynamic(&self, height: f32, cols: i32) {
+    self.row_layout(LayoutFormat::Dynamic, height, cols, 0)
+  }
+
+  pub fn layout_row_static(&self, height: f32, item_width: i32, cols: i32) {
+    self.row_layout(LayoutFormat::Static, height, cols, item_width)
+  }
+
+  /// Dynamic row that is at least `min_height` high, so content that only
+  /// shows up on some frames doesn't make the rows below it jump around.
+  pub fn layout_row_dynamic_reserved(
     &self,
-    fmt: LayoutFormat,
     height: f32,
-    widget_count: i32,
+    min_height: f32,
+    cols: i32,
   ) {
+    self.layout_row_dynamic(height.max(min_height), cols)
+  }
+
+  /// Skips `height` pixels below the current row without drawing anything.
+  /// Start a new row before adding more widgets.
+  pub fn layout_reserve(&self, height: f32) {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
+    self.current_win.borrow().as_ref().map(|winptr| {
+      // the next row is placed below the current one, so moving the row
+      // origin down moves everything after it
       let win = winptr.borrow();
-      self.panel_layout(&win, height, widget_count);
-      let mut layout = win.layout.borrow_mut();
-      layout.row.typ = if fmt == LayoutFormat::Static {
-        PanelRowLayoutType::StaticFree
-      } else {
-        PanelRowLayoutType::DynamicFree
-      };
-
-      layout.row.ratio = std::ptr::null_mut();
-      layout.row.filled = 0f32;
-      layout.row.item_width = 0f32;
-      layout.row.item_offset = 0f32;
-
-      Some(())
+      win.layout.borrow_mut().at_y += height.max(0f32);
     });
   }
 
-  pub fn layout_space_end(&self) {
+  pub fn layout_row_begin(
+    &self,
+    fmt: LayoutFormat,
+    row_height: f32,
+    cols: i32,
+  ) {
     debug_assert!(self.current_win.borrow().is_some());
 
     self.current_win.borrow().as_ref().and_then(|winptr| {
       let win = winptr.borrow();
+
+      self.panel_layout(&win, row_height, cols);
       let mut layout = win.layout.borrow_mut();
+      layout.row.typ = if fmt == LayoutFormat::Dynamic {
+        PanelRowLayoutType::DynamicRow
+      } else {
+        PanelRowLayoutType::StaticRow
+      };
 
+      layout.row.ratio = std::ptr::null_mut();
+      layout.row.filled = 0f32;
       layout.row.item_width = 0f32;
       layout.row.item_offset = 0f32;
-      layout.row.item_height = 0f32;
-      layout.row.item = RectangleF32::new(0f32, 0f32, 0f32, 0f32);
-
+      layout.row.columns = cols;
       Some(())
     });
   }
 
-  pub fn layout_space_push(&self, rect: &RectangleF32) {
+  pub fn layout_row_push(&self, ratio_or_width: f32) {
     debug_assert!(self.current_win.borrow().is_some());
 
     self.current_win.borrow().as_ref().and_then(|winptr| {
       let win = winptr.borrow();
+
       let mut layout = win.layout.borrow_mut();
-      layout.row.item = *rect;
+      if layout.row.typ != PanelRowLayoutType::StaticRow
+        && layout.row.typ != PanelRowLayoutType::DynamicRow
+      {
+        return Some(());
+      }
+
+      if layout.row.typ == PanelRowLayoutType::DynamicRow {
+        let ratio = ratio_or_width;
+        if (ratio + layout.row.filled) > 1f32 {
+          return Some(());
+        }
+
+        layout.row.item_width = if ratio > 0f32 {
+          saturate(ratio)
+        } else {
+          1f32 - layout.row.filled
+        };
+      } else {
+        layout.row.item_width = ratio_or_width;
+      }
 
       Some(())
     });
   }
 
-  pub fn layout_space_bounds(&self) -> RectangleF32 {
+  pub fn layout_row_end(&self) {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        let win = winptr.borrow();
-        let layout = win.layout.borrow_mut();
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
+      debug_assert!(
+        layout.row.typ == PanelRowLayoutType::StaticRow
+          || layout.row.typ == PanelRowLayoutType::DynamicRow
+      );
 
-        RectangleF32::new(
-          layout.clip.x,
-          layout.clip.y,
-          layout.clip.w,
-          layout.row.height,
-        )
-      },
-    )
+      if layout.row.typ == PanelRowLayoutType::StaticRow
+        || layout.row.typ == PanelRowLayoutType::DynamicRow
+      {
+        layout.row.item_width = 0f32;
+        layout.row.item_offset = 0f32;
+      }
+
+      Some(())
+    });
   }
 
-  pub fn layout_widget_bounds(&self) -> RectangleF32 {
+  pub fn layout_row(&self, fmt: LayoutFormat, height: f32, ratio: &[f32]) {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        let win = winptr.borrow();
-        let layout = win.layout.borrow_mut();
+    self.current_win.borrow().as_ref().map(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
 
-        RectangleF32::new(
-          layout.at_x,
-          layout.at_y,
-          layout.bounds.w - (layout.at_x - layout.bounds.x).max(0f32),
-          layout.row.height,
-        )
-      },
-    )
-  }
+      self.panel_layout(&win, height, ratio.len() as i32);
+      if fmt == LayoutFormat::Dynamic {
+        // calculate width of undefined widget ratios
+        layout.row.ratio = ratio.as_ptr();
+        let (n_undef, r) = ratio.iter().fold((0i32, 0f32), |acc, r| {
+          if *r < 0f32 {
+            (acc.0 + 1, acc.1)
+          } else {
+            (acc.0, acc.1 + r)
+          }
+        });
 
-  pub fn layout_space_to_screen(&self, ret: Vec2F32) -> Vec2F32 {
-    debug_assert!(self.current_win.borrow().is_some());
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(Vec2F32::same(0f32), |winptr| {
-        let win = winptr.borrow();
-        let layout = win.layout.borrow();
+        let r = saturate(1f32 - r);
+        layout.row.typ = PanelRowLayoutType::Dynamic;
+        layout.row.item_width = if r > 0f32 && n_undef > 0 {
+          r / n_undef as f32
+        } else {
+          0f32
+        };
+      } else {
+        layout.row.ratio = ratio.as_ptr();
+        layout.row.typ = PanelRowLayoutType::Static;
+        layout.row.item_width = 0f32;
+      }
 
-        let res = Vec2F32::new(
-          ret.x + layout.at_x - layout.offsets.borrow().scrollbar.x as f32,
-          ret.y + layout.at_y - layout.offsets.borrow().scrollbar.y as f32,
-        );
+      layout.row.item_offset = 0f32;
+      layout.row.filled = 0f32;
 
-        res
-      })
+      Some(())
+    });
   }
 
-  pub fn layout_space_to_local(&self, ret: Vec2F32) -> Vec2F32 {
-    debug_assert!(self.current_win.borrow().is_some());
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(Vec2F32::same(0f32), |winptr| {
-        let win = winptr.borrow();
-        let layout = win.layout.borrow();
-
-        let res = Vec2F32::new(
-          ret.x - layout.at_x + layout.offsets.borrow().scrollbar.x as f32,
-          ret.y - layout.at_y + layout.offsets.borrow().scrollbar.y as f32,
-        );
-
-        res
-      })
+  /// Row with a label column of fixed width and a widget column taking the
+  /// remaining space. Use `labeled()` to emit the label, followed by the
+  /// widget.
+  pub fn layout_row_label_widget(&self, label_width: f32, height: f32) {
+    self.layout_row_template_begin(height);
+    self.layout_row_template_push_static(label_width);
+    self.layout_row_template_push_dynamic();
+    self.layout_row_template_end();
   }
 
-  pub fn layout_space_rect_to_screen(
-    &self,
-    ret: &RectangleF32,
-  ) -> RectangleF32 {
+  pub fn layout_row_template_begin(&self, height: f32) {
     debug_assert!(self.current_win.borrow().is_some());
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        let win = winptr.borrow();
-        let layout = win.layout.borrow();
 
-        let res = RectangleF32::new(
-          ret.x + layout.at_x - layout.offsets.borrow().scrollbar.x as f32,
-          ret.y + layout.at_y - layout.offsets.borrow().scrollbar.y as f32,
-          ret.w,
-          ret.h,
-        );
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      self.panel_layout(&win, height, 1);
 
-        res
-      },
-    )
+      let mut layout = win.layout.borrow_mut();
+      layout.row.typ = PanelRowLayoutType::Template;
+      layout.row.columns = 0;
+      layout.row.ratio = std::ptr::null_mut();
+      layout.row.item_width = 0f32;
+      layout.row.item_height = 0f32;
+      layout.row.item_offset = 0f32;
+      layout.row.filled = 0f32;
+      layout.row.item.x = 0f32;
+      layout.row.item.y = 0f32;
+      layout.row.item.w = 0f32;
+      layout.row.item.h = 0f32;
+
+      Some(())
+    });
   }
 
-  pub fn layout_space_rect_to_local(&self, ret: &RectangleF32) -> RectangleF32 {
+  pub fn layout_row_template_push_dynamic(&self) {
     debug_assert!(self.current_win.borrow().is_some());
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        let win = winptr.borrow();
-        let layout = win.layout.borrow();
 
-        let res = RectangleF32::new(
-          ret.x - layout.at_x + layout.offsets.borrow().scrollbar.x as f32,
-          ret.y - layout.at_y + layout.offsets.borrow().scrollbar.y as f32,
-          ret.w,
-          ret.h,
-        );
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
 
-        res
-      },
-    )
-  }
+      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
+      debug_assert!(
+        layout.row.columns
+          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+      );
 
-  pub fn panel_alloc_row(&self, win: &Window) {
-    let (row_height, num_columns) = {
-      let spacing = self.style.window.spacing;
-      let layout = win.layout.borrow();
-      (layout.row.height - spacing.y, layout.row.columns)
-    };
+      if layout.row.typ == PanelRowLayoutType::Template
+        && layout.row.columns
+          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+      {
+        let idx = layout.row.columns as usize;
+        layout.row.templates[idx] = -1f32;
+        layout.row.columns += 1;
+      }
 
-    self.panel_layout(win, row_height, num_columns)
+      Some(())
+    });
   }
 
-  pub fn layout_widget_space(
-    &self,
-    // bounds: &RectangleF32,
-    modify: bool,
-  ) -> RectangleF32 {
+  pub fn layout_row_template_push_variable(&self, min_width: f32) {
     debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        let win = winptr.borrow();
-        let mut layout = win.layout.borrow_mut();
-        // let mut bounds = RectangleF32;
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
 
-        let spacing = self.style.window.spacing;
-        let padding = self.style.get_panel_padding(layout.typ);
-        let panel_space = Self::layout_row_calculate_usable_space(
-          &self.style,
-          layout.typ,
-          layout.bounds.w,
-          layout.row.columns,
-        );
+      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
+      debug_assert!(
+        layout.row.columns
+          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+      );
 
-        enum CalcRectResult {
-          Finished(RectangleF32),
-          NeedsAdjusting {
-            item_offset:  f32,
-            item_width:   f32,
-            item_spacing: f32,
-          },
-        }
+      if layout.row.typ == PanelRowLayoutType::Template
+        && layout.row.columns
+          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+      {
+        let idx = layout.row.columns as usize;
+        layout.row.templates[idx] = -min_width;
+        layout.row.columns += 1;
+      }
 
-        struct ItemSpacingInfo {
-          item_offset:  f32,
-          item_width:   f32,
-          item_spacing: f32,
-        }
+      Some(())
+    });
+  }
 
-        let frac_fn = |x: f32| x - (x as i32) as f32;
-        // calculate the width of one item inside the current layout space.
+  pub fn layout_row_template_push_static(&self, width: f32) {
+    debug_assert!(self.current_win.borrow().is_some());
 
-        let calc_result = match layout.row.typ {
-          PanelRowLayoutType::DynamicFixed => {
-            // scaling fixed size widgets item width
-            let w = panel_space.max(1f32) / layout.row.columns as f32;
-            let item_offset = layout.row.index as f32 * w;
-            let item_width = w + frac_fn(item_offset);
-            let item_spacing = layout.row.index as f32 + spacing.x;
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
 
-            CalcRectResult::NeedsAdjusting {
-              item_offset,
-              item_spacing,
-              item_width,
-            }
+      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
+      debug_assert!(
+        layout.row.columns
+          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+      );
+
+      if layout.row.typ == PanelRowLayoutType::Template
+        && layout.row.columns
+          < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+      {
+        let idx = layout.row.columns as usize;
+        layout.row.templates[idx] = width;
+        layout.row.columns += 1;
+      }
+
+      Some(())
+    });
+  }
+
+  pub fn layout_row_template_end(&self) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
+
+      debug_assert!(layout.row.typ == PanelRowLayoutType::Template);
+
+      if layout.row.typ != PanelRowLayoutType::Template {
+        return Some(());
+      }
+
+      // from 0 .. layout.row.columns
+      let (
+        variable_count,
+        min_variable_count,
+        min_fixed_width,
+        total_fixed_width,
+        max_variable_width,
+      ) = (0 .. layout.row.columns).fold(
+        (0, 0, 0f32, 0f32, 0f32),
+        |(
+          variable_count,
+          min_variable_count,
+          min_fixed_width,
+          total_fixed_width,
+          max_variable_width,
+        ),
+         idx| {
+          let width = layout.row.templates[idx as usize];
+          if width >= 0f32 {
+            (
+              variable_count,
+              min_variable_count,
+              min_fixed_width + width,
+              total_fixed_width + width,
+              max_variable_width,
+            )
+          } else if width < -1f32 {
+            let width = -width;
+            (
+              variable_count + 1,
+              min_variable_count,
+              min_fixed_width,
+              total_fixed_width + width,
+              max_variable_width.max(width),
+            )
+          } else {
+            (
+              variable_count + 1,
+              min_variable_count + 1,
+              min_fixed_width,
+              total_fixed_width,
+              max_variable_width,
+            )
           }
+        },
+      );
 
-          PanelRowLayoutType::DynamicRow => {
-            // scaling single ratio widget width
-            let w = layout.row.item_width * panel_space;
-            let item_offset = layout.row.item_offset;
-            let item_width = w + frac_fn(item_offset);
-            let item_spacing = 0f32;
+      if variable_count == 0 {
+        return Some(());
+      }
+
+      let space = Self::layout_row_calculate_usable_space(
+        &self.style,
+        layout.typ,
+        layout.bounds.w,
+        layout.row.columns,
+      );
+
+      let var_width =
+        (space - min_fixed_width).max(0f32) / variable_count as f32;
+      let enough_space = var_width >= max_variable_width;
+      let var_width = if !enough_space {
+        (space - total_fixed_width).max(0f32) / min_variable_count as f32
+      } else {
+        var_width
+      };
+
+      (0 .. layout.row.columns).for_each(|idx| {
+        let w = layout.row.templates[idx as usize];
+        let w = if w >= 0f32 {
+          w
+        } else {
+          if w < -1f32 && !enough_space {
+            -w
+          } else {
+            var_width
+          }
+        };
+
+        layout.row.templates[idx as usize] = w;
+      });
+
+      Some(())
+    });
+  }
+
+  pub fn layout_space_begin(
+    &self,
+    fmt: LayoutFormat,
+    height: f32,
+    widget_count: i32,
+  ) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      self.panel_layout(&win, height, widget_count);
+      let mut layout = win.layout.borrow_mut();
+      layout.row.typ = if fmt == LayoutFormat::Static {
+        PanelRowLayoutType::StaticFree
+      } else {
+        PanelRowLayoutType::DynamicFree
+      };
+
+      layout.row.ratio = std::ptr::null_mut();
+      layout.row.filled = 0f32;
+      layout.row.item_width = 0f32;
+      layout.row.item_offset = 0f32;
+
+      Some(())
+    });
+  }
+
+  pub fn layout_space_end(&self) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
+
+      layout.row.item_width = 0f32;
+      layout.row.item_offset = 0f32;
+      layout.row.item_height = 0f32;
+      layout.row.item = RectangleF32::new(0f32, 0f32, 0f32, 0f32);
+
+      Some(())
+    });
+  }
+
+  pub fn layout_space_push(&self, rect: &RectangleF32) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      let win = winptr.borrow();
+      let mut layout = win.layout.borrow_mut();
+      layout.row.item = *rect;
+
+      Some(())
+    });
+  }
+
+  pub fn layout_space_bounds(&self) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        let win = winptr.borrow();
+        let layout = win.layout.borrow_mut();
+
+        RectangleF32::new(
+          layout.clip.x,
+          layout.clip.y,
+          layout.clip.w,
+          layout.row.height,
+        )
+      },
+    )
+  }
+
+  pub fn layout_widget_bounds(&self) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        let win = winptr.borrow();
+        let layout = win.layout.borrow_mut();
+
+        RectangleF32::new(
+          layout.at_x,
+          layout.at_y,
+          layout.bounds.w - (layout.at_x - layout.bounds.x).max(0f32),
+          layout.row.height,
+        )
+      },
+    )
+  }
+
+  pub fn layout_space_to_screen(&self, ret: Vec2F32) -> Vec2F32 {
+    debug_assert!(self.current_win.borrow().is_some());
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(Vec2F32::same(0f32), |winptr| {
+        let win = winptr.borrow();
+        let layout = win.layout.borrow();
+
+        let res = Vec2F32::new(
+          ret.x + layout.at_x - layout.offsets.borrow().scrollbar.x as f32,
+          ret.y + layout.at_y - layout.offsets.borrow().scrollbar.y as f32,
+        );
+
+        res
+      })
+  }
+
+  pub fn layout_space_to_local(&self, ret: Vec2F32) -> Vec2F32 {
+    debug_assert!(self.current_win.borrow().is_some());
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(Vec2F32::same(0f32), |winptr| {
+        let win = winptr.borrow();
+        let layout = win.layout.borrow();
+
+        let res = Vec2F32::new(
+          ret.x - layout.at_x + layout.offsets.borrow().scrollbar.x as f32,
+          ret.y - layout.at_y + layout.offsets.borrow().scrollbar.y as f32,
+        );
+
+        res
+      })
+  }
+
+  pub fn layout_space_rect_to_screen(
+    &self,
+    ret: &RectangleF32,
+  ) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        let win = winptr.borrow();
+        let layout = win.layout.borrow();
+
+        let res = RectangleF32::new(
+          ret.x + layout.at_x - layout.offsets.borrow().scrollbar.x as f32,
+          ret.y + layout.at_y - layout.offsets.borrow().scrollbar.y as f32,
+          ret.w,
+          ret.h,
+        );
+
+        res
+      },
+    )
+  }
+
+  pub fn layout_space_rect_to_local(&self, ret: &RectangleF32) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        let win = winptr.borrow();
+        let layout = win.layout.borrow();
+
+        let res = RectangleF32::new(
+          ret.x - layout.at_x + layout.offsets.borrow().scrollbar.x as f32,
+          ret.y - layout.at_y + layout.offsets.borrow().scrollbar.y as f32,
+          ret.w,
+          ret.h,
+        );
+
+        res
+      },
+    )
+  }
+
+  /// Allocates `bounds` (in layout space, see `layout_space_push()`) for a
+  /// widget drawn by the user and returns its interaction state together
+  /// with its screen rectangle. Nothing gets drawn.
+  pub fn custom_widget(
+    &self,
+    bounds: RectangleF32,
+  ) -> (BitFlags<WidgetStates>, RectangleF32) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.layout_space_push(&bounds);
+    let (state, bounds) = self.widget();
+    if state == WidgetLayoutStates::Invalid {
+      return (BitFlags::default(), bounds);
+    }
+
+    let is_rom = state == WidgetLayoutStates::Rom
+      || self.current_win.borrow().as_ref().map_or(true, |winptr| {
+        winptr
+          .borrow()
+          .layout
+          .borrow()
+          .flags
+          .intersects(PanelFlags::WindowRom)
+      });
+
+    use crate::hmi::button::button_behaviour;
+
+    let input = self.input.borrow();
+    let mut widget_state = self.last_widget_state.borrow_mut();
+    button_behaviour(
+      &mut widget_state,
+      bounds,
+      if is_rom { None } else { Some(&*input) },
+      ButtonBehaviour::ButtonDefault,
+    );
+
+    (*widget_state, bounds)
+  }
+
+  /// Scrolls the window's content vertically with the mouse wheel, either in
+  /// steps or eased towards the target offset
+  /// (`StyleScrollbar::smooth_scroll`).
+  fn scroll_with_wheel(&self, win: &Window, scroll: &mut ScrollState) {
+    // fraction of the remaining distance covered per second when smoothing
+    const SMOOTH_SCROLL_RATE: f32 = 15f32;
+
+    let wheel = {
+      let input = self.input.borrow();
+      if input.is_mouse_hovering_rect(&win.bounds()) {
+        input.mouse.scroll_delta.y
+      } else {
+        0f32
+      }
+    };
+
+    let scroll_step = scroll.view.y * 0.1f32;
+    let max_offset = scroll.max_offset().y;
+    let current = scroll.scrollbar.y as f32;
+
+    if !self.style.scrollv.smooth_scroll {
+      if wheel != 0f32 {
+        let offset = clamp(0f32, current - wheel * scroll_step, max_offset);
+        scroll.scrollbar.y = offset as u32;
+      }
+      scroll.target.y = scroll.scrollbar.y as f32;
+      return;
+    }
+
+    if wheel != 0f32 {
+      scroll.target.y =
+        clamp(0f32, scroll.target.y - wheel * scroll_step, max_offset);
+    }
+
+    let distance = scroll.target.y - current;
+    if distance == 0f32 {
+      return;
+    }
+
+    // move at least one pixel per frame so the offset always arrives
+    let t = 1f32 - (-SMOOTH_SCROLL_RATE * self.delta_time_sec).exp();
+    let step = (distance.abs() * t).max(1f32).min(distance.abs());
+    scroll.scrollbar.y = (current + step * distance.signum()).round() as u32;
+  }
+
+  fn scrollbar_fade_id(win: &Window) -> HashType {
+    murmur_hash64a(format!("scrollbar{}", win.id.borrow().name).as_bytes(), 64)
+  }
+
+  fn draw_scrollbar_v(
+    &self,
+    win: &Window,
+    state: BitFlags<WidgetStates>,
+    track: RectangleF32,
+    cursor: RectangleF32,
+    auto_hide: bool,
+  ) {
+    let style = &self.style.scrollv;
+    let hovered = state.contains(WidgetStates::Hover);
+    let active = state.contains(WidgetStates::Activated);
+    let scrolled = {
+      let input = self.input.borrow();
+      input.mouse.scroll_delta.y != 0f32
+        && input.is_mouse_hovering_rect(&win.bounds())
+    };
+
+    // fully visible while used, fades out when idle
+    let alpha = if !auto_hide {
+      1f32
+    } else {
+      let id = Self::scrollbar_fade_id(win);
+      if hovered || active || scrolled {
+        self.animations.borrow_mut().insert(id, 1f32);
+        1f32
+      } else {
+        self.animations.borrow_mut().entry(id).or_insert(1f32);
+        self.animate(id, 0f32, 1f32 / style.fade_time.max(std::f32::EPSILON))
+      }
+    };
+
+    if alpha <= 0f32 {
+      return;
+    }
+
+    draw_scrollbar(&mut win.buffer_mut(), state, track, cursor, style, alpha);
+  }
+
+  pub fn panel_alloc_row(&self, win: &Window) {
+    let (row_height, num_columns) = {
+      let spacing = self.style.window.spacing;
+      let layout = win.layout.borrow();
+      (layout.row.height - spacing.y, layout.row.columns)
+    };
+
+    self.panel_layout(win, row_height, num_columns)
+  }
+
+  pub fn layout_widget_space(
+    &self,
+    // bounds: &RectangleF32,
+    modify: bool,
+  ) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        let win = winptr.borrow();
+        let mut layout = win.layout.borrow_mut();
+        // let mut bounds = RectangleF32;
+
+        let spacing = self.style.window.spacing;
+        let padding = self.style.get_panel_padding(layout.typ);
+        let panel_space = Self::layout_row_calculate_usable_space(
+          &self.style,
+          layout.typ,
+          layout.bounds.w,
+          layout.row.columns,
+        );
+
+        enum CalcRectResult {
+          Finished(RectangleF32),
+          NeedsAdjusting {
+            item_offset:  f32,
+            item_width:   f32,
+            item_spacing: f32,
+          },
+        }
+
+        struct ItemSpacingInfo {
+          item_offset:  f32,
+          item_width:   f32,
+          item_spacing: f32,
+        }
+
+        let frac_fn = |x: f32| x - (x as i32) as f32;
+        // calculate the width of one item inside the current layout space.
+
+        let calc_result = match layout.row.typ {
+          PanelRowLayoutType::DynamicFixed => {
+            // scaling fixed size widgets item width
+            let w = panel_space.max(1f32) / layout.row.columns as f32;
+            let item_offset = layout.row.index as f32 * w;
+            let item_width = w + frac_fn(item_offset);
+            let item_spacing = layout.row.index as f32 + spacing.x;
+
+            CalcRectResult::NeedsAdjusting {
+              item_offset,
+              item_spacing,
+              item_width,
+            }
+          }
+
+          PanelRowLayoutType::DynamicRow => {
+            // scaling single ratio widget width
+            let w = layout.row.item_width * panel_space;
+            let item_offset = layout.row.item_offset;
+            let item_width = w + frac_fn(item_offset);
+            let item_spacing = 0f32;
+
+            if modify {
+              layout.row.item_offset += w + spacing.x;
+              layout.row.filled += layout.row.item_width;
+              layout.row.index = 0;
+            }
+
+            CalcRectResult::NeedsAdjusting {
+              item_offset,
+              item_spacing,
+              item_width,
+            }
+          }
+
+          PanelRowLayoutType::DynamicFree => {
+            // free widget placing
+            let mut bounds = RectangleF32::new(0f32, 0f32, 0f32, 0f32);
+            bounds.x = layout.at_x + (layout.bounds.w * layout.row.item.x);
+            bounds.x -= layout.offsets.borrow().scrollbar.x as f32;
+            bounds.y = layout.at_y + (layout.row.height * layout.row.item.y);
+            bounds.y -= layout.offsets.borrow().scrollbar.y as f32;
+            bounds.w = layout.bounds.w * layout.row.item.w + frac_fn(bounds.x);
+            bounds.h =
+              layout.row.height * layout.row.item.h + frac_fn(bounds.y);
+            CalcRectResult::Finished(bounds)
+          }
+
+          PanelRowLayoutType::Dynamic => {
+            // scaling arrays of panel width rations for every widget
+            assert!(layout.row.ratio != std::ptr::null_mut());
+            let ratio = unsafe {
+              let idx = layout.row.index as isize;
+              if *layout.row.ratio.offset(idx) < 0f32 {
+                layout.row.item_width
+              } else {
+                *layout.row.ratio.offset(idx)
+              }
+            };
+
+            let w = ratio * panel_space;
+            if modify {
+              layout.row.item_offset += w;
+              layout.row.filled += ratio;
+            }
+
+            CalcRectResult::NeedsAdjusting {
+              item_spacing: layout.row.index as f32 * spacing.x,
+              item_offset:  layout.row.item_offset,
+              item_width:   w + frac_fn(layout.row.item_offset),
+            }
+          }
+
+          PanelRowLayoutType::StaticFixed => {
+            // non-scaling fixed widgets item width
+            let item_width = layout.row.item_width;
+            let item_offset = layout.row.index as f32 * item_width;
+            let item_spacing = layout.row.index as f32 * spacing.x;
+
+            CalcRectResult::NeedsAdjusting {
+              item_width,
+              item_offset,
+              item_spacing,
+            }
+          }
+
+          PanelRowLayoutType::StaticRow => {
+            // scaling single ratio widget width
+            let item_width = layout.row.item_width;
+            let item_offset = layout.row.item_offset;
+            let item_spacing = layout.row.index as f32 * spacing.x;
+            if modify {
+              layout.row.item_offset += item_width;
+            }
+
+            CalcRectResult::NeedsAdjusting {
+              item_width,
+              item_offset,
+              item_spacing,
+            }
+          }
+
+          PanelRowLayoutType::StaticFree => {
+            // free widget placing
+            let mut bounds = RectangleF32::new(0f32, 0f32, 0f32, 0f32);
+            bounds.x = layout.at_x + layout.row.item.x;
+            bounds.w = layout.row.item.w;
+            if (bounds.x + bounds.w) > layout.max_x && modify {
+              layout.max_x = bounds.x + bounds.w;
+            }
+            bounds.x -= layout.offsets.borrow().scrollbar.x as f32;
+            bounds.y = layout.at_y + layout.row.item.y;
+            bounds.y -= layout.offsets.borrow().scrollbar.y as f32;
+            bounds.h = layout.row.item.h;
+
+            CalcRectResult::Finished(bounds)
+          }
+
+          PanelRowLayoutType::Static => {
+            // non-scaling array of panel pixel width for every widget
+            let item_spacing = layout.row.index as f32 * spacing.x;
+            let item_width = unsafe {
+              let idx = layout.row.index as isize;
+              *layout.row.ratio.offset(idx)
+            };
+
+            let item_offset = layout.row.item_offset;
+            if modify {
+              layout.row.item_offset += item_width;
+            }
+
+            CalcRectResult::NeedsAdjusting {
+              item_spacing,
+              item_width,
+              item_offset,
+            }
+          }
+
+          PanelRowLayoutType::Template => {
+            // stretchy row layout with combined dynamic/static widget width
+            assert!(layout.row.index < layout.row.columns);
+            assert!(
+              layout.row.index
+                < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
+            );
+
+            let w = layout.row.templates[layout.row.index as usize];
+
+            let item_offset = layout.row.item_offset;
+            let item_width = w + frac_fn(item_offset);
+            let item_spacing = layout.row.index as f32 * spacing.x;
+            if modify {
+              layout.row.item_offset += w;
+            }
+
+            CalcRectResult::NeedsAdjusting {
+              item_offset,
+              item_width,
+              item_spacing,
+            }
+          }
+
+          _ => {
+            debug_assert!(false, "No layout defined!");
+            CalcRectResult::Finished(RectangleF32::new(0f32, 0f32, 0f32, 0f32))
+          }
+        };
+
+        match calc_result {
+          CalcRectResult::Finished(bounds_rect) => bounds_rect,
+          CalcRectResult::NeedsAdjusting {
+            item_width,
+            item_spacing,
+            item_offset,
+          } => {
+            let bounds = RectangleF32 {
+              w: item_width,
+              h: layout.row.height - spacing.y,
+              y: layout.at_y - layout.offsets.borrow().scrollbar.y as f32,
+              x: layout.at_x + item_offset + item_spacing + padding.x,
+            };
+
+            if (bounds.x + bounds.w) > layout.max_x && modify {
+              layout.max_x = bounds.x + bounds.w
+            }
+
+            bounds
+          }
+        }
+      },
+    )
+  }
+
+  fn panel_alloc_space(&self) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        // check if the end of the row was hit and begin a new row if true
+        let win = winptr.borrow();
+        let (row_full, same_line) = {
+          let mut layout = win.layout.borrow_mut();
+          let same_line = layout.row.same_line;
+          layout.row.same_line = false;
+          (layout.row.index >= layout.row.columns, same_line)
+        };
+
+        // a widget after same_line() continues the full row if it fits
+        let alloc_row = row_full && !(same_line && self.same_line_fits(&win));
+
+        if alloc_row {
+          self.panel_alloc_row(&win);
+        }
+
+        let bounds = self.layout_widget_space(true);
+        win.layout.borrow_mut().row.index += 1;
+        bounds
+      },
+    )
+  }
+
+  /// Places the next widget on the current row, right of the last one, even
+  /// if the row has no columns left. It wraps to a new row if the widget
+  /// would not fit the row's width. Only works with rows of fixed or ratio
+  /// sized items (`layout_row_static`, `layout_row_dynamic`,
+  /// `layout_row_begin`), with the other row layouts it has no effect.
+  pub fn same_line(&mut self) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map(|winptr| {
+      winptr.borrow().layout.borrow_mut().row.same_line = true;
+    });
+  }
+
+  fn same_line_fits(&self, win: &Window) -> bool {
+    let (typ, right) = {
+      let layout = win.layout.borrow();
+      (layout.row.typ, layout.bounds.x + layout.bounds.w)
+    };
+
+    match typ {
+      PanelRowLayoutType::DynamicFixed
+      | PanelRowLayoutType::DynamicRow
+      | PanelRowLayoutType::StaticFixed
+      | PanelRowLayoutType::StaticRow => {
+        let next = self.layout_widget_space(false);
+        next.x + next.w <= right
+      }
+      _ => false,
+    }
+  }
+
+  fn layout_peek(&self) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.current_win.borrow().as_ref().map_or(
+      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      |winptr| {
+        let win = winptr.borrow();
+
+        let (y, index) = {
+          // make this go out of scope because it's mut borrowed by
+          // layout_widget_space() below
+          let mut layout = win.layout.borrow_mut();
+          if layout.row.index >= layout.row.columns {
+            layout.at_y += layout.row.height;
+            layout.row.index = 0;
+          }
+
+          (layout.at_y, layout.row.index)
+        };
+
+        let bounds = self.layout_widget_space(true);
+        let mut layout = win.layout.borrow_mut();
+        let bounds = RectangleF32 {
+          x: if layout.row.index == 0 {
+            bounds.x - layout.row.item_offset
+          } else {
+            bounds.x
+          },
+          ..bounds
+        };
+        layout.at_y = y;
+        layout.row.index = index;
+
+        bounds
+      },
+    )
+  }
+
+  fn widget_bounds(&self) -> RectangleF32 {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.layout_peek()
+  }
+
+  fn widget_position(&self) -> Vec2F32 {
+    let bounds = self.widget_bounds();
+    Vec2F32::new(bounds.x, bounds.y)
+  }
+
+  fn widget_size(&self) -> Vec2F32 {
+    let bounds = self.widget_bounds();
+    Vec2F32::new(bounds.w, bounds.h)
+  }
+
+  fn widget_width(&self) -> f32 {
+    let bounds = self.widget_bounds();
+    bounds.w
+  }
+
+  fn widget_height(&self) -> f32 {
+    let bounds = self.widget_bounds();
+    bounds.h
+  }
+
+  fn widget_is_hovered(&self) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.current_win.borrow().as_ref().map_or(false, |winptr| {
+      let clip = winptr.borrow().layout.borrow().clip;
+      let clip = RectangleF32::new(
+        (clip.x as i32) as f32,
+        (clip.y as i32) as f32,
+        (clip.w as i32) as f32,
+        (clip.h as i32) as f32,
+      );
+
+      let bounds = self.layout_peek();
+
+      if !clip.intersect(&bounds) {
+        false
+      } else {
+        self.input.borrow().is_mouse_hovering_rect(&bounds)
+      }
+    })
+  }
+
+  fn widget_is_mouse_clicked(&self, btn: MouseButtonId) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.current_win.borrow().as_ref().map_or(false, |winptr| {
+      let clip = winptr.borrow().layout.borrow().clip;
+      let clip = RectangleF32::new(
+        (clip.x as i32) as f32,
+        (clip.y as i32) as f32,
+        (clip.w as i32) as f32,
+        (clip.h as i32) as f32,
+      );
+
+      let bounds = self.layout_peek();
+
+      if !clip.intersect(&bounds) {
+        false
+      } else {
+        self.input.borrow().mouse_clicked(btn, &bounds)
+      }
+    })
+  }
+
+  fn widget_has_mouse_click_down(
+    &self,
+    btn: MouseButtonId,
+    down: bool,
+  ) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+    self.current_win.borrow().as_ref().map_or(false, |winptr| {
+      let clip = winptr.borrow().layout.borrow().clip;
+      let clip = RectangleF32::new(
+        (clip.x as i32) as f32,
+        (clip.y as i32) as f32,
+        (clip.w as i32) as f32,
+        (clip.h as i32) as f32,
+      );
+
+      let bounds = self.layout_peek();
+
+      if !clip.intersect(&bounds) {
+        false
+      } else {
+        self
+          .input
+          .borrow()
+          .has_mouse_click_down_in_rect(btn, &bounds, down)
+      }
+    })
+  }
+
+  /// True on the frame the mouse moves onto the last allocated widget.
+  pub fn widget_entered(&self) -> bool {
+    self.hover_edges.borrow().0
+  }
+
+  /// True on the frame the mouse moves off the last allocated widget.
+  pub fn widget_left(&self) -> bool {
+    self.hover_edges.borrow().1
+  }
+
+  /// Compares the hover state of the widget with the previous frame's, for
+  /// `widget_entered()` and `widget_left()`.
+  /// True if the mouse is over the current window and not over one of the
+  /// windows drawn above it.
+  fn current_window_under_mouse(&self) -> bool {
+    let mouse = self.input.borrow().mouse.pos;
+    self.current_win.borrow().as_ref().map_or(false, |winptr| {
+      let windows = self.windows.borrow();
+      let above = windows
+        .iter()
+        .position(|w| same_window(w, winptr))
+        .map_or(&windows[.. 0], |idx| &windows[idx + 1 ..]);
+
+      winptr.borrow().bounds().contains_point(mouse.x, mouse.y)
+        && above.iter().all(|w| {
+          let w = w.borrow();
+          w.flags.contains(PanelFlags::WindowHidden)
+            || !w.bounds().contains_point(mouse.x, mouse.y)
+        })
+    })
+  }
+
+  fn widget_track_hover(
+    &self,
+    state: WidgetLayoutStates,
+    bounds: RectangleF32,
+  ) {
+    let hovered = state == WidgetLayoutStates::Valid
+      && self.input.borrow().is_mouse_hovering_rect(&bounds)
+      && self.current_window_under_mouse();
+    let seq = self.seq;
+    let seed = self.id_seed();
+
+    let edges = self.current_win.borrow().as_ref().map(|winptr| {
+      let mut win = winptr.borrow_mut();
+      // keyed by the position in the unscrolled content, so a widget scrolled
+      // to where another one was is not taken for it
+      let origin = win.bounds();
+      let scroll = win.layout.borrow().offsets.borrow().scrollbar;
+      let x = (bounds.x - origin.x) as i32 + scroll.x as i32;
+      let y = (bounds.y - origin.y) as i32 + scroll.y as i32;
+      let mut pos = [0u8; 8];
+      pos[.. 4].copy_from_slice(&x.to_le_bytes());
+      pos[4 ..].copy_from_slice(&y.to_le_bytes());
+      let key = murmur_hash64a(&pos, seed);
+
+      let was_hovered = win
+        .hovered
+        .get(&key)
+        .map_or(false, |last_seq| *last_seq == seq.wrapping_sub(1));
+      if hovered {
+        win.hovered.insert(key, seq);
+      } else {
+        win.hovered.remove(&key);
+      }
+
+      (hovered && !was_hovered, !hovered && was_hovered)
+    });
+
+    self.hover_edges.replace(edges.unwrap_or((false, false)));
+  }
+
+  fn widget(&self) -> (WidgetLayoutStates, RectangleF32) {
+    let (state, bounds) = self.widget_alloc();
+    // the measure pass would hide the hover edges from the real one
+    if self.measure.is_none() {
+      self.widget_track_hover(state, bounds);
+    }
+    // widgets between begin_disabled() and end_disabled() ignore input, so
+    // do all widgets while measuring and the ones under an open drop down
+    let state = if state == WidgetLayoutStates::Valid && self.input_blocked() {
+      WidgetLayoutStates::Rom
+    } else {
+      state
+    };
+
+    if let Some((name, role)) = self.accessible_next.borrow_mut().take() {
+      if state != WidgetLayoutStates::Invalid && self.measure.is_none() {
+        self.accessibility.borrow_mut().push(AccessibleWidget {
+          name,
+          role,
+          bounds,
+        });
+      }
+    }
+
+    if self.debug_draw && self.measure.is_none() {
+      self.debug_outline(
+        bounds,
+        match state {
+          WidgetLayoutStates::Valid => RGBAColor::new(0, 255, 0),
+          WidgetLayoutStates::Rom => RGBAColor::new(255, 160, 0),
+          WidgetLayoutStates::Invalid => RGBAColor::new(255, 0, 0),
+        },
+      );
+    }
+
+    (state, bounds)
+  }
+
+  /// True if the widgets laid out now ignore the input, see `widget()`.
+  fn input_blocked(&self) -> bool {
+    !self.disabled.is_empty()
+      || self.measure.is_some()
+      || self.covered_by_popup()
+  }
+
+  fn widget_alloc(&self) -> (WidgetLayoutStates, RectangleF32) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map_or(
+      (
+        WidgetLayoutStates::Invalid,
+        RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      ),
+      |winptr| {
+        // allocate space and check if the widget needs to be updated and drawn
+        let mut bounds = self.panel_alloc_space();
+
+        let win = winptr.borrow();
+        let layout = win.layout.borrow();
+
+        //  if one of these triggers you forgot to add an `if` condition around
+        // either a window, group, popup, combobox or contextual menu
+        // `begin` and `end` block. Example:
+        // if (nk_begin(...) {...} nk_end(...); or
+        // if (nk_group_begin(...) { nk_group_end(...);}
+        debug_assert!(!(layout.flags.contains(PanelFlags::WindowMinimized)));
+        debug_assert!(!(layout.flags.contains(PanelFlags::WindowHidden)));
+        debug_assert!(!(layout.flags.contains(PanelFlags::WindowClosed)));
+
+        // need to convert to int here to remove floating point errors
+        bounds.x = (bounds.x as i32) as f32;
+        bounds.y = (bounds.y as i32) as f32;
+        bounds.w = (bounds.w as i32) as f32;
+        bounds.h = (bounds.h as i32) as f32;
+
+        let c = RectangleF32::new(
+          (layout.clip.x as i32) as f32,
+          (layout.clip.y as i32) as f32,
+          (layout.clip.w as i32) as f32,
+          (layout.clip.h as i32) as f32,
+        );
+
+        if !c.intersect(&bounds) {
+          return (WidgetLayoutStates::Invalid, bounds);
+        }
+
+        let v = RectangleF32::union(&bounds, &c);
+        if !v.contains_point(
+          self.input.borrow().mouse.pos.x,
+          self.input.borrow().mouse.pos.y,
+        ) {
+          return (WidgetLayoutStates::Rom, bounds);
+        }
+
+        (WidgetLayoutStates::Valid, bounds)
+      },
+    )
+  }
+
+  fn widget_fitting(
+    &self,
+    item_padding: Vec2F32,
+  ) -> (WidgetLayoutStates, RectangleF32) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map_or(
+      (
+        WidgetLayoutStates::Invalid,
+        RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      ),
+      |winptr| {
+        // update the bounds to have no padding
+        let (state, mut bounds) = self.widget();
+
+        let win = winptr.borrow();
+        let layout = win.layout.borrow();
+        let panel_padding = self.style.get_panel_padding(layout.typ);
+        if layout.row.index == 1 {
+          bounds.w += panel_padding.x;
+          bounds.x -= panel_padding.x;
+        } else {
+          bounds.x -= item_padding.x;
+        }
+
+        if layout.row.index == layout.row.columns {
+          bounds.w += panel_padding.x;
+        } else {
+          bounds.w += item_padding.x;
+        }
+
+        (state, bounds)
+      },
+    )
+  }
+
+  fn spacing(&self, cols: i32) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().and_then(|winptr| {
+      // spacing over row boundaries
+      let win = winptr.borrow();
+      let (index, rows) = {
+        let layout = win.layout.borrow();
+        (
+          (layout.row.index + cols) % layout.row.columns,
+          (layout.row.index + cols) / layout.row.columns,
+        )
+      };
+
+      let cols = if rows > 0 {
+        (0 .. rows).for_each(|_| self.panel_alloc_row(&win));
+        index
+      } else {
+        cols
+      };
+
+      // non table laout need to allocate space
+      let layout_type = win.layout.borrow().row.typ;
+      if layout_type != PanelRowLayoutType::DynamicFixed
+        && layout_type != PanelRowLayoutType::StaticFixed
+      {
+        (0 .. cols).for_each(|_| {
+          self.panel_alloc_space();
+        });
+      } else {
+        win.layout.borrow_mut().row.index = index;
+      }
+
+      Some(())
+    });
+  }
+
+  /// text widgets
+
+  pub fn text(&mut self, s: &str, alignment: BitFlags<TextAlign>) {
+    self.text_colored(s, alignment, self.style.text.color);
+  }
+
+  pub fn text_colored(
+    &mut self,
+    txt: &str,
+    alignment: BitFlags<TextAlign>,
+    color: RGBAColor,
+  ) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map(|curr_win| {
+      let bounds = self.panel_alloc_space();
+
+      use crate::hmi::text::text_colored;
+      text_colored(
+        Rc::clone(curr_win),
+        &self.style,
+        bounds,
+        txt,
+        alignment,
+        color,
+      );
+    });
+  }
+
+  /// Label that copies its text to the clipboard when clicked. The label
+  /// background flashes briefly to show that the text was copied.
+  pub fn label_selectable(&mut self, text: &str) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    let (state, bounds) = self.widget();
+    if state == WidgetLayoutStates::Invalid {
+      return;
+    }
+
+    let is_rom = state == WidgetLayoutStates::Rom
+      || self.current_win.borrow().as_ref().map_or(true, |winptr| {
+        winptr
+          .borrow()
+          .layout
+          .borrow()
+          .flags
+          .intersects(PanelFlags::WindowRom)
+      });
+
+    let clicked = !is_rom
+      && self
+        .input
+        .borrow()
+        .mouse_clicked(MouseButtonId::ButtonLeft, &bounds);
+
+    let id = self.widget_id(text);
+    if clicked {
+      if let Some(clipboard) = self.clipboard.as_mut() {
+        clipboard.set_text(text);
+      }
+      self.animations.borrow_mut().insert(id, 1f32);
+    }
+
+    let highlight = self.animate(id, 0f32, 4f32);
+    let style = &self.style.selectable;
+    let background = style.pressed.background_color(style.text_background);
+
+    self.current_win.borrow().as_ref().map(|curr_win| {
+      use crate::hmi::text::{widget_text, Text};
+
+      let win = curr_win.borrow();
+      let mut out = win.buffer_mut();
+      if highlight > 0f32 {
+        let alpha = (background.a as f32 * highlight) as u8;
+        out.fill_rect(
+          bounds,
+          style.rounding,
+          RGBAColor {
+            a: alpha,
+            ..background
+          },
+        );
+      }
+
+      let txt = Text {
+        padding:    self.style.text.padding,
+        background: self.style.window.background,
+        text:       self.style.text.color,
+      };
+      widget_text(
+        &mut out,
+        bounds,
+        text,
+        &txt,
+        TextAlign::left(),
+        &self.style.font,
+      );
+    });
+  }
+
+  pub fn text_wrap(&mut self, s: &str) {
+    self.text_wrap_colored(s, self.style.text.color);
+  }
+
+  pub fn text_wrap_colored(&mut self, txt: &str, color: RGBAColor) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map(|curr_win| {
+      let bounds = self.panel_alloc_space();
+
+      use crate::hmi::text::text_wrap_colored;
+      text_wrap_colored(Rc::clone(curr_win), &self.style, bounds, txt, color);
+    });
+  }
+
+  pub fn label(&mut self, s: &str, align: BitFlags<TextAlign>) {
+    self.text(s, align);
+  }
+
+  pub fn label_colored(
+    &mut self,
+    s: &str,
+    alignment: BitFlags<TextAlign>,
+    color: RGBAColor,
+  ) {
+    self.text_colored(s, alignment, color);
+  }
+
+  /// Draws the label cell of a `layout_row_label_widget()` row, the next
+  /// widget goes into the right cell.
+  pub fn labeled(&mut self, label: &str) {
+    self.text(label, TextAlign::AlignLeft | TextAlign::AlignMiddle);
+  }
+
+  pub fn label_wrap(&mut self, s: &str) {
+    self.text_wrap(s);
+  }
+
+  pub fn label_colored_wrap(&mut self, s: &str, color: RGBAColor) {
+    self.text_wrap_colored(s, color);
+  }
+
+  pub fn image(&mut self, img: Image) {
+    self.image_color(img, RGBAColor::new(255, 255, 255));
+  }
+
+  pub fn image_color(&mut self, img: Image, color: RGBAColor) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map(|curr_win| {
+      let (widget_states, bounds) = self.widget();
+      if widget_states == WidgetLayoutStates::Invalid {
+        return;
+      }
+
+      curr_win
+        .borrow()
+        .buffer
+        .borrow_mut()
+        .draw_image(bounds, img, color);
+    });
+  }
+
+  pub fn value<T: std::fmt::Display>(&mut self, prefix: &str, val: T) {
+    self.label(&format!("{} : {}", prefix, val), TextAlign::left());
+  }
+
+  pub fn value_bool(&mut self, prefix: &str, b: bool) {
+    self.value(prefix, b);
+  }
+
+  /// Draws the color's components followed by a swatch of the color.
+  pub fn value_color(&mut self, prefix: &str, color: RGBAColor) {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self.current_win.borrow().as_ref().map(|curr_win| {
+      let (widget_state, bounds) = self.widget();
+      if widget_state == WidgetLayoutStates::Invalid {
+        return;
+      }
+
+      let swatch = RectangleF32 {
+        x: bounds.x + bounds.w - bounds.h,
+        w: bounds.h,
+        ..bounds
+      };
+      let label = RectangleF32 {
+        w: (bounds.w - swatch.w - self.style.window.spacing.x).max(0f32),
+        ..bounds
+      };
+
+      use crate::hmi::text::text_colored;
+      text_colored(
+        Rc::clone(curr_win),
+        &self.style,
+        label,
+        &format!(
+          "{} : {}, {}, {}, {}",
+          prefix, color.r, color.g, color.b, color.a
+        ),
+        TextAlign::left(),
+        self.style.text.color,
+      );
+
+      curr_win
+        .borrow()
+        .buffer_mut()
+        .fill_rect(swatch, 0f32, color);
+    });
+  }
+
+  pub fn value_rect(&mut self, prefix: &str, r: RectangleF32) {
+    self.label(
+      &format!("{} : {}, {}, {}, {}", prefix, r.x, r.y, r.w, r.h),
+      TextAlign::left(),
+    );
+  }
+
+  /// buttons
+  pub fn button_set_behaviour(&mut self, behavior: ButtonBehaviour) {
+    self.button_behviour = behavior;
+  }
+
+  pub fn button_push_behavior(&mut self, _behavior: ButtonBehaviour) -> bool {
+    // TODO: add support for this
+    false
+  }
+
+  pub fn button_pop_behaviour(&mut self, _behavior: ButtonBehaviour) -> bool {
+    // TODO: add support for this
+    false
+  }
+
+  pub fn button_text_styled(&self, style: &StyleButton, title: &str) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(false, |curr_win| {
+        let (state, bounds) = self.widget();
+        if state == WidgetLayoutStates::Invalid {
+          return false;
+        }
+
+        use crate::hmi::button::do_button_text;
+
+        let input = self.input.borrow();
+
+        do_button_text(
+          &mut self.last_widget_state.borrow_mut(),
+          &mut curr_win.borrow().buffer_mut(),
+          bounds,
+          title,
+          style.text_alignment,
+          self.button_behviour,
+          style,
+          if state == WidgetLayoutStates::Rom
+            || curr_win
+              .borrow()
+              .layout
+              .borrow()
+              .flags
+              .intersects(PanelFlags::WindowRom)
+          {
+            None
+          } else {
+            Some(&*input)
+          },
+          &self.style.font,
+        )
+      })
+  }
+
+  pub fn button_text(&self, title: &str) -> bool {
+    let style_btn = self.style.button;
+    self.button_text_styled(&style_btn, title)
+  }
+
+  pub fn button_label_styled(&self, style: &StyleButton, title: &str) -> bool {
+    self.button_text_styled(style, title)
+  }
+
+  pub fn button_label(&self, title: &str) -> bool {
+    self.button_text(title)
+  }
+
+  /// Button in a row of its own that is only as wide as its label plus the
+  /// button padding, instead of filling the row.
+  pub fn button_label_autowidth(&mut self, label: &str) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    let style = &self.style.button;
+    let width = self.style.font.text_width(label)
+      + 2f32 * (style.padding.x + style.border + style.rounding);
+    let height = self.style.font.scale + 2f32 * style.padding.y;
+    self.layout_row_static(height, width.ceil() as i32, 1);
+    self.button_label(label)
+  }
+
+  /// Row of mutually exclusive buttons (segmented control), the selected one
+  /// is drawn with the button's active style. Returns true if the selection
+  /// changed.
+  pub fn selection_group(
+    &mut self,
+    labels: &[&str],
+    selected: &mut usize,
+  ) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    if labels.is_empty() {
+      return false;
+    }
+
+    let row_height = self.style.font.scale + 2f32 * self.style.button.padding.y;
+    self.layout_row_dynamic(row_height, labels.len() as i32);
+
+    let style_normal = self.style.button;
+    let style_selected = StyleButton {
+      normal: style_normal.active,
+      hover: style_normal.active,
+      text_normal: style_normal.text_active,
+      text_hover: style_normal.text_active,
+      ..style_normal
+    };
+
+    let mut changed = false;
+    labels.iter().enumerate().for_each(|(idx, label)| {
+      let style = if idx == *selected {
+        &style_selected
+      } else {
+        &style_normal
+      };
+
+      if self.button_text_styled(style, label) && idx != *selected {
+        *selected = idx;
+        changed = true;
+      }
+    });
+
+    changed
+  }
+
+  pub fn button_color(&self, color: RGBAColor) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    let (state, bounds) = self.widget();
+
+    if state == WidgetLayoutStates::Invalid {
+      return false;
+    }
+
+    let input = self.input.borrow();
+
+    let style = StyleButton {
+      normal: StyleItem::Color(color),
+      hover: StyleItem::Color(color),
+      active: StyleItem::Color(color),
+      ..self.style.button
+    };
+
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(false, |curr_win| {
+        use crate::hmi::button::do_button;
+        let (res, _content) = do_button(
+          &mut self.last_widget_state.borrow_mut(),
+          &mut curr_win.borrow().buffer_mut(),
+          bounds,
+          &style,
+          if state == WidgetLayoutStates::Rom
+            || curr_win
+              .borrow()
+              .layout
+              .borrow()
+              .flags
+              .intersects(PanelFlags::WindowRom)
+          {
+            None
+          } else {
+            Some(&*input)
+          },
+          self.button_behviour,
+        );
+
+        use crate::hmi::button::draw_button;
+        draw_button(
+          &mut curr_win.borrow().buffer_mut(),
+          &bounds,
+          *self.last_widget_state.borrow(),
+          &style,
+        );
+
+        res
+      })
+  }
+
+  pub fn button_symbol_styled(
+    &self,
+    style: &StyleButton,
+    symbol: SymbolType,
+  ) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(false, |curr_win| {
+        let (state, bounds) = self.widget();
+        if state == WidgetLayoutStates::Invalid {
+          return false;
+        }
+
+        let input = self.input.borrow();
+        use crate::hmi::button::do_button_symbol;
+        do_button_symbol(
+          &mut self.last_widget_state.borrow_mut(),
+          &mut curr_win.borrow().buffer_mut(),
+          bounds,
+          symbol,
+          self.button_behviour,
+          style,
+          if state == WidgetLayoutStates::Rom
+            || curr_win
+              .borrow()
+              .layout
+              .borrow()
+              .flags
+              .intersects(PanelFlags::WindowRom)
+          {
+            None
+          } else {
+            Some(&*input)
+          },
+          &self.style.font,
+        )
+      })
+  }
+
+  pub fn button_symbol(&self, symbol: SymbolType) -> bool {
+    self.button_symbol_styled(&self.style.button, symbol)
+  }
+
+  pub fn button_image_styled(&self, style: &StyleButton, img: Image) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(false, |curr_win| {
+        let (state, bounds) = self.widget();
+        if state == WidgetLayoutStates::Invalid {
+          return false;
+        }
+
+        let input = self.input.borrow();
+        use crate::hmi::button::do_button_image;
+
+        do_button_image(
+          &mut self.last_widget_state.borrow_mut(),
+          &mut curr_win.borrow().buffer_mut(),
+          bounds,
+          img,
+          self.button_behviour,
+          style,
+          if state == WidgetLayoutStates::Rom
+            || curr_win
+              .borrow()
+              .layout
+              .borrow()
+              .flags
+              .intersects(PanelFlags::WindowRom)
+          {
+            None
+          } else {
+            Some(&*input)
+          },
+        )
+      })
+  }
+
+  pub fn button_image(&self, img: Image) -> bool {
+    self.button_image_styled(&self.style.button, img)
+  }
+
+  pub fn button_symbol_text_styled(
+    &self,
+    style: &StyleButton,
+    symbol: SymbolType,
+    text: &str,
+    align: BitFlags<TextAlign>,
+  ) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
+
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(false, |curr_win| {
+        let (state, bounds) = self.widget();
+        if state == WidgetLayoutStates::Invalid {
+          return false;
+        }
+
+        let input = self.input.borrow();
+        use crate::hmi::button::do_button_text_symbol;
+        do_button_text_symbol(
+          &mut self.last_widget_state.borrow_mut(),
+          &mut curr_win.borrow().buffer_mut(),
+          bounds,
+          symbol,
+          text,
+          align,
+          self.button_behviour,
+          style,
+          &self.style.font,
+          if state == WidgetLayoutStates::Rom
+            || curr_win
+              .borrow()
+              .layout
+              .borrow()
+              .flags
+              .intersects(PanelFlags::WindowRom)
+          {
+            None
+          } else {
+            Some(&*input)
+          },
+        )
+      })
+  }
 
-            if modify {
-              layout.row.item_offset += w + spacing.x;
-              layout.row.filled += layout.row.item_width;
-              layout.row.index = 0;
-            }
+  pub fn button_symbol_text(
+    &self,
+    symbol: SymbolType,
+    text: &str,
+    align: BitFlags<TextAlign>,
+  ) -> bool {
+    self.button_symbol_text_styled(&self.style.button, symbol, text, align)
+  }
 
-            CalcRectResult::NeedsAdjusting {
-              item_offset,
-              item_spacing,
-              item_width,
-            }
-          }
+  pub fn button_image_text_styled(
+    &self,
+    style: &StyleButton,
+    img: Image,
+    text: &str,
+    align: BitFlags<TextAlign>,
+  ) -> bool {
+    debug_assert!(self.current_win.borrow().is_some());
 
-          PanelRowLayoutType::DynamicFree => {
-            // free widget placing
-            let mut bounds = RectangleF32::new(0f32, 0f32, 0f32, 0f32);
-            bounds.x = layout.at_x + (layout.bounds.w * layout.row.item.x);
-            bounds.x -= layout.offsets.borrow().scrollbar.x as f32;
-            bounds.y = layout.at_y + (layout.row.height * layout.row.item.y);
-            bounds.y -= layout.offsets.borrow().scrollbar.y as f32;
-            bounds.w = layout.bounds.w * layout.row.item.w + frac_fn(bounds.x);
-            bounds.h =
-              layout.row.height * layout.row.item.h + frac_fn(bounds.y);
-            CalcRectResult::Finished(bounds)
-          }
+    self
+      .current_win
+      .borrow()
+      .as_ref()
+      .map_or(false, |curr_win| {
+        let (state, bounds) = self.widget();
+        if state == WidgetLayoutStates::Invalid {
+          return false;
+        }
 
-          PanelRowLayoutType::Dynamic => {
-            // scaling arrays of panel width rations for every widget
-            assert!(layout.row.ratio != std::ptr::null_mut());
-            let ratio = unsafe {
-              let idx = layout.row.index as isize;
-              if *layout.row.ratio.offset(idx) < 0f32 {
-                layout.row.item_width
-              } else {
-                *layout.row.ratio.offset(idx)
-              }
-            };
+        let input = self.input.borrow();
+        use crate::hmi::button::do_button_text_image;
+        do_button_text_image(
+          &mut self.last_widget_state.borrow_mut(),
+          &mut curr_win.borrow().buffer_mut(),
+          bounds,
+          img,
+          text,
+          align,
+          self.button_behviour,
+          style,
+          &self.style.font,
+          if state == WidgetLayoutStates::Rom
+            || curr_win
+              .borrow()
+              .layout
+              .borrow()
+              .flags
+              .intersects(PanelFlags::WindowRom)
+          {
+            None
+          } else {
+            Some(&*input)
+          },
+        )
+      })
+  }
 
-            let w = ratio * panel_space;
-            if modify {
-              layout.row.item_offset += w;
-              layout.row.filled += ratio;
-            }
+  pub fn button_image_text(
+    &self,
+    img: Image,
+    text: &str,
+    align: BitFlags<TextAlign>,
+  ) -> bool {
+    self.button_image_text_styled(&self.style.button, img, text, align)
+  }
+}
 
-            CalcRectResult::NeedsAdjusting {
-              item_spacing: layout.row.index as f32 * spacing.x,
-              item_offset:  layout.row.item_offset,
-              item_width:   w + frac_fn(layout.row.item_offset),
-            }
-          }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::{base::GenericHandle, input::KeyId};
 
-          PanelRowLayoutType::StaticFixed => {
-            // non-scaling fixed widgets item width
-            let item_width = layout.row.item_width;
-            let item_offset = layout.row.index as f32 * item_width;
-            let item_spacing = layout.row.index as f32 * spacing.x;
+  fn test_config() -> ConvertConfig {
+    ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1))
+  }
 
-            CalcRectResult::NeedsAdjusting {
-              item_width,
-              item_offset,
-              item_spacing,
-            }
-          }
+  fn test_context() -> UiContext {
+    UiContext::new(
+      Font::default(),
+      test_config(),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap()
+  }
 
-          PanelRowLayoutType::StaticRow => {
-            // scaling single ratio widget width
-            let item_width = layout.row.item_width;
-            let item_offset = layout.row.item_offset;
-            let item_spacing = layout.row.index as f32 * spacing.x;
-            if modify {
-              layout.row.item_offset += item_width;
-            }
+  #[test]
+  fn test_overlay_commands_are_built() {
+    let mut ctx = test_context();
+    ctx.overlay_mut().fill_rect(
+      RectangleF32::new(10f32, 10f32, 20f32, 20f32),
+      0f32,
+      RGBAColor::new(255, 0, 0),
+    );
 
-            CalcRectResult::NeedsAdjusting {
-              item_width,
-              item_offset,
-              item_spacing,
-            }
-          }
+    assert!(ctx
+      .commands_iter()
+      .any(|cmd| matches!(cmd, Command::RectFilled(_))));
 
-          PanelRowLayoutType::StaticFree => {
-            // free widget placing
-            let mut bounds = RectangleF32::new(0f32, 0f32, 0f32, 0f32);
-            bounds.x = layout.at_x + layout.row.item.x;
-            bounds.w = layout.row.item.w;
-            if (bounds.x + bounds.w) > layout.max_x && modify {
-              layout.max_x = bounds.x + bounds.w;
-            }
-            bounds.x -= layout.offsets.borrow().scrollbar.x as f32;
-            bounds.y = layout.at_y + layout.row.item.y;
-            bounds.y -= layout.offsets.borrow().scrollbar.y as f32;
-            bounds.h = layout.row.item.h;
+    ctx.clear();
+    assert_eq!(ctx.commands_iter().count(), 0);
+  }
 
-            CalcRectResult::Finished(bounds)
-          }
+  #[test]
+  fn test_scaler_hover_sets_resize_cursor() {
+    let mut ctx = test_context();
+    ctx.input_mut().begin();
+    ctx.input_mut().end();
 
-          PanelRowLayoutType::Static => {
-            // non-scaling array of panel pixel width for every widget
-            let item_spacing = layout.row.index as f32 * spacing.x;
-            let item_width = unsafe {
-              let idx = layout.row.index as isize;
-              *layout.row.ratio.offset(idx)
-            };
+    assert!(ctx.begin(
+      "scalable",
+      RectangleF32::new(10f32, 10f32, 200f32, 200f32),
+      PanelFlags::WindowScalable | PanelFlags::WindowBorder,
+    ));
 
-            let item_offset = layout.row.item_offset;
-            if modify {
-              layout.row.item_offset += item_width;
-            }
+    let scaler_center = {
+      let winptr = ctx.current_win.borrow().as_ref().unwrap().clone();
+      let win = winptr.borrow();
+      let layout = win.layout.borrow();
+      let padding = ctx.style.get_panel_padding(layout.typ);
+      let size = ctx.style.window.scrollbar_size;
+      Vec2F32::new(
+        layout.bounds.x + layout.bounds.w + padding.x + size.x * 0.5f32,
+        layout.bounds.y + layout.bounds.h + size.y * 0.5f32,
+      )
+    };
+    ctx
+      .input_mut()
+      .motion(scaler_center.x as i32, scaler_center.y as i32);
+    ctx.end();
+
+    assert_eq!(
+      ctx.style.cursor_active,
+      StyleCursor::CursorResizeTopLeftDownRight as usize
+    );
 
-            CalcRectResult::NeedsAdjusting {
-              item_spacing,
-              item_width,
-              item_offset,
-            }
-          }
+    ctx.clear();
+    assert_eq!(ctx.style.cursor_active, StyleCursor::CursorArrow as usize);
+  }
 
-          PanelRowLayoutType::Template => {
-            // stretchy row layout with combined dynamic/static widget width
-            assert!(layout.row.index < layout.row.columns);
-            assert!(
-              layout.row.index
-                < crate::hmi::panel::MAX_LAYOUT_ROW_TEMPLATE_COLUMNS as i32
-            );
+  #[test]
+  fn test_viewports_are_disjoint() {
+    let mut ctx = test_context();
+    let flags = PanelFlags::WindowBorder.into();
+
+    ctx.begin("main", RectangleF32::new(0f32, 0f32, 100f32, 100f32), flags);
+    ctx.end();
+    ctx.begin(
+      "tools",
+      RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+      flags,
+    );
+    ctx.end();
+    ctx.window_set_viewport("tools", 1);
 
-            let w = layout.row.templates[layout.row.index as usize];
+    let main_cmds = ctx.build_for_viewport(Some(0)).to_vec();
+    let tool_cmds = ctx.build_for_viewport(Some(1)).to_vec();
 
-            let item_offset = layout.row.item_offset;
-            let item_width = w + frac_fn(item_offset);
-            let item_spacing = layout.row.index as f32 * spacing.x;
-            if modify {
-              layout.row.item_offset += w;
-            }
+    assert!(!main_cmds.is_empty());
+    assert!(!tool_cmds.is_empty());
+    assert!(main_cmds.iter().all(|cmd| !tool_cmds.contains(cmd)));
+    assert_eq!(ctx.build().len(), main_cmds.len() + tool_cmds.len());
+  }
 
-            CalcRectResult::NeedsAdjusting {
-              item_offset,
-              item_width,
-              item_spacing,
-            }
-          }
+  #[test]
+  fn test_window_scroll_shifts_widgets() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 100f32);
+    let flags = PanelFlags::WindowBorder.into();
+
+    ctx.begin("log", bounds, flags);
+    ctx.layout_row_dynamic(30f32, 1);
+    let first = ctx.layout_widget_space(false);
+    (0 .. 10).for_each(|_| {
+      ctx.panel_alloc_space();
+    });
+    ctx.end();
+    ctx.clear();
 
-          _ => {
-            debug_assert!(false, "No layout defined!");
-            CalcRectResult::Finished(RectangleF32::new(0f32, 0f32, 0f32, 0f32))
-          }
-        };
+    ctx.window_set_scroll("log", Vec2F32::new(0f32, 50f32));
+    assert_eq!(ctx.window_get_scroll("log").y, 50f32);
 
-        match calc_result {
-          CalcRectResult::Finished(bounds_rect) => bounds_rect,
-          CalcRectResult::NeedsAdjusting {
-            item_width,
-            item_spacing,
-            item_offset,
-          } => {
-            let bounds = RectangleF32 {
-              w: item_width,
-              h: layout.row.height - spacing.y,
-              y: layout.at_y - layout.offsets.borrow().scrollbar.y as f32,
-              x: layout.at_x + item_offset + item_spacing + padding.x,
-            };
+    ctx.begin("log", bounds, flags);
+    ctx.layout_row_dynamic(30f32, 1);
+    let scrolled = ctx.layout_widget_space(false);
+    ctx.end();
 
-            if (bounds.x + bounds.w) > layout.max_x && modify {
-              layout.max_x = bounds.x + bounds.w
-            }
+    assert_eq!(scrolled.y, first.y - 50f32);
 
-            bounds
-          }
-        }
-      },
-    )
+    // can't scroll past the content
+    ctx.window_set_scroll("log", Vec2F32::new(0f32, 1.0e6f32));
+    assert!(ctx.window_get_scroll("log").y < 1.0e6f32);
   }
 
-  fn panel_alloc_space(&self) -> RectangleF32 {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_unchanged_window_reuses_geometry() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 100f32, 100f32);
+    let (mut cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+
+    ctx.begin("static", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+    ctx.convert(&mut cmds, &mut vertices, &mut indices);
+    assert_eq!(ctx.geometry_regenerations(), 1);
+    ctx.clear();
+
+    let (mut cmds2, mut vertices2, mut indices2) = (vec![], vec![], vec![]);
+    ctx.begin("static", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+    ctx.convert(&mut cmds2, &mut vertices2, &mut indices2);
+
+    assert_eq!(ctx.geometry_regenerations(), 1);
+    assert_eq!(vertices.len(), vertices2.len());
+    assert_eq!(indices, indices2);
+  }
 
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        // check if the end of the row was hit and begin a new row if true
-        let win = winptr.borrow();
-        let alloc_row = {
-          let layout = win.layout.borrow();
-          layout.row.index >= layout.row.columns
-        };
+  #[test]
+  fn test_geometry_not_drawn_is_dropped_per_viewport() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 100f32, 100f32);
+    let frame = |ctx: &mut UiContext, viewport: u32| {
+      let (mut cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      ctx.begin("tools", bounds, PanelFlags::WindowBorder.into());
+      ctx.end();
+      ctx.window_set_viewport("tools", viewport);
+      ctx.convert_for_viewport(1, &mut cmds, &mut vertices, &mut indices);
+      ctx.clear();
+    };
 
-        if alloc_row {
-          self.panel_alloc_row(&win);
-        }
+    frame(&mut ctx, 1);
+    frame(&mut ctx, 1);
+    assert_eq!(ctx.geometry_regenerations(), 1);
 
-        let bounds = self.layout_widget_space(true);
-        win.layout.borrow_mut().row.index += 1;
-        bounds
-      },
-    )
+    // a frame that doesn't draw the window drops its geometry, even though
+    // convert() is never called for the default viewport
+    frame(&mut ctx, 2);
+    frame(&mut ctx, 1);
+    assert_eq!(ctx.geometry_regenerations(), 2);
   }
 
-  fn layout_peek(&self) -> RectangleF32 {
-    debug_assert!(self.current_win.borrow().is_some());
-    self.current_win.borrow().as_ref().map_or(
-      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      |winptr| {
-        let win = winptr.borrow();
+  #[test]
+  fn test_convert_stats_cover_the_whole_frame() {
+    let mut ctx = test_context();
+    let frame = |ctx: &mut UiContext| {
+      let (mut cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      for (idx, name) in ["first", "second"].iter().enumerate() {
+        let x = idx as f32 * 120f32;
+        ctx.begin(
+          name,
+          RectangleF32::new(x, 0f32, 100f32, 100f32),
+          PanelFlags::WindowBorder.into(),
+        );
+        ctx.end();
+      }
+      ctx.convert(&mut cmds, &mut vertices, &mut indices);
+      let stats = ctx.convert_stats();
+      ctx.clear();
+      (stats, cmds.len(), vertices.len(), indices.len())
+    };
+
+    let (stats, cmds, vertices, indices) = frame(&mut ctx);
+    assert_eq!(
+      (stats.draw_commands, stats.vertices, stats.indices),
+      (cmds, vertices, indices)
+    );
+    assert!(stats.vertices > 0);
 
-        let (y, index) = {
-          // make this go out of scope because it's mut borrowed by
-          // layout_widget_space() below
-          let mut layout = win.layout.borrow_mut();
-          if layout.row.index >= layout.row.columns {
-            layout.at_y += layout.row.height;
-            layout.row.index = 0;
-          }
+    // the same with both windows taken from the cache
+    let regenerations = ctx.geometry_regenerations();
+    assert_eq!(frame(&mut ctx).0, stats);
+    assert_eq!(ctx.geometry_regenerations(), regenerations);
+    assert_eq!(ctx.convert_stats(), DrawListStats::default());
+  }
 
-          (layout.at_y, layout.row.index)
-        };
+  #[test]
+  fn test_convert_config_change_regenerates_geometry() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 100f32, 100f32);
+    let frame = |ctx: &mut UiContext| {
+      let (mut cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      ctx.begin("static", bounds, PanelFlags::WindowBorder.into());
+      ctx.end();
+      ctx.convert(&mut cmds, &mut vertices, &mut indices);
+      ctx.clear();
+    };
 
-        let bounds = self.layout_widget_space(true);
-        let mut layout = win.layout.borrow_mut();
-        let bounds = RectangleF32 {
-          x: if layout.row.index == 0 {
-            bounds.x - layout.row.item_offset
-          } else {
-            bounds.x
-          },
-          ..bounds
-        };
-        layout.at_y = y;
-        layout.row.index = index;
+    frame(&mut ctx);
+    frame(&mut ctx);
+    assert_eq!(ctx.geometry_regenerations(), 1);
 
-        bounds
-      },
-    )
+    ctx
+      .set_convert_config(ConvertConfig {
+        snap_to_pixel: true,
+        ..test_config()
+      })
+      .unwrap();
+    frame(&mut ctx);
+    frame(&mut ctx);
+    assert_eq!(ctx.geometry_regenerations(), 2);
   }
 
-  fn widget_bounds(&self) -> RectangleF32 {
-    debug_assert!(self.current_win.borrow().is_some());
-    self.layout_peek()
+  #[test]
+  fn test_window_alpha_scales_vertex_alpha() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 100f32, 100f32);
+    let (mut cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+
+    ctx.begin("fading", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+    ctx.convert(&mut cmds, &mut vertices, &mut indices);
+    ctx.clear();
+
+    let (mut cmds2, mut vertices2, mut indices2) = (vec![], vec![], vec![]);
+    ctx.window_set_alpha("fading", 0.5f32);
+    ctx.begin("fading", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+    ctx.convert(&mut cmds2, &mut vertices2, &mut indices2);
+
+    assert!(!vertices.is_empty());
+    assert_eq!(vertices.len(), vertices2.len());
+    vertices
+      .iter()
+      .zip(vertices2.iter())
+      .for_each(|(opaque, faded)| {
+        assert_eq!(faded.color.a, opaque.color.a * 0.5f32);
+      });
   }
 
-  fn widget_position(&self) -> Vec2F32 {
-    let bounds = self.widget_bounds();
-    Vec2F32::new(bounds.x, bounds.y)
+  #[test]
+  fn test_custom_widget_reports_hover() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
+    let custom = RectangleF32::new(10f32, 10f32, 50f32, 20f32);
+
+    ctx.begin("canvas", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_space_begin(LayoutFormat::Static, 100f32, 1);
+    let (state, screen_rect) = ctx.custom_widget(custom);
+    ctx.layout_space_end();
+    ctx.end();
+    ctx.clear();
+    assert!(!state.contains(WidgetStates::Hover));
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(
+      (screen_rect.x + screen_rect.w * 0.5f32) as i32,
+      (screen_rect.y + screen_rect.h * 0.5f32) as i32,
+    );
+    ctx.input_mut().end();
+
+    ctx.begin("canvas", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_space_begin(LayoutFormat::Static, 100f32, 1);
+    let (state, _) = ctx.custom_widget(custom);
+    ctx.layout_space_end();
+    ctx.end();
+
+    assert!(state.contains(WidgetStates::Hover));
   }
 
-  fn widget_size(&self) -> Vec2F32 {
-    let bounds = self.widget_bounds();
-    Vec2F32::new(bounds.x, bounds.y)
+  #[test]
+  fn test_label_widget_row() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 200f32);
+
+    ctx.begin("props", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_label_widget(80f32, 20f32);
+    let (_, label) = ctx.widget();
+    let (_, widget) = ctx.widget();
+    ctx.end();
+
+    let spacing = ctx.style.window.spacing.x;
+    assert_eq!(label.w, 80f32);
+    assert_eq!(widget.x, label.x + label.w + spacing);
+    // the widget cell takes the rest of the row
+    assert!(widget.w > label.w);
   }
 
-  fn widget_width(&self) -> f32 {
-    let bounds = self.widget_bounds();
-    bounds.w
+  #[test]
+  fn test_dynamic_template_column_after_another_template_row() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 200f32);
+
+    ctx.begin("props", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_label_widget(80f32, 20f32);
+    let (_, first_label) = ctx.widget();
+    let (_, first) = ctx.widget();
+    // the slot still holds the width the first row resolved to
+    ctx.layout_row_label_widget(120f32, 20f32);
+    let (_, second_label) = ctx.widget();
+    let (_, second) = ctx.widget();
+    ctx.end();
+
+    // both widget cells end at the right edge of the row
+    assert_eq!(second_label.w, 120f32);
+    assert_eq!(second.x + second.w, first.x + first.w);
+    assert_eq!(first_label.x, second_label.x);
   }
 
-  fn widget_height(&self) -> f32 {
-    let bounds = self.widget_bounds();
-    bounds.h
+  #[test]
+  fn test_spinner_phase_advances() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
+    let spinner_phase = |ctx: &UiContext| {
+      ctx
+        .window_find("busy")
+        .map(|win| win.borrow().spinners.values().cloned().collect::<Vec<_>>())
+        .unwrap()
+    };
+
+    ctx.delta_time_sec = 0.1f32;
+    ctx.begin("busy", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_dynamic(30f32, 1);
+    ctx.spinner(10f32);
+    ctx.end();
+    ctx.clear();
+    let first = spinner_phase(&ctx);
+
+    ctx.begin("busy", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_dynamic(30f32, 1);
+    ctx.spinner(10f32);
+    ctx.end();
+    let second = spinner_phase(&ctx);
+
+    assert_eq!(first.len(), 1);
+    assert_eq!(second.len(), 1);
+    assert!(first[0] > 0f32);
+    assert!(second[0] > first[0]);
   }
 
-  fn widget_is_hovered(&self) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
-    self.current_win.borrow().as_ref().map_or(false, |winptr| {
-      let clip = winptr.borrow().layout.borrow().clip;
-      let clip = RectangleF32::new(
-        (clip.x as i32) as f32,
-        (clip.y as i32) as f32,
-        (clip.w as i32) as f32,
-        (clip.h as i32) as f32,
-      );
+  #[test]
+  fn test_measure_pass_keeps_overlay_and_spinners() {
+    let mut ctx = test_context();
+    ctx.set_debug_draw(true);
+    ctx.delta_time_sec = 0.1f32;
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
+    let ui = |ctx: &mut UiContext| {
+      ctx.begin("busy", bounds, PanelFlags::WindowBorder.into());
+      ctx.layout_row_dynamic(30f32, 1);
+      ctx.spinner(10f32);
+      ctx.end();
+    };
+    let spinner_phase = |ctx: &UiContext| {
+      ctx
+        .window_find("busy")
+        .map(|win| win.borrow().spinners.values().cloned().collect::<Vec<_>>())
+        .unwrap_or_default()
+    };
 
-      let bounds = self.layout_peek();
+    ui(&mut ctx);
+    ctx.clear();
+    let phase = spinner_phase(&ctx);
+    assert_eq!(phase.len(), 1);
 
-      if !clip.intersect(&bounds) {
-        false
-      } else {
-        self.input.borrow().is_mouse_hovering_rect(&bounds)
-      }
-    })
+    ctx.measure(ui);
+    assert_eq!(spinner_phase(&ctx), phase);
+    assert!(ctx.overlay.borrow().is_empty());
+
+    // the real pass turns the spinner by a single step
+    ui(&mut ctx);
+    assert_eq!(spinner_phase(&ctx), vec![2f32 * phase[0]]);
   }
 
-  fn widget_is_mouse_clicked(&self, btn: MouseButtonId) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
-    self.current_win.borrow().as_ref().map_or(false, |winptr| {
-      let clip = winptr.borrow().layout.borrow().clip;
-      let clip = RectangleF32::new(
-        (clip.x as i32) as f32,
-        (clip.y as i32) as f32,
-        (clip.w as i32) as f32,
-        (clip.h as i32) as f32,
-      );
+  #[test]
+  fn test_debug_draw_outlines_widgets() {
+    let mut ctx = test_context();
+    ctx.set_debug_draw(true);
 
-      let bounds = self.layout_peek();
+    ctx.begin(
+      "debug",
+      RectangleF32::new(0f32, 0f32, 200f32, 200f32),
+      PanelFlags::WindowBorder.into(),
+    );
+    ctx.layout_row_dynamic(30f32, 1);
+    ctx.button_text("ok");
+    ctx.end();
 
-      if !clip.intersect(&bounds) {
-        false
-      } else {
-        self.input.borrow().mouse_clicked(btn, &bounds)
-      }
-    })
+    let outlines = ctx
+      .overlay_mut()
+      .commands()
+      .iter()
+      .filter(|cmd| matches!(cmd, Command::Rect(_)))
+      .count();
+
+    // clip rectangle of the window and the button
+    assert!(outlines >= 2);
   }
 
-  fn widget_has_mouse_click_down(
-    &self,
-    btn: MouseButtonId,
-    down: bool,
-  ) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
-    self.current_win.borrow().as_ref().map_or(false, |winptr| {
-      let clip = winptr.borrow().layout.borrow().clip;
-      let clip = RectangleF32::new(
-        (clip.x as i32) as f32,
-        (clip.y as i32) as f32,
-        (clip.w as i32) as f32,
-        (clip.h as i32) as f32,
-      );
+  #[test]
+  fn test_animate_converges() {
+    let mut ctx = test_context();
+    ctx.delta_time_sec = 0.1f32;
+    let id = murmur_hash64a(b"highlight", 64);
+
+    assert_eq!(ctx.animate(id, 0f32, 2f32), 0f32);
+    let steps = (0 .. 4)
+      .map(|_| ctx.animate(id, 1f32, 2f32))
+      .collect::<Vec<_>>();
+
+    assert!(steps.windows(2).all(|w| w[1] > w[0]));
+    assert!((steps[3] - 0.8f32).abs() < 1.0e-5f32);
+    (0 .. 10).for_each(|_| {
+      ctx.animate(id, 1f32, 2f32);
+    });
+    assert_eq!(ctx.animate(id, 1f32, 2f32), 1f32);
+  }
 
-      let bounds = self.layout_peek();
+  #[test]
+  fn test_combo_callback_renders_visible_items() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 400f32);
+    let size = Vec2F32::new(120f32, 200f32);
+    let rendered = RefCell::new(Vec::new());
+    let frame = |ctx: &mut UiContext| {
+      rendered.borrow_mut().clear();
+      ctx.begin("picker", bounds, PanelFlags::WindowBorder.into());
+      let selected = ctx.combo_callback(5, 3, 20f32, size, |ctx, idx| {
+        ctx.widget();
+        rendered.borrow_mut().push(idx);
+      });
+      ctx.end();
+      ctx.clear();
+      selected
+    };
 
-      if !clip.intersect(&bounds) {
-        false
-      } else {
-        self
-          .input
-          .borrow()
-          .has_mouse_click_down_in_rect(btn, &bounds, down)
-      }
-    })
+    // the combo header occupies the first row of the window
+    ctx.begin("picker", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_static(20f32, size.x as i32, 1);
+    let header = ctx.layout_widget_bounds();
+    ctx.end();
+    ctx.clear();
+
+    // the header shows the selected item
+    let (x, y) = ((header.x + 10f32) as i32, (header.y + 10f32) as i32);
+    frame(&mut ctx);
+    assert_eq!(*rendered.borrow(), vec![3]);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, false);
+    ctx.input_mut().end();
+    assert_eq!(frame(&mut ctx), 3);
+    assert_eq!(*rendered.borrow(), vec![3, 0, 1, 2, 3, 4]);
+
+    // stays open on the following frames
+    ctx.input_mut().begin();
+    ctx.input_mut().end();
+    frame(&mut ctx);
+    assert_eq!(*rendered.borrow(), vec![3, 0, 1, 2, 3, 4]);
   }
 
-  fn widget(&self) -> (WidgetLayoutStates, RectangleF32) {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_close_all_popups() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 400f32);
+    let size = Vec2F32::new(120f32, 200f32);
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin("picker", bounds, PanelFlags::WindowBorder.into());
+      ctx.combo_callback(5, 0, 20f32, size, |ctx, _| {
+        ctx.widget();
+      });
+      ctx.end();
+      ctx.clear();
+    };
+    let open_combo = |ctx: &mut UiContext| {
+      let (x, y) = ((bounds.x + 20f32) as i32, (bounds.y + 20f32) as i32);
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, true);
+      ctx.input_mut().end();
+      frame(ctx);
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+      frame(ctx);
+    };
 
-    self.current_win.borrow().as_ref().map_or(
-      (
-        WidgetLayoutStates::Invalid,
-        RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      ),
-      |winptr| {
-        // allocate space and check if the widget needs to be updated and drawn
-        let mut bounds = self.panel_alloc_space();
+    frame(&mut ctx);
+    assert!(!ctx.any_popup_open());
+    open_combo(&mut ctx);
+    assert!(ctx.any_popup_open());
+
+    ctx.close_all_popups();
+    assert!(!ctx.any_popup_open());
+    ctx.input_mut().begin();
+    ctx.input_mut().end();
+    frame(&mut ctx);
+    assert!(!ctx.any_popup_open());
+
+    // Escape closes it as well
+    open_combo(&mut ctx);
+    assert!(ctx.any_popup_open());
+    ctx.input_mut().begin();
+    ctx.input_mut().key(KeyId::KeyEscape, true);
+    ctx.input_mut().key(KeyId::KeyEscape, false);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+    assert!(!ctx.any_popup_open());
+  }
 
-        let win = winptr.borrow();
-        let layout = win.layout.borrow();
+  fn overlapping_windows_frame(ctx: &mut UiContext) {
+    let flags = PanelFlags::WindowBorder.into();
+    ctx.begin("back", RectangleF32::new(0f32, 0f32, 200f32, 200f32), flags);
+    ctx.end();
+    ctx.begin(
+      "front",
+      RectangleF32::new(100f32, 100f32, 200f32, 200f32),
+      flags,
+    );
+    ctx.end();
+    ctx.clear();
+  }
 
-        //  if one of these triggers you forgot to add an `if` condition around
-        // either a window, group, popup, combobox or contextual menu
-        // `begin` and `end` block. Example:
-        // if (nk_begin(...) {...} nk_end(...); or
-        // if (nk_group_begin(...) { nk_group_end(...);}
-        debug_assert!(!(layout.flags.contains(PanelFlags::WindowMinimized)));
-        debug_assert!(!(layout.flags.contains(PanelFlags::WindowHidden)));
-        debug_assert!(!(layout.flags.contains(PanelFlags::WindowClosed)));
+  #[test]
+  fn test_widget_entered_fires_once() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
+    let frame = |ctx: &mut UiContext, x: i32, y: i32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx.input_mut().end();
+      ctx.begin("sounds", bounds, PanelFlags::WindowBorder.into());
+      ctx.layout_row_static(30f32, 100, 1);
+      let button = ctx.layout_widget_bounds();
+      ctx.button_text("play");
+      let edges = (ctx.widget_entered(), ctx.widget_left());
+      ctx.end();
+      ctx.clear();
+      (button, edges)
+    };
 
-        // need to convert to int here to remove floating point errors
-        bounds.x = (bounds.x as i32) as f32;
-        bounds.y = (bounds.y as i32) as f32;
-        bounds.w = (bounds.w as i32) as f32;
-        bounds.h = (bounds.h as i32) as f32;
+    let (button, edges) = frame(&mut ctx, 190, 190);
+    assert_eq!(edges, (false, false));
 
-        let c = RectangleF32::new(
-          (layout.clip.x as i32) as f32,
-          (layout.clip.y as i32) as f32,
-          (layout.clip.w as i32) as f32,
-          (layout.clip.h as i32) as f32,
-        );
+    let (x, y) = ((button.x + 10f32) as i32, (button.y + 10f32) as i32);
+    assert_eq!(frame(&mut ctx, x, y).1, (true, false));
+    assert_eq!(frame(&mut ctx, x + 5, y).1, (false, false));
+    assert_eq!(frame(&mut ctx, 190, 190).1, (false, true));
+    assert_eq!(frame(&mut ctx, 190, 190).1, (false, false));
+  }
+  #[test]
+  fn test_widget_scrolled_under_mouse_is_entered() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 100f32);
+    let frame = |ctx: &mut UiContext, x: i32, y: i32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx.input_mut().end();
+      ctx.begin("sounds", bounds, PanelFlags::WindowBorder.into());
+      let rows = (0 .. 10)
+        .map(|_| {
+          ctx.layout_row_static(30f32, 100, 1);
+          let (_, bounds) = ctx.widget();
+          (bounds, ctx.widget_entered())
+        })
+        .collect::<Vec<_>>();
+      ctx.end();
+      ctx.clear();
+      rows
+    };
 
-        if !c.intersect(&bounds) {
-          return (WidgetLayoutStates::Invalid, bounds);
-        }
+    let rows = frame(&mut ctx, 190, 90);
+    let (first, second) = (rows[0].0, rows[1].0);
+    let (x, y) = ((first.x + 10f32) as i32, (first.y + 10f32) as i32);
+    assert!(frame(&mut ctx, x, y)[0].1);
+
+    // the second widget moves to where the first one was
+    let pitch = second.y - first.y;
+    ctx.window_set_scroll("sounds", Vec2F32::new(0f32, pitch));
+    let rows = frame(&mut ctx, x, y);
+    assert_eq!(rows[1].0.y, first.y);
+    assert!(rows[1].1);
+  }
 
-        let v = RectangleF32::union(&bounds, &c);
-        if !v.contains_point(
-          self.input.borrow().mouse.pos.x,
-          self.input.borrow().mouse.pos.y,
-        ) {
-          return (WidgetLayoutStates::Rom, bounds);
-        }
+  #[test]
+  fn test_widget_under_other_window_is_not_entered() {
+    let mut ctx = test_context();
+    let frame = |ctx: &mut UiContext, x: i32, y: i32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx.input_mut().end();
+      ctx.begin(
+        "below",
+        RectangleF32::new(0f32, 0f32, 200f32, 200f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.layout_row_static(30f32, 100, 1);
+      let button = ctx.layout_widget_bounds();
+      ctx.button_text("play");
+      let entered = ctx.widget_entered();
+      ctx.end();
+      ctx.begin(
+        "above",
+        RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.end();
+      ctx.clear();
+      (button, entered)
+    };
 
-        (WidgetLayoutStates::Valid, bounds)
-      },
-    )
+    let (button, _) = frame(&mut ctx, 190, 190);
+    let (x, y) = ((button.x + 10f32) as i32, (button.y + 10f32) as i32);
+    assert!(!frame(&mut ctx, x, y).1);
   }
 
-  fn widget_fitting(
-    &self,
-    item_padding: Vec2F32,
-  ) -> (WidgetLayoutStates, RectangleF32) {
-    debug_assert!(self.current_win.borrow().is_some());
 
-    self.current_win.borrow().as_ref().map_or(
-      (
-        WidgetLayoutStates::Invalid,
-        RectangleF32::new(0f32, 0f32, 0f32, 0f32),
-      ),
-      |winptr| {
-        // update the bounds to have no padding
-        let (state, mut bounds) = self.widget();
+  #[test]
+  fn test_window_identity_does_not_borrow() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 100f32, 100f32);
+    ctx.begin("first", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+    ctx.begin("second", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+
+    let first = ctx.window_find("first").unwrap();
+    let second = ctx.window_find("second").unwrap();
+    // both windows stay borrowed while they are compared
+    let _first_guard = first.borrow_mut();
+    let _second_guard = second.borrow_mut();
+
+    assert!(!same_window(&first, &second));
+    assert!(same_window(&first, &Rc::clone(&first)));
+    assert!(ctx.is_last_window(&second));
+    assert!(!ctx.is_last_window(&first));
+  }
 
-        let win = winptr.borrow();
-        let layout = win.layout.borrow();
-        let panel_padding = self.style.get_panel_padding(layout.typ);
-        if layout.row.index == 1 {
-          bounds.w += panel_padding.x;
-          bounds.x -= panel_padding.x;
-        } else {
-          bounds.x -= item_padding.x;
-        }
+  #[test]
+  fn test_dropping_frame_clears_context() {
+    let mut ctx = test_context();
+    let seq = ctx.seq;
 
-        if layout.row.index == layout.row.columns {
-          bounds.w += panel_padding.x;
-        } else {
-          bounds.w += item_padding.x;
-        }
+    {
+      let mut frame = ctx.begin_frame(0.25f32);
+      frame.begin(
+        "frame",
+        RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      frame.end();
+      assert_eq!(frame.seq, seq);
+    }
 
-        (state, bounds)
-      },
-    )
+    assert_eq!(ctx.seq, seq + 1);
+    assert_eq!(ctx.delta_time_sec, 0.25f32);
   }
 
-  fn spacing(&self, cols: i32) {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_panic_inside_frame_is_not_masked() {
+    let mut ctx = test_context();
 
-    self.current_win.borrow().as_ref().and_then(|winptr| {
-      // spacing over row boundaries
-      let win = winptr.borrow();
-      let (index, rows) = {
-        let layout = win.layout.borrow();
-        (
-          (layout.row.index + cols) % layout.row.columns,
-          (layout.row.index + cols) / layout.row.columns,
-        )
-      };
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      let mut frame = ctx.begin_frame(0.25f32);
+      frame.begin(
+        "frame",
+        RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      panic!("widget failed");
+    }));
 
-      let cols = if rows > 0 {
-        (0 .. rows).for_each(|_| self.panel_alloc_row(&win));
-        index
-      } else {
-        cols
-      };
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"widget failed"));
+  }
 
-      // non table laout need to allocate space
-      let layout_type = win.layout.borrow().row.typ;
-      if layout_type != PanelRowLayoutType::DynamicFixed
-        && layout_type != PanelRowLayoutType::StaticFixed
-      {
-        (0 .. cols).for_each(|_| {
-          self.panel_alloc_space();
-        });
-      } else {
-        win.layout.borrow_mut().row.index = index;
-      }
+  #[cfg(debug_assertions)]
+  #[test]
+  fn test_missing_end_disabled_is_reported() {
+    let mut ctx = test_context();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      ctx.begin(
+        "frame",
+        RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.begin_disabled();
+      ctx.end();
+      ctx.clear();
+    }));
+
+    let payload = result.unwrap_err();
+    assert!(payload
+      .downcast_ref::<&str>()
+      .map_or(false, |msg| msg.contains("end_disabled()")));
+    // the next frame starts with its widgets enabled
+    assert!(!ctx.input_blocked());
+  }
+
+  #[cfg(debug_assertions)]
+  #[test]
+  fn test_missing_pop_id_is_reported() {
+    let mut ctx = test_context();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      ctx.begin(
+        "frame",
+        RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.push_id(1);
+      ctx.end();
+      ctx.clear();
+    }));
+
+    let payload = result.unwrap_err();
+    assert!(payload
+      .downcast_ref::<&str>()
+      .map_or(false, |msg| msg.contains("pop_id()")));
+    // the next frame starts with an empty id stack
+    assert!(ctx.id_stack.is_empty());
+  }
 
-      Some(())
-    });
+  #[test]
+  fn test_frame_stats_overlay_keeps_the_frame_delta() {
+    let mut ctx = test_context();
+    ctx.delta_time_sec = 0.25f32;
+
+    ctx.frame_stats_overlay(0.5f32);
+
+    // only begin_frame() and the caller set the time widgets animate with
+    assert_eq!(ctx.delta_time_sec, 0.25f32);
+    assert_eq!(ctx.frame_stats.iter().collect::<Vec<_>>(), vec![0.5f32]);
   }
 
-  /// text widgets
+  #[test]
+  fn test_held_key_repeats_across_frames() {
+    let mut ctx = test_context();
+    ctx.input_mut().keyboard.repeat_delay = 0.5f32;
+    ctx.input_mut().keyboard.repeat_rate = 4f32;
+    ctx.delta_time_sec = 0.125f32;
+
+    let frame = |ctx: &mut UiContext, key: Option<bool>| {
+      ctx.input_mut().begin();
+      key.map(|down| ctx.input_mut().key(KeyId::KeyDown, down));
+      ctx.input_mut().end();
+      ctx.begin(
+        "keys",
+        RectangleF32::new(0f32, 0f32, 100f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      let repeated = ctx.input().is_key_repeated(KeyId::KeyDown);
+      ctx.end();
+      ctx.clear();
+      repeated
+    };
 
-  pub fn text(&mut self, s: &str, alignment: BitFlags<TextAlign>) {
-    self.text_colored(s, alignment, self.style.text.color);
+    assert!(frame(&mut ctx, Some(true)));
+    // held for 1.25 seconds: once after the delay, then every 0.25 seconds,
+    // each one showing up in the frame after the one in which it elapsed
+    let fired = (0 .. 11).filter(|_| frame(&mut ctx, None)).count();
+    assert_eq!(fired, 4);
+
+    assert!(!frame(&mut ctx, Some(false)));
+    assert!(!frame(&mut ctx, None));
   }
 
-  pub fn text_colored(
-    &mut self,
-    txt: &str,
-    alignment: BitFlags<TextAlign>,
-    color: RGBAColor,
-  ) {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_measure_returns_content_size() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 400f32);
+    let flags = PanelFlags::WindowBorder | PanelFlags::WindowNoScrollbar;
+    let ui = |ctx: &mut UiContext, bounds: RectangleF32| {
+      ctx.begin("fitted", bounds, flags);
+      ctx.layout_row_dynamic(30f32, 1);
+      ctx.button_text("first");
+      ctx.layout_row_dynamic(20f32, 1);
+      ctx.button_text("second");
+      ctx.end();
+    };
 
-    self.current_win.borrow().as_ref().map(|curr_win| {
-      let bounds = self.panel_alloc_space();
+    let size = ctx.measure(|ctx| ui(ctx, bounds));
+    let padding = ctx.style.window.padding;
+    let spacing = ctx.style.window.spacing;
+    let border = ctx.style.get_panel_border(PanelType::Window.into(), flags);
+    assert_eq!(
+      size.y,
+      30f32 + 20f32 + 2f32 * spacing.y + 2f32 * padding.y + 2f32 * border
+    );
+    assert!(size.x <= bounds.w);
+    // measuring draws nothing
+    assert_eq!(ctx.commands_iter().count(), 0);
 
-      use crate::hmi::text::text_colored;
-      text_colored(
-        Rc::clone(curr_win),
-        &self.style,
-        bounds,
-        txt,
-        alignment,
-        color,
-      );
-    });
+    ui(
+      &mut ctx,
+      RectangleF32 {
+        h: size.y,
+        ..bounds
+      },
+    );
+    assert!(ctx.commands_iter().count() > 0);
   }
 
-  pub fn text_wrap(&mut self, s: &str) {
-    self.text_wrap_colored(s, self.style.text.color);
+  #[test]
+  fn test_dragging_horizontal_scrollbar_cursor() {
+    let mut ctx = test_context();
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "wide",
+        RectangleF32::new(0f32, 0f32, 200f32, 150f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.layout_row_static(20f32, 600, 1);
+      ctx.widget();
+      let offsets = ctx
+        .current_win
+        .borrow()
+        .as_ref()
+        .map(|win| *win.borrow().layout.borrow().offsets.borrow())
+        .unwrap();
+      let bounds = ctx
+        .window_find("wide")
+        .unwrap()
+        .borrow()
+        .layout
+        .borrow()
+        .bounds;
+      ctx.end();
+      ctx.clear();
+      (offsets, bounds)
+    };
+
+    // the content extent is known after the first frame
+    frame(&mut ctx);
+    let (offsets, bounds) = frame(&mut ctx);
+    assert!(offsets.content.x > offsets.view.x);
+    assert_eq!(offsets.scrollbar.x, 0);
+
+    // grab the cursor at the left end of the track below the content
+    let track_h = ctx.style.window.scrollbar_size.y;
+    let cursor_w = (bounds.w * offsets.view.x / offsets.content.x)
+      .max(track_h)
+      .min(bounds.w);
+    let x = (bounds.x + cursor_w * 0.5f32) as i32;
+    let y = (bounds.y + bounds.h + track_h * 0.5f32) as i32;
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x + 40, y);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    let (dragged, _) = frame(&mut ctx);
+    let expected = 40f32 * offsets.max_offset().x / (bounds.w - cursor_w);
+    assert!((dragged.scrollbar.x as f32 - expected).abs() <= 1f32);
   }
 
-  pub fn text_wrap_colored(&mut self, txt: &str, color: RGBAColor) {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_dragging_vertical_scrollbar_cursor() {
+    let mut ctx = test_context();
+    ctx.style.scrollv.smooth_scroll = true;
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "tall",
+        RectangleF32::new(0f32, 0f32, 200f32, 150f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      (0 .. 20).for_each(|_| {
+        ctx.layout_row_dynamic(20f32, 1);
+        ctx.widget();
+      });
+      let offsets = ctx
+        .current_win
+        .borrow()
+        .as_ref()
+        .map(|win| *win.borrow().layout.borrow().offsets.borrow())
+        .unwrap();
+      let bounds = ctx
+        .window_find("tall")
+        .unwrap()
+        .borrow()
+        .layout
+        .borrow()
+        .bounds;
+      ctx.end();
+      ctx.clear();
+      (offsets, bounds)
+    };
 
-    self.current_win.borrow().as_ref().map(|curr_win| {
-      let bounds = self.panel_alloc_space();
+    frame(&mut ctx);
+    let (offsets, bounds) = frame(&mut ctx);
+    assert!(offsets.content.y > offsets.view.y);
+    assert_eq!(offsets.scrollbar.y, 0);
+
+    // grab the cursor at the top of the track right of the content
+    let track_w = ctx.style.window.scrollbar_size.x;
+    let cursor_h = (bounds.h * offsets.view.y / offsets.content.y)
+      .max(track_w)
+      .min(bounds.h);
+    let x = (bounds.x + bounds.w + track_w * 0.5f32) as i32;
+    let y = (bounds.y + cursor_h * 0.5f32) as i32;
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y + 30);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    // smooth scrolling doesn't pull the dragged offset back
+    let (dragged, _) = frame(&mut ctx);
+    let expected = 30f32 * offsets.max_offset().y / (bounds.h - cursor_h);
+    assert!((dragged.scrollbar.y as f32 - expected).abs() <= 1f32);
+    assert_eq!(dragged.target.y, dragged.scrollbar.y as f32);
+  }
 
-      use crate::hmi::text::text_wrap_colored;
-      text_wrap_colored(Rc::clone(curr_win), &self.style, bounds, txt, color);
-    });
+  fn press_at(ctx: &mut UiContext, x: i32, y: i32) {
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx.input_mut().end();
   }
 
-  pub fn label(&mut self, s: &str, align: BitFlags<TextAlign>) {
-    self.text(s, align);
+  fn last_window_name(ctx: &UiContext) -> String {
+    ctx.windows.borrow().last().map_or(String::new(), |win| {
+      win.borrow().id.borrow().name_str.clone()
+    })
   }
 
-  pub fn label_colored(
-    &mut self,
-    s: &str,
-    alignment: BitFlags<TextAlign>,
-    color: RGBAColor,
-  ) {
-    self.text_colored(s, alignment, color);
+  #[test]
+  fn test_click_raises_partially_covered_window() {
+    let mut ctx = test_context();
+    overlapping_windows_frame(&mut ctx);
+    assert!(ctx.window_is_active("front"));
+    assert_eq!(last_window_name(&ctx), "front");
+
+    // the uncovered part of the back window
+    press_at(&mut ctx, 50, 50);
+    overlapping_windows_frame(&mut ctx);
+
+    assert!(ctx.window_is_active("back"));
+    assert!(!ctx.window_is_active("front"));
+    assert_eq!(last_window_name(&ctx), "back");
+    assert!(ctx.window_find("front").map_or(false, |win| win
+      .borrow()
+      .flags
+      .contains(PanelFlags::WindowRom)));
   }
 
-  pub fn label_wrap(&mut self, s: &str) {
-    self.text_wrap(s);
+  #[test]
+  fn test_click_on_covered_part_keeps_top_window() {
+    let mut ctx = test_context();
+    overlapping_windows_frame(&mut ctx);
+
+    // inside both windows, the front one is on top there
+    press_at(&mut ctx, 150, 150);
+    overlapping_windows_frame(&mut ctx);
+
+    assert!(ctx.window_is_active("front"));
+    assert_eq!(last_window_name(&ctx), "front");
   }
 
-  pub fn label_colored_wrap(&mut self, s: &str, color: RGBAColor) {
-    self.text_wrap_colored(s, color);
+  #[test]
+  fn test_click_does_not_raise_background_window() {
+    let mut ctx = test_context();
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "desktop",
+        RectangleF32::new(0f32, 0f32, 200f32, 200f32),
+        PanelFlags::WindowBorder | PanelFlags::WindowBackground,
+      );
+      ctx.end();
+      ctx.begin(
+        "tool",
+        RectangleF32::new(100f32, 100f32, 200f32, 200f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.end();
+      ctx.clear();
+    };
+    frame(&mut ctx);
+
+    press_at(&mut ctx, 50, 50);
+    frame(&mut ctx);
+
+    assert_eq!(last_window_name(&ctx), "tool");
   }
 
-  pub fn image(&mut self, img: Image) {
-    self.image_color(img, RGBAColor::new(255, 255, 255));
+  #[test]
+  fn test_layout_row_push_sets_item_width() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
+
+    ctx.begin("rows", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_begin(LayoutFormat::Dynamic, 20f32, 2);
+    ctx.layout_row_push(0.5f32);
+    let item_width = ctx
+      .window_find("rows")
+      .map_or(0f32, |win| win.borrow().layout.borrow().row.item_width);
+    ctx.widget();
+    ctx.layout_row_end();
+    ctx.end();
+
+    assert_eq!(item_width, 0.5f32);
   }
 
-  pub fn image_color(&mut self, img: Image, color: RGBAColor) {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_widget_size_matches_width_and_height() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(50f32, 50f32, 300f32, 200f32);
+
+    ctx.begin("sizes", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_static(30f32, 80, 1);
+    let position = ctx.widget_position();
+    let size = ctx.widget_size();
+    let (width, height) = (ctx.widget_width(), ctx.widget_height());
+    ctx.end();
+
+    assert_eq!((size.x, size.y), (width, height));
+    assert_eq!(size.x, 80f32);
+    assert_ne!((size.x, size.y), (position.x, position.y));
+  }
 
-    self.current_win.borrow().as_ref().map(|curr_win| {
-      let (widget_states, bounds) = self.widget();
-      if widget_states == WidgetLayoutStates::Invalid {
-        return;
-      }
+  #[test]
+  fn test_accessible_button_is_recorded() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
 
-      curr_win
-        .borrow()
-        .buffer
-        .borrow_mut()
-        .draw_image(bounds, img, color);
-    });
-  }
+    ctx.begin("dialog", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_dynamic(30f32, 1);
+    ctx.widget_set_accessible("Save document", WidgetRole::Button);
+    ctx.button_text("Save");
+    ctx.button_text("Cancel");
+    ctx.end();
 
-  pub fn value<T: std::fmt::Display>(&mut self, prefix: &str, val: T) {
-    self.label(&format!("{} : {}", prefix, val), TextAlign::left());
+    {
+      let widgets = ctx.accessible_widgets();
+      assert_eq!(widgets.len(), 1);
+      assert_eq!(widgets[0].name, "Save document");
+      assert_eq!(widgets[0].role, WidgetRole::Button);
+      assert!(widgets[0].bounds.w > 0f32);
+    }
+
+    ctx.clear();
+    assert!(ctx.accessible_widgets().is_empty());
   }
 
-  /// buttons
-  pub fn button_set_behaviour(&mut self, behavior: ButtonBehaviour) {
-    self.button_behviour = behavior;
+  #[test]
+  fn test_measured_window_is_dragged_once() {
+    let mut ctx = test_context();
+    let flags = PanelFlags::WindowBorder
+      | PanelFlags::WindowMovable
+      | PanelFlags::WindowTitle;
+    let ui = |ctx: &mut UiContext| {
+      ctx.begin(
+        "mover",
+        RectangleF32::new(100f32, 100f32, 200f32, 150f32),
+        flags,
+      );
+      ctx.layout_row_dynamic(20f32, 1);
+      ctx.button_text("content");
+      ctx.end();
+    };
+    let frame = |ctx: &mut UiContext| {
+      ctx.measure(ui);
+      ui(ctx);
+      ctx.clear();
+    };
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(150, 105);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, 150, 105, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(180, 105);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    let bounds = ctx
+      .window_find("mover")
+      .map(|win| *win.borrow().bounds.borrow())
+      .unwrap();
+    assert_eq!((bounds.x, bounds.y), (130f32, 100f32));
   }
 
-  pub fn button_push_behavior(&mut self, _behavior: ButtonBehaviour) -> bool {
-    // TODO: add support for this
-    false
+  #[test]
+  fn test_dragged_window_keeps_header_on_screen() {
+    let mut ctx = test_context();
+    ctx.set_display_size(Vec2F32::new(800f32, 600f32));
+    let flags = PanelFlags::WindowBorder
+      | PanelFlags::WindowMovable
+      | PanelFlags::WindowTitle;
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "mover",
+        RectangleF32::new(100f32, 100f32, 200f32, 150f32),
+        flags,
+      );
+      ctx.end();
+      ctx.clear();
+    };
+    frame(&mut ctx);
+
+    // grab the header ...
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(150, 105);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, 150, 105, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    // ... and throw the window far to the left
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(-1000, 105);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    let bounds = ctx
+      .window_find("mover")
+      .map(|win| *win.borrow().bounds.borrow())
+      .unwrap();
+    assert!(bounds.x < 0f32);
+    assert_eq!(bounds.x + bounds.w, UiContext::DEFAULT_DRAG_MARGIN);
+    assert_eq!(bounds.y, 100f32);
   }
 
-  pub fn button_pop_behaviour(&mut self, _behavior: ButtonBehaviour) -> bool {
-    // TODO: add support for this
-    false
+  #[test]
+  fn test_idle_scrollbar_fades_out() {
+    let mut ctx = test_context();
+    ctx.style.scrollv.auto_hide = true;
+    ctx.style.scrollv.fade_time = 0.5f32;
+    ctx.delta_time_sec = 0.1f32;
+
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "list",
+        RectangleF32::new(0f32, 0f32, 200f32, 150f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      (0 .. 20).for_each(|_| {
+        ctx.layout_row_dynamic(20f32, 1);
+        ctx.widget();
+      });
+      ctx.end();
+      ctx.clear();
+    };
+    let scrollbar_alpha = |ctx: &UiContext| {
+      let win = ctx.window_find("list").unwrap();
+      let id = UiContext::scrollbar_fade_id(&win.borrow());
+      ctx.animations.borrow().get(&id).cloned().unwrap_or(-1f32)
+    };
+
+    frame(&mut ctx);
+    assert!(scrollbar_alpha(&ctx) > 0.5f32);
+
+    (0 .. 10).for_each(|_| frame(&mut ctx));
+    assert!(scrollbar_alpha(&ctx) < 0.1f32);
+
+    // hover the scrollbar track
+    let bounds = ctx
+      .window_find("list")
+      .map(|win| win.borrow().layout.borrow().bounds)
+      .unwrap();
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(
+      (bounds.x + bounds.w + 2f32) as i32,
+      (bounds.y + bounds.h * 0.5f32) as i32,
+    );
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    assert_eq!(scrollbar_alpha(&ctx), 1f32);
   }
 
-  pub fn button_text_styled(&self, style: &StyleButton, title: &str) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_same_tree_labels_under_different_ids() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 300f32);
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin("trees", bounds, PanelFlags::WindowBorder.into());
+      let open = (0 .. 2)
+        .map(|i| {
+          ctx.push_id(i);
+          let open = ctx.tree_push("Enemies", CollapseStates::Minimized);
+          if open {
+            ctx.tree_pop();
+          }
+          ctx.pop_id();
+          open
+        })
+        .collect::<Vec<_>>();
+      ctx.end();
+      ctx.clear();
+      open
+    };
 
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(false, |curr_win| {
-        let (state, bounds) = self.widget();
-        if state == WidgetLayoutStates::Invalid {
-          return false;
-        }
+    assert_eq!(frame(&mut ctx), vec![false, false]);
+
+    // the first node is the first row of the window
+    ctx.begin("trees", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_dynamic(14f32 + 2f32 * ctx.style.tab.padding.y, 1);
+    let header = ctx.layout_widget_bounds();
+    ctx.end();
+    ctx.clear();
+
+    let (x, y) = ((header.x + 10f32) as i32, (header.y + 5f32) as i32);
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, false);
+    ctx.input_mut().end();
+    assert_eq!(frame(&mut ctx), vec![true, false]);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().end();
+    assert_eq!(frame(&mut ctx), vec![true, false]);
+  }
 
-        use crate::hmi::button::do_button_text;
+  #[test]
+  fn test_value_color_draws_text_and_swatch() {
+    use crate::hmi::text_engine::FontAtlas;
 
-        let input = self.input.borrow();
+    let mut ctx = test_context();
+    ctx.style.font = FontAtlas::monospace_for_tests(8f32);
+    let swatch_color = RGBAColor::new(255, 0, 0);
 
-        do_button_text(
-          &mut self.last_widget_state.borrow_mut(),
-          &mut curr_win.borrow().buffer_mut(),
-          bounds,
-          title,
-          style.text_alignment,
-          self.button_behviour,
-          style,
-          if state == WidgetLayoutStates::Rom
-            || curr_win
-              .borrow()
-              .layout
-              .borrow()
-              .flags
-              .intersects(PanelFlags::WindowRom)
-          {
-            None
-          } else {
-            Some(&*input)
-          },
-          self.style.font,
-        )
+    ctx.begin(
+      "values",
+      RectangleF32::new(0f32, 0f32, 300f32, 200f32),
+      PanelFlags::WindowBorder.into(),
+    );
+    ctx.layout_row_dynamic(20f32, 1);
+    ctx.value_color("tint", swatch_color);
+    ctx.begin_disabled();
+    ctx.value_color("disabled", swatch_color);
+    ctx.end_disabled();
+    ctx.end();
+
+    let win = ctx.window_find("values").unwrap();
+    let win = win.borrow();
+    let buffer = win.buffer_mut();
+    let cmds = buffer.commands();
+
+    assert!(cmds.iter().any(|cmd| match cmd {
+      Command::Text(txt) => txt.text.starts_with("tint : 255, 0, 0"),
+      _ => false,
+    }));
+    let swatches = cmds
+      .iter()
+      .filter_map(|cmd| match cmd {
+        Command::RectFilled(rect) if rect.w == rect.h => Some(rect.color),
+        _ => None,
       })
+      .collect::<Vec<_>>();
+    // the disabled swatch is grayed out like every other widget
+    assert_eq!(swatches.len(), 2);
+    assert_eq!(swatches[0], swatch_color);
+    assert_ne!(swatches[1], swatch_color);
   }
 
-  pub fn button_text(&self, title: &str) -> bool {
-    let style_btn = self.style.button;
-    self.button_text_styled(&style_btn, title)
+  #[test]
+  fn test_selection_group_click_selects_segment() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 100f32);
+    let labels = ["Move", "Rotate", "Scale"];
+    let mut selected = 0;
+    let mut frame = |ctx: &mut UiContext| {
+      ctx.begin("tools", bounds, PanelFlags::WindowBorder.into());
+      let changed = ctx.selection_group(&labels, &mut selected);
+      ctx.end();
+      ctx.clear();
+      (changed, selected)
+    };
+
+    // the segments split the first row of the window
+    ctx.begin("tools", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_dynamic(14f32 + 2f32 * ctx.style.button.padding.y, 3);
+    let third = (0 .. 3).map(|_| ctx.widget().1).last().unwrap();
+    ctx.end();
+    ctx.clear();
+
+    let x = (third.x + third.w * 0.5f32) as i32;
+    let y = (third.y + third.h * 0.5f32) as i32;
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx.input_mut().end();
+    assert_eq!(frame(&mut ctx), (false, 0));
+
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, false);
+    ctx.input_mut().end();
+    assert_eq!(frame(&mut ctx), (true, 2));
   }
 
-  pub fn button_label_styled(&self, style: &StyleButton, title: &str) -> bool {
-    self.button_text_styled(style, title)
+  #[test]
+  fn test_smooth_wheel_scrolling_eases_over_frames() {
+    let mut ctx = test_context();
+    ctx.style.scrollv.smooth_scroll = true;
+    ctx.delta_time_sec = 1f32 / 60f32;
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 150f32);
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin("list", bounds, PanelFlags::WindowBorder.into());
+      (0 .. 40).for_each(|_| {
+        ctx.layout_row_dynamic(20f32, 1);
+        ctx.widget();
+      });
+      ctx.end();
+      ctx.clear();
+      ctx.window_get_scroll("list").y
+    };
+    frame(&mut ctx);
+
+    // a single wheel tick towards the end of the content
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(100, 75);
+    ctx.input_mut().scroll(Vec2F32::new(0f32, -1f32));
+    ctx.input_mut().end();
+    let mut offsets = vec![frame(&mut ctx)];
+
+    ctx.input_mut().begin();
+    ctx.input_mut().end();
+    (0 .. 60).for_each(|_| offsets.push(frame(&mut ctx)));
+
+    let target = *offsets.last().unwrap();
+    assert!(target > 0f32);
+    assert!(offsets.windows(2).all(|w| w[1] >= w[0]));
+    let intermediate = offsets
+      .iter()
+      .filter(|&&offset| offset > 0f32 && offset < target)
+      .count();
+    assert!(intermediate >= 3);
   }
 
-  pub fn button_label(&self, title: &str) -> bool {
-    self.button_text(title)
+  #[test]
+  fn test_discrete_wheel_scrolling_jumps() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 150f32);
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin("list", bounds, PanelFlags::WindowBorder.into());
+      (0 .. 40).for_each(|_| {
+        ctx.layout_row_dynamic(20f32, 1);
+        ctx.widget();
+      });
+      ctx.end();
+      ctx.clear();
+      ctx.window_get_scroll("list").y
+    };
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(100, 75);
+    ctx.input_mut().scroll(Vec2F32::new(0f32, -1f32));
+    ctx.input_mut().end();
+    let jumped = frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().end();
+    assert!(jumped > 0f32);
+    assert_eq!(frame(&mut ctx), jumped);
   }
 
-  pub fn button_color(&self, color: RGBAColor) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_property_typed_value_commits_on_enter() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 100f32);
+    let mut value = 10f32;
+    let frame = |ctx: &mut UiContext, value: &mut f32| {
+      ctx.begin("props", bounds, PanelFlags::WindowBorder.into());
+      ctx.layout_row_dynamic(24f32, 1);
+      let prop = ctx.layout_widget_bounds();
+      ctx.property_float("speed", 0f32, value, 100f32, 1f32, 0.5f32);
+      ctx.end();
+      ctx.clear();
+      prop
+    };
 
-    let (state, bounds) = self.widget();
+    let prop = frame(&mut ctx, &mut value);
+    let x = (prop.x + prop.w * 0.5f32) as i32;
+    let y = (prop.y + prop.h * 0.5f32) as i32;
+
+    // clicking the value without dragging starts text entry
+    let click = |ctx: &mut UiContext, value: &mut f32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, true);
+      ctx.input_mut().end();
+      frame(ctx, value);
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+      frame(ctx, value);
+    };
+    let tap = |ctx: &mut UiContext, value: &mut f32, key: KeyId| {
+      ctx.input_mut().begin();
+      ctx.input_mut().key(key, true);
+      ctx.input_mut().key(key, false);
+      ctx.input_mut().end();
+      frame(ctx, value);
+    };
+    let type_text = |ctx: &mut UiContext, value: &mut f32, txt: &str| {
+      ctx.input_mut().begin();
+      txt.chars().for_each(|c| ctx.input_mut().glyph(c));
+      ctx.input_mut().end();
+      frame(ctx, value);
+    };
 
-    if state == WidgetLayoutStates::Invalid {
-      return false;
-    }
+    click(&mut ctx, &mut value);
+    // the text starts out as the current value
+    tap(&mut ctx, &mut value, KeyId::KeyBackspace);
+    tap(&mut ctx, &mut value, KeyId::KeyBackspace);
+    type_text(&mut ctx, &mut value, "42");
+    assert_eq!(value, 10f32);
+    tap(&mut ctx, &mut value, KeyId::KeyEnter);
+    assert_eq!(value, 42f32);
+
+    // escape leaves the value untouched
+    click(&mut ctx, &mut value);
+    type_text(&mut ctx, &mut value, "7");
+    tap(&mut ctx, &mut value, KeyId::KeyEscape);
+    assert_eq!(value, 42f32);
+  }
 
-    let input = self.input.borrow();
+  #[test]
+  fn test_layout_reserve_advances_cursor() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 200f32, 200f32);
+
+    // where the first row goes without reserving anything
+    ctx.begin("reserve", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_dynamic(30f32, 1);
+    let first_row = ctx.widget().1;
+    ctx.end();
+    ctx.clear();
+
+    ctx.begin("reserve", bounds, PanelFlags::WindowBorder.into());
+    let at_y = |ctx: &UiContext| {
+      ctx
+        .current_win
+        .borrow()
+        .as_ref()
+        .map(|winptr| winptr.borrow().layout.borrow().at_y)
+        .unwrap()
+    };
 
-    let style = StyleButton {
-      normal: StyleItem::Color(color),
-      hover: StyleItem::Color(color),
-      active: StyleItem::Color(color),
-      ..self.style.button
+    let before = at_y(&ctx);
+    ctx.layout_reserve(20f32);
+    assert_eq!(at_y(&ctx), before + 20f32);
+
+    // the next row starts below the reserved space
+    ctx.layout_row_dynamic_reserved(10f32, 30f32, 1);
+    let (_, row) = ctx.widget();
+    assert_eq!(row.y, first_row.y + 20f32);
+    assert_eq!(row.h, 30f32);
+
+    ctx.end();
+  }
+
+  #[test]
+  fn test_modal_window_blocks_other_windows() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "main",
+        RectangleF32::new(0f32, 0f32, 200f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.layout_row_dynamic(30f32, 1);
+      let button = ctx.layout_widget_bounds();
+      let clicked = ctx.button_text("save");
+      ctx.end();
+
+      ctx.begin(
+        "dialog",
+        RectangleF32::new(250f32, 0f32, 200f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.end();
+      ctx.clear();
+      (button, clicked)
+    };
+    let click = |ctx: &mut UiContext, x: i32, y: i32| {
+      press_at(ctx, x, y);
+      let (_, pressed) = frame(ctx);
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+      let (_, released) = frame(ctx);
+      pressed || released
     };
 
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(false, |curr_win| {
-        use crate::hmi::button::do_button;
-        let (res, _content) = do_button(
-          &mut self.last_widget_state.borrow_mut(),
-          &mut curr_win.borrow().buffer_mut(),
-          bounds,
-          &style,
-          if state == WidgetLayoutStates::Rom
-            || curr_win
-              .borrow()
-              .layout
-              .borrow()
-              .flags
-              .intersects(PanelFlags::WindowRom)
-          {
-            None
-          } else {
-            Some(&*input)
-          },
-          self.button_behviour,
-        );
+    let (button, _) = frame(&mut ctx);
+    let x = (button.x + button.w * 0.5f32) as i32;
+    let y = (button.y + button.h * 0.5f32) as i32;
+    assert!(click(&mut ctx, x, y));
 
-        use crate::hmi::button::draw_button;
-        draw_button(
-          &mut curr_win.borrow().buffer_mut(),
-          &bounds,
-          *self.last_widget_state.borrow(),
-          &style,
-        );
+    ctx.window_set_modal("dialog", true);
+    assert!(!click(&mut ctx, x, y));
+    assert_eq!(last_window_name(&ctx), "dialog");
+
+    ctx.window_set_modal("dialog", false);
+    assert!(click(&mut ctx, x, y));
+  }
+
+  #[test]
+  fn test_button_autowidth_hugs_label() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let mut ctx = test_context();
+    ctx.style.font = FontAtlas::monospace_for_tests(8f32);
+
+    ctx.begin(
+      "toolbar",
+      RectangleF32::new(0f32, 0f32, 300f32, 200f32),
+      PanelFlags::WindowBorder.into(),
+    );
+    let item_width = |ctx: &UiContext| {
+      ctx
+        .current_win
+        .borrow()
+        .as_ref()
+        .map(|winptr| winptr.borrow().layout.borrow().row.item_width)
+        .unwrap()
+    };
+
+    ctx.button_label_autowidth("Ok");
+    let short = item_width(&ctx);
+    ctx.button_label_autowidth("Cancel");
+    let long = item_width(&ctx);
+    ctx.end();
+
+    assert_eq!(long - short, 4f32 * 8f32);
+    assert!(short < 300f32);
+  }
+
+  #[test]
+  fn test_scaler_double_click_fits_content() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let flags = PanelFlags::WindowBorder
+      | PanelFlags::WindowScalable
+      | PanelFlags::WindowNoScrollbar;
+
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin("fit", RectangleF32::new(0f32, 0f32, 400f32, 300f32), flags);
+      ctx.layout_row_static(30f32, 100, 1);
+      ctx.button_label("one");
+      ctx.button_label("two");
+      let offsets = ctx
+        .current_win
+        .borrow()
+        .as_ref()
+        .map(|winptr| winptr.borrow().layout.borrow().offsets.clone())
+        .unwrap();
+      ctx.end();
+      ctx.clear();
 
-        res
-      })
+      let offsets = offsets.borrow();
+      (offsets.content, offsets.view)
+    };
+
+    let (content, view) = frame(&mut ctx);
+    assert!(content.x < view.x && content.y < view.y);
+
+    // the scaler grip sits in the bottom right corner, inside the border
+    let border = ctx.style.window.border;
+    let grip = ctx.style.window.scrollbar_size;
+    let x = (400f32 - border - grip.x * 0.5f32) as i32;
+    let y = (300f32 - border - grip.y * 0.5f32) as i32;
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x, y);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, true);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonDouble, x, y, true);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, false);
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonDouble, x, y, false);
+    ctx.input_mut().end();
+    let (fitted_content, fitted_view) = frame(&mut ctx);
+
+    let padding = ctx.style.window.padding;
+    assert_eq!(fitted_content.x, content.x);
+    assert_eq!(fitted_view.x, content.x);
+    assert_eq!(fitted_view.y, content.y + padding.y);
   }
 
-  pub fn button_symbol_styled(
-    &self,
-    style: &StyleButton,
-    symbol: SymbolType,
-  ) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_untouched_int_property_keeps_large_value() {
+    let mut ctx = test_context();
+    let mut value = (1 << 24) + 1;
 
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(false, |curr_win| {
-        let (state, bounds) = self.widget();
-        if state == WidgetLayoutStates::Invalid {
-          return false;
-        }
+    ctx.begin(
+      "props",
+      RectangleF32::new(0f32, 0f32, 200f32, 100f32),
+      PanelFlags::WindowBorder.into(),
+    );
+    ctx.layout_row_dynamic(20f32, 1);
+    let changed =
+      ctx.property_int("big", 0, &mut value, std::i32::MAX, 1, 1f32);
+    ctx.end();
 
-        let input = self.input.borrow();
-        use crate::hmi::button::do_button_symbol;
-        do_button_symbol(
-          &mut self.last_widget_state.borrow_mut(),
-          &mut curr_win.borrow().buffer_mut(),
-          bounds,
-          symbol,
-          self.button_behviour,
-          style,
-          if state == WidgetLayoutStates::Rom
-            || curr_win
-              .borrow()
-              .layout
+    assert!(!changed);
+    assert_eq!(value, (1 << 24) + 1);
+  }
+
+  #[test]
+  fn test_property_wheel_steps_hovered_value() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 100f32);
+    let mut values = (10f32, 10f32);
+    let frame = |ctx: &mut UiContext, values: &mut (f32, f32)| {
+      ctx.begin("props", bounds, PanelFlags::WindowBorder.into());
+      ctx.layout_row_dynamic(24f32, 2);
+      let prop = ctx.layout_widget_bounds();
+      ctx.property_float("a", 0f32, &mut values.0, 11f32, 0.5f32, 1f32);
+      ctx.property_float("b", 0f32, &mut values.1, 11f32, 0.5f32, 1f32);
+      ctx.end();
+      ctx.clear();
+      prop
+    };
+    let prop = frame(&mut ctx, &mut values);
+    let x = (prop.x + prop.w * 0.5f32) as i32;
+    let y = (prop.y + prop.h * 0.5f32) as i32;
+
+    let wheel = |ctx: &mut UiContext, values: &mut (f32, f32), ticks: f32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx.input_mut().scroll(Vec2F32::new(0f32, ticks));
+      ctx.input_mut().end();
+      frame(ctx, values);
+    };
+
+    wheel(&mut ctx, &mut values, 1f32);
+    assert_eq!(values, (10.5f32, 10f32));
+    wheel(&mut ctx, &mut values, -1f32);
+    assert_eq!(values, (10f32, 10f32));
+
+    // clamped to the range
+    wheel(&mut ctx, &mut values, 4f32);
+    assert_eq!(values, (11f32, 10f32));
+  }
+
+  #[test]
+  fn test_button_with_blank_label_is_clickable() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+
+    ["", "   "].iter().for_each(|label| {
+      let frame = |ctx: &mut UiContext| {
+        ctx.begin(
+          "blank",
+          RectangleF32::new(0f32, 0f32, 200f32, 100f32),
+          PanelFlags::WindowBorder.into(),
+        );
+        ctx.layout_row_dynamic(30f32, 1);
+        let button = ctx.layout_peek();
+        let clicked = ctx.button_text(label);
+        let texts = ctx
+          .current_win
+          .borrow()
+          .as_ref()
+          .map(|winptr| {
+            winptr
               .borrow()
-              .flags
-              .intersects(PanelFlags::WindowRom)
-          {
-            None
-          } else {
-            Some(&*input)
-          },
-          self.style.font,
-        )
+              .buffer_mut()
+              .commands()
+              .iter()
+              .filter(|cmd| match cmd {
+                Command::Text(_) => true,
+                _ => false,
+              })
+              .count()
+          })
+          .unwrap();
+        ctx.end();
+        ctx.clear();
+        (button, clicked, texts)
+      };
+
+      let (button, _, texts) = frame(&mut ctx);
+      assert!(button.w.is_finite() && button.h.is_finite());
+      assert!(button.w > 0f32 && button.h > 0f32);
+      assert_eq!(texts, 0);
+
+      // the whole button is clickable, not just where a label would be
+      let x = (button.x + 2f32) as i32;
+      let y = (button.y + button.h - 2f32) as i32;
+      press_at(&mut ctx, x, y);
+      let (_, pressed, _) = frame(&mut ctx);
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+      let (_, released, _) = frame(&mut ctx);
+      assert!(pressed || released);
+    });
+  }
+
+  #[test]
+  fn test_same_line_continues_row() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    ctx.begin(
+      "toolbar",
+      RectangleF32::new(0f32, 0f32, 300f32, 200f32),
+      PanelFlags::WindowBorder.into(),
+    );
+    ctx.layout_row_static(30f32, 80, 1);
+
+    let (_, first) = ctx.widget();
+    ctx.same_line();
+    let (_, second) = ctx.widget();
+    assert_eq!(second.y, first.y);
+    assert!(second.x >= first.x + first.w);
+
+    // without same_line the row is done
+    let (_, below) = ctx.widget();
+    assert!(below.y > first.y);
+    assert_eq!(below.x, first.x);
+
+    // wraps once the row is full
+    let ys = (0 .. 4)
+      .map(|_| {
+        ctx.same_line();
+        ctx.widget().1.y
       })
+      .collect::<Vec<_>>();
+    assert_eq!(&ys[.. 2], &[below.y, below.y]);
+    assert!(ys[2] > below.y);
+
+    ctx.end();
   }
 
-  pub fn button_symbol(&self, symbol: SymbolType) -> bool {
-    self.button_symbol_styled(&self.style.button, symbol)
+  #[test]
+  fn test_label_selectable_copies_text() {
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl ClipboardProvider for Recorder {
+      fn set_text(&mut self, text: &str) {
+        self.0.borrow_mut().push(text.to_string());
+      }
+
+      fn get_text(&self) -> Option<String> {
+        self.0.borrow().last().cloned()
+      }
+    }
+
+    let copied = Rc::new(RefCell::new(Vec::new()));
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    ctx.set_clipboard(Box::new(Recorder(Rc::clone(&copied))));
+
+    let frame = |ctx: &mut UiContext| {
+      ctx.begin(
+        "labels",
+        RectangleF32::new(0f32, 0f32, 200f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.layout_row_dynamic(30f32, 1);
+      let label = ctx.layout_peek();
+      ctx.label_selectable("id: 0x2a");
+      ctx.end();
+      ctx.clear();
+      label
+    };
+
+    let label = frame(&mut ctx);
+    assert!(copied.borrow().is_empty());
+
+    let x = (label.x + label.w * 0.5f32) as i32;
+    let y = (label.y + label.h * 0.5f32) as i32;
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, false);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    assert_eq!(*copied.borrow(), vec!["id: 0x2a".to_string()]);
   }
 
-  pub fn button_image_styled(&self, style: &StyleButton, img: Image) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_combo_near_display_bottom_opens_upward() {
+    let mut ctx = test_context();
+    ctx.set_display_size(Vec2F32::new(800f32, 600f32));
+    let size = Vec2F32::new(120f32, 100f32);
+
+    // the combo header follows `rows` rows of buttons
+    let open_combo =
+      |ctx: &mut UiContext, name: &str, bounds: RectangleF32, rows: usize| {
+        let rows_above = |ctx: &mut UiContext| {
+          (0 .. rows).for_each(|_| {
+            ctx.layout_row_dynamic(20f32, 1);
+            ctx.button_text("above");
+          })
+        };
 
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(false, |curr_win| {
-        let (state, bounds) = self.widget();
-        if state == WidgetLayoutStates::Invalid {
-          return false;
+        ctx.begin(name, bounds, PanelFlags::WindowBorder.into());
+        rows_above(ctx);
+        ctx.layout_row_static(20f32, size.x as i32, 1);
+        let anchor = ctx.layout_peek();
+        ctx.end();
+        ctx.clear();
+
+        let items = RefCell::new(Vec::new());
+        let frame = |ctx: &mut UiContext| {
+          items.borrow_mut().clear();
+          ctx.begin(name, bounds, PanelFlags::WindowBorder.into());
+          rows_above(ctx);
+          ctx.combo_callback(5, 0, 20f32, size, |ctx, _| {
+            items.borrow_mut().push(ctx.widget().1);
+          });
+          ctx.end();
+          ctx.clear();
+        };
+
+        frame(ctx);
+        let (x, y) = ((anchor.x + 10f32) as i32, (anchor.y + 10f32) as i32);
+        press_at(ctx, x, y);
+        frame(ctx);
+        ctx.input_mut().begin();
+        ctx
+          .input_mut()
+          .button(MouseButtonId::ButtonLeft, x, y, false);
+        ctx.input_mut().end();
+        frame(ctx);
+
+        // the first item is the selected one, drawn in the header
+        let items = items.into_inner().split_off(1);
+        assert_eq!(items.len(), 5);
+        (anchor, items)
+      };
+
+    let (header, items) = open_combo(
+      &mut ctx,
+      "top",
+      RectangleF32::new(0f32, 0f32, 300f32, 300f32),
+      0,
+    );
+    assert!(items.iter().all(|item| item.y >= header.y + header.h));
+    assert!(items.windows(2).all(|w| w[1].y > w[0].y));
+
+    let bounds = RectangleF32::new(0f32, 380f32, 300f32, 220f32);
+    let (header, items) = open_combo(&mut ctx, "bottom", bounds, 5);
+    assert!(header.y + header.h + 100f32 > 600f32);
+    assert!(items.iter().all(|item| item.y + item.h <= header.y));
+    assert!(items.iter().all(|item| item.y >= bounds.y));
+    assert!(items.windows(2).all(|w| w[1].y > w[0].y));
+
+    // no room above the header inside the window, the drop down is not
+    // moved out of it
+    let bounds = RectangleF32::new(0f32, 490f32, 300f32, 110f32);
+    let (header, items) = open_combo(&mut ctx, "cramped", bounds, 0);
+    assert!(items.iter().all(|item| item.y >= header.y + header.h));
+  }
+
+  #[test]
+  fn test_click_on_upward_combo_item_skips_widget_below() {
+    let mut ctx = test_context();
+    ctx.set_display_size(Vec2F32::new(800f32, 600f32));
+    let bounds = RectangleF32::new(0f32, 380f32, 300f32, 220f32);
+    let size = Vec2F32::new(120f32, 100f32);
+    let clicks = std::cell::Cell::new(0);
+    let items = RefCell::new(Vec::new());
+    let mut selected = 0;
+
+    let buttons = |ctx: &mut UiContext| {
+      (0 .. 5).for_each(|_| {
+        ctx.layout_row_dynamic(20f32, 1);
+        if ctx.button_text("below") {
+          clicks.set(clicks.get() + 1);
         }
+      });
+    };
 
-        let input = self.input.borrow();
-        use crate::hmi::button::do_button_image;
+    // the combo header follows the buttons
+    ctx.begin("combo", bounds, PanelFlags::WindowBorder.into());
+    buttons(&mut ctx);
+    ctx.layout_row_static(20f32, size.x as i32, 1);
+    let header = ctx.layout_peek();
+    ctx.end();
+    ctx.clear();
+
+    let mut frame = |ctx: &mut UiContext| {
+      items.borrow_mut().clear();
+      ctx.begin("combo", bounds, PanelFlags::WindowBorder.into());
+      buttons(ctx);
+      selected = ctx.combo_callback(5, selected, 20f32, size, |ctx, _| {
+        items.borrow_mut().push(ctx.widget().1);
+      });
+      ctx.end();
+      ctx.clear();
+      selected
+    };
+    let release_at = |ctx: &mut UiContext, x: i32, y: i32| {
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+    };
 
-        do_button_image(
-          &mut self.last_widget_state.borrow_mut(),
-          &mut curr_win.borrow().buffer_mut(),
-          bounds,
-          img,
-          self.button_behviour,
-          style,
-          if state == WidgetLayoutStates::Rom
-            || curr_win
-              .borrow()
-              .layout
-              .borrow()
-              .flags
-              .intersects(PanelFlags::WindowRom)
-          {
-            None
-          } else {
-            Some(&*input)
-          },
-        )
-      })
+    frame(&mut ctx);
+    let (x, y) = ((header.x + 10f32) as i32, (header.y + 10f32) as i32);
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    release_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    // the selected item in the header comes first
+    let item = items.borrow()[3];
+    assert!(item.y + item.h <= header.y);
+
+    // the item lies on top of one of the buttons
+    let (x, y) = ((item.x + 10f32) as i32, (item.y + 10f32) as i32);
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    release_at(&mut ctx, x, y);
+    assert_eq!(frame(&mut ctx), 2);
+    assert_eq!(clicks.get(), 0);
   }
 
-  pub fn button_image(&self, img: Image) -> bool {
-    self.button_image_styled(&self.style.button, img)
+  #[test]
+  fn test_combo_wheel_scrolls_to_later_items() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 400f32);
+    let size = Vec2F32::new(120f32, 60f32);
+    let items = RefCell::new(Vec::new());
+    let mut selected = 0;
+
+    let mut frame = |ctx: &mut UiContext| {
+      items.borrow_mut().clear();
+      ctx.begin("picker", bounds, PanelFlags::WindowBorder.into());
+      selected = ctx.combo_callback(10, selected, 20f32, size, |ctx, idx| {
+        items.borrow_mut().push((idx, ctx.widget().1));
+      });
+      ctx.end();
+      ctx.clear();
+      selected
+    };
+    let release_at = |ctx: &mut UiContext, x: i32, y: i32| {
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+    };
+    let indices = |items: &RefCell<Vec<(usize, RectangleF32)>>| {
+      items
+        .borrow()
+        .iter()
+        .map(|(idx, _)| *idx)
+        .collect::<Vec<_>>()
+    };
+
+    frame(&mut ctx);
+    let header = items.borrow()[0].1;
+    let (x, y) = ((header.x + 10f32) as i32, (header.y + 10f32) as i32);
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    release_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    assert_eq!(indices(&items), vec![0, 0, 1, 2]);
+
+    // the wheel over the drop down scrolls through the items, up to the last
+    let item = items.borrow()[2].1;
+    let (x, y) = ((item.x + 10f32) as i32, (item.y + 10f32) as i32);
+    let wheel = |ctx: &mut UiContext, lines: f32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx.input_mut().scroll(Vec2F32::new(0f32, lines));
+      ctx.input_mut().end();
+    };
+    wheel(&mut ctx, -2f32);
+    frame(&mut ctx);
+    frame(&mut ctx);
+    assert_eq!(indices(&items), vec![0, 2, 3, 4]);
+    wheel(&mut ctx, -20f32);
+    frame(&mut ctx);
+    frame(&mut ctx);
+    assert_eq!(indices(&items), vec![0, 7, 8, 9]);
+
+    let item = items.borrow()[3].1;
+    let (x, y) = ((item.x + 10f32) as i32, (item.y + 10f32) as i32);
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    release_at(&mut ctx, x, y);
+    assert_eq!(frame(&mut ctx), 9);
+
+    // reopens scrolled to the selected item
+    let (x, y) = ((header.x + 10f32) as i32, (header.y + 10f32) as i32);
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    release_at(&mut ctx, x, y);
+    frame(&mut ctx);
+    assert_eq!(indices(&items), vec![9, 7, 8, 9]);
   }
 
-  pub fn button_symbol_text_styled(
-    &self,
-    style: &StyleButton,
-    symbol: SymbolType,
-    text: &str,
-    align: BitFlags<TextAlign>,
-  ) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_combo_stays_open_when_layout_moves() {
+    let mut ctx = test_context();
+    let bounds = RectangleF32::new(0f32, 0f32, 300f32, 400f32);
+    let size = Vec2F32::new(120f32, 100f32);
+    let rendered = std::cell::Cell::new(0);
+
+    // `rows` rows of buttons come before the combo
+    let frame = |ctx: &mut UiContext, rows: usize| {
+      rendered.set(0);
+      ctx.begin("picker", bounds, PanelFlags::WindowBorder.into());
+      (0 .. rows).for_each(|_| {
+        ctx.layout_row_dynamic(20f32, 1);
+        ctx.button_text("above");
+      });
+      ctx.combo_callback(5, 0, 20f32, size, |ctx, _| {
+        ctx.widget();
+        rendered.set(rendered.get() + 1);
+      });
+      ctx.end();
+      ctx.clear();
+    };
 
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(false, |curr_win| {
-        let (state, bounds) = self.widget();
-        if state == WidgetLayoutStates::Invalid {
-          return false;
-        }
+    ctx.begin("picker", bounds, PanelFlags::WindowBorder.into());
+    ctx.layout_row_static(20f32, size.x as i32, 1);
+    let header = ctx.layout_widget_bounds();
+    ctx.end();
+    ctx.clear();
+
+    frame(&mut ctx, 0);
+    let (x, y) = ((header.x + 10f32) as i32, (header.y + 10f32) as i32);
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx, 0);
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x, y, false);
+    ctx.input_mut().end();
+    frame(&mut ctx, 0);
+    assert_eq!(rendered.get(), 6);
+
+    // moved down by the rows before it, the combo is still open
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(0, 0);
+    ctx.input_mut().end();
+    frame(&mut ctx, 2);
+    assert_eq!(rendered.get(), 6);
+  }
 
-        let input = self.input.borrow();
-        use crate::hmi::button::do_button_text_symbol;
-        do_button_text_symbol(
-          &mut self.last_widget_state.borrow_mut(),
-          &mut curr_win.borrow().buffer_mut(),
-          bounds,
-          symbol,
-          text,
-          align,
-          self.button_behviour,
-          style,
-          self.style.font,
-          if state == WidgetLayoutStates::Rom
-            || curr_win
-              .borrow()
-              .layout
-              .borrow()
-              .flags
-              .intersects(PanelFlags::WindowRom)
-          {
-            None
-          } else {
-            Some(&*input)
-          },
-        )
-      })
+  #[test]
+  fn test_table_separator_drag_resizes_column() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let headers = ["name", "size", "kind"];
+
+    // returns the widths and the bounds of the first row's cells, the
+    // second row is filled with text cells
+    let frame = |ctx: &mut UiContext| {
+      let mut widths = [60f32, 60f32, 60f32];
+      ctx.begin(
+        "grid",
+        RectangleF32::new(0f32, 0f32, 400f32, 200f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      assert!(ctx.table_begin(&headers, &mut widths));
+      ctx.table_next_row();
+      let cells = widths
+        .iter()
+        .map(|width| {
+          ctx.layout_row_push(*width);
+          ctx.widget().1
+        })
+        .collect::<Vec<_>>();
+      ctx.table_next_row();
+      ["a.txt", "12", "text"]
+        .iter()
+        .for_each(|cell| ctx.table_cell(cell));
+      ctx.table_end();
+      ctx.end();
+      ctx.clear();
+      (widths, cells)
+    };
+
+    let (widths, cells) = frame(&mut ctx);
+    assert_eq!(widths, [60f32, 60f32, 60f32]);
+    assert!(cells[1].x > cells[0].x + cells[0].w);
+
+    // the separator sits in the gap after the first column, in the header row
+    let x = ((cells[0].x + cells[0].w + cells[1].x) * 0.5f32) as i32;
+    let y = (cells[0].y - 8f32) as i32;
+    press_at(&mut ctx, x, y);
+    frame(&mut ctx);
+
+    ctx.input_mut().begin();
+    ctx.input_mut().motion(x + 20, y);
+    ctx.input_mut().end();
+    let (widths, _) = frame(&mut ctx);
+    assert_eq!(widths, [80f32, 60f32, 60f32]);
+
+    ctx.input_mut().begin();
+    ctx
+      .input_mut()
+      .button(MouseButtonId::ButtonLeft, x + 20, y, false);
+    ctx.input_mut().end();
+    frame(&mut ctx);
+
+    // the widths are kept by the window and the next column moved along
+    let (widths, moved) = frame(&mut ctx);
+    assert_eq!(widths, [80f32, 60f32, 60f32]);
+    assert_eq!(moved[0].w, cells[0].w + 20f32);
+    assert_eq!(moved[1].x, cells[1].x + 20f32);
+    assert_eq!(moved[2].x, cells[2].x + 20f32);
   }
 
-  pub fn button_symbol_text(
-    &self,
-    symbol: SymbolType,
-    text: &str,
-    align: BitFlags<TextAlign>,
-  ) -> bool {
-    self.button_symbol_text_styled(&self.style.button, symbol, text, align)
+  #[test]
+  fn test_disabled_button_ignores_clicks() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+
+    // returns whether the button was clicked, its bounds and text color
+    let frame = |ctx: &mut UiContext, disabled: bool| {
+      ctx.begin(
+        "form",
+        RectangleF32::new(0f32, 0f32, 200f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.layout_row_dynamic(30f32, 1);
+      if disabled {
+        ctx.begin_disabled();
+        ctx.begin_disabled();
+      }
+      let button = ctx.layout_peek();
+      let clicked = ctx.button_text("submit");
+      if disabled {
+        ctx.end_disabled();
+        ctx.end_disabled();
+      }
+      let color = ctx.current_win.borrow().as_ref().and_then(|winptr| {
+        winptr
+          .borrow()
+          .buffer_mut()
+          .commands()
+          .iter()
+          .rev()
+          .find_map(|cmd| match cmd {
+            Command::Text(txt) => Some(txt.foreground),
+            _ => None,
+          })
+      });
+      ctx.end();
+      ctx.clear();
+      (clicked, button, color.unwrap())
+    };
+
+    let click = |ctx: &mut UiContext, disabled: bool| {
+      let (_, button, _) = frame(ctx, disabled);
+      let x = (button.x + button.w * 0.5f32) as i32;
+      let y = (button.y + button.h * 0.5f32) as i32;
+      press_at(ctx, x, y);
+      let (pressed, ..) = frame(ctx, disabled);
+      ctx.input_mut().begin();
+      ctx
+        .input_mut()
+        .button(MouseButtonId::ButtonLeft, x, y, false);
+      ctx.input_mut().end();
+      let (released, _, color) = frame(ctx, disabled);
+      (pressed || released, color)
+    };
+
+    let (clicked, enabled) = click(&mut ctx, false);
+    assert!(clicked);
+
+    let (clicked, dimmed) = click(&mut ctx, true);
+    assert!(!clicked);
+    let gray = enabled.to_grayscale();
+    // nested blocks dim only once
+    assert_eq!(
+      dimmed,
+      RGBAColor {
+        a: gray.a / 2,
+        ..gray
+      }
+    );
   }
 
-  pub fn button_image_text_styled(
-    &self,
-    style: &StyleButton,
-    img: Image,
-    text: &str,
-    align: BitFlags<TextAlign>,
-  ) -> bool {
-    debug_assert!(self.current_win.borrow().is_some());
+  #[test]
+  fn test_progress_label_is_centered() {
+    use crate::hmi::text_engine::FontAtlas;
 
-    self
-      .current_win
-      .borrow()
-      .as_ref()
-      .map_or(false, |curr_win| {
-        let (state, bounds) = self.widget();
-        if state == WidgetLayoutStates::Invalid {
-          return false;
-        }
+    let mut ctx = test_context();
+    ctx.style.font = FontAtlas::monospace_for_tests(8f32);
+    ctx.begin(
+      "download",
+      RectangleF32::new(0f32, 0f32, 300f32, 100f32),
+      PanelFlags::WindowBorder.into(),
+    );
+    ctx.layout_row_dynamic(30f32, 1);
+    let bar = ctx.layout_peek();
+    ctx.progress_labeled(50, 100, ProgressLabel::Percent);
 
-        let input = self.input.borrow();
-        use crate::hmi::button::do_button_text_image;
-        do_button_text_image(
-          &mut self.last_widget_state.borrow_mut(),
-          &mut curr_win.borrow().buffer_mut(),
-          bounds,
-          img,
-          text,
-          align,
-          self.button_behviour,
-          style,
-          self.style.font,
-          if state == WidgetLayoutStates::Rom
-            || curr_win
-              .borrow()
-              .layout
-              .borrow()
-              .flags
-              .intersects(PanelFlags::WindowRom)
-          {
-            None
-          } else {
-            Some(&*input)
-          },
-        )
-      })
+    let text = ctx.current_win.borrow().as_ref().and_then(|winptr| {
+      winptr
+        .borrow()
+        .buffer_mut()
+        .commands()
+        .iter()
+        .rev()
+        .find_map(|cmd| match cmd {
+          Command::Text(txt) => Some(txt.clone()),
+          _ => None,
+        })
+    });
+    ctx.end();
+
+    let text = text.unwrap();
+    assert_eq!(text.text, "50%");
+    let center_x = text.x as f32 + text.w as f32 * 0.5f32;
+    let center_y = text.y as f32 + ctx.style.font.scale * 0.5f32;
+    assert!((center_x - (bar.x + bar.w * 0.5f32)).abs() <= 1f32);
+    assert!((center_y - (bar.y + bar.h * 0.5f32)).abs() <= 1f32);
+    assert_eq!(ProgressLabel::Fraction.format(42, 100), "42/100");
   }
 
-  pub fn button_image_text(
-    &self,
-    img: Image,
-    text: &str,
-    align: BitFlags<TextAlign>,
-  ) -> bool {
-    self.button_image_text_styled(&self.style.button, img, text, align)
+  #[test]
+  fn test_wants_capture_mouse_over_window() {
+    let mut ctx = test_context();
+    ctx.style.font.scale = 14f32;
+    let frame = |ctx: &mut UiContext, x: i32, y: i32| {
+      ctx.input_mut().begin();
+      ctx.input_mut().motion(x, y);
+      ctx.input_mut().end();
+      ctx.begin(
+        "capture",
+        RectangleF32::new(0f32, 0f32, 200f32, 100f32),
+        PanelFlags::WindowBorder.into(),
+      );
+      ctx.end();
+      let capture = ctx.wants_capture_mouse();
+      ctx.clear();
+      capture
+    };
+
+    assert!(frame(&mut ctx, 50, 50));
+    assert!(!frame(&mut ctx, 400, 300));
+    assert!(!ctx.wants_capture_keyboard());
+
+    // a drag started on the window keeps the mouse after leaving it
+    press_at(&mut ctx, 50, 50);
+    assert!(frame(&mut ctx, 400, 300));
   }
 }
@@ -1,15 +1,17 @@
 use crate::math::{
   colors::{RGBAColor, RGBAColorF32},
   rectangle::RectangleF32,
-  vec2::{normalize, Vec2F32},
+  utility::clamp,
+  vec2::{normalize, rotate, Vec2F32},
   vertex_types::VertexPTC,
 };
 
 use crate::hmi::{
   base::{AntialiasingType, Consts, ConvertConfig, GenericHandle},
-  commands::Command,
+  buffer::ScratchArena,
+  commands::{Command, LineStyle, TextColorSpan},
   image::Image,
-  text_engine::Font,
+  text_engine::{is_combining_mark, Font, TextDirection},
 };
 
 pub type DrawIndexType = u16;
@@ -20,8 +22,51 @@ pub enum DrawListStroke {
   Closed,
 }
 
+/// Splits the open path `points` into the runs drawn by a dash pattern of
+/// `on` pixels drawn and `off` pixels left out. The pattern continues around
+/// the corners of the path, so a run can have more than two points.
+pub fn dash_path(points: &[Vec2F32], on: f32, off: f32) -> Vec<Vec<Vec2F32>> {
+  let mut runs = vec![];
+  let mut run = points.first().map_or(vec![], |p| vec![*p]);
+  let mut drawing = true;
+  let mut left = on;
+
+  points.windows(2).for_each(|segment| {
+    let (a, b) = (segment[0], segment[1]);
+    let len = (b - a).len();
+    if len <= 0_f32 {
+      return;
+    }
+
+    let dir = (b - a) * (1_f32 / len);
+    let mut pos = 0_f32;
+    while len - pos > left {
+      pos += left;
+      run.push(a + dir * pos);
+      if drawing {
+        runs.push(std::mem::take(&mut run));
+      }
+      drawing = !drawing;
+      left = if drawing { on } else { off };
+    }
+
+    left -= len - pos;
+    if drawing {
+      run.push(b);
+    }
+  });
+
+  if drawing && run.len() >= 2 {
+    runs.push(run);
+  }
+
+  runs
+}
+
 #[derive(Copy, Debug, Clone)]
 pub struct DrawCommand {
+  /// Number of indices drawn by this command. The indices of a command
+  /// follow the ones of the previous command in the index buffer.
   pub element_count: u32,
   pub clip_rect:     RectangleF32,
   pub texture:       GenericHandle,
@@ -33,14 +78,43 @@ pub struct BufferOutput<'a> {
   pub index_buff:  &'a mut Vec<DrawIndexType>,
 }
 
+/// Counters collected while converting commands, useful to spot batching
+/// problems.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DrawListStats {
+  pub vertices:             usize,
+  pub indices:              usize,
+  pub draw_commands:        usize,
+  pub clip_changes:         usize,
+  pub texture_switches:     usize,
+  /// Largest number of vertices emitted for a single command.
+  pub max_command_vertices: usize,
+}
+
+impl DrawListStats {
+  /// Adds the counters of another conversion.
+  pub fn merge(&mut self, other: &DrawListStats) {
+    self.vertices += other.vertices;
+    self.indices += other.indices;
+    self.draw_commands += other.draw_commands;
+    self.clip_changes += other.clip_changes;
+    self.texture_switches += other.texture_switches;
+    self.max_command_vertices =
+      self.max_command_vertices.max(other.max_command_vertices);
+  }
+}
+
 #[derive(Debug)]
 pub struct DrawList {
   clip_rect:  RectangleF32,
   circle_vtx: Vec<Vec2F32>,
   config:     ConvertConfig,
-  path:       std::cell::RefCell<Vec<Vec2F32>>,
+  path:       std::cell::RefCell<ScratchArena<Vec2F32>>,
   line_aa:    AntialiasingType,
   shape_aa:   AntialiasingType,
+  null_valid: bool,
+  stats:      DrawListStats,
+  generation: u32,
 }
 
 impl DrawList {
@@ -48,27 +122,75 @@ impl DrawList {
   //   RectangleF32::new(-8192_f32, -8192_f32, 16834_f32, 16834_f32)
   // }
 
+  /// Fails with the message of `ConvertConfig::validate()` if the config
+  /// can't be used. A config without a null texture is accepted, solid fills
+  /// will sample garbage though (see `has_null_texture()`).
   pub fn new(
     config: ConvertConfig,
     line_aa: AntialiasingType,
     shape_aa: AntialiasingType,
-  ) -> Self {
+  ) -> Result<Self, String> {
     const GEN_CIRCLE_VERTICES_COUNT: i32 = 12;
 
-    DrawList {
+    config.validate()?;
+    let null_valid = config.null.is_valid();
+
+    Ok(DrawList {
       clip_rect: Consts::null_rect(),
       circle_vtx: (0 .. GEN_CIRCLE_VERTICES_COUNT)
         .map(|idx| {
-          let a = idx as f32
-            / (GEN_CIRCLE_VERTICES_COUNT as f32 * 2_f32 * std::f32::consts::PI);
+          let a = idx as f32 / GEN_CIRCLE_VERTICES_COUNT as f32
+            * 2_f32
+            * std::f32::consts::PI;
           Vec2F32::new(a.cos(), a.sin())
         })
         .collect(),
       config,
-      path: std::cell::RefCell::new(vec![]),
+      path: std::cell::RefCell::new(ScratchArena::new()),
       line_aa,
       shape_aa,
-    }
+      null_valid,
+      stats: DrawListStats::default(),
+      generation: 0,
+    })
+  }
+
+  /// Replaces the settings the commands are converted with. An invalid
+  /// config is rejected like in `new()`, the old one is kept.
+  pub fn set_config(&mut self, config: ConvertConfig) -> Result<(), String> {
+    config.validate()?;
+    self.null_valid = config.null.is_valid();
+    self.config = config;
+    self.generation += 1;
+    Ok(())
+  }
+
+  /// Changes each time the settings are replaced, geometry converted with
+  /// other settings can't be reused.
+  pub fn generation(&self) -> u32 {
+    self.generation
+  }
+
+  /// True if the vertex colors are emitted with premultiplied alpha.
+  pub fn premultiplied_alpha(&self) -> bool {
+    self.config.premultiplied_alpha
+  }
+
+  /// False if the config had no white pixel texture set.
+  pub fn has_null_texture(&self) -> bool {
+    self.null_valid
+  }
+
+  /// Number of times the path buffer had to grow, stays the same once it
+  /// is large enough for the longest path.
+  pub fn path_allocations(&self) -> u32 {
+    self.path.borrow().allocations()
+  }
+
+  /// Stats for the last call to `convert()`, see
+  /// `UiContext::convert_stats()` for the ones of a whole frame.
+  pub fn last_stats(&self) -> DrawListStats {
+    self.stats
   }
 
   fn push_command(
@@ -119,6 +241,7 @@ impl DrawList {
         } else if last_cmd.texture != texture {
           // texture change so insert a new command using this command's clip
           // rectangle
+          self.stats.texture_switches += 1;
           Some(last_cmd.clip_rect)
         } else {
           // nothing to do, same texture
@@ -131,6 +254,15 @@ impl DrawList {
       });
   }
 
+  /// Adds the indices pushed since `first_index` to the last command.
+  fn add_elements(outbuff: &mut BufferOutput, first_index: usize) {
+    let count = (outbuff.index_buff.len() - first_index) as u32;
+    outbuff
+      .cmds_buff
+      .last_mut()
+      .map(|last_cmd| last_cmd.element_count += count);
+  }
+
   fn draw_vertex(
     // _config: &ConvertConfig,
     pos: Vec2F32,
@@ -177,6 +309,7 @@ impl DrawList {
     // aliased only for now
 
     // let vtx_count = count * 4;
+    let first_index = outbuff.index_buff.len();
 
     (0 .. count).for_each(|i1| {
       let uv = self.config.null.uv;
@@ -207,15 +340,9 @@ impl DrawList {
       [0, 1, 2, 0, 2, 3].into_iter().for_each(|&offset| {
         outbuff.index_buff.push((idx + offset) as DrawIndexType);
       });
-
-      let element_count = outbuff.index_buff.len();
-
-      // update element count of the last command
-      outbuff
-        .cmds_buff
-        .last_mut()
-        .map(|last_cmd| last_cmd.element_count = element_count as u32);
     });
+
+    Self::add_elements(outbuff, first_index);
   }
 
   pub fn fill_poly_convex(
@@ -233,6 +360,7 @@ impl DrawList {
 
     let null_uv = self.config.null.uv;
     let idx = outbuff.vertex_buff.len();
+    let first_index = outbuff.index_buff.len();
 
     points.iter().for_each(|&vertex| {
       outbuff
@@ -246,15 +374,16 @@ impl DrawList {
       outbuff.index_buff.push((idx + offset) as DrawIndexType);
     });
 
-    let element_count = outbuff.index_buff.len();
+    Self::add_elements(outbuff, first_index);
+  }
 
-    outbuff
-      .cmds_buff
-      .last_mut()
-      .map(|last_cmd| last_cmd.element_count = element_count as u32);
+  /// Starts a new user path, discarding any unfinished one. Paths must be
+  /// finished with `path_stroke` or `path_fill` before calling `convert`.
+  pub fn path_begin(&mut self) {
+    self.path.borrow_mut().reset();
   }
 
-  fn path_line_to(&mut self, outbuff: &mut BufferOutput, pos: Vec2F32) {
+  pub fn path_line_to(&mut self, outbuff: &mut BufferOutput, pos: Vec2F32) {
     // if no previous commands, push the null clipping rectangle
     if outbuff.cmds_buff.is_empty() {
       self.add_clip(outbuff, Consts::null_rect());
@@ -291,7 +420,7 @@ impl DrawList {
     });
   }
 
-  fn path_arc_to(
+  pub fn path_arc_to(
     &mut self,
     outbuff: &mut BufferOutput,
     center: Vec2F32,
@@ -342,37 +471,67 @@ impl DrawList {
     b: Vec2F32,
     rounding: f32,
   ) {
-    let r = {
-      let r = rounding;
-      let dist = b - a;
-      let r = if dist.x < 0_f32 {
-        r.min(-dist.x)
-      } else {
-        r.min(dist.x)
-      };
-      let r = if dist.y < 0_f32 {
-        r.min(-dist.y)
-      } else {
-        r.min(dist.y)
-      };
-
-      r
-    };
+    self.path_rect_to_corners(outbuff, a, b, [rounding; 4]);
+  }
 
-    if r == 0_f32 {
-      self.path_line_to(outbuff, a);
-      self.path_line_to(outbuff, Vec2F32::new(b.x, a.y));
-      self.path_line_to(outbuff, b);
-      self.path_line_to(outbuff, Vec2F32::new(a.x, b.y));
-    } else {
-      self.path_arc_to_fast(outbuff, a + Vec2F32::same(r), r, 6, 9);
-      self.path_arc_to_fast(outbuff, b + Vec2F32::new(-r, r), r, 9, 12);
-      self.path_arc_to_fast(outbuff, b - Vec2F32::same(r), r, 0, 3);
-      self.path_arc_to_fast(outbuff, a + Vec2F32::new(r, -r), r, 3, 6);
-    }
+  /// Rectangle path with a radius for each corner, in the order top-left,
+  /// top-right, bottom-right, bottom-left. A radius of zero gives a sharp
+  /// corner.
+  pub fn path_rect_to_corners(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    a: Vec2F32,
+    b: Vec2F32,
+    radii: [f32; 4],
+  ) {
+    // arcs of neighbouring corners must not overlap, a rounding that large
+    // gives a capsule or a circle
+    let dist = b - a;
+    let max_radius = dist.x.abs().min(dist.y.abs()) * 0.5f32;
+    let [tl, tr, br, bl] = [
+      clamp(0f32, radii[0], max_radius),
+      clamp(0f32, radii[1], max_radius),
+      clamp(0f32, radii[2], max_radius),
+      clamp(0f32, radii[3], max_radius),
+    ];
+
+    let corners = [
+      (
+        Vec2F32::new(a.x + tl, a.y + tl),
+        tl,
+        Vec2F32::new(a.x, a.y),
+        6,
+      ),
+      (
+        Vec2F32::new(b.x - tr, a.y + tr),
+        tr,
+        Vec2F32::new(b.x, a.y),
+        9,
+      ),
+      (
+        Vec2F32::new(b.x - br, b.y - br),
+        br,
+        Vec2F32::new(b.x, b.y),
+        0,
+      ),
+      (
+        Vec2F32::new(a.x + bl, b.y - bl),
+        bl,
+        Vec2F32::new(a.x, b.y),
+        3,
+      ),
+    ];
+
+    corners.iter().for_each(|&(center, r, corner, a_min)| {
+      if r == 0_f32 {
+        self.path_line_to(outbuff, corner);
+      } else {
+        self.path_arc_to_fast(outbuff, center, r, a_min, a_min + 3);
+      }
+    });
   }
 
-  fn path_curve_to(
+  pub fn path_curve_to(
     &mut self,
     outbuff: &mut BufferOutput,
     p2: Vec2F32,
@@ -385,7 +544,7 @@ impl DrawList {
     }
 
     let segments = segments.max(1);
-    let p1 = *self.path.borrow().last().unwrap();
+    let p1 = *self.path.borrow().elements().last().unwrap();
     let t_step = 1_f32 / segments as f32;
 
     (1 .. segments + 1).for_each(|i_step| {
@@ -401,27 +560,37 @@ impl DrawList {
     });
   }
 
-  fn path_fill(&mut self, outbuff: &mut BufferOutput, color: RGBAColor) {
-    let path = self.path.replace(vec![]);
-    self.fill_poly_convex(outbuff, &path, color, self.config.shape_aa);
+  pub fn path_fill(&mut self, outbuff: &mut BufferOutput, color: RGBAColor) {
+    let mut path = self.path.replace(ScratchArena::new());
+    self.fill_poly_convex(
+      outbuff,
+      path.elements(),
+      color,
+      self.config.shape_aa,
+    );
+    // hand the memory back, the next path reuses it
+    path.reset();
+    self.path.replace(path);
   }
 
-  fn path_stroke(
+  pub fn path_stroke(
     &mut self,
     outbuff: &mut BufferOutput,
     color: RGBAColor,
     path_type: DrawListStroke,
     thickness: f32,
   ) {
-    let path = self.path.replace(vec![]);
+    let mut path = self.path.replace(ScratchArena::new());
     self.stroke_poly_line(
       outbuff,
-      &path,
+      path.elements(),
       color,
       path_type,
       thickness,
       self.config.line_aa,
     );
+    path.reset();
+    self.path.replace(path);
   }
 
   fn stroke_line(
@@ -431,20 +600,43 @@ impl DrawList {
     b: Vec2F32,
     col: RGBAColor,
     thickness: f32,
+    style: LineStyle,
   ) {
     if col.a == 0 {
       return;
     }
 
-    if self.line_aa == AntialiasingType::On {
-      self.path_line_to(outbuff, a);
-      self.path_line_to(outbuff, b);
+    let points = if self.line_aa == AntialiasingType::On {
+      [a, b]
     } else {
-      self.path_line_to(outbuff, a - Vec2F32::same(0.5_f32));
-      self.path_line_to(outbuff, b - Vec2F32::same(0.5_f32));
-    }
+      [a - Vec2F32::same(0.5_f32), b - Vec2F32::same(0.5_f32)]
+    };
 
-    self.path_stroke(outbuff, col, DrawListStroke::Open, thickness);
+    self.stroke_open_path(outbuff, &points, col, thickness, style);
+  }
+
+  /// Strokes an open path, as one line or as the runs of its dash pattern.
+  fn stroke_open_path(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    points: &[Vec2F32],
+    col: RGBAColor,
+    thickness: f32,
+    style: LineStyle,
+  ) {
+    let (on, off) = match style.pattern(thickness) {
+      Some(pattern) => pattern,
+      None => {
+        points.iter().for_each(|p| self.path_line_to(outbuff, *p));
+        self.path_stroke(outbuff, col, DrawListStroke::Open, thickness);
+        return;
+      }
+    };
+
+    dash_path(points, on, off).iter().for_each(|run| {
+      run.iter().for_each(|p| self.path_line_to(outbuff, *p));
+      self.path_stroke(outbuff, col, DrawListStroke::Open, thickness);
+    });
   }
 
   fn fill_rect(
@@ -453,29 +645,125 @@ impl DrawList {
     rect: RectangleF32,
     col: RGBAColor,
     rounding: f32,
+  ) {
+    self.fill_rect_corners(outbuff, rect, col, [rounding; 4]);
+  }
+
+  fn fill_rect_corners(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    rect: RectangleF32,
+    col: RGBAColor,
+    radii: [f32; 4],
   ) {
     if col.a == 0 {
       return;
     }
 
     if self.line_aa == AntialiasingType::On {
-      self.path_rect_to(
+      self.path_rect_to_corners(
         outbuff,
         Vec2F32::new(rect.x, rect.y),
         Vec2F32::new(rect.x + rect.w, rect.y + rect.h),
-        rounding,
+        radii,
       );
     } else {
-      self.path_rect_to(
+      self.path_rect_to_corners(
         outbuff,
         Vec2F32::new(rect.x - 0.5_f32, rect.y - 0.5_f32),
         Vec2F32::new(rect.x + rect.w, rect.y + rect.h),
-        rounding,
+        radii,
       );
     }
     self.path_fill(outbuff, col);
   }
 
+  /// Rectangle filled with `col`, surrounded by rings of quads that fade out
+  /// over `blur` pixels, the same way the antialiasing fringe of filled
+  /// shapes does.
+  fn fill_rect_shadow(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    rect: RectangleF32,
+    col: RGBAColor,
+    rounding: f32,
+    blur: f32,
+  ) {
+    const SHADOW_RINGS: usize = 4;
+
+    if col.a == 0 {
+      return;
+    }
+
+    self.fill_rect(outbuff, rect, col, rounding);
+    if blur <= 0_f32 {
+      return;
+    }
+
+    self.push_image(outbuff, self.config.null.texture);
+
+    // every ring has the same corner centers and 4 points per corner, only
+    // the radius grows
+    let rounding = rounding.max(0_f32).min(rect.w.min(rect.h) * 0.5_f32);
+    let corners = [
+      (Vec2F32::new(rect.x + rounding, rect.y + rounding), 6),
+      (
+        Vec2F32::new(rect.x + rect.w - rounding, rect.y + rounding),
+        9,
+      ),
+      (
+        Vec2F32::new(rect.x + rect.w - rounding, rect.y + rect.h - rounding),
+        0,
+      ),
+      (
+        Vec2F32::new(rect.x + rounding, rect.y + rect.h - rounding),
+        3,
+      ),
+    ];
+    let points_count = corners.len() * 4;
+
+    let null_uv = self.config.null.uv;
+    let first_vtx = outbuff.vertex_buff.len();
+    let first_index = outbuff.index_buff.len();
+    (0 ..= SHADOW_RINGS).for_each(|ring| {
+      let t = ring as f32 / SHADOW_RINGS as f32;
+      let radius = rounding + blur * t;
+      let mut color = RGBAColorF32::from(col);
+      color.a *= (1_f32 - t) * (1_f32 - t) * self.config.global_alpha;
+
+      corners.iter().for_each(|&(center, a_min)| {
+        (a_min .. a_min + 4).for_each(|a| {
+          let c = self.circle_vtx[a % self.circle_vtx.len()];
+          outbuff.vertex_buff.push(Self::draw_vertex(
+            center + c * radius,
+            null_uv,
+            color,
+          ));
+        });
+      });
+    });
+
+    (0 .. SHADOW_RINGS).for_each(|ring| {
+      let inner = first_vtx + ring * points_count;
+      let outer = inner + points_count;
+      (0 .. points_count).for_each(|i0| {
+        let i1 = (i0 + 1) % points_count;
+        [
+          inner + i0,
+          outer + i0,
+          outer + i1,
+          inner + i0,
+          outer + i1,
+          inner + i1,
+        ]
+        .iter()
+        .for_each(|&idx| outbuff.index_buff.push(idx as DrawIndexType));
+      });
+    });
+
+    Self::add_elements(outbuff, first_index);
+  }
+
   fn stroke_rect(
     &mut self,
     outbuff: &mut BufferOutput,
@@ -525,6 +813,7 @@ impl DrawList {
 
     let null_uv = self.config.null.uv;
     let idx = outbuff.vertex_buff.len() as u32;
+    let first_index = outbuff.index_buff.len();
 
     [
       (Vec2F32::new(rect.x, rect.y), col_left),
@@ -545,12 +834,54 @@ impl DrawList {
         .push(idx as DrawIndexType + offset as DrawIndexType)
     });
 
-    let element_count = outbuff.index_buff.len() as u32;
+    Self::add_elements(outbuff, first_index);
+  }
 
-    outbuff
-      .cmds_buff
-      .last_mut()
-      .map(|last_cmd| last_cmd.element_count = element_count);
+  /// Fills many axis aligned rectangles at once. All quads go into the
+  /// current draw command, no paths are built.
+  pub fn fill_rects(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    rects: &[(RectangleF32, RGBAColor)],
+  ) {
+    if rects.is_empty() {
+      return;
+    }
+
+    self.push_image(outbuff, self.config.null.texture);
+
+    let null_uv = self.config.null.uv;
+    let global_alpha = self.config.global_alpha;
+    let first_index = outbuff.index_buff.len();
+    outbuff.vertex_buff.reserve(rects.len() * 4);
+    outbuff.index_buff.reserve(rects.len() * 6);
+
+    rects.iter().filter(|(_, color)| color.a != 0).for_each(
+      |&(rect, color)| {
+        let mut col = RGBAColorF32::from(color);
+        col.a *= global_alpha;
+        let idx = outbuff.vertex_buff.len();
+
+        [
+          Vec2F32::new(rect.x, rect.y),
+          Vec2F32::new(rect.x + rect.w, rect.y),
+          Vec2F32::new(rect.x + rect.w, rect.y + rect.h),
+          Vec2F32::new(rect.x, rect.y + rect.h),
+        ]
+        .iter()
+        .for_each(|&pos| {
+          outbuff
+            .vertex_buff
+            .push(Self::draw_vertex(pos, null_uv, col));
+        });
+
+        [0, 1, 2, 0, 2, 3].iter().for_each(|&offset| {
+          outbuff.index_buff.push((idx + offset) as DrawIndexType)
+        });
+      },
+    );
+
+    Self::add_elements(outbuff, first_index);
   }
 
   fn stroke_triangle(
@@ -590,6 +921,18 @@ impl DrawList {
     self.path_fill(outbuff, col);
   }
 
+  /// Segment count for a circle command: its own count if set, otherwise one
+  /// derived from the radius or the fixed count from the config.
+  fn circle_segments(&self, radius: f32, segments: u16) -> u32 {
+    if segments != 0 {
+      segments as u32
+    } else if self.config.circle_quality > 0f32 {
+      Consts::circle_segments_for_radius(radius, self.config.circle_quality)
+    } else {
+      self.config.circle_segment_count
+    }
+  }
+
   fn fill_circle(
     &mut self,
     outbuff: &mut BufferOutput,
@@ -658,20 +1001,30 @@ impl DrawList {
     uvc: Vec2F32,
     color: RGBAColor,
   ) {
-    let col = RGBAColorF32::from(color);
     let uvb = Vec2F32::new(uvc.x, uva.y);
     let uvd = Vec2F32::new(uva.x, uvc.y);
 
     let b = Vec2F32::new(c.x, a.y);
     let d = Vec2F32::new(a.x, c.y);
 
+    self.push_quad_uv(outbuff, [a, b, c, d], [uva, uvb, uvc, uvd], color);
+  }
+
+  /// Quad with arbitrary corners, given clockwise starting at the top left.
+  fn push_quad_uv(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    pos: [Vec2F32; 4],
+    uv: [Vec2F32; 4],
+    color: RGBAColor,
+  ) {
+    let col = RGBAColorF32::from(color);
     let idx = outbuff.vertex_buff.len() as u32;
+    let first_index = outbuff.index_buff.len();
 
-    [(a, uva), (b, uvb), (c, uvc), (d, uvd)]
-      .into_iter()
-      .for_each(|&(v, uv)| {
-        outbuff.vertex_buff.push(Self::draw_vertex(v, uv, col));
-      });
+    pos.iter().zip(uv.iter()).for_each(|(&v, &uv)| {
+      outbuff.vertex_buff.push(Self::draw_vertex(v, uv, col));
+    });
 
     [0, 1, 2, 0, 2, 3].into_iter().for_each(|&offset| {
       outbuff
@@ -679,12 +1032,7 @@ impl DrawList {
         .push(offset as DrawIndexType + idx as u16)
     });
 
-    let element_count = outbuff.index_buff.len() as u32;
-
-    outbuff
-      .cmds_buff
-      .last_mut()
-      .map(|last_cmd| last_cmd.element_count = element_count);
+    Self::add_elements(outbuff, first_index);
   }
 
   fn add_image(
@@ -695,77 +1043,216 @@ impl DrawList {
     color: RGBAColor,
   ) {
     self.push_image(outbuff, texture.handle);
-    if texture.is_subimage() {
-      // add the region inside of the texture
-      let uv = [
-        Vec2F32::new(
-          texture.region[0] as f32 / texture.w as f32,
-          texture.region[1] as f32 / texture.h as f32,
-        ),
-        Vec2F32::new(
-          (texture.region[0] + texture.region[2]) as f32 / texture.w as f32,
-          (texture.region[1] + texture.region[3]) as f32 / texture.h as f32,
-        ),
-      ];
+    let (uva, uvc) = texture.uv();
+    self.push_rect_uv(
+      outbuff,
+      Vec2F32::new(rect.x, rect.y),
+      Vec2F32::new(rect.x + rect.w, rect.y + rect.h),
+      uva,
+      uvc,
+      color,
+    );
+  }
 
-      self.push_rect_uv(
-        outbuff,
-        Vec2F32::new(rect.x, rect.y),
-        Vec2F32::new(rect.x + rect.w, rect.y + rect.h),
-        uv[0],
-        uv[1],
-        color,
-      );
-    } else {
-      self.push_rect_uv(
-        outbuff,
-        Vec2F32::new(rect.x, rect.y),
-        Vec2F32::new(rect.x + rect.w, rect.y + rect.h),
-        Vec2F32::same(0_f32),
-        Vec2F32::same(1_f32),
-        color,
-      );
+  /// Image tinted with the RGB of `rgb_tint` (its alpha is ignored) and
+  /// drawn with opacity `alpha`.
+  fn add_image_ex(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    texture: Image,
+    rect: RectangleF32,
+    rgb_tint: RGBAColor,
+    alpha: f32,
+  ) {
+    self.add_image(outbuff, texture, rect, rgb_tint.with_alpha(alpha));
+  }
+
+  fn add_mesh(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    texture: GenericHandle,
+    vertices: &[VertexPTC],
+    indices: &[DrawIndexType],
+  ) {
+    // the indices address the whole vertex buffer (draw commands have no
+    // vertex offset), so a mesh whose vertices end past the 16 bit index
+    // range, or whose indices point outside of it, can't be drawn
+    let fits = outbuff.vertex_buff.len() + vertices.len()
+      <= DrawIndexType::MAX as usize + 1;
+    if !fits || indices.iter().any(|&idx| idx as usize >= vertices.len()) {
+      return;
     }
+
+    self.push_image(outbuff, texture);
+    let first_vertex = outbuff.vertex_buff.len() as DrawIndexType;
+    let first_index = outbuff.index_buff.len();
+    let global_alpha = self.config.global_alpha;
+    outbuff.vertex_buff.extend(vertices.iter().map(|vtx| {
+      let mut vtx = *vtx;
+      vtx.color.a *= global_alpha;
+      vtx
+    }));
+    outbuff
+      .index_buff
+      .extend(indices.iter().map(|&idx| idx + first_vertex));
+
+    Self::add_elements(outbuff, first_index);
   }
 
   fn add_text(
     &mut self,
     outbuff: &mut BufferOutput,
-    font: Font,
+    font: &Font,
     rect: RectangleF32,
     text: &str,
     _font_height: f32,
     fg: RGBAColorF32,
+    direction: TextDirection,
+    spans: &[TextColorSpan],
   ) {
     if !rect.intersect(&self.clip_rect) {
       return;
     }
 
     self.push_image(outbuff, font.texture());
-    let mut x = rect.x;
+    let rtl = direction == TextDirection::RightToLeft;
+    let mut x = if rtl { rect.x + rect.w } else { rect.x };
+    let (clip_left, clip_right) =
+      (self.clip_rect.x, self.clip_rect.x + self.clip_rect.w);
+    // start and advance of the last base glyph, combining marks are
+    // centered over it
+    let mut base = (x, 0f32);
     // process each codepoint end emit draw info
-    text.chars().for_each(|codepoint| {
+    text.chars().enumerate().for_each(|(char_idx, codepoint)| {
+      if rtl {
+        // the pen moves left, so the glyph ends where the pen was
+        x -= font.advance(codepoint, rect.x + rect.w - x);
+      }
+
       // query glyph info for this codepoint
       let glyph_info = font.query(codepoint);
       // compute quad for the codepoint's glyph
-      let gx = x + glyph_info.bearing_x;
+      let gx = if is_combining_mark(codepoint) {
+        base.0 + (base.1 - glyph_info.bbox.w as f32) * 0.5f32
+      } else {
+        base = (x, glyph_info.xadvance);
+        x + glyph_info.bearing_x
+      };
       let gy = rect.y + glyph_info.bearing_y;
       let gw = glyph_info.bbox.w as f32;
       let gh = glyph_info.bbox.h as f32;
 
+      // glyphs entirely outside the clip rect only move the pen
+      if gx + gw < clip_left || gx > clip_right {
+        if !rtl {
+          x += font.advance(codepoint, x - rect.x);
+        }
+        return;
+      }
+
+      // later spans win over earlier ones
+      let color = spans
+        .iter()
+        .rev()
+        .find(|(range, _)| range.contains(&char_idx))
+        .map_or(fg, |(_, color)| *color);
+
       self.push_rect_uv(
         outbuff,
         Vec2F32::new(gx, gy),
         Vec2F32::new(gx + gw, gy + gh),
         glyph_info.uv_top_left,
         glyph_info.uv_bottom_right,
-        RGBAColor::from(fg),
+        RGBAColor::from(color),
+      );
+
+      if !rtl {
+        x += font.advance(codepoint, x - rect.x);
+      }
+    });
+  }
+
+  /// Text whose glyph quads are rotated by `angle` radians around `origin`,
+  /// the top left corner of the unrotated text. Texture coordinates are not
+  /// changed.
+  pub fn add_text_rotated(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    font: &Font,
+    origin: Vec2F32,
+    text: &str,
+    _font_height: f32,
+    angle: f32,
+    fg: RGBAColorF32,
+  ) {
+    self.push_image(outbuff, font.texture());
+    let color = RGBAColor::from(fg);
+    let mut x = 0f32;
+    let mut base = (x, 0f32);
+
+    text.chars().for_each(|codepoint| {
+      let glyph_info = font.query(codepoint);
+      let gx = if is_combining_mark(codepoint) {
+        base.0 + (base.1 - glyph_info.bbox.w as f32) * 0.5f32
+      } else {
+        base = (x, glyph_info.xadvance);
+        x + glyph_info.bearing_x
+      };
+      let gy = glyph_info.bearing_y;
+      let gw = glyph_info.bbox.w as f32;
+      let gh = glyph_info.bbox.h as f32;
+
+      let corners = [
+        Vec2F32::new(gx, gy),
+        Vec2F32::new(gx + gw, gy),
+        Vec2F32::new(gx + gw, gy + gh),
+        Vec2F32::new(gx, gy + gh),
+      ];
+      let uva = glyph_info.uv_top_left;
+      let uvc = glyph_info.uv_bottom_right;
+
+      self.push_quad_uv(
+        outbuff,
+        [
+          origin + rotate(corners[0], angle),
+          origin + rotate(corners[1], angle),
+          origin + rotate(corners[2], angle),
+          origin + rotate(corners[3], angle),
+        ],
+        [
+          uva,
+          Vec2F32::new(uvc.x, uva.y),
+          uvc,
+          Vec2F32::new(uva.x, uvc.y),
+        ],
+        color,
       );
 
-      x += glyph_info.xadvance;
+      x += font.advance(codepoint, x);
     });
   }
 
+  /// Text reading from bottom to top, as used for vertical axis labels.
+  pub fn add_text_vertical(
+    &mut self,
+    outbuff: &mut BufferOutput,
+    font: &Font,
+    origin: Vec2F32,
+    text: &str,
+    font_height: f32,
+    fg: RGBAColorF32,
+  ) {
+    self.add_text_rotated(
+      outbuff,
+      font,
+      origin,
+      text,
+      font_height,
+      -std::f32::consts::FRAC_PI_2,
+      fg,
+    );
+  }
+
   pub fn convert<'a>(
     &mut self,
     cmds: &[*const Command],
@@ -778,10 +1265,29 @@ impl DrawList {
       vertex_buff: vertex_buffer,
       index_buff:  index_buffer,
     };
+
+    // a user path left open would get mixed into the converted commands
+    debug_assert!(
+      self.path.borrow().is_empty(),
+      "Unfinished path, call path_stroke()/path_fill() before convert()"
+    );
+    self.path.borrow_mut().reset();
+    // the clip rectangle of the commands converted before doesn't apply
+    self.clip_rect = Consts::null_rect();
+
+    self.stats = DrawListStats::default();
+    let (vertices_start, indices_start, cmds_start) = (
+      outbuff.vertex_buff.len(),
+      outbuff.index_buff.len(),
+      outbuff.cmds_buff.len(),
+    );
+
     cmds.iter().for_each(|input_cmd| {
       let input_cmd = unsafe { &**input_cmd };
+      let cmd_vertices_start = outbuff.vertex_buff.len();
       match input_cmd {
         Command::Scissor(ref s) => {
+          self.stats.clip_changes += 1;
           self.add_clip(
             &mut outbuff,
             RectangleF32::new(
@@ -800,6 +1306,7 @@ impl DrawList {
             Vec2F32::new(l.end.x as f32, l.end.y as f32),
             l.color,
             l.line_thickness as f32,
+            l.style,
           );
         }
 
@@ -835,6 +1342,30 @@ impl DrawList {
           );
         }
 
+        Command::RectShadow(ref r) => {
+          self.fill_rect_shadow(
+            &mut outbuff,
+            RectangleF32::new(r.x as f32, r.y as f32, r.w as f32, r.h as f32),
+            r.color,
+            r.rounding as f32,
+            r.blur as f32,
+          );
+        }
+
+        Command::RectFilledCorners(ref r) => {
+          self.fill_rect_corners(
+            &mut outbuff,
+            RectangleF32::new(r.x as f32, r.y as f32, r.w as f32, r.h as f32),
+            r.color,
+            [
+              r.radii[0] as f32,
+              r.radii[1] as f32,
+              r.radii[2] as f32,
+              r.radii[3] as f32,
+            ],
+          );
+        }
+
         Command::RectMulticolor(ref r) => {
           self.fill_rect_multi_color(
             &mut outbuff,
@@ -855,7 +1386,7 @@ impl DrawList {
             ),
             (c.w / 2) as f32,
             c.color,
-            self.config.circle_segment_count,
+            self.circle_segments((c.w / 2) as f32, c.segments),
             c.line_thickness as f32,
           );
         }
@@ -869,7 +1400,7 @@ impl DrawList {
             ),
             (c.w / 2) as f32,
             c.color,
-            self.config.circle_segment_count,
+            self.circle_segments((c.w / 2) as f32, c.segments),
           );
         }
 
@@ -950,26 +1481,30 @@ impl DrawList {
         }
 
         Command::Polyline(ref p) => {
-          p.points.iter().for_each(|p| {
-            let pnt = Vec2F32::new(p.x as f32, p.y as f32);
-            self.path_line_to(&mut outbuff, pnt);
-          });
-          self.path_stroke(
+          let points = p
+            .points
+            .iter()
+            .map(|p| Vec2F32::new(p.x as f32, p.y as f32))
+            .collect::<Vec<_>>();
+          self.stroke_open_path(
             &mut outbuff,
+            &points,
             p.color,
-            DrawListStroke::Open,
             p.line_thickness as f32,
+            p.style,
           );
         }
 
         Command::Text(ref t) => {
           self.add_text(
             &mut outbuff,
-            t.font,
+            &t.font,
             RectangleF32::new(t.x as f32, t.y as f32, t.w as f32, t.h as f32),
             &t.text,
             t.height,
             RGBAColorF32::from(t.foreground),
+            t.direction,
+            &t.spans,
           );
         }
 
@@ -982,10 +1517,1050 @@ impl DrawList {
           );
         }
 
+        Command::Mesh(ref m) => {
+          self.add_mesh(&mut outbuff, m.texture, &m.vertices, &m.indices);
+        }
+
         _ => {
           println!("Unhandled command");
         }
       }
+
+      self.stats.max_command_vertices = self
+        .stats
+        .max_command_vertices
+        .max(outbuff.vertex_buff.len() - cmd_vertices_start);
+    });
+
+    if self.config.snap_to_pixel {
+      outbuff.vertex_buff[vertices_start ..]
+        .iter_mut()
+        .for_each(|vtx| {
+          vtx.pos = Vec2F32::new(vtx.pos.x.round(), vtx.pos.y.round());
+        });
+    }
+
+    if self.config.premultiplied_alpha {
+      outbuff.vertex_buff[vertices_start ..]
+        .iter_mut()
+        .for_each(|vtx| {
+          vtx.color.r *= vtx.color.a;
+          vtx.color.g *= vtx.color.a;
+          vtx.color.b *= vtx.color.a;
+        });
+    }
+
+    coalesce_draw_commands(outbuff.cmds_buff, cmds_start);
+
+    self.stats.vertices = outbuff.vertex_buff.len() - vertices_start;
+    self.stats.indices = outbuff.index_buff.len() - indices_start;
+    self.stats.draw_commands = outbuff.cmds_buff.len() - cmds_start;
+  }
+}
+
+/// Merges neighbouring draw commands, starting at `first`, that use the same
+/// clip rectangle and texture, so the renderer changes the scissor and the
+/// texture only when needed. The merged command draws the indices of both.
+/// Works in place, the buffer is not reallocated.
+pub fn coalesce_draw_commands(cmds: &mut Vec<DrawCommand>, first: usize) {
+  if first >= cmds.len() {
+    return;
+  }
+
+  let mut last = first;
+  (first + 1 .. cmds.len()).for_each(|i| {
+    let cmd = cmds[i];
+    if cmds[last].texture == cmd.texture
+      && cmds[last].clip_rect == cmd.clip_rect
+    {
+      cmds[last].element_count += cmd.element_count;
+    } else {
+      last += 1;
+      cmds[last] = cmd;
+    }
+  });
+  cmds.truncate(last + 1);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::commands::CommandBuffer;
+
+  /// Vectors the tests convert into, `output()` hands them to the draw list.
+  #[derive(Default)]
+  struct TestBuffers {
+    cmds:     Vec<DrawCommand>,
+    vertices: Vec<VertexPTC>,
+    indices:  Vec<DrawIndexType>,
+  }
+
+  impl TestBuffers {
+    fn output(&mut self) -> BufferOutput<'_> {
+      BufferOutput {
+        cmds_buff:   &mut self.cmds,
+        vertex_buff: &mut self.vertices,
+        index_buff:  &mut self.indices,
+      }
+    }
+  }
+
+  #[test]
+  fn test_unset_null_texture() {
+    let dl = DrawList::new(
+      ConvertConfig::for_tests(),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+    assert!(!dl.has_null_texture());
+
+    let dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+    assert!(dl.has_null_texture());
+  }
+
+  #[test]
+  fn test_invalid_config_is_rejected() {
+    let invalid = ConvertConfig {
+      vertex_size: 20,
+      ..ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1))
+    };
+    let err = DrawList::new(
+      invalid.clone(),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap_err();
+    assert!(err.contains("vertex_size is 20 bytes"));
+
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+    assert!(dl.set_config(invalid).is_err());
+    // the valid config is kept
+    assert_eq!(dl.generation(), 0);
+    assert!(dl.has_null_texture());
+  }
+
+  #[test]
+  fn test_path_bezier_stroke() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    dl.path_begin();
+    dl.path_line_to(&mut outbuff, Vec2F32::new(0f32, 0f32));
+    dl.path_curve_to(
+      &mut outbuff,
+      Vec2F32::new(10f32, 20f32),
+      Vec2F32::new(20f32, 20f32),
+      Vec2F32::new(30f32, 0f32),
+      8,
+    );
+    dl.path_curve_to(
+      &mut outbuff,
+      Vec2F32::new(40f32, -20f32),
+      Vec2F32::new(50f32, -20f32),
+      Vec2F32::new(60f32, 0f32),
+      8,
+    );
+    dl.path_stroke(
+      &mut outbuff,
+      RGBAColor::new(255, 0, 0),
+      DrawListStroke::Open,
+      1f32,
+    );
+
+    assert!(!outbuff.vertex_buff.is_empty());
+    assert!(!outbuff.index_buff.is_empty());
+    assert!(dl.path.borrow().is_empty());
+  }
+
+  #[test]
+  fn test_add_image_region_uv() {
+    use crate::math::rectangle::RectangleI32;
+
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let sprite = Image::sub_image(
+      GenericHandle::Id(2),
+      256,
+      128,
+      RectangleI32::new(64, 32, 32, 32),
+    );
+    dl.add_image(
+      &mut outbuff,
+      sprite,
+      RectangleF32::new(0f32, 0f32, 32f32, 32f32),
+      RGBAColor::new(255, 255, 255),
+    );
+
+    let top_left = outbuff.vertex_buff[0].texcoords;
+    let bottom_right = outbuff.vertex_buff[2].texcoords;
+    assert_eq!((top_left.x, top_left.y), (0.25f32, 0.25f32));
+    assert_eq!((bottom_right.x, bottom_right.y), (0.375f32, 0.5f32));
+  }
+
+  #[test]
+  fn test_image_alpha_is_separate_from_tint() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let icon = Image::whole(GenericHandle::Id(2), 16, 16);
+    let rect = RectangleF32::new(0f32, 0f32, 16f32, 16f32);
+    let tint = RGBAColor::new(255, 0, 255);
+    dl.add_image_ex(&mut outbuff, icon, rect, tint, 1f32);
+    dl.add_image_ex(&mut outbuff, icon, rect, tint, 0.5f32);
+
+    let (opaque, faded) = (outbuff.vertex_buff[0], outbuff.vertex_buff[4]);
+    assert!((faded.color.a - opaque.color.a * 0.5f32).abs() < 1f32 / 255f32);
+    assert_eq!(
+      (faded.color.r, faded.color.g, faded.color.b),
+      (1f32, 0f32, 1f32)
+    );
+  }
+
+  #[test]
+  fn test_shadow_blur_adds_translucent_fringe() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let rect = RectangleF32::new(20f32, 20f32, 60f32, 40f32);
+    let color = RGBAColor::new_with_alpha(0, 0, 0, 200);
+    let outside = |v: &VertexPTC| {
+      v.pos.x < rect.x - 1f32
+        || v.pos.y < rect.y - 1f32
+        || v.pos.x > rect.x + rect.w + 1f32
+        || v.pos.y > rect.y + rect.h + 1f32
+    };
+
+    dl.fill_rect_shadow(&mut outbuff, rect, color, 4f32, 0f32);
+    assert!(!outbuff.vertex_buff.iter().any(|v| outside(v)));
+
+    dl.fill_rect_shadow(&mut outbuff, rect, color, 4f32, 8f32);
+    let alpha = RGBAColorF32::from(color).a;
+    let fringe = outbuff
+      .vertex_buff
+      .iter()
+      .filter(|v| outside(v))
+      .collect::<Vec<_>>();
+    assert!(fringe.iter().any(|v| v.color.a > 0f32 && v.color.a < alpha));
+    assert!(fringe.iter().all(|v| v.color.a < alpha));
+    assert!(fringe
+      .iter()
+      .all(|v| v.pos.x >= rect.x - 8.5f32 && v.pos.y >= rect.y - 8.5f32));
+  }
+
+  #[test]
+  fn test_tabs_align_to_tab_stops() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let rect = RectangleF32::new(10f32, 0f32, 200f32, 20f32);
+    let white = RGBAColorF32::new(1f32, 1f32, 1f32);
+    dl.add_text(
+      &mut outbuff,
+      &font,
+      rect,
+      "\ta",
+      0f32,
+      white,
+      TextDirection::LeftToRight,
+      &[],
+    );
+    let first_x = outbuff.vertex_buff.last().unwrap().pos.x;
+    dl.add_text(
+      &mut outbuff,
+      &font,
+      rect,
+      "ab\ta",
+      0f32,
+      white,
+      TextDirection::LeftToRight,
+      &[],
+    );
+    let second_x = outbuff.vertex_buff.last().unwrap().pos.x;
+
+    assert_eq!(first_x, second_x);
+    assert_eq!(first_x, rect.x + 32f32);
+    assert_eq!(font.text_width("\t"), font.text_width("ab\t"));
+  }
+
+  #[test]
+  fn test_rtl_text_starts_at_right_edge() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let rect = RectangleF32::new(10f32, 0f32, 100f32, 20f32);
+    dl.add_text(
+      &mut outbuff,
+      &font,
+      rect,
+      "abc",
+      0f32,
+      RGBAColorF32::new(1f32, 1f32, 1f32),
+      TextDirection::RightToLeft,
+      &[],
+    );
+
+    // first glyph ends at the right edge, the last one is leftmost
+    assert_eq!(outbuff.vertex_buff[0].pos.x, rect.x + rect.w - 8f32);
+    assert_eq!(outbuff.vertex_buff[8].pos.x, rect.x + rect.w - 24f32);
+  }
+
+  #[test]
+  fn test_text_skips_glyphs_outside_clip() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    // 10 glyphs wide clip somewhere in the middle of a 1000 glyph line
+    dl.add_clip(&mut outbuff, RectangleF32::new(4000f32, 0f32, 80f32, 20f32));
+    let text = "x".repeat(1000);
+    dl.add_text(
+      &mut outbuff,
+      &font,
+      RectangleF32::new(0f32, 0f32, 8000f32, 20f32),
+      &text,
+      0f32,
+      RGBAColorF32::new(1f32, 1f32, 1f32),
+      TextDirection::LeftToRight,
+      &[],
+    );
+
+    let quads = outbuff.vertex_buff.len() / 4;
+    assert!(quads > 0 && quads <= 12);
+    // the pen kept advancing over the skipped glyphs
+    assert!(outbuff.vertex_buff[0].pos.x >= 4000f32 - 8f32);
+  }
+
+  #[test]
+  fn test_convert_starts_unclipped() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+    let convert = |dl: &mut DrawList, cmd_buff: &CommandBuffer| {
+      let cmds = cmd_buff
+        .commands()
+        .iter()
+        .map(|cmd| cmd as *const Command)
+        .collect::<Vec<_>>();
+      let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+      vertices
+    };
+
+    // a window whose last clip rectangle is far away from the text
+    let mut window = CommandBuffer::new(Some(Consts::null_rect()), 16);
+    window.push_scissor(RectangleF32::new(4000f32, 0f32, 80f32, 20f32));
+    convert(&mut dl, &window);
+
+    let mut overlay = CommandBuffer::new(Some(Consts::null_rect()), 16);
+    overlay.draw_text(
+      RectangleF32::new(0f32, 0f32, 80f32, 20f32),
+      "xxxxxxxxxx",
+      &font,
+      RGBAColor::new(0, 0, 0),
+      RGBAColor::new(255, 255, 255),
+    );
+    assert_eq!(convert(&mut dl, &overlay).len(), 40);
+  }
+
+  #[test]
+  fn test_mesh_passes_through() {
+    let texture = GenericHandle::Id(7);
+    let white = RGBAColorF32::new(1f32, 1f32, 1f32);
+    let vertex = |x: f32, y: f32| VertexPTC {
+      pos:       Vec2F32::new(x, y),
+      texcoords: Vec2F32::new(x / 10f32, y / 10f32),
+      color:     white,
+    };
+    let vertices = [
+      vertex(0f32, 0f32),
+      vertex(10f32, 0f32),
+      vertex(10f32, 10f32),
+      vertex(0f32, 10f32),
+    ];
+    let indices = [0, 1, 2, 0, 2, 3];
+
+    let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+    out.draw_mesh(texture, &vertices, &indices);
+    // completely clipped meshes are dropped
+    let mut clipped = CommandBuffer::new(
+      Some(RectangleF32::new(100f32, 100f32, 10f32, 10f32)),
+      8,
+    );
+    clipped.draw_mesh(texture, &vertices, &indices);
+    assert!(clipped.is_empty());
+
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+    let cmds = out
+      .commands()
+      .iter()
+      .map(|cmd| cmd as *const Command)
+      .collect::<Vec<_>>();
+    let mut draw_cmds = vec![];
+    let mut vertex_buff = vec![];
+    let mut index_buff = vec![];
+    dl.convert(&cmds, &mut vertex_buff, &mut index_buff, &mut draw_cmds);
+
+    assert_eq!(index_buff, indices.to_vec());
+    assert_eq!(vertex_buff.len(), vertices.len());
+    vertex_buff
+      .iter()
+      .zip(vertices.iter())
+      .for_each(|(out, v)| {
+        assert_eq!((out.pos.x, out.pos.y), (v.pos.x, v.pos.y));
+        assert_eq!(
+          (out.texcoords.x, out.texcoords.y),
+          (v.texcoords.x, v.texcoords.y)
+        );
+      });
+    assert_eq!(draw_cmds.last().unwrap().texture, texture);
+    assert_eq!(draw_cmds.last().unwrap().element_count, 6);
+  }
+
+  #[test]
+  fn test_mesh_past_the_index_range_is_dropped() {
+    let vertex = |x: f32, y: f32| VertexPTC {
+      pos:       Vec2F32::new(x, y),
+      texcoords: Vec2F32::new(0f32, 0f32),
+      color:     RGBAColorF32::new(1f32, 1f32, 1f32),
+    };
+    let vertices =
+      [vertex(0f32, 0f32), vertex(10f32, 0f32), vertex(0f32, 10f32)];
+
+    let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+    out.draw_mesh(GenericHandle::Id(7), &vertices, &[0, 1, 2]);
+    // index 3 is not one of the mesh's vertices
+    out.draw_mesh(GenericHandle::Id(7), &vertices, &[0, 1, 3]);
+    let cmds = out
+      .commands()
+      .iter()
+      .map(|cmd| cmd as *const Command)
+      .collect::<Vec<_>>();
+
+    let mut dl = DrawList::new(
+      ConvertConfig {
+        global_alpha: 0.5f32,
+        ..ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1))
+      },
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+    let (mut draw_cmds, mut vertex_buff, mut index_buff) =
+      (vec![], vec![], vec![]);
+    dl.convert(&cmds, &mut vertex_buff, &mut index_buff, &mut draw_cmds);
+    assert_eq!(index_buff, vec![0, 1, 2]);
+    assert!(vertex_buff.iter().all(|vtx| vtx.color.a == 0.5f32));
+
+    // the buffer already holds enough vertices that the mesh's indices
+    // would wrap around
+    let mut vertex_buff =
+      vec![vertex(0f32, 0f32); DrawIndexType::MAX as usize - 1];
+    let mut index_buff = vec![];
+    dl.convert(&cmds, &mut vertex_buff, &mut index_buff, &mut draw_cmds);
+    assert_eq!(vertex_buff.len(), DrawIndexType::MAX as usize - 1);
+    assert!(index_buff.is_empty());
+  }
+
+  #[test]
+  fn test_snap_to_pixel_rounds_vertices() {
+    let white = RGBAColorF32::new(1f32, 1f32, 1f32);
+    let vertex = |x: f32, y: f32| VertexPTC {
+      pos:       Vec2F32::new(x, y),
+      texcoords: Vec2F32::new(0f32, 0f32),
+      color:     white,
+    };
+    // a rect at x = 10.3, 32.4 pixels wide
+    let vertices = [
+      vertex(10.3f32, 5.6f32),
+      vertex(42.7f32, 5.6f32),
+      vertex(42.7f32, 20.2f32),
+      vertex(10.3f32, 20.2f32),
+    ];
+
+    let mut out = CommandBuffer::new(Some(Consts::null_rect()), 8);
+    out.draw_mesh(GenericHandle::Id(1), &vertices, &[0, 1, 2, 0, 2, 3]);
+    let cmds = out
+      .commands()
+      .iter()
+      .map(|cmd| cmd as *const Command)
+      .collect::<Vec<_>>();
+
+    let positions = |snap_to_pixel: bool| {
+      let mut dl = DrawList::new(
+        ConvertConfig {
+          snap_to_pixel,
+          ..ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1))
+        },
+        AntialiasingType::Off,
+        AntialiasingType::Off,
+      )
+      .unwrap();
+      let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+      vertices
+        .iter()
+        .map(|v| (v.pos.x, v.pos.y))
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+      positions(true),
+      vec![(10f32, 6f32), (43f32, 6f32), (43f32, 20f32), (10f32, 20f32)]
+    );
+    // without snapping the vertices are kept as they are
+    assert_eq!(positions(false)[0], (10.3f32, 5.6f32));
+  }
+
+  #[test]
+  fn test_convert_stats() {
+    use crate::hmi::commands::CommandBuffer;
+
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut cmd_buff = CommandBuffer::new(Some(Consts::null_rect()), 16);
+    let r = RectangleF32::new(0f32, 0f32, 32f32, 32f32);
+    cmd_buff.fill_rect(r, 0f32, RGBAColor::new(255, 0, 0));
+    cmd_buff.draw_image(
+      r,
+      Image::whole(GenericHandle::Id(2), 32, 32),
+      RGBAColor::new(255, 255, 255),
+    );
+    cmd_buff.fill_rect(r, 0f32, RGBAColor::new(0, 255, 0));
+    cmd_buff.push_scissor(RectangleF32::new(0f32, 0f32, 16f32, 16f32));
+
+    let cmds = cmd_buff
+      .commands()
+      .iter()
+      .map(|cmd| cmd as *const Command)
+      .collect::<Vec<_>>();
+
+    let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+    dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+
+    let stats = dl.last_stats();
+    assert_eq!(stats.vertices, vertices.len());
+    assert_eq!(stats.indices, indices.len());
+    assert_eq!(stats.draw_commands, draw_cmds.len());
+    assert_eq!(stats.clip_changes, 1);
+    // white pixel -> image -> white pixel
+    assert_eq!(stats.texture_switches, 2);
+    assert_eq!(stats.max_command_vertices, 4);
+    assert_eq!(stats.vertices, 12);
+  }
+
+  #[test]
+  fn test_path_rect_per_corner_radii() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let a = Vec2F32::new(0f32, 0f32);
+    let b = Vec2F32::new(100f32, 50f32);
+    dl.path_begin();
+    dl.path_rect_to_corners(&mut outbuff, a, b, [8f32, 0f32, 16f32, 0f32]);
+
+    let path = dl.path.borrow().elements().to_vec();
+    dl.path_begin();
+
+    // two arcs of 4 points each and two sharp corners
+    assert_eq!(path.len(), 10);
+
+    let dist = |p: Vec2F32, c: Vec2F32| {
+      ((p.x - c.x) * (p.x - c.x) + (p.y - c.y) * (p.y - c.y)).sqrt()
+    };
+    let tl_center = Vec2F32::new(8f32, 8f32);
+    path[0 .. 4]
+      .iter()
+      .for_each(|p| assert!((dist(*p, tl_center) - 8f32).abs() < 1.0e-4f32));
+    let br_center = Vec2F32::new(84f32, 34f32);
+    path[5 .. 9]
+      .iter()
+      .for_each(|p| assert!((dist(*p, br_center) - 16f32).abs() < 1.0e-4f32));
+
+    assert_eq!((path[4].x, path[4].y), (b.x, a.y));
+    assert_eq!((path[9].x, path[9].y), (a.x, b.y));
+  }
+
+  #[test]
+  fn test_rounded_rect_arcs_span_their_own_corners() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    // the table goes once around the circle, a quarter turn every 3 entries
+    let near = |p: Vec2F32, x: f32, y: f32| {
+      (p.x - x).abs() < 1.0e-4f32 && (p.y - y).abs() < 1.0e-4f32
+    };
+    assert!(near(dl.circle_vtx[0], 1f32, 0f32));
+    assert!(near(dl.circle_vtx[3], 0f32, 1f32));
+    assert!(near(dl.circle_vtx[6], -1f32, 0f32));
+    assert!(near(dl.circle_vtx[9], 0f32, -1f32));
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    dl.path_begin();
+    dl.path_rect_to(
+      &mut outbuff,
+      Vec2F32::new(0f32, 0f32),
+      Vec2F32::new(100f32, 50f32),
+      10f32,
+    );
+    let path = dl.path.borrow().elements().to_vec();
+    dl.path_begin();
+
+    // clockwise from the top-left corner, each arc a quarter turn that
+    // starts and ends on the edges next to its corner
+    assert_eq!(path.len(), 16);
+    [
+      ((0f32, 10f32), (10f32, 0f32)),
+      ((90f32, 0f32), (100f32, 10f32)),
+      ((100f32, 40f32), (90f32, 50f32)),
+      ((10f32, 50f32), (0f32, 40f32)),
+    ]
+    .iter()
+    .enumerate()
+    .for_each(|(corner, &((x0, y0), (x1, y1)))| {
+      assert!(near(path[corner * 4], x0, y0));
+      assert!(near(path[corner * 4 + 3], x1, y1));
+    });
+  }
+
+  #[test]
+  fn test_huge_rounding_is_clamped_to_capsule() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let a = Vec2F32::new(0f32, 0f32);
+    let b = Vec2F32::new(40f32, 20f32);
+    dl.path_begin();
+    dl.path_rect_to(&mut outbuff, a, b, 1000f32);
+
+    let path = dl.path.borrow().elements().to_vec();
+    dl.path_begin();
+
+    const EPS: f32 = 1.0e-3f32;
+    path.iter().for_each(|p| {
+      assert!(p.x >= a.x - EPS && p.x <= b.x + EPS);
+      assert!(p.y >= a.y - EPS && p.y <= b.y + EPS);
+    });
+
+    // the ends are half circles with a radius of half the height
+    let dist = |p: Vec2F32, c: Vec2F32| {
+      ((p.x - c.x) * (p.x - c.x) + (p.y - c.y) * (p.y - c.y)).sqrt()
+    };
+    let left = Vec2F32::new(10f32, 10f32);
+    path[0 .. 4]
+      .iter()
+      .for_each(|p| assert!((dist(*p, left) - 10f32).abs() < 1.0e-4f32));
+
+    // the outline keeps turning the same way, it doesn't cross itself
+    let turns = (0 .. path.len())
+      .map(|i| {
+        let (p0, p1, p2) = (
+          path[i],
+          path[(i + 1) % path.len()],
+          path[(i + 2) % path.len()],
+        );
+        (p1.x - p0.x) * (p2.y - p1.y) - (p1.y - p0.y) * (p2.x - p1.x)
+      })
+      .collect::<Vec<_>>();
+    assert!(
+      turns.iter().all(|t| *t >= -EPS) || turns.iter().all(|t| *t <= EPS)
+    );
+  }
+
+  #[test]
+  fn test_vertical_text_is_taller_than_wide() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    dl.add_text_vertical(
+      &mut outbuff,
+      &font,
+      Vec2F32::new(50f32, 100f32),
+      "axis",
+      8f32,
+      RGBAColorF32::new(1f32, 1f32, 1f32),
+    );
+
+    let (min, max) = outbuff.vertex_buff.iter().fold(
+      (Vec2F32::same(std::f32::MAX), Vec2F32::same(std::f32::MIN)),
+      |(min, max), vtx| {
+        (
+          Vec2F32::new(min.x.min(vtx.pos.x), min.y.min(vtx.pos.y)),
+          Vec2F32::new(max.x.max(vtx.pos.x), max.y.max(vtx.pos.y)),
+        )
+      },
+    );
+
+    assert!(max.y - min.y > max.x - min.x);
+    // reads upwards from the origin
+    assert!(max.y <= 100f32 + 1.0e-4f32);
+  }
+
+  #[test]
+  fn test_text_color_spans() {
+    use crate::hmi::text_engine::FontAtlas;
+
+    let font = FontAtlas::monospace_for_tests(8f32);
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+
+    let white = RGBAColorF32::new(1f32, 1f32, 1f32);
+    let red = RGBAColorF32::new(1f32, 0f32, 0f32);
+    dl.add_text(
+      &mut outbuff,
+      &font,
+      RectangleF32::new(0f32, 0f32, 100f32, 20f32),
+      "fn main",
+      0f32,
+      white,
+      TextDirection::LeftToRight,
+      &[(0 .. 3, red)],
+    );
+
+    // 4 vertices per glyph
+    outbuff
+      .vertex_buff
+      .chunks(4)
+      .enumerate()
+      .for_each(|(idx, quad)| {
+        let expected = if idx < 3 { red } else { white };
+        quad.iter().for_each(|vtx| {
+          assert_eq!(
+            (vtx.color.r, vtx.color.g, vtx.color.b),
+            (expected.r, expected.g, expected.b)
+          );
+        });
+      });
+    assert_eq!(outbuff.vertex_buff.len(), 7 * 4);
+  }
+
+  #[test]
+  fn test_fill_rects_single_command() {
+    let mut dl = DrawList::new(
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+      AntialiasingType::Off,
+      AntialiasingType::Off,
+    )
+    .unwrap();
+
+    let rects = (0 .. 100)
+      .map(|i| {
+        (
+          RectangleF32::new(
+            (i % 10) as f32 * 20f32,
+            (i / 10) as f32 * 20f32,
+            16f32,
+            16f32,
+          ),
+          RGBAColor::new(0, 255, 0),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    let mut buffers = TestBuffers::default();
+    let mut outbuff = buffers.output();
+    dl.fill_rects(&mut outbuff, &rects);
+
+    assert_eq!(buffers.cmds.len(), 1);
+    assert_eq!(buffers.cmds[0].element_count, 600);
+    assert_eq!(buffers.indices.len(), 600);
+    assert_eq!(buffers.vertices.len(), 400);
+    let corner = buffers.vertices[4].pos;
+    assert_eq!((corner.x, corner.y), (20f32, 0f32));
+  }
+
+  #[test]
+  fn test_coalesce_draw_commands() {
+    let clip = RectangleF32::new(0f32, 0f32, 100f32, 50f32);
+    let cmd = |element_count, clip_rect, texture| DrawCommand {
+      element_count,
+      clip_rect,
+      texture,
+    };
+
+    let mut cmds = vec![
+      cmd(6, Consts::null_rect(), GenericHandle::Id(1)),
+      cmd(12, clip, GenericHandle::Id(1)),
+      cmd(18, clip, GenericHandle::Id(1)),
+      cmd(24, clip, GenericHandle::Id(1)),
+      cmd(30, clip, GenericHandle::Id(2)),
+    ];
+    coalesce_draw_commands(&mut cmds, 0);
+
+    assert_eq!(cmds.len(), 3);
+    assert_eq!(cmds[0].element_count, 6);
+    assert_eq!((cmds[1].element_count, cmds[1].clip_rect), (54, clip));
+    assert_eq!(
+      (cmds[2].element_count, cmds[2].texture),
+      (30, GenericHandle::Id(2))
+    );
+
+    // commands before `first` are left alone
+    let mut cmds = vec![
+      cmd(6, clip, GenericHandle::Id(1)),
+      cmd(12, clip, GenericHandle::Id(1)),
+      cmd(18, clip, GenericHandle::Id(1)),
+    ];
+    coalesce_draw_commands(&mut cmds, 1);
+    assert_eq!(cmds.len(), 2);
+    assert_eq!(cmds[1].element_count, 30);
+  }
+
+  #[test]
+  fn test_coalesced_commands_render_the_same() {
+    use crate::sys::headless::HeadlessTarget;
+
+    let (red, green) = (GenericHandle::Id(7), GenericHandle::Id(8));
+    let white = RGBAColorF32::new(1f32, 1f32, 1f32);
+    // three quads side by side, one command each
+    let vertices = (0 .. 3)
+      .flat_map(|i| {
+        let x = i as f32 * 4f32;
+        vec![
+          VertexPTC {
+            pos:       Vec2F32::new(x, 0f32),
+            texcoords: Vec2F32::new(0f32, 0f32),
+            color:     white,
+          },
+          VertexPTC {
+            pos:       Vec2F32::new(x + 4f32, 0f32),
+            texcoords: Vec2F32::new(1f32, 0f32),
+            color:     white,
+          },
+          VertexPTC {
+            pos:       Vec2F32::new(x + 4f32, 4f32),
+            texcoords: Vec2F32::new(1f32, 1f32),
+            color:     white,
+          },
+          VertexPTC {
+            pos:       Vec2F32::new(x, 4f32),
+            texcoords: Vec2F32::new(0f32, 1f32),
+            color:     white,
+          },
+        ]
+      })
+      .collect::<Vec<_>>();
+    let indices = (0 .. 3)
+      .flat_map(|i| {
+        [0, 1, 2, 0, 2, 3]
+          .iter()
+          .map(|&idx| (i * 4 + idx) as DrawIndexType)
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    let cmd = |texture| DrawCommand {
+      element_count: 6,
+      clip_rect: Consts::null_rect(),
+      texture,
+    };
+    let cmds = vec![cmd(red), cmd(red), cmd(green)];
+
+    let render = |cmds: &[DrawCommand]| {
+      let mut target = HeadlessTarget::new(12, 4);
+      target.add_texture(red, 1, 1, &[255, 0, 0, 255]);
+      target.add_texture(green, 1, 1, &[0, 255, 0, 255]);
+      target.render(&vertices, &indices, cmds);
+      target
+    };
+
+    let mut merged = cmds.clone();
+    coalesce_draw_commands(&mut merged, 0);
+    assert_eq!(merged.len(), 2);
+
+    let target = render(&merged);
+    assert_eq!(target.to_rgba8(), render(&cmds).to_rgba8());
+    assert_eq!(target.pixel(2, 2), RGBAColor::new(255, 0, 0));
+    assert_eq!(target.pixel(6, 2), RGBAColor::new(255, 0, 0));
+    assert_eq!(target.pixel(10, 2), RGBAColor::new(0, 255, 0));
+  }
+
+  #[test]
+  fn test_dashed_line_is_split_into_runs() {
+    use crate::hmi::commands::{CommandBuffer, LineStyle};
+
+    let convert = |style: LineStyle| {
+      let mut dl = DrawList::new(
+        ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1)),
+        AntialiasingType::On,
+        AntialiasingType::Off,
+      )
+      .unwrap();
+      let mut cmd_buff = CommandBuffer::new(Some(Consts::null_rect()), 16);
+      cmd_buff.stroke_line_styled(
+        0f32,
+        10f32,
+        40f32,
+        10f32,
+        2f32,
+        RGBAColor::new(255, 255, 255),
+        style,
+      );
+
+      let cmds = cmd_buff
+        .commands()
+        .iter()
+        .map(|cmd| cmd as *const Command)
+        .collect::<Vec<_>>();
+      let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+      dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+      vertices
+    };
+
+    // one quad for the solid line
+    assert_eq!(convert(LineStyle::Solid).len(), 4);
+
+    // 40 pixels of 4 on / 4 off
+    let dashed = convert(LineStyle::Dashed {
+      dash: 4f32,
+      gap:  4f32,
+    });
+    assert_eq!(dashed.len(), 5 * 4);
+    let starts = dashed
+      .chunks(4)
+      .map(|quad| quad[0].pos.x.round() as i32)
+      .collect::<Vec<_>>();
+    assert_eq!(starts, vec![0, 8, 16, 24, 32]);
+
+    // runs shorter than half a pixel are lengthened
+    let tiny = convert(LineStyle::Dashed {
+      dash: 0.001f32,
+      gap:  0.001f32,
     });
+    assert!(!tiny.is_empty() && tiny.len() <= 40 * 4);
+
+    // the pattern continues around corners
+    let runs = dash_path(
+      &[
+        Vec2F32::new(0f32, 0f32),
+        Vec2F32::new(6f32, 0f32),
+        Vec2F32::new(6f32, 6f32),
+      ],
+      4f32,
+      1f32,
+    );
+    assert_eq!(runs.len(), 3);
+    let corner = runs[1].iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+    assert_eq!(corner, vec![(5f32, 0f32), (6f32, 0f32), (6f32, 3f32)]);
   }
 }
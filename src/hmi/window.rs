@@ -1,13 +1,17 @@
 use crate::{
   hmi::{
-    base::HashType,
+    base::{HashType, TextAlign},
     commands::CommandBuffer,
     panel::{Panel, PanelFlags, PanelType, PopupBuffer},
+    ui_context::CollapseStates,
+  },
+  math::{
+    rectangle::RectangleF32,
+    vec2::{Vec2F32, Vec2U32},
   },
-  math::{rectangle::RectangleF32, vec2::Vec2U32},
 };
 use enumflags2::BitFlags;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 #[derive(Clone, Debug)]
 pub struct PopupState {
@@ -21,6 +25,11 @@ pub struct PopupState {
   pub con_old:     u32,
   pub active_con:  u32,
   pub header:      RectangleF32,
+  /// Drop down of a combo box that opened above its header, the widgets
+  /// under it ignore the mouse while it is open.
+  pub list:        Option<RectangleF32>,
+  /// First item shown in the drop down of the open combo box.
+  pub scroll:      u32,
 }
 
 impl std::default::Default for PopupState {
@@ -36,6 +45,8 @@ impl std::default::Default for PopupState {
       con_old:     0,
       active_con:  0,
       header:      RectangleF32::new(0f32, 0f32, 0f32, 0f32),
+      list:        None,
+      scroll:      0,
     }
   }
 }
@@ -73,6 +84,13 @@ impl std::default::Default for EditState {
   }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PropertyStatus {
+  Default,
+  Edit,
+  Drag,
+}
+
 #[derive(Clone, Debug)]
 pub struct PropertyState {
   pub active:       i32,
@@ -85,7 +103,7 @@ pub struct PropertyState {
   pub name:         u32,
   pub seq:          u32,
   pub old:          u32,
-  pub state:        i32,
+  pub state:        PropertyStatus,
 }
 
 impl std::default::Default for PropertyState {
@@ -101,7 +119,26 @@ impl std::default::Default for PropertyState {
       name:         0,
       seq:          0,
       old:          0,
-      state:        0,
+      state:        PropertyStatus::Default,
+    }
+  }
+}
+
+/// Column layout of a table, kept across frames (see `table_begin()`).
+#[derive(Clone, Debug)]
+pub struct TableState {
+  pub widths: Vec<f32>,
+  pub align:  Vec<BitFlags<TextAlign>>,
+  /// Column whose right separator is being dragged.
+  pub drag:   Option<usize>,
+}
+
+impl TableState {
+  pub fn new(widths: &[f32]) -> TableState {
+    TableState {
+      widths: widths.to_vec(),
+      align:  vec![TextAlign::left(); widths.len()],
+      drag:   None,
     }
   }
 }
@@ -128,6 +165,22 @@ pub struct ScrollState {
   pub scrollbar:    Vec2U32,
   pub hiding_timer: f32,
   pub scrolled:     u32,
+  /// size of the panel's content and of its visible part, as of the last
+  /// frame
+  pub content:      Vec2F32,
+  pub view:         Vec2F32,
+  /// offset smooth scrolling eases towards
+  pub target:       Vec2F32,
+}
+
+impl ScrollState {
+  /// Largest scroll offset that still shows content.
+  pub fn max_offset(&self) -> Vec2F32 {
+    Vec2F32::new(
+      (self.content.x - self.view.x).max(0f32),
+      (self.content.y - self.view.y).max(0f32),
+    )
+  }
 }
 
 impl std::default::Default for ScrollState {
@@ -136,6 +189,9 @@ impl std::default::Default for ScrollState {
       scrollbar:    Vec2U32::same(0),
       hiding_timer: 0f32,
       scrolled:     0,
+      content:      Vec2F32::same(0f32),
+      view:         Vec2F32::same(0f32),
+      target:       Vec2F32::same(0f32),
     }
   }
 }
@@ -153,9 +209,20 @@ pub struct Window {
   pub property: PropertyState,
   pub popup:    PopupState,
   pub edit:     EditState,
+  /// Animation phase of spinners, keyed by their position in the window.
+  pub spinners: HashMap<HashType, f32>,
+  /// Collapse state of tree nodes, keyed by their id (see `push_id()`).
+  pub trees:    HashMap<HashType, CollapseStates>,
+  /// Column widths and alignment of tables, keyed by their id.
+  pub tables:   HashMap<HashType, TableState>,
+  /// Frame (context seq) in which each widget was last hovered, keyed by its
+  /// position inside the window.
+  pub hovered:  HashMap<HashType, u32>,
   pub killed:   bool,
-
-  // tables ??!!
+  /// Viewport (OS window/render target) this window is drawn into.
+  pub viewport: u32,
+  /// Opacity multiplied into every color of the window when converting.
+  pub alpha:    f32,
 
   // window list hooks
 
@@ -166,6 +233,8 @@ pub struct Window {
 }
 
 impl Window {
+  pub const DEFAULT_VIEWPORT: u32 = 0;
+
   pub fn new(
     handle: usize,
     name: HashType,
@@ -198,7 +267,13 @@ impl Window {
       property: PropertyState::default(),
       popup: PopupState::default(),
       edit: EditState::default(),
+      spinners: HashMap::new(),
+      trees: HashMap::new(),
+      tables: HashMap::new(),
+      hovered: HashMap::new(),
       killed: false,
+      viewport: Window::DEFAULT_VIEWPORT,
+      alpha: 1f32,
       parent: None,
     }
   }
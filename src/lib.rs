@@ -0,0 +1,6 @@
+#![allow(dead_code)]
+
+pub mod hmi;
+pub mod math;
+pub mod render_gl;
+pub mod sys;
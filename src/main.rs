@@ -1,18 +1,13 @@
 #![allow(dead_code)]
 
-mod hmi;
-mod math;
-mod render_gl;
-mod sys;
-
-use crate::math::{
+use simple_ui::math::{
   colors::{HslColor, HsvColor, RGBAColor, RGBAColorF32, XyzColor},
   rectangle::RectangleF32,
   vec2::{Vec2F32, Vec2I16},
   vertex_types::VertexPTC,
 };
 
-use crate::{
+use simple_ui::{
   hmi::{
     base::{
       AntialiasingType, ConvertConfig, DrawNullTexture, GenericHandle,
@@ -27,7 +22,7 @@ use crate::{
     ui_context::UiContext,
     vertex_output::{DrawCommand, DrawIndexType, DrawList},
   },
-  render_gl::OpenGLStateSaveSetRestore,
+  render_gl::{self, OpenGLStateSaveSetRestore},
   sys::memory_mapped_file::MemoryMappedFile,
 };
 
@@ -97,16 +92,14 @@ fn test_font_atlas() {
         .add_font(
           &cfg,
           TTFDataSource::File(std::path::PathBuf::from("DroidSans.ttf")),
-        )
-        .expect("Failed to load ttf file!");
+        )?;
 
       let cfg = FontConfigBuilder::new().size(64f32).build();
       let _f02 = atlas_builder
         .add_font(
           &cfg,
           TTFDataSource::File(std::path::PathBuf::from("Babylon5.ttf")),
-        )
-        .expect("Failed to load ttf file!");
+        )?;
 
       atlas_builder.build(|width: u32, height: u32, pixels: &[u8]| {
         write_atlas_png(width, height, pixels);
@@ -200,7 +193,9 @@ fn main() {
   };
 
   unsafe {
-    let cc = RGBAColorF32::from(HsvColor::new(217f32, 87f32, 46f32));
+    let cc = RGBAColorF32::from(HsvColor::from_degrees_percent(
+      217f32, 87f32, 46f32,
+    ));
     gl::ClearColor(cc.r, cc.g, cc.b, cc.a);
   }
 
@@ -224,17 +219,21 @@ fn main() {
     line_aa:              AntialiasingType::On,
     shape_aa:             AntialiasingType::On,
     circle_segment_count: 22,
+    circle_quality:       1_f32,
     arc_segment_count:    22,
     curve_segment_count:  22,
     null:                 null_tex,
     vertex_layout:        vec![],
     vertex_size:          std::mem::size_of::<VertexPTC>(),
+    snap_to_pixel:        false,
+    premultiplied_alpha:  false,
   };
 
   let mut fonts = vec![];
   let font_atlas = FontAtlasBuilder::new(300)
     .ok_or("Failed to create font atlas")
     .and_then(|mut atlas_builder| {
+      // a missing font file falls back to the built-in font
       let cfg = FontConfigBuilder::new().size(14f32).build();
 
       let _f01 = atlas_builder
@@ -242,7 +241,7 @@ fn main() {
           &cfg,
           TTFDataSource::File(std::path::PathBuf::from("DroidSans.ttf")),
         )
-        .expect("Failed to load ttf file!");
+        .unwrap_or_else(|_| atlas_builder.add_builtin_font(&cfg));
 
       fonts.push(_f01);
 
@@ -252,7 +251,7 @@ fn main() {
           &cfg,
           TTFDataSource::File(std::path::PathBuf::from("Babylon5.ttf")),
         )
-        .expect("Failed to load ttf file!");
+        .unwrap_or_else(|_| atlas_builder.add_builtin_font(&cfg));
 
       fonts.push(_f02);
 
@@ -290,11 +289,12 @@ fn main() {
     .expect("Failed to initialize font engine!");
 
   let mut ui_ctx = UiContext::new(
-    fonts[0],
+    fonts[0].clone(),
     convert_cfg,
     AntialiasingType::Off,
     AntialiasingType::Off,
-  );
+  )
+  .expect("Invalid convert config!");
 
   let nk_vbuff = unsafe {
     let mut buffid: gl::types::GLuint = 0;
@@ -364,6 +364,9 @@ fn main() {
     }
     ui_ctx.input_mut().end();
 
+    let (wnd_w, wnd_h) = window.get_size();
+    ui_ctx.set_display_size(Vec2F32::new(wnd_w as f32, wnd_h as f32));
+
     // UI here
     ui_ctx.begin(
       "Demo",
@@ -466,11 +469,13 @@ fn main() {
           (cmd.clip_rect.h * fb_scale_y) as GLint,
         );
 
+        // the offset into the index buffer is in bytes
         gl::DrawElements(
           gl::TRIANGLES,
           cmd.element_count as GLsizei,
           gl::UNSIGNED_SHORT,
-          offset as *const GLvoid,
+          (offset as usize * std::mem::size_of::<DrawIndexType>())
+            as *const GLvoid,
         );
         offset += cmd.element_count;
       });
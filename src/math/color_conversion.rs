@@ -62,8 +62,8 @@ impl std::convert::From<RGBAColorF32> for HslColor {
 impl std::convert::From<HsvColor> for RGBAColorF32 {
   fn from(hsv: HsvColor) -> RGBAColorF32 {
     let h = hsv.h / 360f32;
-    let s = hsv.s * 0.01f32;
-    let v = hsv.v * 0.01f32;
+    let s = hsv.s;
+    let v = hsv.v;
 
     let i = (h * 6f32).floor();
     let f = h * 6f32 - i;
@@ -184,4 +184,12 @@ mod tests {
       RGBAColorF32::new(0.85f32, 0.15f32, 0.15f32)
     );
   }
+
+  #[test]
+  fn test_hsv_degrees_percent_to_rgb() {
+    assert_eq!(
+      RGBAColorF32::from(HsvColor::from_degrees_percent(0f32, 100f32, 100f32)),
+      RGBAColorF32::new(1f32, 0f32, 0f32)
+    );
+  }
 }
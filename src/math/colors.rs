@@ -95,6 +95,15 @@ where
       .map_err(|_| "Invalid color value")
   }
 
+  pub fn from_array(c: [T; 4]) -> Self {
+    Self::new_with_alpha(c[0], c[1], c[2], c[3])
+  }
+
+  /// Components in r, g, b, a order.
+  pub fn into_array(self) -> [T; 4] {
+    [self.r, self.g, self.b, self.a]
+  }
+
   pub fn as_slice(&self) -> &[T] {
     unsafe {
       std::slice::from_raw_parts(self as *const TColorRGBA<T> as *const T, 4)
@@ -118,6 +127,39 @@ where
   }
 }
 
+/// Component access by index, 0 is red through 3 being alpha.
+impl<T> std::ops::Index<usize> for TColorRGBA<T>
+where
+  T: Copy + Clone + std::fmt::Debug + Num + NumColorComponent,
+{
+  type Output = T;
+
+  fn index(&self, idx: usize) -> &T {
+    match idx {
+      0 => &self.r,
+      1 => &self.g,
+      2 => &self.b,
+      3 => &self.a,
+      _ => panic!("color component index out of range: {}", idx),
+    }
+  }
+}
+
+impl<T> std::ops::IndexMut<usize> for TColorRGBA<T>
+where
+  T: Copy + Clone + std::fmt::Debug + Num + NumColorComponent,
+{
+  fn index_mut(&mut self, idx: usize) -> &mut T {
+    match idx {
+      0 => &mut self.r,
+      1 => &mut self.g,
+      2 => &mut self.b,
+      3 => &mut self.a,
+      _ => panic!("color component index out of range: {}", idx),
+    }
+  }
+}
+
 impl<T> std::convert::From<(T, T, T, T)> for TColorRGBA<T>
 where
   T: Copy + Clone + std::fmt::Debug + Num + NumColorComponent,
@@ -141,7 +183,7 @@ where
   T: Copy + Clone + std::fmt::Debug + Num + NumColorComponent,
 {
   fn from(c: [T; 4]) -> Self {
-    Self::new_with_alpha(c[0], c[1], c[2], c[3])
+    Self::from_array(c)
   }
 }
 
@@ -154,6 +196,64 @@ where
   }
 }
 
+impl RGBAColor {
+  /// `#rrggbbaa` string, the reverse of `from_html()`.
+  pub fn to_html(&self) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+  }
+
+  /// Relative luminance in [0, 1], using the Rec.709 coefficients.
+  pub fn luminance(&self) -> f32 {
+    (0.2126_f32 * self.r as f32
+      + 0.7152_f32 * self.g as f32
+      + 0.0722_f32 * self.b as f32)
+      / 255_f32
+  }
+
+  /// The same color with its alpha replaced by `alpha`, in [0, 1]. The color
+  /// components are not premultiplied.
+  pub fn with_alpha(&self, alpha: f32) -> RGBAColor {
+    let a = (alpha.max(0_f32).min(1_f32) * 255_f32).round() as u8;
+    RGBAColor::new_with_alpha(self.r, self.g, self.b, a)
+  }
+
+  /// Gray with the luminance of the color, alpha is kept.
+  pub fn to_grayscale(&self) -> RGBAColor {
+    let l = (self.luminance() * 255_f32).round() as u8;
+    RGBAColor::new_with_alpha(l, l, l, self.a)
+  }
+}
+
+impl RGBAColorF32 {
+  /// Converts an sRGB encoded color (what color pickers and textures use) to
+  /// linear space. Do blending/interpolation on linear colors, then convert
+  /// back with `from_linear` before output. Alpha is always linear.
+  pub fn to_linear(&self) -> RGBAColorF32 {
+    let f = |c: f32| {
+      if c <= 0.04045_f32 {
+        c / 12.92_f32
+      } else {
+        ((c + 0.055_f32) / 1.055_f32).powf(2.4_f32)
+      }
+    };
+
+    RGBAColorF32::new_with_alpha(f(self.r), f(self.g), f(self.b), self.a)
+  }
+
+  /// Converts a linear color back to sRGB encoding.
+  pub fn from_linear(c: RGBAColorF32) -> RGBAColorF32 {
+    let f = |c: f32| {
+      if c <= 0.0031308_f32 {
+        c * 12.92_f32
+      } else {
+        1.055_f32 * c.powf(1_f32 / 2.4_f32) - 0.055_f32
+      }
+    };
+
+    RGBAColorF32::new_with_alpha(f(c.r), f(c.g), f(c.b), c.a)
+  }
+}
+
 impl std::convert::From<RGBAColor> for RGBAColorF32 {
   fn from(rgba: RGBAColor) -> Self {
     RGBAColorF32::new_with_alpha(
@@ -458,6 +558,16 @@ define_color_type!(HsvColor, f32, 3usize, (h => hue), (s => saturation), (v => v
 define_color_type!(HslColor, f32, 3usize, (h => hue), (s => lightness), (l => saturation));
 define_color_type!(XyzColor, f32, 3usize, (x => xval), (y => yval), (z => zval));
 
+/// HSV colors store the hue in degrees [0, 360) and the saturation and value
+/// in the [0, 1] range.
+impl HsvColor {
+  /// Builds a color from the hue in degrees and saturation/value given as
+  /// percentages (0 - 100), the way most color pickers show them.
+  pub fn from_degrees_percent(h_deg: f32, s_pct: f32, v_pct: f32) -> HsvColor {
+    HsvColor::new(h_deg, s_pct * 0.01f32, v_pct * 0.01f32)
+  }
+}
+
 pub const TURBO_SRGB_FLOATS: [[f32; 3]; 256] = [
   [0.18995_f32, 0.07176_f32, 0.23217_f32],
   [0.19483_f32, 0.08339_f32, 0.26149_f32],
@@ -1005,6 +1115,13 @@ mod tests {
     assert_eq!(RGBAColor::from_html("invalid str").is_ok(), false);
   }
 
+  #[test]
+  fn test_conversion_to_html() {
+    let clr = RGBAColor::new_with_alpha(15, 73, 159, 128);
+    assert_eq!(clr.to_html(), "#0f499f80");
+    assert_eq!(RGBAColor::from_html(&clr.to_html()), Ok(clr));
+  }
+
   #[test]
   fn test_conversion_from_slice() {
     let clr = [255u8, 0u8, 128u8, 255u8];
@@ -1016,4 +1133,38 @@ mod tests {
     let c: u32 = RGBAColor::new(0, 51, 153).into();
     assert_eq!(c, 0x003399ff);
   }
+
+  #[test]
+  fn test_component_indexing() {
+    let mut clr = RGBAColor::new_with_alpha(10, 20, 30, 40);
+    assert_eq!(clr.into_array(), [clr[0], clr[1], clr[2], clr[3]]);
+    assert_eq!(clr.into_array(), [clr.r, clr.g, clr.b, clr.a]);
+
+    clr[3] = 255;
+    assert_eq!(clr.a, 255);
+    assert_eq!(RGBAColor::from_array(clr.into_array()), clr);
+  }
+
+  #[test]
+  fn test_srgb_linear() {
+    let lin = RGBAColorF32::new_with_alpha(0.5, 1.0, 0.0, 0.5).to_linear();
+    assert!((lin.r - 0.214_f32).abs() < 0.001_f32);
+    assert_eq!(lin.g, 1_f32);
+    assert_eq!(lin.b, 0_f32);
+    assert_eq!(lin.a, 0.5_f32);
+
+    let srgb = RGBAColorF32::from_linear(lin);
+    assert!((srgb.r - 0.5_f32).abs() < 0.0001_f32);
+  }
+
+  #[test]
+  fn test_luminance_and_grayscale() {
+    let green = RGBAColor::new(0, 255, 0);
+    let blue = RGBAColor::new(0, 0, 255);
+    assert!(green.luminance() > blue.luminance());
+    assert_eq!(RGBAColor::new(255, 255, 255).luminance(), 1_f32);
+
+    let gray = RGBAColor::new_with_alpha(0, 255, 0, 128).to_grayscale();
+    assert_eq!(gray, RGBAColor::new_with_alpha(182, 182, 182, 128));
+  }
 }
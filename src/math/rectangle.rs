@@ -1,11 +1,11 @@
 #![allow(dead_code)]
 
-use num_traits::Num;
+use num_traits::{Float, Num};
 use std::ops::{Add, Sub};
 
 use crate::math::{minmax::MinMax, vec2::TVec2};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct TRectangle<T>
 where
   T: Copy + Clone + std::fmt::Debug + Num,
@@ -103,8 +103,104 @@ where
       h - pad.y - pad.y,
     )
   }
+
+  // Rect-cut layout: each function cuts a piece of `amount` (clamped to the
+  // rectangle's size) off one side and returns (piece, remainder).
+
+  pub fn cut_left(&self, amount: T) -> (TRectangle<T>, TRectangle<T>) {
+    let a = T::min(T::max(amount, T::zero()), self.w);
+    (
+      TRectangle::new(self.x, self.y, a, self.h),
+      TRectangle::new(self.x + a, self.y, self.w - a, self.h),
+    )
+  }
+
+  pub fn cut_right(&self, amount: T) -> (TRectangle<T>, TRectangle<T>) {
+    let a = T::min(T::max(amount, T::zero()), self.w);
+    (
+      TRectangle::new(self.x + self.w - a, self.y, a, self.h),
+      TRectangle::new(self.x, self.y, self.w - a, self.h),
+    )
+  }
+
+  pub fn cut_top(&self, amount: T) -> (TRectangle<T>, TRectangle<T>) {
+    let a = T::min(T::max(amount, T::zero()), self.h);
+    (
+      TRectangle::new(self.x, self.y, self.w, a),
+      TRectangle::new(self.x, self.y + a, self.w, self.h - a),
+    )
+  }
+
+  pub fn cut_bottom(&self, amount: T) -> (TRectangle<T>, TRectangle<T>) {
+    let a = T::min(T::max(amount, T::zero()), self.h);
+    (
+      TRectangle::new(self.x, self.y + self.h - a, self.w, a),
+      TRectangle::new(self.x, self.y, self.w, self.h - a),
+    )
+  }
+}
+
+impl<T> TRectangle<T>
+where
+  T: Copy + Clone + std::fmt::Debug + Float,
+{
+  /// \brief  Compares position and size, with a tolerance of `eps`.
+  pub fn approx_eq(&self, other: &Self, eps: T) -> bool {
+    TVec2::new(self.x, self.y).approx_eq(&TVec2::new(other.x, other.y), eps)
+      && TVec2::new(self.w, self.h)
+        .approx_eq(&TVec2::new(other.w, other.h), eps)
+  }
+}
+
+impl<T> std::fmt::Display for TRectangle<T>
+where
+  T: Copy + Clone + std::fmt::Display + std::fmt::Debug + Num,
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "({}, {}, {} x {})", self.x, self.y, self.w, self.h)
+  }
 }
 
 pub type RectangleI16 = TRectangle<i16>;
 pub type RectangleI32 = TRectangle<i32>;
 pub type RectangleF32 = TRectangle<f32>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_cut_left_right() {
+    let r = RectangleF32::new(5f32, 0f32, 100f32, 20f32);
+
+    let (piece, rest) = r.cut_left(10f32);
+    assert_eq!((piece.x, piece.w), (5f32, 10f32));
+    assert_eq!((rest.x, rest.w), (15f32, 90f32));
+
+    let (piece, rest) = r.cut_right(10f32);
+    assert_eq!((piece.x, piece.w), (95f32, 10f32));
+    assert_eq!((rest.x, rest.w), (5f32, 90f32));
+  }
+
+  #[test]
+  fn test_cut_top_bottom_clamped() {
+    let r = RectangleI32::new(0, 10, 50, 40);
+
+    let (piece, rest) = r.cut_top(15);
+    assert_eq!((piece.y, piece.h, rest.y, rest.h), (10, 15, 25, 25));
+
+    let (piece, rest) = r.cut_bottom(100);
+    assert_eq!((piece.y, piece.h, rest.h), (10, 40, 0));
+  }
+
+  #[test]
+  fn test_approx_eq() {
+    let r = RectangleF32::new(0.5f32, 0.25f32, 1f32, 0.75f32);
+    let close = RectangleF32::new(0.500001f32, 0.25f32, 1f32, 0.750001f32);
+    let far = RectangleF32::new(0.5f32, 0.25f32, 1.001f32, 0.75f32);
+
+    assert!(r.approx_eq(&close, 1e-5f32));
+    assert!(!r.approx_eq(&far, 1e-5f32));
+    assert_eq!(format!("{}", r), "(0.5, 0.25, 1 x 0.75)");
+  }
+}
@@ -40,6 +40,18 @@ where
   {
     self.square_len().sqrt()
   }
+
+  pub fn zero() -> Self {
+    Self::same(T::zero())
+  }
+
+  /// \brief  Component-wise comparison, with a tolerance of `eps`.
+  pub fn approx_eq(&self, other: &Self, eps: T) -> bool
+  where
+    T: Float,
+  {
+    (self.x - other.x).abs() <= eps && (self.y - other.y).abs() <= eps
+  }
 }
 
 impl<T> std::default::Default for TVec2<T>
@@ -234,6 +246,15 @@ where
   }
 }
 
+impl<T> std::fmt::Display for TVec2<T>
+where
+  T: Copy + Clone + std::fmt::Display + std::fmt::Debug,
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "({}, {})", self.x, self.y)
+  }
+}
+
 /// @{ Operations on TVec2
 
 /// \brief  Normalizes the input vector.
@@ -289,6 +310,15 @@ where
   dot(a, b).is_zero()
 }
 
+/// \brief  Rotates the vector by `angle` radians around the origin.
+pub fn rotate<T>(a: TVec2<T>, angle: T) -> TVec2<T>
+where
+  T: Copy + Clone + std::fmt::Debug + Float,
+{
+  let (sin, cos) = angle.sin_cos();
+  TVec2::new(a.x * cos - a.y * sin, a.x * sin + a.y * cos)
+}
+
 /// @}
 
 pub type Vec2I8 = TVec2<i8>;
@@ -298,3 +328,66 @@ pub type Vec2U16 = TVec2<u16>;
 pub type Vec2I32 = TVec2<i32>;
 pub type Vec2U32 = TVec2<u32>;
 pub type Vec2F32 = TVec2<f32>;
+
+impl Vec2F32 {
+  pub const ONE: Vec2F32 = TVec2 { x: 1f32, y: 1f32 };
+}
+
+impl std::convert::From<Vec2I16> for Vec2F32 {
+  fn from(v: Vec2I16) -> Self {
+    Vec2F32::new(v.x as f32, v.y as f32)
+  }
+}
+
+/// \brief Rounds to the nearest integer coordinates (saturating at the i16
+/// limits).
+impl std::convert::From<Vec2F32> for Vec2I16 {
+  fn from(v: Vec2F32) -> Self {
+    Vec2I16::new(v.x.round() as i16, v.y.round() as i16)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_vec2i16_conversions() {
+    let v = Vec2I16::new(-3, 7);
+    let f = Vec2F32::from(v);
+    assert_eq!((f.x, f.y), (-3f32, 7f32));
+
+    let back = Vec2I16::from(f);
+    assert_eq!((back.x, back.y), (v.x, v.y));
+
+    let rounded = Vec2I16::from(Vec2F32::new(1.6f32, -2.4f32));
+    assert_eq!((rounded.x, rounded.y), (2, -2));
+  }
+
+  #[test]
+  fn test_vec2i16_arithmetic() {
+    let a = Vec2I16::new(2, 3);
+    let b = Vec2I16::new(5, -1);
+
+    let sum = a + b;
+    assert_eq!((sum.x, sum.y), (7, 2));
+    let diff = a - b;
+    assert_eq!((diff.x, diff.y), (-3, 4));
+    let scaled = a * 4;
+    assert_eq!((scaled.x, scaled.y), (8, 12));
+  }
+
+  #[test]
+  fn test_vec2f32_approx_eq() {
+    let a = Vec2F32::new(0.5f32, -0.25f32);
+    let close = a + Vec2F32::same(1e-6f32);
+    let far = a + Vec2F32::new(0f32, 1e-3f32);
+
+    assert!(a.approx_eq(&close, 1e-5f32));
+    assert!(close.approx_eq(&a, 1e-5f32));
+    assert!(!a.approx_eq(&far, 1e-5f32));
+
+    assert!(Vec2F32::zero().approx_eq(&(Vec2F32::ONE - Vec2F32::ONE), 0f32));
+    assert_eq!(format!("{}", Vec2F32::ONE), "(1, 1)");
+  }
+}
@@ -4,6 +4,8 @@ use std::{
   ffi::{CStr, CString},
 };
 
+pub mod sprite_batch;
+
 /// Saves the OpenGL state on creation, enables blending and restores the saved
 /// state when dropped.
 pub struct OpenGLStateSaveSetRestore {
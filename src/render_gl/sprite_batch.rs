@@ -0,0 +1,185 @@
+use crate::{
+  hmi::{
+    base::Consts,
+    image::Image,
+    vertex_output::{DrawCommand, DrawIndexType},
+  },
+  math::{
+    colors::{RGBAColor, RGBAColorF32},
+    rectangle::RectangleF32,
+    vec2::Vec2F32,
+    vertex_types::VertexPTC,
+  },
+};
+
+#[derive(Copy, Debug, Clone)]
+struct Sprite {
+  image: Image,
+  dest:  RectangleF32,
+  tint:  RGBAColor,
+}
+
+/// Collects textured quads and writes them out in one go, in the same vertex
+/// and index format the UI uses. Consecutive sprites sharing a texture end up
+/// in the same draw command.
+#[derive(Debug, Clone)]
+pub struct SpriteBatch {
+  sprites: Vec<Sprite>,
+}
+
+impl SpriteBatch {
+  pub fn new() -> SpriteBatch {
+    SpriteBatch { sprites: vec![] }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.sprites.is_empty()
+  }
+
+  pub fn len(&self) -> usize {
+    self.sprites.len()
+  }
+
+  pub fn push_sprite(
+    &mut self,
+    image: Image,
+    dest: RectangleF32,
+    tint: RGBAColor,
+  ) {
+    self.sprites.push(Sprite { image, dest, tint });
+  }
+
+  /// Appends the quads of the pushed sprites to the buffers and removes them
+  /// from the batch. The draw commands have no vertex offset, so only as many
+  /// sprites as the 16-bit indices can address are written, the rest stays in
+  /// the batch. Returns false in that case: draw and clear the buffers, then
+  /// flush again.
+  pub fn flush(
+    &mut self,
+    vertices: &mut Vec<VertexPTC>,
+    indices: &mut Vec<DrawIndexType>,
+    commands: &mut Vec<DrawCommand>,
+  ) -> bool {
+    let max_vertices = DrawIndexType::MAX as usize + 1;
+    let fitting =
+      (max_vertices.saturating_sub(vertices.len()) / 4).min(self.sprites.len());
+
+    self.sprites.drain(.. fitting).for_each(|sprite| {
+      let same_texture = commands
+        .last()
+        .map_or(false, |cmd| cmd.texture == sprite.image.handle);
+
+      if !same_texture {
+        commands.push(DrawCommand {
+          element_count: 0,
+          clip_rect:     Consts::null_rect(),
+          texture:       sprite.image.handle,
+        });
+      }
+
+      let (uva, uvc) = sprite.image.uv();
+      let color = RGBAColorF32::from(sprite.tint);
+      let r = sprite.dest;
+      let idx = vertices.len() as DrawIndexType;
+
+      [
+        (Vec2F32::new(r.x, r.y), uva),
+        (Vec2F32::new(r.x + r.w, r.y), Vec2F32::new(uvc.x, uva.y)),
+        (Vec2F32::new(r.x + r.w, r.y + r.h), uvc),
+        (Vec2F32::new(r.x, r.y + r.h), Vec2F32::new(uva.x, uvc.y)),
+      ]
+      .iter()
+      .for_each(|&(pos, texcoords)| {
+        vertices.push(VertexPTC {
+          pos,
+          texcoords,
+          color,
+        })
+      });
+
+      [0, 1, 2, 0, 2, 3]
+        .iter()
+        .for_each(|&offset| indices.push(idx + offset));
+
+      if let Some(cmd) = commands.last_mut() {
+        cmd.element_count += 6;
+      }
+    });
+
+    self.sprites.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{hmi::base::GenericHandle, math::rectangle::RectangleI32};
+
+  #[test]
+  fn test_same_texture_single_command() {
+    let mut batch = SpriteBatch::new();
+    let invader = Image::sub_image(
+      GenericHandle::Id(3),
+      128,
+      128,
+      RectangleI32::new(0, 0, 16, 16),
+    );
+    let ship = Image::sub_image(
+      GenericHandle::Id(3),
+      128,
+      128,
+      RectangleI32::new(16, 0, 16, 16),
+    );
+
+    batch.push_sprite(
+      invader,
+      RectangleF32::new(0f32, 0f32, 16f32, 16f32),
+      RGBAColor::new(255, 255, 255),
+    );
+    batch.push_sprite(
+      ship,
+      RectangleF32::new(32f32, 0f32, 16f32, 16f32),
+      RGBAColor::new(255, 255, 255),
+    );
+
+    let (mut vertices, mut indices, mut commands) = (vec![], vec![], vec![]);
+    assert!(batch.flush(&mut vertices, &mut indices, &mut commands));
+
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].element_count, 12);
+    assert_eq!(vertices.len(), 8);
+    assert!(batch.is_empty());
+  }
+  #[test]
+  fn test_sprites_past_the_index_range_stay_batched() {
+    let mut batch = SpriteBatch::new();
+    let invader = Image::sub_image(
+      GenericHandle::Id(3),
+      128,
+      128,
+      RectangleI32::new(0, 0, 16, 16),
+    );
+    (0 .. 16385).for_each(|i| {
+      batch.push_sprite(
+        invader,
+        RectangleF32::new(i as f32, 0f32, 16f32, 16f32),
+        RGBAColor::new(255, 255, 255),
+      )
+    });
+
+    let (mut vertices, mut indices, mut commands) = (vec![], vec![], vec![]);
+    assert!(!batch.flush(&mut vertices, &mut indices, &mut commands));
+    assert_eq!(vertices.len(), 65536);
+    assert_eq!(indices.iter().max(), Some(&DrawIndexType::MAX));
+    assert_eq!(batch.len(), 1);
+
+    // the caller drew and cleared the buffers
+    vertices.clear();
+    indices.clear();
+    commands.clear();
+    assert!(batch.flush(&mut vertices, &mut indices, &mut commands));
+    assert_eq!(vertices.len(), 4);
+    assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+    assert!(batch.is_empty());
+  }
+}
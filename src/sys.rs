@@ -1,2 +1,4 @@
+pub mod headless;
 pub mod memory_mapped_file;
+pub mod texture_registry;
 pub mod unique_resource;
@@ -0,0 +1,519 @@
+// CPU rasterizer for the converted draw data, lets tests look at the pixels
+// widgets produce without a GL context. Textures registered with
+// `add_texture` are sampled (nearest texel), any other texture is treated as
+// the white pixel.
+
+use crate::{
+  hmi::{
+    base::{AntialiasingType, Consts, ConvertConfig, GenericHandle},
+    commands::CommandBuffer,
+    text_engine::Font,
+    vertex_output::{DrawCommand, DrawIndexType, DrawList},
+  },
+  math::{
+    colors::{RGBAColor, RGBAColorF32},
+    rectangle::RectangleF32,
+    vec2::Vec2F32,
+    vertex_types::VertexPTC,
+  },
+};
+
+struct HeadlessTexture {
+  handle: GenericHandle,
+  width:  u32,
+  height: u32,
+  texels: Vec<RGBAColorF32>,
+}
+
+impl HeadlessTexture {
+  fn sample(&self, uv: Vec2F32) -> RGBAColorF32 {
+    let x = ((uv.x * self.width as f32) as i64)
+      .max(0)
+      .min(self.width as i64 - 1);
+    let y = ((uv.y * self.height as f32) as i64)
+      .max(0)
+      .min(self.height as i64 - 1);
+    self.texels[(y * self.width as i64 + x) as usize]
+  }
+}
+
+pub struct HeadlessTarget {
+  width:         u32,
+  height:        u32,
+  pixels:        Vec<RGBAColorF32>,
+  textures:      Vec<HeadlessTexture>,
+  premultiplied: bool,
+}
+
+impl HeadlessTarget {
+  pub fn new(width: u32, height: u32) -> HeadlessTarget {
+    HeadlessTarget {
+      width,
+      height,
+      pixels: vec![
+        RGBAColorF32::new_with_alpha(0f32, 0f32, 0f32, 0f32);
+        (width * height) as usize
+      ],
+      textures: vec![],
+      premultiplied: false,
+    }
+  }
+
+  /// Blends like `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)`, for vertex
+  /// colors and textures with premultiplied alpha.
+  pub fn set_premultiplied_alpha(&mut self, premultiplied: bool) {
+    self.premultiplied = premultiplied;
+  }
+
+  /// Makes the RGBA8 `texels` available for sampling, for every draw
+  /// command that uses `handle`.
+  pub fn add_texture(
+    &mut self,
+    handle: GenericHandle,
+    width: u32,
+    height: u32,
+    texels: &[u8],
+  ) {
+    if width == 0 || height == 0 {
+      return;
+    }
+
+    let texels = texels
+      .chunks(4)
+      .take((width * height) as usize)
+      .map(|t| {
+        RGBAColorF32::from(RGBAColor::new_with_alpha(t[0], t[1], t[2], t[3]))
+      })
+      .collect();
+
+    self.textures.retain(|tex| tex.handle != handle);
+    self.textures.push(HeadlessTexture {
+      handle,
+      width,
+      height,
+      texels,
+    });
+  }
+
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+
+  pub fn clear(&mut self, color: RGBAColor) {
+    let color = RGBAColorF32::from(color);
+    self.pixels.iter_mut().for_each(|px| *px = color);
+  }
+
+  pub fn pixel(&self, x: u32, y: u32) -> RGBAColor {
+    RGBAColor::from(self.pixels[(y * self.width + x) as usize])
+  }
+
+  /// RGBA8 pixels, row by row starting at the top.
+  pub fn to_rgba8(&self) -> Vec<u8> {
+    self
+      .pixels
+      .iter()
+      .flat_map(|px| {
+        let px = RGBAColor::from(*px);
+        vec![px.r, px.g, px.b, px.a]
+      })
+      .collect()
+  }
+
+  /// Renders the output of `UiContext::convert()`, the same way the GL
+  /// renderer consumes it (element counts per command, clip rectangles).
+  pub fn render(
+    &mut self,
+    vertices: &[VertexPTC],
+    indices: &[DrawIndexType],
+    commands: &[DrawCommand],
+  ) {
+    let mut offset = 0usize;
+    commands.iter().for_each(|cmd| {
+      if cmd.element_count == 0 {
+        return;
+      }
+
+      let texture = self
+        .textures
+        .iter()
+        .position(|tex| tex.handle == cmd.texture);
+      let end = offset + cmd.element_count as usize;
+      indices[offset .. end].chunks(3).for_each(|tri| {
+        if tri.len() == 3 {
+          self.fill_triangle(
+            &vertices[tri[0] as usize],
+            &vertices[tri[1] as usize],
+            &vertices[tri[2] as usize],
+            &cmd.clip_rect,
+            texture,
+          );
+        }
+      });
+
+      offset = end;
+    });
+  }
+
+  fn fill_triangle(
+    &mut self,
+    v0: &VertexPTC,
+    v1: &VertexPTC,
+    v2: &VertexPTC,
+    clip: &RectangleF32,
+    texture: Option<usize>,
+  ) {
+    let edge = |a: Vec2F32, b: Vec2F32, p: Vec2F32| {
+      (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+    };
+
+    let area = edge(v0.pos, v1.pos, v2.pos);
+    if area == 0f32 {
+      return;
+    }
+
+    let min_x = v0.pos.x.min(v1.pos.x).min(v2.pos.x).max(clip.x).max(0f32);
+    let min_y = v0.pos.y.min(v1.pos.y).min(v2.pos.y).max(clip.y).max(0f32);
+    let max_x = v0
+      .pos
+      .x
+      .max(v1.pos.x)
+      .max(v2.pos.x)
+      .min(clip.x + clip.w)
+      .min(self.width as f32);
+    let max_y = v0
+      .pos
+      .y
+      .max(v1.pos.y)
+      .max(v2.pos.y)
+      .min(clip.y + clip.h)
+      .min(self.height as f32);
+
+    if min_x >= max_x || min_y >= max_y {
+      return;
+    }
+
+    (min_y as u32 .. max_y.ceil() as u32).for_each(|y| {
+      (min_x as u32 .. max_x.ceil() as u32).for_each(|x| {
+        // sample at the pixel center
+        let p = Vec2F32::new(x as f32 + 0.5f32, y as f32 + 0.5f32);
+        let w0 = edge(v1.pos, v2.pos, p) / area;
+        let w1 = edge(v2.pos, v0.pos, p) / area;
+        let w2 = edge(v0.pos, v1.pos, p) / area;
+
+        if w0 < 0f32 || w1 < 0f32 || w2 < 0f32 {
+          return;
+        }
+
+        let texel = texture.map_or(
+          RGBAColorF32::new_with_alpha(1f32, 1f32, 1f32, 1f32),
+          |tex| {
+            self.textures[tex].sample(Vec2F32::new(
+              v0.texcoords.x * w0 + v1.texcoords.x * w1 + v2.texcoords.x * w2,
+              v0.texcoords.y * w0 + v1.texcoords.y * w1 + v2.texcoords.y * w2,
+            ))
+          },
+        );
+
+        let src = RGBAColorF32::new_with_alpha(
+          (v0.color.r * w0 + v1.color.r * w1 + v2.color.r * w2) * texel.r,
+          (v0.color.g * w0 + v1.color.g * w1 + v2.color.g * w2) * texel.g,
+          (v0.color.b * w0 + v1.color.b * w1 + v2.color.b * w2) * texel.b,
+          (v0.color.a * w0 + v1.color.a * w1 + v2.color.a * w2) * texel.a,
+        );
+
+        // source over blending, like the GL renderer
+        let src_factor = if self.premultiplied { 1f32 } else { src.a };
+        let dst = &mut self.pixels[(y * self.width + x) as usize];
+        *dst = RGBAColorF32::new_with_alpha(
+          src.r * src_factor + dst.r * (1f32 - src.a),
+          src.g * src_factor + dst.g * (1f32 - src.a),
+          src.b * src_factor + dst.b * (1f32 - src.a),
+          src.a + dst.a * (1f32 - src.a),
+        );
+      });
+    });
+  }
+}
+
+/// Rasterizes `text` once into an RGBA8 image (premultiplied alpha) as wide
+/// as the text and as tall as the font. Upload it and draw it as an `Image`,
+/// a single quad, instead of drawing the glyphs every frame. The glyphs are
+/// sampled from the atlas, so it must be built with `keep_pixels(true)`, None
+/// if it wasn't.
+pub fn bake_text(
+  font: &Font,
+  text: &str,
+  fg: RGBAColor,
+) -> Option<(Vec<u8>, u32, u32)> {
+  let (texels, tex_width, tex_height) = font.texture_pixels()?;
+  let width = font.text_width(text).ceil().max(0f32) as u32;
+  let height = font.scale.ceil().max(0f32) as u32;
+  if width == 0 || height == 0 {
+    return Some((vec![], width, height));
+  }
+
+  let mut target = HeadlessTarget::new(width, height);
+  target.set_premultiplied_alpha(font.premultiplied_alpha());
+
+  let mut cmd_buff = CommandBuffer::new(Some(Consts::null_rect()), 4);
+  // the text is clamped to less than the rect's width, leave some slack so
+  // the last glyph is kept (the background is not drawn for text)
+  cmd_buff.draw_text(
+    RectangleF32::new(0f32, 0f32, width as f32 + 1f32, height as f32),
+    text,
+    font,
+    fg,
+    fg,
+  );
+  if cmd_buff.is_empty() {
+    // nothing visible, a transparent foreground
+    return Some((target.to_rgba8(), width, height));
+  }
+
+  let (cmds_ptr, cmds_len) = cmd_buff.commands_range();
+  let cmds = (0 .. cmds_len)
+    .map(|i| unsafe { cmds_ptr.add(i) })
+    .collect::<Vec<_>>();
+
+  let config = ConvertConfig {
+    global_alpha:         1f32,
+    line_aa:              AntialiasingType::Off,
+    shape_aa:             AntialiasingType::Off,
+    circle_segment_count: 22,
+    circle_quality:       1f32,
+    arc_segment_count:    22,
+    curve_segment_count:  22,
+    null:                 font.draw_null_texture(),
+    vertex_layout:        vec![],
+    vertex_size:          std::mem::size_of::<VertexPTC>(),
+    snap_to_pixel:        false,
+    premultiplied_alpha:  font.premultiplied_alpha(),
+  };
+  // the config is valid, it has the vertex size of VertexPTC
+  let mut dl =
+    DrawList::new(config, AntialiasingType::Off, AntialiasingType::Off).ok()?;
+  let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+  dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+
+  target.add_texture(font.texture(), tex_width, tex_height, &texels);
+  target.render(&vertices, &indices, &draw_cmds);
+
+  Some((target.to_rgba8(), width, height))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hmi::{
+    base::{AntialiasingType, Consts, ConvertConfig, GenericHandle},
+    commands::{Command, CommandBuffer},
+    vertex_output::DrawList,
+  };
+
+  #[test]
+  fn test_render_filled_rect() {
+    let config =
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1));
+    let mut dl =
+      DrawList::new(config, AntialiasingType::Off, AntialiasingType::Off)
+        .unwrap();
+
+    let mut cmd_buff = CommandBuffer::new(Some(Consts::null_rect()), 4);
+    cmd_buff.fill_rect(
+      RectangleF32::new(2f32, 2f32, 4f32, 4f32),
+      0f32,
+      RGBAColor::new(255, 0, 0),
+    );
+    let cmds = cmd_buff
+      .commands()
+      .iter()
+      .map(|cmd| cmd as *const Command)
+      .collect::<Vec<_>>();
+
+    let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+    dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+
+    let mut target = HeadlessTarget::new(10, 10);
+    target.clear(RGBAColor::new(0, 0, 255));
+    target.render(&vertices, &indices, &draw_cmds);
+
+    let red = target.pixel(4, 4);
+    assert_eq!((red.r, red.g, red.b, red.a), (255, 0, 0, 255));
+    let outside = target.pixel(8, 8);
+    assert_eq!((outside.r, outside.g, outside.b), (0, 0, 255));
+    let outside = target.pixel(0, 0);
+    assert_eq!((outside.r, outside.g, outside.b), (0, 0, 255));
+  }
+
+  #[test]
+  fn test_render_samples_textures() {
+    let vertex = |x: f32, y: f32, u: f32| VertexPTC {
+      pos:       Vec2F32::new(x, y),
+      texcoords: Vec2F32::new(u, 0.5f32),
+      color:     RGBAColorF32::new_with_alpha(1f32, 1f32, 1f32, 1f32),
+    };
+    let vertices = vec![
+      vertex(0f32, 0f32, 0f32),
+      vertex(4f32, 0f32, 1f32),
+      vertex(4f32, 2f32, 1f32),
+      vertex(0f32, 2f32, 0f32),
+    ];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    let draw_cmd = |texture| DrawCommand {
+      element_count: 6,
+      clip_rect: Consts::null_rect(),
+      texture,
+    };
+
+    // red on the left half, transparent on the right half
+    let mut target = HeadlessTarget::new(4, 2);
+    target.add_texture(
+      GenericHandle::Id(7),
+      2,
+      1,
+      &[255, 0, 0, 255, 0, 0, 0, 0],
+    );
+    target.render(&vertices, &indices, &[draw_cmd(GenericHandle::Id(7))]);
+    let left = target.pixel(0, 1);
+    assert_eq!((left.r, left.g, left.b, left.a), (255, 0, 0, 255));
+    assert_eq!(target.pixel(3, 1).a, 0);
+
+    // unknown textures are white
+    target.render(&vertices, &indices, &[draw_cmd(GenericHandle::Id(8))]);
+    let right = target.pixel(3, 1);
+    assert_eq!((right.r, right.g, right.b, right.a), (255, 255, 255, 255));
+  }
+
+  #[test]
+  fn test_commands_draw_only_their_own_indices() {
+    use crate::hmi::image::Image;
+
+    let config =
+      ConvertConfig::for_tests().with_white_pixel(GenericHandle::Id(1));
+    let mut dl =
+      DrawList::new(config, AntialiasingType::Off, AntialiasingType::Off)
+        .unwrap();
+
+    let (red, green) = (GenericHandle::Id(7), GenericHandle::Id(8));
+    let mut cmd_buff = CommandBuffer::new(Some(Consts::null_rect()), 4);
+    cmd_buff.draw_image(
+      RectangleF32::new(0f32, 0f32, 4f32, 4f32),
+      Image::whole(red, 1, 1),
+      RGBAColor::new(255, 255, 255),
+    );
+    cmd_buff.draw_image(
+      RectangleF32::new(6f32, 0f32, 4f32, 4f32),
+      Image::whole(green, 1, 1),
+      RGBAColor::new(255, 255, 255),
+    );
+    let cmds = cmd_buff
+      .commands()
+      .iter()
+      .map(|cmd| cmd as *const Command)
+      .collect::<Vec<_>>();
+
+    let (mut draw_cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+    dl.convert(&cmds, &mut vertices, &mut indices, &mut draw_cmds);
+
+    let counts = draw_cmds
+      .iter()
+      .filter(|cmd| cmd.element_count != 0)
+      .map(|cmd| (cmd.texture, cmd.element_count))
+      .collect::<Vec<_>>();
+    assert_eq!(counts, vec![(red, 6), (green, 6)]);
+
+    let mut target = HeadlessTarget::new(10, 4);
+    target.add_texture(red, 1, 1, &[255, 0, 0, 255]);
+    target.add_texture(green, 1, 1, &[0, 255, 0, 255]);
+    target.render(&vertices, &indices, &draw_cmds);
+
+    // the second command must not draw the first quad again with its texture
+    let left = target.pixel(2, 2);
+    assert_eq!((left.r, left.g, left.b, left.a), (255, 0, 0, 255));
+    let right = target.pixel(8, 2);
+    assert_eq!((right.r, right.g, right.b, right.a), (0, 255, 0, 255));
+    assert_eq!(target.pixel(5, 2).a, 0);
+  }
+
+  #[test]
+  fn test_bake_text_size_matches_measured_text() {
+    use crate::hmi::{
+      base::DrawNullTexture,
+      text_engine::{FontAtlas, FontAtlasBuilder, FontConfigBuilder},
+    };
+
+    // at 72 dpi an 8pt font keeps the 8x8 cells unscaled
+    let mut builder = FontAtlasBuilder::new(72).unwrap();
+    builder.keep_pixels(true);
+    let font =
+      builder.add_builtin_font(&FontConfigBuilder::new().size(8f32).build());
+    builder
+      .build(|_, _, _| Some((GenericHandle::Id(1), DrawNullTexture::default())))
+      .unwrap();
+
+    let (pixels, width, height) =
+      bake_text(&font, "score: 100", RGBAColor::new(255, 255, 255)).unwrap();
+    assert_eq!(width as f32, font.text_width("score: 100").ceil());
+    assert_eq!(height as f32, font.scale.ceil());
+    assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+    // the glyphs are covered partially, not drawn as solid boxes
+    let alpha = |x: u32, y: u32| pixels[((y * width + x) * 4 + 3) as usize];
+    let covered = (0 .. width)
+      .flat_map(|x| (0 .. height).map(move |y| (x, y)))
+      .filter(|&(x, y)| alpha(x, y) > 0)
+      .count();
+    assert!(covered > 0 && covered < (width * height / 2) as usize);
+    // the space between "score:" and "100" stays empty
+    assert!((0 .. height).all(|y| alpha(7 * 8 - 4, y) == 0));
+
+    let (pixels, width, _) =
+      bake_text(&font, "", RGBAColor::new(255, 255, 255)).unwrap();
+    assert_eq!((pixels.len(), width), (0, 0));
+
+    // without a copy of the atlas pixels there is nothing to sample
+    let font = FontAtlas::monospace_for_tests(8f32);
+    assert!(bake_text(&font, "score", RGBAColor::new(255, 255, 255)).is_none());
+  }
+
+  #[test]
+  fn test_premultiplied_atlas_renders_like_straight_alpha() {
+    use crate::hmi::{
+      base::DrawNullTexture,
+      text_engine::{FontAtlasBuilder, FontConfigBuilder},
+    };
+
+    let bake = |premultiply: bool| {
+      let mut builder = FontAtlasBuilder::new(72).unwrap();
+      builder.keep_pixels(true).premultiply_alpha(premultiply);
+      let font =
+        builder.add_builtin_font(&FontConfigBuilder::new().size(8f32).build());
+      builder
+        .build(|_, _, _| {
+          Some((GenericHandle::Id(1), DrawNullTexture::default()))
+        })
+        .unwrap();
+      assert_eq!(font.premultiplied_alpha(), premultiply);
+
+      // a translucent color, its alpha must scale the color channels too
+      bake_text(&font, "score", RGBAColor::new_with_alpha(255, 128, 0, 128))
+        .unwrap()
+    };
+
+    let (straight, width, height) = bake(false);
+    let (premultiplied, ..) = bake(true);
+    assert_eq!(premultiplied.len(), (width * height * 4) as usize);
+    assert!(straight.iter().any(|&c| c > 0));
+    straight
+      .iter()
+      .zip(premultiplied.iter())
+      .for_each(|(&a, &b)| {
+        assert!((a as i32 - b as i32).abs() <= 1, "{} != {}", a, b);
+      });
+  }
+}
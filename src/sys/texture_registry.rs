@@ -0,0 +1,114 @@
+// Named textures, so widget code can use `registry.get("icon_close")` instead
+// of passing texture handles around. The registry only maps names to images,
+// creating and deleting the textures is still up to the application.
+
+use crate::{
+  hmi::{base::GenericHandle, image::Image},
+  math::rectangle::RectangleI32,
+};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct TextureRegistry {
+  images: HashMap<String, Image>,
+}
+
+impl TextureRegistry {
+  pub fn new() -> TextureRegistry {
+    TextureRegistry {
+      images: HashMap::new(),
+    }
+  }
+
+  /// Registers the whole `w` x `h` texture under `name`, replacing any image
+  /// registered before with the same name.
+  pub fn register(
+    &mut self,
+    name: &str,
+    handle: GenericHandle,
+    w: u16,
+    h: u16,
+  ) -> Image {
+    self.register_image(name, Image::whole(handle, w, h))
+  }
+
+  /// Registers a region of a texture, like an icon in an icon atlas.
+  pub fn register_region(
+    &mut self,
+    name: &str,
+    handle: GenericHandle,
+    full_w: u16,
+    full_h: u16,
+    region: RectangleI32,
+  ) -> Image {
+    self.register_image(name, Image::sub_image(handle, full_w, full_h, region))
+  }
+
+  pub fn register_image(&mut self, name: &str, img: Image) -> Image {
+    self.images.insert(name.to_string(), img);
+    img
+  }
+
+  pub fn get(&self, name: &str) -> Option<Image> {
+    self.images.get(name).copied()
+  }
+
+  /// Removes the image, the texture itself is left alone.
+  pub fn unregister(&mut self, name: &str) -> Option<Image> {
+    self.images.remove(name)
+  }
+
+  /// Names of the images using the texture `handle`, useful when the
+  /// texture gets deleted.
+  pub fn names_for_texture(
+    &self,
+    handle: GenericHandle,
+  ) -> impl Iterator<Item = &str> {
+    self
+      .images
+      .iter()
+      .filter(move |(_, img)| img.handle == handle)
+      .map(|(name, _)| name.as_str())
+  }
+
+  pub fn len(&self) -> usize {
+    self.images.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.images.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_registered_texture_is_retrieved() {
+    let mut registry = TextureRegistry::new();
+    registry.register("background", GenericHandle::Id(3), 512, 256);
+    registry.register_region(
+      "icon_close",
+      GenericHandle::Id(7),
+      128,
+      128,
+      RectangleI32::new(32, 0, 16, 16),
+    );
+
+    let bg = registry.get("background").unwrap();
+    assert_eq!(bg.handle, GenericHandle::Id(3));
+    assert_eq!(bg.texture_size(), (512, 256));
+    assert_eq!(bg.region, [0, 0, 512, 256]);
+
+    let icon = registry.get("icon_close").unwrap();
+    assert_eq!(icon.handle, GenericHandle::Id(7));
+    assert_eq!(icon.texture_size(), (128, 128));
+    assert_eq!(icon.region, [32, 0, 16, 16]);
+
+    assert!(registry.get("icon_open").is_none());
+    assert!(registry.unregister("background").is_some());
+    assert_eq!(registry.len(), 1);
+  }
+}
@@ -0,0 +1,121 @@
+//! The counting allocator replaces the global allocator of the whole test
+//! binary, so it lives in a binary of its own.
+
+use simple_ui::{
+  hmi::{
+    base::{AntialiasingType, ConvertConfig, DrawNullTexture, GenericHandle},
+    panel::PanelFlags,
+    text_engine::Font,
+    ui_context::UiContext,
+    vertex_output::DrawList,
+  },
+  math::{colors::RGBAColor, rectangle::RectangleF32, vertex_types::VertexPTC},
+};
+
+/// Counts the heap allocations made by each thread, the tests run on
+/// threads of their own so they only see the allocations of their code.
+struct CountingAllocator;
+
+thread_local! {
+  static ALLOCATIONS: std::cell::Cell<usize> =
+    const { std::cell::Cell::new(0) };
+}
+
+fn count_allocation() {
+  // the counter is gone while the thread exits
+  let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+    count_allocation();
+    std::alloc::System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+    std::alloc::System.dealloc(ptr, layout)
+  }
+
+  unsafe fn realloc(
+    &self,
+    ptr: *mut u8,
+    layout: std::alloc::Layout,
+    new_size: usize,
+  ) -> *mut u8 {
+    count_allocation();
+    std::alloc::System.realloc(ptr, layout, new_size)
+  }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn test_config() -> ConvertConfig {
+  ConvertConfig {
+    global_alpha:         1f32,
+    line_aa:              AntialiasingType::Off,
+    shape_aa:             AntialiasingType::Off,
+    circle_segment_count: 22,
+    circle_quality:       1f32,
+    arc_segment_count:    22,
+    curve_segment_count:  22,
+    null:                 DrawNullTexture::default(),
+    vertex_layout:        vec![],
+    vertex_size:          std::mem::size_of::<VertexPTC>(),
+    snap_to_pixel:        false,
+    premultiplied_alpha:  false,
+  }
+  .with_white_pixel(GenericHandle::Id(1))
+}
+
+fn test_context() -> UiContext {
+  UiContext::new(
+    Font::default(),
+    test_config(),
+    AntialiasingType::Off,
+    AntialiasingType::Off,
+  )
+  .unwrap()
+}
+
+#[test]
+fn test_frames_reuse_scratch_memory() {
+  let mut ctx = test_context();
+  let mut draw_list =
+    DrawList::new(test_config(), AntialiasingType::Off, AntialiasingType::Off)
+      .unwrap();
+  let bounds = RectangleF32::new(0f32, 0f32, 100f32, 100f32);
+  let allocations = || ALLOCATIONS.with(|count| count.get());
+  let (mut cmds, mut vertices, mut indices) = (vec![], vec![], vec![]);
+  let mut commands = vec![];
+
+  (0 .. 100).for_each(|n| {
+    cmds.clear();
+    vertices.clear();
+    indices.clear();
+    commands.clear();
+    ctx.begin("frame", bounds, PanelFlags::WindowBorder.into());
+    ctx.end();
+    // a rounded rect, converted through the path buffer
+    ctx.overlay_mut().fill_rect(
+      RectangleF32::new(n as f32, 10f32, 40f32, 20f32),
+      4f32,
+      RGBAColor::new(255, 0, 0),
+    );
+
+    // the command lists of the windows go into the scratch arena
+    let before = allocations();
+    commands.extend(ctx.commands_iter().map(|cmd| cmd as *const _));
+    let built = allocations() - before;
+
+    let before = allocations();
+    draw_list.convert(&commands, &mut vertices, &mut indices, &mut cmds);
+    let converted = allocations() - before;
+    ctx.clear();
+
+    // the first frame grows the memory, the others reuse it
+    if n > 0 {
+      assert_eq!((built, converted), (0, 0), "frame {}", n);
+    }
+  });
+}